@@ -17,11 +17,11 @@ use {
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub enum BorrowerOperationsInstruction {
-    ///   Initializes a new Borrower Operations.
+    ///   Initializes a new Borrower Operations, fails if it is already initialized.
     ///   These represent the parameters that will be included from client side
     ///   [w] - writable, [s] - signer
     /// 
-    ///   0. `[w]` New Borrower Operations account to create.
+    ///   0. `[w, s]` New Borrower Operations account to create.
     ///   1. `[]` authority to initialize this pool account
     ///   4. `[]` nonce
     ///   5. `[]` program id
@@ -31,15 +31,41 @@ pub enum BorrowerOperationsInstruction {
         nonce: u8,
     },
 
-    /// Provide to Borrower Operations
+    /// Open a trove for the borrower
     ///
-    /// If caller has a pre-existing stake, send any accumulated SOL and solUSD gains to them. 
-    /// 
-    ///   0. `[w]` SOLIDStaking account to stake
-    ///   1. `[]` authority of this pool account
-    ///   7. `[]` Token program id
-    ///   8. `[]` program id
-    ///   9. `[]` amount
+    /// Moves the wSOL collateral into the active pool, mints the requested solUSD to the borrower,
+    /// charges the borrowing fee to the SOLID staking pool and mints the gas compensation to the gas pool.
+    /// The trove, its reward snapshot and the borrower coll surplus account are PDAs of the trove manager program,
+    /// the trove manager creates them when they don't exist yet and records the trove in the active pool.
+    ///
+    ///   0. `[w]` BorrowerOperations account
+    ///   1. `[]` authority of the BorrowerOperations account
+    ///   2. `[w]` TroveManager account
+    ///   3. `[w]` ActivePool account
+    ///   4. `[]` DefaultPool account
+    ///   5. `[w]` SOLIDStaking account
+    ///   6. `[w]` solUSD fee pool token account of SOLIDStaking
+    ///   7. `[w]` gas pool solUSD token account
+    ///   8. `[w]` solUSD mint
+    ///   9. `[w]` active pool wSOL token account
+    ///   10. `[]` Token program id
+    ///   11. `[]` pyth product account
+    ///   12. `[]` pyth price account
+    ///   13. `[w]` PriceFeed account of the TroveManager
+    ///   14. `[]` clock sysvar
+    ///   15. `[s]` borrower wallet
+    ///   16. `[w]` borrower trove account
+    ///   17. `[w]` borrower reward snapshot account
+    ///   18. `[w]` borrower wSOL token account
+    ///   19. `[w]` borrower solUSD token account
+    ///   20. `[]` rent sysvar
+    ///   21. `[]` system program
+    ///   22. `[w]` borrower coll surplus account
+    ///   23. `[]` TroveManager program id
+    ///   24. `[]` authority of the TroveManager account
+    ///   25. `[]` CollSurplusPool account
+    ///   26. `[]` default pool wSOL token account
+    ///   27. `[]` coll surplus pool wSOL token account
     OpenTrove{
         max_fee_percentage: u64,
 
@@ -48,6 +74,38 @@ pub enum BorrowerOperationsInstruction {
         coll_increase:u64,
     },
 
+    /// Adjust the collateral and debt of an active trove
+    ///
+    /// Added collateral is moved from the borrower wSOL account to the active pool,
+    /// withdrawn collateral is sent back by the trove manager.
+    ///
+    ///   0. `[w]` BorrowerOperations account
+    ///   1. `[]` authority of the BorrowerOperations account
+    ///   2. `[w]` TroveManager account
+    ///   3. `[w]` ActivePool account
+    ///   4. `[]` trove owner
+    ///   5. `[]` StabilityPool account
+    ///   6. `[w]` gas pool solUSD token account
+    ///   7. `[w]` solUSD mint
+    ///   8. `[w]` SOLIDStaking account
+    ///   9. `[w]` solUSD fee pool token account of SOLIDStaking
+    ///   10. `[]` Token program id
+    ///   11. `[s]` borrower wallet
+    ///   12. `[w]` borrower trove account
+    ///   13. `[]` pyth product account
+    ///   14. `[]` pyth price account
+    ///   15. `[w]` PriceFeed account of the TroveManager
+    ///   16. `[]` clock sysvar
+    ///   17. `[w]` borrower wSOL token account
+    ///   18. `[]` TroveManager program id
+    ///   19. `[]` authority of the TroveManager account
+    ///   20. `[w]` DefaultPool account
+    ///   21. `[]` CollSurplusPool account
+    ///   22. `[w]` active pool wSOL token account
+    ///   23. `[w]` default pool wSOL token account
+    ///   24. `[]` coll surplus pool wSOL token account
+    ///   25. `[w]` borrower reward snapshot account
+    ///   26. `[w]` borrower solUSD token account
     AdjustTrove{
         coll_withdrawal: u64,
     
@@ -60,5 +118,53 @@ pub enum BorrowerOperationsInstruction {
         sol_amount: u64
     },
 
+    /// Close an active trove
+    ///
+    /// Pending rewards are applied by the trove manager first, then the debt is repaid
+    /// and the whole collateral is sent back to the borrower wSOL account.
+    ///
+    ///   0. `[w]` BorrowerOperations account
+    ///   1. `[]` authority of the BorrowerOperations account
+    ///   2. `[w]` TroveManager account
+    ///   3. `[w]` ActivePool account
+    ///   4. `[w]` DefaultPool account
+    ///   5. `[]` StabilityPool account
+    ///   6. `[w]` gas pool solUSD token account
+    ///   7. `[w]` solUSD mint
+    ///   8. `[]` SOLIDStaking account
+    ///   9. `[]` Token program id
+    ///   10. `[w]` borrower reward snapshot account
+    ///   11. `[s]` borrower wallet
+    ///   12. `[w]` borrower trove account
+    ///   13. `[]` pyth product account
+    ///   14. `[]` pyth price account
+    ///   15. `[w]` PriceFeed account of the TroveManager
+    ///   16. `[]` clock sysvar
+    ///   17. `[w]` borrower wSOL token account
+    ///   18. `[]` TroveManager program id
+    ///   19. `[]` authority of the TroveManager account
+    ///   20. `[]` CollSurplusPool account
+    ///   21. `[w]` active pool wSOL token account
+    ///   22. `[w]` default pool wSOL token account
+    ///   23. `[]` coll surplus pool wSOL token account
     CloseTrove(u64),
+
+    /// Claim the collateral surplus left by a liquidation in recovery mode or a full redemption of the borrower's trove
+    ///
+    ///   0. `[]` BorrowerOperations account
+    ///   1. `[]` authority of the BorrowerOperations account
+    ///   2. `[s]` borrower wallet
+    ///   3. `[w]` borrower coll surplus account, a PDA of the trove manager program
+    ///   4. `[w]` borrower wSOL token account
+    ///   5. `[]` TroveManager program id
+    ///   6. `[]` TroveManager account
+    ///   7. `[]` authority of the TroveManager account
+    ///   8. `[]` ActivePool account
+    ///   9. `[]` DefaultPool account
+    ///   10. `[w]` CollSurplusPool account
+    ///   11. `[]` active pool wSOL token account
+    ///   12. `[]` default pool wSOL token account
+    ///   13. `[w]` coll surplus pool wSOL token account
+    ///   14. `[]` Token program id
+    ClaimCollSurplus,
 }
\ No newline at end of file
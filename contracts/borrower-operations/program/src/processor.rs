@@ -8,6 +8,7 @@ use {
     liquity_common::{
         state::*,
         utils::*,
+        price_feed::read_price,
         error::{LiquityError},
         constant::{
             DECIMAL_PRECISION,
@@ -15,7 +16,13 @@ use {
             SOLUSD_GAS_COMPENSATION,
             BORROWING_FEE_FLOOR,
             MCR,
-            CCR
+            CCR,
+            USD_QUOTE_CURRENCY,
+            PAUSE_BORROW,
+            PAUSE_WITHDRAW_COLL,
+            TROVE_PREFIX,
+            REWARD_SNAPSHOT_PREFIX,
+            COLL_SURPLUS_PREFIX
         },
         pyth,
         math::{Decimal, Rate, TryAdd, TryDiv, TryMul, WAD},
@@ -33,6 +40,7 @@ use {
         entrypoint::ProgramResult,
         msg,
         program::{ invoke_signed},
+        instruction::Instruction,
         program_error::PrintProgramError,
         program_error::ProgramError,
         pubkey::Pubkey,
@@ -48,6 +56,337 @@ struct TroveAmount{
     pub debt: u128
 }
 
+/// Trove manager accounts needed to move wSOL out of the pools and to update the troves.
+/// The pool wSOL accounts are owned by the trove manager authority and the trove accounts, the trove manager
+/// and the pools by the trove manager program, so it is done through CPI
+struct TroveManagerAccounts<'a, 'b> {
+    pub program: &'b AccountInfo<'a>,
+    pub trove_manager: &'b AccountInfo<'a>,
+    pub authority: &'b AccountInfo<'a>,
+    pub active_pool: &'b AccountInfo<'a>,
+    pub default_pool: &'b AccountInfo<'a>,
+    pub coll_surplus_pool: &'b AccountInfo<'a>,
+    pub active_pool_wsol: &'b AccountInfo<'a>,
+    pub default_pool_wsol: &'b AccountInfo<'a>,
+    pub coll_surplus_pool_wsol: &'b AccountInfo<'a>,
+    pub token_program: &'b AccountInfo<'a>,
+}
+impl<'a, 'b> TroveManagerAccounts<'a, 'b> {
+    /// check that the trove manager account belongs to the trove manager program the instructions are sent to
+    pub fn assert_program(&self) -> ProgramResult {
+        if self.trove_manager.owner != self.program.key {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        Ok(())
+    }
+
+    /// invoke a trove manager instruction signed by the borrower operations authority
+    fn invoke(
+        &self,
+        ix: &Instruction,
+        account_infos: &[AccountInfo<'a>],
+        borrower_operation_info: &AccountInfo<'a>,
+        nonce: u8,
+    ) -> ProgramResult {
+        let bo_bytes = borrower_operation_info.key.to_bytes();
+        let authority_signature_seeds = [&bo_bytes[..32], &[nonce]];
+        let signers = &[&authority_signature_seeds[..]];
+        invoke_signed(ix, account_infos, signers)
+    }
+
+    /// send wSOL collateral from the active pool to the destination token account
+    pub fn send_sol(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        destination_info: &AccountInfo<'a>,
+        amount: u128,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::send_sol(
+            self.trove_manager.key,
+            self.authority.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            self.active_pool.key,
+            self.default_pool.key,
+            self.coll_surplus_pool.key,
+            self.active_pool_wsol.key,
+            self.default_pool_wsol.key,
+            self.coll_surplus_pool_wsol.key,
+            destination_info.key,
+            self.token_program.key,
+            self.program.key,
+            amount,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                self.authority.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                self.active_pool.clone(),
+                self.default_pool.clone(),
+                self.coll_surplus_pool.clone(),
+                self.active_pool_wsol.clone(),
+                self.default_pool_wsol.clone(),
+                self.coll_surplus_pool_wsol.clone(),
+                destination_info.clone(),
+                self.token_program.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+
+    /// apply the pending redistribution rewards of the trove, moving their wSOL from the default pool
+    pub fn apply_pending_rewards(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        borrower_trove_info: &AccountInfo<'a>,
+        reward_snapshot_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::apply_pending_rewards(
+            self.trove_manager.key,
+            self.authority.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            borrower_trove_info.key,
+            reward_snapshot_info.key,
+            self.active_pool.key,
+            self.default_pool.key,
+            self.coll_surplus_pool.key,
+            self.active_pool_wsol.key,
+            self.default_pool_wsol.key,
+            self.coll_surplus_pool_wsol.key,
+            self.token_program.key,
+            self.program.key,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                self.authority.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                borrower_trove_info.clone(),
+                reward_snapshot_info.clone(),
+                self.active_pool.clone(),
+                self.default_pool.clone(),
+                self.coll_surplus_pool.clone(),
+                self.active_pool_wsol.clone(),
+                self.default_pool_wsol.clone(),
+                self.coll_surplus_pool_wsol.clone(),
+                self.token_program.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+
+    /// create the trove accounts of the borrower when needed and open the trove, adding its collateral and debt to the active pool
+    pub fn open_trove(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        borrower_info: &AccountInfo<'a>,
+        borrower_trove_info: &AccountInfo<'a>,
+        reward_snapshot_info: &AccountInfo<'a>,
+        coll_surplus_info: &AccountInfo<'a>,
+        rent_info: &AccountInfo<'a>,
+        system_info: &AccountInfo<'a>,
+        coll: u128,
+        debt: u128,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::open_trove(
+            self.trove_manager.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            borrower_info.key,
+            borrower_trove_info.key,
+            reward_snapshot_info.key,
+            coll_surplus_info.key,
+            self.active_pool.key,
+            self.program.key,
+            coll,
+            debt,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                borrower_info.clone(),
+                borrower_trove_info.clone(),
+                reward_snapshot_info.clone(),
+                coll_surplus_info.clone(),
+                self.active_pool.clone(),
+                rent_info.clone(),
+                system_info.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+
+    /// apply a collateral and debt change to the trove and the active pool, withdrawn collateral is sent with `send_sol`
+    pub fn adjust_trove(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        borrower_trove_info: &AccountInfo<'a>,
+        coll_change: u128,
+        is_coll_increase: bool,
+        debt_change: u128,
+        is_debt_increase: bool,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::adjust_trove(
+            self.trove_manager.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            borrower_trove_info.key,
+            self.active_pool.key,
+            self.program.key,
+            coll_change,
+            is_coll_increase,
+            debt_change,
+            is_debt_increase,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                borrower_trove_info.clone(),
+                self.active_pool.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+
+    /// close the trove and remove its debt from the active pool, its collateral is sent with `send_sol`
+    pub fn close_trove(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        borrower_trove_info: &AccountInfo<'a>,
+        reward_snapshot_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::close_trove(
+            self.trove_manager.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            borrower_trove_info.key,
+            reward_snapshot_info.key,
+            self.active_pool.key,
+            self.program.key,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                borrower_trove_info.clone(),
+                reward_snapshot_info.clone(),
+                self.active_pool.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+
+    /// decay the base rate of the trove manager before a borrowing fee is charged
+    pub fn decay_base_rate_from_borrowing(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        clock_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::decay_base_rate_from_borrowing(
+            self.trove_manager.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            self.program.key,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                clock_info.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+
+    /// send the whole collateral surplus of the borrower from the coll surplus pool to the destination token account
+    pub fn claim_coll_surplus(
+        &self,
+        borrower_operation_info: &AccountInfo<'a>,
+        authority_info: &AccountInfo<'a>,
+        nonce: u8,
+        coll_surplus_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let ix = trove_manager::instruction::claim_coll_surplus(
+            self.trove_manager.key,
+            self.authority.key,
+            borrower_operation_info.key,
+            authority_info.key,
+            coll_surplus_info.key,
+            self.active_pool.key,
+            self.default_pool.key,
+            self.coll_surplus_pool.key,
+            self.active_pool_wsol.key,
+            self.default_pool_wsol.key,
+            self.coll_surplus_pool_wsol.key,
+            destination_info.key,
+            self.token_program.key,
+            self.program.key,
+        );
+        self.invoke(
+            &ix,
+            &[
+                self.trove_manager.clone(),
+                self.authority.clone(),
+                borrower_operation_info.clone(),
+                authority_info.clone(),
+                coll_surplus_info.clone(),
+                self.active_pool.clone(),
+                self.default_pool.clone(),
+                self.coll_surplus_pool.clone(),
+                self.active_pool_wsol.clone(),
+                self.default_pool_wsol.clone(),
+                self.coll_surplus_pool_wsol.clone(),
+                destination_info.clone(),
+                self.token_program.clone(),
+                self.program.clone(),
+            ],
+            borrower_operation_info,
+            nonce,
+        )
+    }
+}
+
 /// Program state handler.
 /// Main logic of this program
 pub struct Processor {}
@@ -120,29 +459,22 @@ impl Processor {
                 // Instruction: CloseTrove
                 Self::process_close_trove(program_id, accounts, amount  as u128)
             }
+            BorrowerOperationsInstruction::ClaimCollSurplus => {
+                // Instruction: ClaimCollSurplus
+                Self::process_claim_coll_surplus(program_id, accounts)
+            }
         }
     }
-    fn active_pool_add_coll(active_pool_info:&AccountInfo, amount:u128) -> Result<(), ProgramError> {
-        let mut active_pool = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
-        active_pool.sol = amount;
-        active_pool.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
-        Ok(())
-    }
+    /// mint solUSD to the destination, the debt is recorded in the active pool by the trove manager
     fn withdraw_solusd<'a>(
         borrower_data_info: &AccountInfo<'a>,
         authority_info: &AccountInfo<'a>,
-        active_pool_info: &AccountInfo<'a>,
         solusd_token_info: &AccountInfo<'a>,
         destination_info: & AccountInfo<'a>, 
         token_program_info: &AccountInfo<'a>,
         nonce: u8,
         solusd_amount: u128,
-        netdebt_increase: u128
      )->Result<(), ProgramError> {
-        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
-        active_pool_data.increase_solusd_debt(netdebt_increase);
-        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
-
         token_mint_to(
             borrower_data_info.key,
             token_program_info.clone(),
@@ -154,21 +486,16 @@ impl Processor {
         )?;
         Ok(())
     }
+    /// burn solUSD from the destination, the debt is removed from the active pool by the trove manager
     fn repay_solusd<'a>(
         borrower_data_info: &AccountInfo<'a>,
         authority_info: &AccountInfo<'a>,
-        active_pool_info: &AccountInfo<'a>,
         solusd_token_info: &AccountInfo<'a>,
         destination_info: &AccountInfo<'a>, 
         token_program_info: &AccountInfo<'a>,
         nonce: u8,
         netdebt_increase: u128
      )->Result<(), ProgramError> {
-        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
-        active_pool_data.decrease_solusd_debt(netdebt_increase);
-        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..]);
-
-
         token_burn(            
             borrower_data_info.key,
             token_program_info.clone(),
@@ -182,9 +509,10 @@ impl Processor {
     }
 
     fn trigger_borrowing_fee<'a>(
+        trove_manager_accounts:&TroveManagerAccounts<'a, '_>,
         borrower_operation_info:&AccountInfo<'a>,
         authority_info:&AccountInfo<'a>,
-        trove_manager_info:&AccountInfo<'a>,
+        clock_info:&AccountInfo<'a>,
         solusd_token_info: &AccountInfo<'a>,
         token_program_info: &AccountInfo<'a>,
         solid_staking_info: &AccountInfo<'a>,
@@ -193,14 +521,18 @@ impl Processor {
         solusd_amount: u128,
         max_fee_percentage: u128
     )->Result<u128, ProgramError> {
-        let trove_manager = TroveManager::try_from_slice(&trove_manager_info.data.borrow_mut())?;
-        decay_base_rate_from_borrowing(&trove_manager, borrower_operation_info)?;
+        // the fee is charged at the decayed base rate, the trove manager decays it
+        trove_manager_accounts.decay_base_rate_from_borrowing(borrower_operation_info, authority_info, nonce, clock_info)?;
+        let trove_manager = try_from_slice_unchecked::<TroveManager>(&trove_manager_accounts.trove_manager.data.borrow())?;
         let solusd_fee:u128 = get_borrowing_fee(&trove_manager, solusd_amount);
         if !(solusd_fee * DECIMAL_PRECISION / solusd_amount <= max_fee_percentage){
             return Err(LiquityError::FeeExceeded.into());
         }
 
         let mut solid_staking = SOLIDStaking::try_from_slice(&solid_staking_info.data.borrow_mut())?;
+        if *solid_staking_token_pool_info.key != solid_staking.solusd_pool_token_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
         solid_staking.increase_f_solusd(solusd_fee);
 
         token_mint_to(            
@@ -213,7 +545,6 @@ impl Processor {
             solusd_fee as u64,
         )?;
 
-        trove_manager.serialize(&mut &mut trove_manager_info.data.borrow_mut()[..])?;
         solid_staking.serialize(&mut &mut solid_staking_info.data.borrow_mut()[..])?;
 
         Ok(solusd_fee)
@@ -237,6 +568,22 @@ impl Processor {
             debt:newDebt
         })
     }
+    /// check if pda address is correct
+    pub fn assert_pda(program_id:&Pubkey, pool_key:&Pubkey, key: &Pubkey,authority: &Pubkey, tag: &str)->Result<u8, ProgramError>{
+        let seeds = [
+            tag.as_bytes(),
+            authority.as_ref(),
+            pool_key.as_ref(),
+        ];
+
+        let (pda_key, _bump) = Pubkey::find_program_address(&seeds, program_id);
+        if pda_key != *key {
+            return Err(LiquityError::InvalidPdaAddress.into());
+        }
+        else {
+            Ok(_bump)
+        }
+    }
     /// Calculates the authority id by generating a program address.
     pub fn authority_id(
         program_id: &Pubkey,
@@ -262,66 +609,6 @@ impl Processor {
         let new_iCR = compute_cr(res.coll, res.debt, price);
         Ok(new_iCR)
     }
-    #[allow(clippy::too_many_arguments)]
-    fn check_accounts(
-        borrower_operations: &dyn BorrowerOperations,
-        program_id: &Pubkey,
-        borrower_operation_info: &AccountInfo,
-        authority_info: &AccountInfo,
-        token_a_info: &AccountInfo,
-        token_b_info: &AccountInfo,
-        pool_mint_info: &AccountInfo,
-        token_program_info: &AccountInfo,
-        user_token_a_info: Option<&AccountInfo>,
-        user_token_b_info: Option<&AccountInfo>,
-    ) -> ProgramResult {
-        if swap_account_info.owner != program_id {
-            return Err(ProgramError::IncorrectProgramId);
-        }
-        if *authority_info.key
-            != Self::authority_id(program_id, swap_account_info.key, token_swap.nonce())?
-        {
-            return Err(AmmError::InvalidProgramAddress.into());
-        }
-
-        let borrower_operations_info = next_account_info(account_info_iter)?;
-        let authority_info = next_account_info(account_info_iter)?;
-        let trove_manager_info = next_account_info(account_info_iter)?;
-        let active_pool_info = next_account_info(account_info_iter)?;
-        let default_pool_info = next_account_info(account_info_iter)?;
-        let stability_pool_info = next_account_info(account_info_iter)?;
-        let gas_pool_info = next_account_info(account_info_iter)?;
-        let coll_surplus_pool = next_account_info(account_info_iter)?;
-        let solusd_token_info = next_account_info(account_info_iter)?;
-        let solid_staking_info = next_account_info(account_info_iter)?;
-        let oracle_program_id_info = next_account_info(account_info_iter)?;
-        let pyth_product_id_info = next_account_info(account_info_iter)?;
-        let pyth_price_id_info = next_account_info(account_info_iter)?;
-
-        if *token_a_info.key != *token_swap.token_a_account() {
-            return Err(AmmError::IncorrectSwapAccount.into());
-        }
-        if *token_b_info.key != *token_swap.token_b_account() {
-            return Err(AmmError::IncorrectSwapAccount.into());
-        }
-        if *pool_mint_info.key != *token_swap.pool_mint() {
-            return Err(AmmError::IncorrectPoolMint.into());
-        }
-        if *token_program_info.key != *token_swap.token_program_id() {
-            return Err(AmmError::IncorrectTokenProgramId.into());
-        }
-        if let Some(user_token_a_info) = user_token_a_info {
-            if token_a_info.key == user_token_a_info.key {
-                return Err(AmmError::InvalidInput.into());
-            }
-        }
-        if let Some(user_token_b_info) = user_token_b_info {
-            if token_b_info.key == user_token_b_info.key {
-                return Err(AmmError::InvalidInput.into());
-            }
-        }
-        Ok(())
-    }
     fn  get_new_normal_icr_from_trove_change
     (
         coll:u128,
@@ -337,6 +624,49 @@ impl Processor {
         Ok(new_iCR)
     }
 
+    /// _requireValidAdjustmentInCurrentMode
+    /// In recovery mode collateral can not be withdrawn and a debt increase must leave the trove with an ICR >= CCR
+    /// that is not lower than before. In normal mode the trove ICR must stay >= MCR and the TCR >= CCR.
+    fn require_valid_adjustment_in_current_mode(
+        is_recovery_mode: bool,
+        coll_withdrawal: u128,
+        is_debt_increase: bool,
+        vars: &mut LocalVariablesAdjustTrove,
+        active_pool: ActivePool,
+        default_pool: DefaultPool,
+    ) -> Result<(), ProgramError> {
+        if is_recovery_mode {
+            if coll_withdrawal != 0 {
+                return Err(LiquityError::ErrorCollWithdrawalInRecoveryMode.into());
+            }
+            if is_debt_increase {
+                if vars.new_icr < CCR {
+                    return Err(LiquityError::CCRError.into());
+                }
+                if vars.new_icr < vars.old_icr {
+                    return Err(LiquityError::ErrorICRDecreaseInRecoveryMode.into());
+                }
+            }
+        }
+        else {
+            if vars.new_icr < MCR {
+                return Err(LiquityError::MCRError.into());
+            }
+            vars.new_tcr = Self::get_new_tcr_from_trove_change(
+                active_pool,
+                default_pool,
+                vars.coll_change,
+                vars.is_coll_increase,
+                vars.net_debt_change,
+                is_debt_increase,
+                vars.price);
+            if vars.new_tcr < CCR {
+                return Err(LiquityError::ErrorTCRBelowCCR.into());
+            }
+        }
+        Ok(())
+    }
+
     /// process `Initialize` instruction.
     pub fn process_initialize(
         program_id: &Pubkey,        // this program id
@@ -362,8 +692,21 @@ impl Processor {
         let pyth_product_id_info = next_account_info(account_info_iter)?;
         let pyth_price_id_info = next_account_info(account_info_iter)?;
 
+        if borrower_operation_info.owner != program_id {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        // the deployer holding the key of the new account signs the initialization
+        if !borrower_operation_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the borrower operations can only be initialized once, a second initialization would replace its ids
         let mut borrower_operations = try_from_slice_unchecked::<BorrowerOperations>(&borrower_operation_info.data.borrow())?;
+        if borrower_operations.trove_manager_id != Pubkey::default() {
+            return Err(LiquityError::AlreadyInUse.into());
+        }
 
+        borrower_operations.nonce = nonce;
         borrower_operations.trove_manager_id = *trove_manager_info.key;
         borrower_operations.active_pool_id = *active_pool_info.key;
         borrower_operations.default_pool_id = *default_pool_info.key;
@@ -375,6 +718,7 @@ impl Processor {
         borrower_operations.oracle_program_id = *oracle_program_id_info.key;
         borrower_operations.pyth_product_id = *pyth_product_id_info.key;
         borrower_operations.pyth_price_id = *pyth_price_id_info.key;
+        borrower_operations.quote_currency = USD_QUOTE_CURRENCY;
 
         if *authority_info.key != Self::authority_id(program_id, borrower_operation_info.key, nonce)? {
             return Err(LiquityError::InvalidProgramAddress.into());
@@ -392,211 +736,196 @@ impl Processor {
         solusd_amount: u128,
         coll_increase:u128,
     ) -> ProgramResult {
-        // new code
         let account_info_iter = &mut accounts.iter();
-<<<<<<< HEAD
         let borrower_operation_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
         let trove_manager_info = next_account_info(account_info_iter)?;
         let active_pool_info = next_account_info(account_info_iter)?;
-        let solusd_token_info = next_account_info(account_info_iter)?;
+        let default_pool_info = next_account_info(account_info_iter)?;
         let solid_staking_info = next_account_info(account_info_iter)?;
-        let token_program_info = next_account_info(account_info_iter)?;
+        let solid_staking_token_pool_info = next_account_info(account_info_iter)?;
         let gas_pool_info = next_account_info(account_info_iter)?;
         let solusd_token_info = next_account_info(account_info_iter)?;
-        let token_program_info = next_account_info(account_info_iter)?;
-
-
-        let oracle_program_info = next_account_info(account_info_iter)?;
 
+        // wSOL token account of the active pool
+        let pool_wsol_token_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
-        let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-        
+        let price_feed_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+
         let borrower_info = next_account_info(account_info_iter)?;
         let borrower_trove_info = next_account_info(account_info_iter)?;
-        let owner_id_info = next_account_info(account_info_iter)?;
-        let borrower_operations = BorrowerOperations::try_from_slice(&borrower_operation_info.data.borrow())?;
-        let mut borrower_trove = Trove::try_from_slice(&borrower_trove_info.data.borrow())?;
-        let mut trove_manager = TroveManager::try_from_slice(&trove_manager_info.data.borrow())?;
-        if *authority_info.key != Self::authority_id(program_id, borrower_info.key, borrower_operations.nonce)? {
+        let reward_snapshot_info = next_account_info(account_info_iter)?;
+        let borrower_wsol_token_info = next_account_info(account_info_iter)?;
+        let borrower_solusd_token_info = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let system_info = next_account_info(account_info_iter)?;
+        let coll_surplus_info = next_account_info(account_info_iter)?;
+
+        let trove_manager_accounts = TroveManagerAccounts {
+            program: next_account_info(account_info_iter)?,
+            trove_manager: trove_manager_info,
+            authority: next_account_info(account_info_iter)?,
+            active_pool: active_pool_info,
+            default_pool: default_pool_info,
+            coll_surplus_pool: next_account_info(account_info_iter)?,
+            active_pool_wsol: pool_wsol_token_info,
+            default_pool_wsol: next_account_info(account_info_iter)?,
+            coll_surplus_pool_wsol: next_account_info(account_info_iter)?,
+            token_program: token_program_info,
+        };
+
+        let clock = &Clock::from_account_info(clock_info)?;
+        let borrower_operations = try_from_slice_unchecked::<BorrowerOperations>(&borrower_operation_info.data.borrow())?;
+
+        if *authority_info.key != Self::authority_id(program_id, borrower_operation_info.key, borrower_operations.nonce)? {
             return Err(LiquityError::InvalidProgramAddress.into());
         }
+        if *trove_manager_info.key != borrower_operations.trove_manager_id ||
+            *active_pool_info.key != borrower_operations.active_pool_id ||
+            *default_pool_info.key != borrower_operations.default_pool_id ||
+            *solid_staking_info.key != borrower_operations.solid_staking_id ||
+            *gas_pool_info.key != borrower_operations.gas_pool_id ||
+            *solusd_token_info.key != borrower_operations.solusd_token_id
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        trove_manager_accounts.assert_program()?;
+        if !borrower_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
-        let token_program_id = *token_program_info.key;
-        let market_price = ((get_pyth_price( pyth_price_info, clock )?).try_round_u64()?) as u128;
-        let is_recovery_mode = true;
+        // the trove accounts are PDAs of the trove manager, it creates them when they don't exist yet
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, borrower_trove_info.key, borrower_info.key, TROVE_PREFIX)?;
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, reward_snapshot_info.key, borrower_info.key, REWARD_SNAPSHOT_PREFIX)?;
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, coll_surplus_info.key, borrower_info.key, COLL_SURPLUS_PREFIX)?;
 
-        Self::require_valid_max_fee_percentage(max_fee_percentage, is_recovery_mode)?;
+        let market_price = read_price(
+            price_feed_info,
+            trove_manager_info,
+            &borrower_operations.trove_manager_id,
+            pyth_product_info,
+            pyth_price_info,
+            clock
+        )?;
 
-        if borrower_trove.status == 1{
-            return Err(LiquityError::ErrorTroveisActive.into())
+        let active_pool = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let default_pool = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow())?;
+        if *pool_wsol_token_info.key != active_pool.wsol_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
         }
-        let solusd_fee = 0;
-        let mut net_debt = solusd_amount;
-        if !is_recovery_mode{
-            solusd_fee;
-            net_debt += solusd_fee;
+        let is_recovery_mode = {
+            let trove_manager = try_from_slice_unchecked::<TroveManager>(&trove_manager_info.data.borrow())?;
+            require_not_paused(trove_manager.paused_operations, PAUSE_BORROW)?;
+            trove_manager.check_recovery_mode(market_price, &active_pool, &default_pool) == 1
+        };
+
+        Self::require_valid_max_fee_percentage(max_fee_percentage, is_recovery_mode)?;
+
+        let mut vars = LocalVariablesOpenTrove::new(*borrower_operation_info.key, *borrower_info.key);
+        vars.price = market_price;
+        vars.net_debt = solusd_amount;
+
+        // no borrowing fee is charged in recovery mode
+        if !is_recovery_mode {
+            vars.solusd_fee = Self::trigger_borrowing_fee(
+                &trove_manager_accounts,
+                borrower_operation_info,
+                authority_info,
+                clock_info,
+                solusd_token_info,
+                token_program_info,
+                solid_staking_info,
+                solid_staking_token_pool_info,
+                borrower_operations.nonce,
+                solusd_amount,
+                max_fee_percentage)?;
+            vars.net_debt += vars.solusd_fee;
         }
-        if net_debt < MIN_NET_DEBT {
+        if vars.net_debt < MIN_NET_DEBT {
             return Err(LiquityError::ErrorMinNetDebt.into());
         }
-        let composite_debt =  net_debt + SOLUSD_GAS_COMPENSATION;
-=======
-        let borrower_info = next_account_info(account_info_iter)?;
-        let borrower_trove_info = next_account_info(account_info_iter)?;
-        let borrower_wsol_token_info = next_account_info(account_info_iter)?;
-        let borrower_solusd_token_info = next_account_info(account_info_iter)?;
->>>>>>> 5b9b54a89629943e2cd6930c9216f96b19c19c67
 
-        let borrower_operations_info = next_account_info(account_info_iter)?;
-        let pool_wsol_token_info = next_account_info(account_info_iter)?;
+        // ICR is based on the composite debt, i.e. the requested solUSD amount + solUSD borrowing fee + solUSD gas comp.
+        vars.composite_debt = vars.net_debt + SOLUSD_GAS_COMPENSATION;
+        if vars.composite_debt == 0 {
+            return Err(LiquityError::InvalidCompositeDebt.into());
+        }
 
-        Ok(())
+        vars.icr = compute_cr(coll_increase, vars.composite_debt, vars.price);
+        vars.nicr = compute_nominal_cr(coll_increase, vars.composite_debt);
 
+        if is_recovery_mode {
+            if vars.icr < CCR {
+                return Err(LiquityError::CCRError.into());
+            }
+        }
+        else {
+            if vars.icr < MCR {
+                return Err(LiquityError::MCRError.into());
+            }
+            let new_tcr = Self::get_new_tcr_from_trove_change(
+                active_pool,
+                default_pool,
+                coll_increase,
+                true,
+                vars.composite_debt,
+                true,
+                vars.price);
+            if new_tcr < CCR {
+                return Err(LiquityError::CCRError.into());
+            }
+        }
 
-        // origin code
-        // let account_info_iter = &mut accounts.iter();
-        // let borrower_operation_info = next_account_info(account_info_iter)?;
-        // let authority_info = next_account_info(account_info_iter)?;
-        // let trove_manager_info = next_account_info(account_info_iter)?;
-        // let solid_staking_info = next_account_info(account_info_iter)?;
-        // let solid_staking_token_pool_info = next_account_info(account_info_iter)?;
-        // let active_pool_info = next_account_info(account_info_iter)?;
-        // let default_pool_info = next_account_info(account_info_iter)?;
-        // let solusd_token_info = next_account_info(account_info_iter)?;
-        // let token_program_info = next_account_info(account_info_iter)?;
-        // let pyth_product_info = next_account_info(account_info_iter)?;
-        // let pyth_price_info = next_account_info(account_info_iter)?;
-        // let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-        // let borrower_info = next_account_info(account_info_iter)?;
-        // let borrower_trove_info = next_account_info(account_info_iter)?;
-
-        // let borrower_operations = BorrowerOperations::try_from_slice(&borrower_operation_info.data.borrow())?;
-        // let mut borrower_trove = Trove::try_from_slice(&borrower_trove_info.data.borrow())?;
-        // let mut trove_manager = TroveManager::try_from_slice(&trove_manager_info.data.borrow())?;
-
-        // let active_pool = ActivePool::try_from_slice(&active_pool_info.data.borrow())?;
-        // let default_pool = DefaultPool::try_from_slice(&default_pool_info.data.borrow())?;
-
-        // let market_price = get_market_price(
-        //     borrower_operations.oracle_program_id,
-        //     borrower_operations.quote_currency,
-        //     pyth_product_info,
-        //     pyth_price_info,
-        //     clock
-        // )?;
-        
-        // let is_recovery_mode = trove_manager.check_recovery_mode(market_price, &active_pool, &default_pool) == 1;
-
-        // Self::require_valid_max_fee_percentage(max_fee_percentage, is_recovery_mode)?;
-
-        // if borrower_trove.status == 1{
-        //     return Err(LiquityError::ErrorTroveisActive.into())
-        // }
-
-        // let mut solusd_fee = 0;
-        // let mut net_debt = solusd_amount;
-        // if !is_recovery_mode{
-        //     solusd_fee = Self::trigger_borrowing_fee(
-        //         &borrower_operation_info.clone(),
-        //         &authority_info.clone(),
-        //         &trove_manager_info.clone(),
-        //         &solusd_token_info.clone(),
-        //         &token_program_info.clone(),
-        //         &solid_staking_info.clone(),
-        //         &solid_staking_token_pool_info.clone(),
-        //         borrower_operations.nonce,
-        //         solusd_amount,
-        //         max_fee_percentage)?;
-        //     net_debt += solusd_fee;
-        // }
-        // if net_debt < MIN_NET_DEBT {
-        //     return Err(LiquityError::ErrorMinNetDebt.into());
-        // }
-        // let composite_debt =  net_debt + SOLUSD_GAS_COMPENSATION;
-
-        // if composite_debt == 0 {
-        //     return Err(LiquityError::InvalidCompositeDebt.into());
-        // }
-
-        // let icr = compute_cr(coll_increase, composite_debt, market_price);
-        // let nicr = compute_nominal_cr(coll_increase, composite_debt);
-
-        // let mut vars = LocalVariablesOpenTrove::new(*borrower_operation_info.key, *borrower_info.key);
-        // vars.price = market_price;
-        // vars.solusd_fee = solusd_fee;
-        // vars.net_debt = net_debt;
-        // vars.composite_debt = composite_debt;
-        // vars.icr = icr;
-        // vars.nicr = nicr;
-
-        // if is_recovery_mode {
-        //     if vars.icr < CCR {
-        //         return Err(LiquityError::CCRError.into());
-        //     }
-        // }
-        // else {
-        //     if vars.icr < MCR {
-        //         return Err(LiquityError::MCRError.into());
-        //     }
-        //     let new_tcr = Self::get_new_tcr_from_trove_change(
-        //         active_pool, 
-        //         default_pool,
-        //         coll_increase, 
-        //         true, 
-        //         vars.composite_debt, 
-        //         true, 
-        //         vars.price);
-
-        //     if new_tcr < CCR {
-        //         return Err(LiquityError::CCRError.into());
-        //     }
-        // }
-       
-        // borrower_trove.status = 1;
-        // borrower_trove.coll += coll_increase;
-        // borrower_trove.debt += composite_debt;
-
-        // let new_stake = borrower_trove.coll;
-        // let old_stake = borrower_trove.stake;
-        // borrower_trove.stake = new_stake;
-        // trove_manager.total_stakes = trove_manager.total_stakes - old_stake + new_stake;
-
-        // vars.stake = trove_manager.total_stakes;
-
-        // Self::withdraw_solusd(
-        //     &borrower_operation_info, 
-        //     &authority_info,
-        //     &active_pool_info,
-        //     &solusd_token_info,
-        //     &borrower_info,
-        //     &token_program_info, 
-        //     borrower_operations.nonce,
-        //     solusd_amount,
-        //     vars.net_debt
-        // )?;
-
-        // Self::withdraw_solusd(
-        //     &borrower_operation_info, 
-        //     &authority_info,
-        //     &active_pool_info,
-        //     &solusd_token_info,
-        //     &borrower_info, 
-        //     &token_program_info, 
-        //     borrower_operations.nonce,
-        //     SOLUSD_GAS_COMPENSATION,
-        //     SOLUSD_GAS_COMPENSATION
-        // )?;
-
-        // borrower_operations
-        //     .serialize(&mut *borrower_operation_info.data.borrow_mut())?;
-        // borrower_trove
-        //     .serialize(&mut *borrower_operation_info.data.borrow_mut())?;
-        // trove_manager
-        //     .serialize(&mut *borrower_operation_info.data.borrow_mut())?;
-        
-        // Ok(())
+        // move the wSOL collateral to the active pool
+        token_transfer(
+            borrower_operation_info.key,
+            token_program_info.clone(),
+            borrower_wsol_token_info.clone(),
+            pool_wsol_token_info.clone(),
+            borrower_info.clone(),
+            borrower_operations.nonce,
+            coll_increase as u64,
+        )?;
+
+        // the trove manager opens the trove, fails if it is already active, and records its collateral and composite debt in the active pool
+        trove_manager_accounts.open_trove(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            borrower_info,
+            borrower_trove_info,
+            reward_snapshot_info,
+            coll_surplus_info,
+            rent_info,
+            system_info,
+            coll_increase,
+            vars.composite_debt
+        )?;
+
+        // mint the solUSD amount to the borrower
+        Self::withdraw_solusd(
+            borrower_operation_info,
+            authority_info,
+            solusd_token_info,
+            borrower_solusd_token_info,
+            token_program_info,
+            borrower_operations.nonce,
+            solusd_amount
+        )?;
+
+        // move the solUSD gas compensation to the gas pool
+        Self::withdraw_solusd(
+            borrower_operation_info,
+            authority_info,
+            solusd_token_info,
+            gas_pool_info,
+            token_program_info,
+            borrower_operations.nonce,
+            SOLUSD_GAS_COMPENSATION
+        )
     }
 
     /// process WithdrawFromSP instruction
@@ -615,8 +944,8 @@ impl Processor {
         let trove_manager_info = next_account_info(account_info_iter)?;
         let active_pool_info = next_account_info(account_info_iter)?;
         let owner_id_info = next_account_info(account_info_iter)?;
-        let stability_pool_info = next_account_info(account_info_iter)?;
-        let gas_pool_info = next_account_info(account_info_iter)?;
+        let _stability_pool_info = next_account_info(account_info_iter)?;
+        let _gas_pool_info = next_account_info(account_info_iter)?;
         let solusd_token_info = next_account_info(account_info_iter)?;
         let solid_staking_info = next_account_info(account_info_iter)?;
         let solid_staking_token_pool_info = next_account_info(account_info_iter)?;
@@ -627,27 +956,66 @@ impl Processor {
 
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
+        let price_feed_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
-        
-        let borrower_operations = BorrowerOperations::try_from_slice(&borrower_operation_info.data.borrow())?;
-        let borrower_trove = Trove::try_from_slice(&borrower_trove_info.data.borrow())?;
-        let stability_pool = StabilityPool::try_from_slice(&stability_pool_info.data.borrow())?;
-        let mut active_pool = ActivePool::try_from_slice(&active_pool_info.data.borrow())?;
+        let borrower_wsol_token_info = next_account_info(account_info_iter)?;
+
+        let trove_manager_accounts = TroveManagerAccounts {
+            program: next_account_info(account_info_iter)?,
+            trove_manager: trove_manager_info,
+            authority: next_account_info(account_info_iter)?,
+            active_pool: active_pool_info,
+            default_pool: next_account_info(account_info_iter)?,
+            coll_surplus_pool: next_account_info(account_info_iter)?,
+            active_pool_wsol: next_account_info(account_info_iter)?,
+            default_pool_wsol: next_account_info(account_info_iter)?,
+            coll_surplus_pool_wsol: next_account_info(account_info_iter)?,
+            token_program: token_program_info,
+        };
+        let reward_snapshot_info = next_account_info(account_info_iter)?;
+        let borrower_solusd_token_info = next_account_info(account_info_iter)?;
+
+        let borrower_operations = try_from_slice_unchecked::<BorrowerOperations>(&borrower_operation_info.data.borrow())?;
         let clock = &Clock::from_account_info(clock_info)?;
 
-        if *authority_info.key != Self::authority_id(program_id, borrower_info.key, borrower_operations.nonce)? {
+        if *authority_info.key != Self::authority_id(program_id, borrower_operation_info.key, borrower_operations.nonce)? {
             return Err(LiquityError::InvalidProgramAddress.into());
         }
+        if *trove_manager_info.key != borrower_operations.trove_manager_id ||
+            *active_pool_info.key != borrower_operations.active_pool_id ||
+            *trove_manager_accounts.default_pool.key != borrower_operations.default_pool_id ||
+            *solid_staking_info.key != borrower_operations.solid_staking_id ||
+            *solusd_token_info.key != borrower_operations.solusd_token_id
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        trove_manager_accounts.assert_program()?;
+        if !borrower_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, borrower_trove_info.key, borrower_info.key, TROVE_PREFIX)?;
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, reward_snapshot_info.key, borrower_info.key, REWARD_SNAPSHOT_PREFIX)?;
+
+        // repaying and adding collateral stay available while borrowing or withdrawing collateral is paused
+        {
+            let trove_manager = try_from_slice_unchecked::<TroveManager>(&trove_manager_info.data.borrow())?;
+            if is_debt_increase && solusd_change > 0 {
+                require_not_paused(trove_manager.paused_operations, PAUSE_BORROW)?;
+            }
+            if coll_withdrawal > 0 {
+                require_not_paused(trove_manager.paused_operations, PAUSE_WITHDRAW_COLL)?;
+            }
+        }
 
         let mut vars = LocalVariablesAdjustTrove::new(*borrower_operation_info.key, *owner_id_info.key);
-        vars.price = get_market_price(
-            stability_pool.oracle_program_id,
-            stability_pool.quote_currency,
+        vars.price = read_price(
+            price_feed_info,
+            trove_manager_info,
+            &borrower_operations.trove_manager_id,
             pyth_product_info,
             pyth_price_info,
             clock
         )?;
-        let is_recovery_mode = true; // check_recovery_mode
         if !(sol_amount == 0 || coll_withdrawal == 0)
         {
             return Err(LiquityError::ErrorSignularCollChange.into());
@@ -658,11 +1026,38 @@ impl Processor {
             return Err(LiquityError::ErrorNoneZeroAdjustment.into());
         }
 
-        if !borrower_trove.is_active()
+        if !try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?.is_active()
         {
             return Err(LiquityError::ErrorTroveisNotActive.into());
         }
 
+        // pending rewards move wSOL from the default pool, so the trove manager applies them
+        trove_manager_accounts.apply_pending_rewards(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            borrower_trove_info,
+            reward_snapshot_info
+        )?;
+
+        let borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+        let active_pool = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let default_pool = try_from_slice_unchecked::<DefaultPool>(&trove_manager_accounts.default_pool.data.borrow())?;
+        if *trove_manager_accounts.active_pool_wsol.key != active_pool.wsol_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        let is_recovery_mode = {
+            let trove_manager = try_from_slice_unchecked::<TroveManager>(&trove_manager_info.data.borrow())?;
+            trove_manager.check_recovery_mode(vars.price, &active_pool, &default_pool) == 1
+        };
+
+        if is_debt_increase {
+            Self::require_valid_max_fee_percentage(max_fee_percentage, is_recovery_mode)?;
+            if solusd_change == 0 {
+                return Err(LiquityError::ZeroAmount.into());
+            }
+        }
+
         vars.coll_change = 0;
         if sol_amount != 0{
             vars.coll_change = sol_amount;
@@ -672,16 +1067,19 @@ impl Processor {
             vars.coll_change = coll_withdrawal;
         }
         vars.net_debt_change = solusd_change;
+
+        // no borrowing fee is charged in recovery mode
         if is_debt_increase && !is_recovery_mode
         {
             vars.solusd_fee = Self::trigger_borrowing_fee(
-                &borrower_operation_info.clone(),
-                &authority_info.clone(),
-                &trove_manager_info.clone(),
-                &solusd_token_info.clone(),
-                &token_program_info.clone(),
-                &solid_staking_info.clone(),
-                &solid_staking_token_pool_info.clone(),
+                &trove_manager_accounts,
+                borrower_operation_info,
+                authority_info,
+                clock_info,
+                solusd_token_info,
+                token_program_info,
+                solid_staking_info,
+                solid_staking_token_pool_info,
                 borrower_operations.nonce,
                 solusd_change,
                 max_fee_percentage)?;
@@ -689,10 +1087,11 @@ impl Processor {
             vars.net_debt_change += vars.solusd_fee;
         }
 
-        let mut borrower_trove = Trove::try_from_slice(&borrower_trove_info.data.borrow_mut())?;
-
         vars.debt = borrower_trove.debt;
         vars.coll = borrower_trove.coll;
+        if coll_withdrawal > vars.coll {
+            return Err(LiquityError::MathOverflow.into());
+        }
         vars.old_icr = compute_cr(vars.coll, vars.debt, vars.price);
         vars.new_icr = Self::get_new_icr_from_trove_change(
                 vars.coll, 
@@ -703,80 +1102,79 @@ impl Processor {
                 is_debt_increase, 
                 vars.price)?;
 
-        //_updateTroveFromAdjustment
-        if vars.is_coll_increase
-        {
-            vars.new_coll = borrower_trove.increase_trove_coll(vars.coll_change)
-        }
-        else
-        {
-            vars.new_coll = borrower_trove.decrease_trove_coll(vars.coll_change)
+        Self::require_valid_adjustment_in_current_mode(is_recovery_mode, coll_withdrawal, is_debt_increase, &mut vars, active_pool, default_pool)?;
+
+        // a repayment can not take the trove below the minimum net debt
+        if !is_debt_increase && solusd_change > 0 {
+            let net_debt = vars.debt.checked_sub(SOLUSD_GAS_COMPENSATION).ok_or(LiquityError::MathOverflow)?;
+            if vars.net_debt_change > net_debt {
+                return Err(LiquityError::ErrorInvalidRepayment.into());
+            }
+            if net_debt - vars.net_debt_change < MIN_NET_DEBT {
+                return Err(LiquityError::ErrorMinNetDebt.into());
+            }
         }
 
-        if is_debt_increase
-        {
-            vars.new_debt = borrower_trove.increase_trove_debt(vars.net_debt_change)
+        // _updateTroveFromAdjustment, the trove manager updates the trove, its stake and the active pool
+        trove_manager_accounts.adjust_trove(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            borrower_trove_info,
+            vars.coll_change,
+            vars.is_coll_increase,
+            vars.net_debt_change,
+            is_debt_increase
+        )?;
+
+        if vars.is_coll_increase {
+            token_transfer(
+                borrower_operation_info.key,
+                token_program_info.clone(),
+                borrower_wsol_token_info.clone(),
+                trove_manager_accounts.active_pool_wsol.clone(),
+                borrower_info.clone(),
+                borrower_operations.nonce,
+                vars.coll_change as u64,
+            )?;
         }
-        else
-        {
-            vars.new_debt = borrower_trove.decrease_trove_debt(vars.net_debt_change)
+        else if vars.coll_change > 0 {
+            trove_manager_accounts.send_sol(
+                borrower_operation_info,
+                authority_info,
+                borrower_operations.nonce,
+                borrower_wsol_token_info,
+                vars.coll_change
+            )?;
         }
 
-        let new_nicr = Self::get_new_normal_icr_from_trove_change(
-            vars.coll, 
-            vars.debt, 
-            vars.coll_change, 
-            vars.is_coll_increase, 
-            vars.net_debt_change, 
-            is_debt_increase,
-            vars.price
-        );
-        
-        if is_debt_increase{
+        if is_debt_increase {
             Self::withdraw_solusd(
-                &borrower_info,
-                &authority_info,
-                &active_pool_info,
-                &solusd_token_info,
-                &borrower_info,
-                &token_program_info,
+                borrower_operation_info,
+                authority_info,
+                solusd_token_info,
+                borrower_solusd_token_info,
+                token_program_info,
                 borrower_operations.nonce,
-                solusd_change,
-                vars.net_debt_change
-            );
+                solusd_change
+            )?;
         }
-        else
-        {
+        else if vars.net_debt_change > 0 {
             Self::repay_solusd(
-                &borrower_operation_info,
-                &authority_info,
-                &active_pool_info,
-                &solusd_token_info,
-                &borrower_info,
-                &token_program_info,
+                borrower_operation_info,
+                authority_info,
+                solusd_token_info,
+                borrower_solusd_token_info,
+                token_program_info,
                 borrower_operations.nonce,
                 vars.net_debt_change
-
-            );
+            )?;
         }
 
-        if vars.is_coll_increase {
-            active_pool.increase_coll(vars.coll_change);
-        }
-        else{
-            active_pool.send_sol(vars.coll_change);
-        }
-        borrower_operations.serialize(&mut &mut borrower_operation_info.data.borrow_mut()[..])?;
-        active_pool.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
-        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
-        // trove_manager.serialize(&mut &mut trove_manager_info.data.borrow_mut()[..])?;
-        stability_pool.serialize(&mut &mut stability_pool_info.data.borrow_mut()[..])?;
-        
-        // check if given pool token account is same with pool token account
         Ok(())
     }
 
-    /// process WithdrawFromSP instruction
+    /// process CloseTrove instruction
     pub fn process_close_trove(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
@@ -799,37 +1197,72 @@ impl Processor {
         let borrower_trove_info = next_account_info(account_info_iter)?;
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
+        let price_feed_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
+        let borrower_wsol_token_info = next_account_info(account_info_iter)?;
+
+        let trove_manager_accounts = TroveManagerAccounts {
+            program: next_account_info(account_info_iter)?,
+            trove_manager: trove_manager_info,
+            authority: next_account_info(account_info_iter)?,
+            active_pool: active_pool_info,
+            default_pool: default_pool_info,
+            coll_surplus_pool: next_account_info(account_info_iter)?,
+            active_pool_wsol: next_account_info(account_info_iter)?,
+            default_pool_wsol: next_account_info(account_info_iter)?,
+            coll_surplus_pool_wsol: next_account_info(account_info_iter)?,
+            token_program: token_program_info,
+        };
 
-        
         let clock = &Clock::from_account_info(clock_info)?;
         
-        let borrower_operations = BorrowerOperations::try_from_slice(&borrower_operation_info.data.borrow())?;
-        let mut borrower_trove = Trove::try_from_slice(&borrower_trove_info.data.borrow())?;
-        let mut active_pool = ActivePool::try_from_slice(&active_pool_info.data.borrow())?;
-        let mut trove_manager = TroveManager::try_from_slice(&trove_manager_info.data.borrow())?;
-        let mut default_pool = DefaultPool::try_from_slice(&default_pool_info.data.borrow())?;
-        let mut reward_snapshot = RewardSnapshot::try_from_slice(&borrower_trove_info.data.borrow())?;
-        let stability_pool = StabilityPool::try_from_slice(&stability_pool_info.data.borrow())?;
+        let borrower_operations = try_from_slice_unchecked::<BorrowerOperations>(&borrower_operation_info.data.borrow())?;
+        let stability_pool = try_from_slice_unchecked::<StabilityPool>(&stability_pool_info.data.borrow())?;
         
-        if *authority_info.key != Self::authority_id(program_id, borrower_info.key, borrower_operations.nonce)? {
+        if *authority_info.key != Self::authority_id(program_id, borrower_operation_info.key, borrower_operations.nonce)? {
             return Err(LiquityError::InvalidProgramAddress.into());
         }
+        if *trove_manager_info.key != borrower_operations.trove_manager_id ||
+            *active_pool_info.key != borrower_operations.active_pool_id ||
+            *default_pool_info.key != borrower_operations.default_pool_id ||
+            *gas_pool_info.key != borrower_operations.gas_pool_id ||
+            *solusd_token_info.key != borrower_operations.solusd_token_id
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        trove_manager_accounts.assert_program()?;
+        if !borrower_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, borrower_trove_info.key, borrower_info.key, TROVE_PREFIX)?;
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, rewardsnapshot_info.key, borrower_info.key, REWARD_SNAPSHOT_PREFIX)?;
 
-        let price = get_market_price(
-            stability_pool.oracle_program_id,
-            stability_pool.quote_currency,
+        let price = read_price(
+            price_feed_info,
+            trove_manager_info,
+            &borrower_operations.trove_manager_id,
             pyth_product_info,
             pyth_price_info,
             clock
         )?;
 
-        apply_pending_rewards(
-            &trove_manager, 
-            &mut borrower_trove, 
-            &mut reward_snapshot, 
-            &mut default_pool, 
-            &mut active_pool);
+        // pending rewards move wSOL from the default pool, so the trove manager applies them
+        trove_manager_accounts.apply_pending_rewards(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            borrower_trove_info,
+            rewardsnapshot_info
+        )?;
+
+        let borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+        let active_pool = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let default_pool = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow())?;
+
+        if !borrower_trove.is_active() {
+            return Err(LiquityError::ErrorTroveisNotActive.into());
+        }
+
         let debt = borrower_trove.debt;
         let coll = borrower_trove.coll;
 
@@ -841,16 +1274,21 @@ impl Processor {
             debt, 
             false, 
             price);
-        remove_stake(&mut trove_manager, &mut borrower_trove);
-        borrower_trove.close_trove();
-        reward_snapshot.reset();
+
+        // the trove manager closes the trove and removes its debt from the active pool
+        trove_manager_accounts.close_trove(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            borrower_trove_info,
+            rewardsnapshot_info
+        )?;
 
         let debt = debt.checked_sub(SOLUSD_GAS_COMPENSATION).ok_or(LiquityError::ErrorMinNetDebt)?;
 
         Self::repay_solusd(
             &borrower_operation_info,
             &authority_info,
-            &active_pool_info,
             &solusd_token_info,
             &borrower_info,
             &token_program_info,
@@ -861,23 +1299,72 @@ impl Processor {
         Self::repay_solusd(
             &borrower_operation_info,
             &authority_info,
-            &active_pool_info,
             &solusd_token_info,
             &gas_pool_info,
             &token_program_info,
             borrower_operations.nonce,
             SOLUSD_GAS_COMPENSATION
         )?;
-        active_pool.send_sol(coll);
 
-        borrower_operations.serialize(&mut &mut borrower_operation_info.data.borrow_mut()[..])?;
-        active_pool.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
-        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
-        trove_manager.serialize(&mut &mut trove_manager_info.data.borrow_mut()[..])?;
-        stability_pool.serialize(&mut &mut stability_pool_info.data.borrow_mut()[..])?;
+        // send the collateral back to the borrower
+        trove_manager_accounts.send_sol(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            borrower_wsol_token_info,
+            coll
+        )
+    }
 
-        // check if given pool token account is same with pool token account
-        Ok(())
-        
+    /// process ClaimCollSurplus instruction
+    pub fn process_claim_coll_surplus(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let borrower_operation_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let borrower_info = next_account_info(account_info_iter)?;
+        let coll_surplus_info = next_account_info(account_info_iter)?;
+        let borrower_wsol_token_info = next_account_info(account_info_iter)?;
+
+        let trove_manager_accounts = TroveManagerAccounts {
+            program: next_account_info(account_info_iter)?,
+            trove_manager: next_account_info(account_info_iter)?,
+            authority: next_account_info(account_info_iter)?,
+            active_pool: next_account_info(account_info_iter)?,
+            default_pool: next_account_info(account_info_iter)?,
+            coll_surplus_pool: next_account_info(account_info_iter)?,
+            active_pool_wsol: next_account_info(account_info_iter)?,
+            default_pool_wsol: next_account_info(account_info_iter)?,
+            coll_surplus_pool_wsol: next_account_info(account_info_iter)?,
+            token_program: next_account_info(account_info_iter)?,
+        };
+
+        let borrower_operations = try_from_slice_unchecked::<BorrowerOperations>(&borrower_operation_info.data.borrow())?;
+
+        if *authority_info.key != Self::authority_id(program_id, borrower_operation_info.key, borrower_operations.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+        if *trove_manager_accounts.trove_manager.key != borrower_operations.trove_manager_id ||
+            *trove_manager_accounts.coll_surplus_pool.key != borrower_operations.coll_surplus_pool_id
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        trove_manager_accounts.assert_program()?;
+        if !borrower_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        // the coll surplus account is a PDA of the trove manager, which records the surplus of liquidations and redemptions in it
+        Self::assert_pda(trove_manager_accounts.program.key, borrower_operation_info.key, coll_surplus_info.key, borrower_info.key, COLL_SURPLUS_PREFIX)?;
+
+        // send the claimable SOL from the coll surplus pool to the borrower
+        trove_manager_accounts.claim_coll_surplus(
+            borrower_operation_info,
+            authority_info,
+            borrower_operations.nonce,
+            coll_surplus_info,
+            borrower_wsol_token_info
+        )
     }
 }
@@ -35,6 +35,26 @@ pub const BETA:u128 = 2;
 
 pub const SCALE_FACTOR:u128 = 1_000_000_000;
 
+// Oracle guards: max age of the aggregate pyth price in slots, and max confidence interval relative to the price (2%)
+pub const MAX_PRICE_AGE_SLOTS: u64 = 5;
+pub const MAX_PRICE_CONF_RATIO: u128 = DECIMAL_PRECISION / 100 * 2;
+// Currency the collateral price must be quoted in, "USD" null padded
+pub const USD_QUOTE_CURRENCY: [u8; 32] = *b"USD\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+// Default price feed limits: max relative change of the pyth price from the last good price (50%), max relative
+// difference between the pyth and the fallback prices to trust both (5%), and max age of the fallback price in slots
+pub const DEFAULT_MAX_PRICE_DEVIATION: u128 = DECIMAL_PRECISION / 2;
+pub const DEFAULT_MAX_PRICE_DIFFERENCE_BETWEEN_SOURCES: u128 = DECIMAL_PRECISION / 100 * 5;
+pub const DEFAULT_MAX_FALLBACK_PRICE_AGE_SLOTS: u64 = 150;
+
+// Operation classes the guardian can pause, as bits of the pause bitmap. Repaying and closing troves can not be paused
+pub const PAUSE_BORROW: u8 = 1 << 0;
+pub const PAUSE_WITHDRAW_COLL: u8 = 1 << 1;
+pub const PAUSE_LIQUIDATE: u8 = 1 << 2;
+pub const PAUSE_REDEEM: u8 = 1 << 3;
+pub const PAUSE_SP_DEPOSIT: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 = PAUSE_BORROW | PAUSE_WITHDRAW_COLL | PAUSE_LIQUIDATE | PAUSE_REDEEM | PAUSE_SP_DEPOSIT;
+
 // Minimum amount of net LUSD debt a trove must have
 //pub const MIN_NET_DEBT: u128 = 1800e18;
 pub const MIN_NET_DEBT: u128 = 0;
@@ -45,4 +65,12 @@ pub const ZERO_ADDRESS:&str = "00000000000000000000000000000000000000000000";
 
 
 pub const ISSUANCE_FACTOR:u128 = 999998681227695000;
-pub const SOLID_SUPPLY_CAP:u128 = 32_000_000_000_000_000_000_000_000;
\ No newline at end of file
+pub const SOLID_SUPPLY_CAP:u128 = 32_000_000_000_000_000_000_000_000;
+
+// Layout version of the Trove account
+pub const TROVE_VERSION: u8 = 1;
+
+// Seed prefixes of the per borrower accounts: the trove, its reward snapshot and the collateral surplus
+pub const TROVE_PREFIX: &str = "liquity-trove";
+pub const REWARD_SNAPSHOT_PREFIX: &str = "liquity-trove-reward-snapshot";
+pub const COLL_SURPLUS_PREFIX: &str = "liquity-coll-surplus";
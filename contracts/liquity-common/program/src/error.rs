@@ -121,6 +121,86 @@ pub enum LiquityError {
     /// BorrowerOperations: Cannot redeem when ICR < MCR
     #[error("BorrowerOperations: Cannot redeem when ICR < MCR")]
     ICROverFlow,
+
+    /// TroveManager: Troves must be passed in ascending ICR order
+    #[error("TroveManager: Troves must be passed in ascending ICR order")]
+    InvalidRedemptionOrder,
+
+    /// TroveManager: Reward snapshot does not belong to the trove
+    #[error("TroveManager: Reward snapshot does not belong to the trove")]
+    InvalidRewardSnapshot,
+
+    /// TroveManager: Unable to redeem any amount
+    #[error("TroveManager: Unable to redeem any amount")]
+    UnableToRedeemAnyAmount,
+
+    /// SOLIDStaking: User must have a non-zero stake
+    #[error("SOLIDStaking: User must have a non-zero stake")]
+    NoStake,
+
+    /// BorrowerOps: Trove account has an outdated layout version
+    #[error("BorrowerOps: Trove account has an outdated layout version")]
+    InvalidTroveVersion,
+
+    /// CollSurplusPool: No collateral available to claim
+    #[error("CollSurplusPool: No collateral available to claim")]
+    NoCollAvailableToClaim,
+
+    /// CollSurplusPool: Collateral surplus account does not belong to the borrower
+    #[error("CollSurplusPool: Collateral surplus account does not belong to the borrower")]
+    InvalidCollSurplus,
+
+    /// Oracle price is stale
+    #[error("Oracle price is stale")]
+    StalePrice,
+
+    /// Oracle price confidence interval is too wide
+    #[error("Oracle price confidence interval is too wide")]
+    PriceUncertain,
+
+    /// Oracle price is not trading
+    #[error("Oracle price is not trading")]
+    PriceNotTrading,
+
+    /// Oracle quote currency does not match the expected currency
+    #[error("Oracle quote currency does not match the expected currency")]
+    InvalidQuoteCurrency,
+
+    /// Price feed does not belong to the trove manager
+    #[error("Price feed does not belong to the trove manager")]
+    InvalidPriceFeed,
+
+    /// Price feed limits are invalid
+    #[error("Price feed limits are invalid")]
+    InvalidPriceFeedConfig,
+
+    /// Fallback price must be positive
+    #[error("Fallback price must be positive")]
+    InvalidFallbackPrice,
+
+    /// Operation is paused by the guardian
+    #[error("Operation is paused by the guardian")]
+    ProtocolPaused,
+
+    /// Collateral withdrawal not permitted Recovery Mode
+    #[error("BorrowerOps: Collateral withdrawal not permitted Recovery Mode")]
+    ErrorCollWithdrawalInRecoveryMode,
+
+    /// Cannot decrease your Trove's ICR in Recovery Mode
+    #[error("BorrowerOps: Cannot decrease your Trove's ICR in Recovery Mode")]
+    ErrorICRDecreaseInRecoveryMode,
+
+    /// An operation that would result in TCR < CCR is not permitted
+    #[error("BorrowerOps: An operation that would result in TCR < CCR is not permitted")]
+    ErrorTCRBelowCCR,
+
+    /// Amount repaid must not be larger than the Trove's debt
+    #[error("BorrowerOps: Amount repaid must not be larger than the Trove's debt")]
+    ErrorInvalidRepayment,
+
+    /// There are no other troves left to redistribute the liquidated debt to
+    #[error("TroveManager: Only one trove in the system")]
+    OnlyOneTroveInSystem,
 }
 impl From<LiquityError> for ProgramError {
     fn from(e: LiquityError) -> Self {
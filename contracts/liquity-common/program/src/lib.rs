@@ -6,4 +6,5 @@ pub mod math;
 pub mod liquity_math;
 pub mod state;
 pub mod utils;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod price_feed;
\ No newline at end of file
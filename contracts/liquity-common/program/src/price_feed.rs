@@ -0,0 +1,290 @@
+/// price feed with a fallback source and a last good price
+
+use {
+    crate::{
+        constant::DECIMAL_PRECISION,
+        error::LiquityError,
+        liquity_math::{min, get_absolute_difference},
+        state::{PriceFeed, PriceFeedStatus},
+        utils::get_market_price,
+    },
+    borsh::BorshSerialize,
+    solana_program::{
+        account_info::AccountInfo,
+        borsh::try_from_slice_unchecked,
+        clock::Clock,
+        msg,
+        program_error::ProgramError,
+        pubkey::Pubkey,
+    },
+};
+
+/*
+* Pyth is broken when its price can not be read or fails the oracle guards, and frozen when its aggregate price is
+* stale. The fallback is broken while no price was pushed, and frozen when the pushed price is older than the max
+* fallback age. A pyth price moving more than the max deviation from the last good price is not trusted either.
+* Depending on the status, the feed switches between the sources and freezes on the last good price when neither of
+* them can be trusted, until both sources are live again and report similar prices.
+*/
+
+enum SourceResponse {
+    Working(u128),
+    Frozen,
+    Broken,
+}
+impl SourceResponse {
+    fn is_broken(&self) -> bool {
+        matches!(self, SourceResponse::Broken)
+    }
+    fn is_frozen(&self) -> bool {
+        matches!(self, SourceResponse::Frozen)
+    }
+}
+
+/// fetch the price of the price feed account of a trove manager and store its new status.
+/// Only the trove manager program owning the feed can write it
+pub fn fetch_price(
+    program_id: &Pubkey,
+    price_feed_info: &AccountInfo,
+    trove_manager_id: &Pubkey,
+    pyth_product_info: &AccountInfo,
+    pyth_price_info: &AccountInfo,
+    clock: &Clock,
+) -> Result<u128, ProgramError> {
+    if price_feed_info.owner != program_id {
+        return Err(LiquityError::InvalidOwner.into());
+    }
+    let mut price_feed = try_from_slice_unchecked::<PriceFeed>(&price_feed_info.data.borrow())?;
+    if price_feed.trove_manager_id != *trove_manager_id {
+        return Err(LiquityError::InvalidPriceFeed.into());
+    }
+    let price = price_feed.fetch_price(pyth_product_info, pyth_price_info, clock)?;
+    price_feed.serialize(&mut &mut price_feed_info.data.borrow_mut()[..])?;
+    Ok(price)
+}
+
+/// read the price of the price feed account of a trove manager from another program, without storing the new status.
+/// The feed must be owned by the program owning the trove manager account
+pub fn read_price(
+    price_feed_info: &AccountInfo,
+    trove_manager_info: &AccountInfo,
+    trove_manager_id: &Pubkey,
+    pyth_product_info: &AccountInfo,
+    pyth_price_info: &AccountInfo,
+    clock: &Clock,
+) -> Result<u128, ProgramError> {
+    if trove_manager_info.key != trove_manager_id {
+        return Err(LiquityError::InvalidAccountInput.into());
+    }
+    if price_feed_info.owner != trove_manager_info.owner {
+        return Err(LiquityError::InvalidOwner.into());
+    }
+    let mut price_feed = try_from_slice_unchecked::<PriceFeed>(&price_feed_info.data.borrow())?;
+    if price_feed.trove_manager_id != *trove_manager_id {
+        return Err(LiquityError::InvalidPriceFeed.into());
+    }
+    price_feed.fetch_price(pyth_product_info, pyth_price_info, clock)
+}
+
+impl PriceFeed {
+    /// price of the collateral, updates the status and the last good price of the feed
+    pub fn fetch_price(&mut self, pyth_product_info: &AccountInfo, pyth_price_info: &AccountInfo, clock: &Clock) -> Result<u128, ProgramError> {
+        if *pyth_product_info.key != self.pyth_product_id || *pyth_price_info.key != self.pyth_price_id {
+            msg!("Pyth accounts provided do not match the price feed");
+            return Err(LiquityError::InvalidOracleConfig.into());
+        }
+        let pyth = match get_market_price(self.oracle_program_id, self.quote_currency, pyth_product_info, pyth_price_info, clock) {
+            Ok(price) => SourceResponse::Working(price),
+            Err(err) if err == LiquityError::StalePrice.into() => SourceResponse::Frozen,
+            Err(_) => SourceResponse::Broken,
+        };
+        let fallback = self.get_fallback_response(clock);
+        Ok(self.update_price(pyth, fallback, clock))
+    }
+
+    /// switch the status of the feed depending on the responses of the sources, returns the price to use
+    fn update_price(&mut self, pyth: SourceResponse, fallback: SourceResponse, clock: &Clock) -> u128 {
+        match self.status {
+            PriceFeedStatus::PythWorking => {
+                if pyth.is_broken() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::BothUntrusted;
+                        return self.last_good_price;
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                    return self.store_price(&fallback, clock);
+                }
+                if pyth.is_frozen() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                        return self.last_good_price;
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythFrozen;
+                    return self.store_price(&fallback, clock);
+                }
+                if self.is_price_change_above_max(&pyth) {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::BothUntrusted;
+                        return self.last_good_price;
+                    }
+                    if fallback.is_frozen() {
+                        self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                        return self.last_good_price;
+                    }
+                    if self.are_both_sources_similar(&pyth, &fallback) {
+                        return self.store_price(&pyth, clock);
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                    return self.store_price(&fallback, clock);
+                }
+                if fallback.is_broken() {
+                    self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                }
+                self.store_price(&pyth, clock)
+            }
+            PriceFeedStatus::UsingFallbackPythUntrusted => {
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                if fallback.is_broken() {
+                    self.status = PriceFeedStatus::BothUntrusted;
+                    return self.last_good_price;
+                }
+                self.store_price(&fallback, clock)
+            }
+            PriceFeedStatus::BothUntrusted => {
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                self.last_good_price
+            }
+            PriceFeedStatus::UsingFallbackPythFrozen => {
+                if pyth.is_broken() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::BothUntrusted;
+                        return self.last_good_price;
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                    return self.store_price(&fallback, clock);
+                }
+                if pyth.is_frozen() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                        return self.last_good_price;
+                    }
+                    return self.store_price(&fallback, clock);
+                }
+                // pyth is live again
+                if fallback.is_broken() {
+                    self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                    return self.store_price(&pyth, clock);
+                }
+                if fallback.is_frozen() {
+                    return self.last_good_price;
+                }
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                self.store_price(&fallback, clock)
+            }
+            PriceFeedStatus::UsingPythFallbackUntrusted => {
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                if pyth.is_broken() || self.is_price_change_above_max(&pyth) {
+                    self.status = PriceFeedStatus::BothUntrusted;
+                    return self.last_good_price;
+                }
+                // a frozen pyth keeps the status and the last good price, the feed uses pyth again once it is live
+                self.store_price(&pyth, clock)
+            }
+        }
+    }
+
+    fn get_fallback_response(&self, clock: &Clock) -> SourceResponse {
+        if self.fallback_price == 0 || self.fallback_price_slot == 0 {
+            return SourceResponse::Broken;
+        }
+        if clock.slot.saturating_sub(self.fallback_price_slot) > self.max_fallback_price_age_slots {
+            return SourceResponse::Frozen;
+        }
+        SourceResponse::Working(self.fallback_price)
+    }
+
+    /// store the price of a live source as the last good price, frozen or broken sources keep the last good price
+    fn store_price(&mut self, source: &SourceResponse, clock: &Clock) -> u128 {
+        if let SourceResponse::Working(price) = source {
+            self.last_good_price = *price;
+            self.last_good_price_slot = clock.slot;
+        }
+        self.last_good_price
+    }
+
+    fn is_price_change_above_max(&self, pyth: &SourceResponse) -> bool {
+        match pyth {
+            SourceResponse::Working(price) => {
+                get_absolute_difference(*price, self.last_good_price) * DECIMAL_PRECISION > self.max_price_deviation * self.last_good_price
+            }
+            _ => false,
+        }
+    }
+
+    /// both sources are live and their prices are within the max difference between sources
+    fn are_both_sources_similar(&self, pyth: &SourceResponse, fallback: &SourceResponse) -> bool {
+        match (pyth, fallback) {
+            (SourceResponse::Working(pyth_price), SourceResponse::Working(fallback_price)) => {
+                let min_price = min(*pyth_price, *fallback_price);
+                get_absolute_difference(*pyth_price, *fallback_price) * DECIMAL_PRECISION <= self.max_price_difference_between_sources * min_price
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frozen_pyth_recovers_without_fallback() {
+        let clock = Clock { slot: 10, ..Clock::default() };
+        let mut price_feed = PriceFeed {
+            status: PriceFeedStatus::UsingPythFallbackUntrusted,
+            last_good_price: 100 * DECIMAL_PRECISION,
+            max_price_deviation: DECIMAL_PRECISION / 2,
+            max_price_difference_between_sources: DECIMAL_PRECISION / 20,
+            ..PriceFeed::default()
+        };
+
+        // pyth is frozen and the fallback is broken, the feed keeps the last good price and waits for pyth
+        let price = price_feed.update_price(SourceResponse::Frozen, SourceResponse::Broken, &clock);
+        assert_eq!(price, 100 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::UsingPythFallbackUntrusted);
+
+        // pyth is live again
+        let price = price_feed.update_price(SourceResponse::Working(101 * DECIMAL_PRECISION), SourceResponse::Broken, &clock);
+        assert_eq!(price, 101 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::UsingPythFallbackUntrusted);
+        assert_eq!(price_feed.last_good_price_slot, 10);
+
+        // the fallback is back with a similar price
+        let price = price_feed.update_price(
+            SourceResponse::Working(102 * DECIMAL_PRECISION),
+            SourceResponse::Working(100 * DECIMAL_PRECISION),
+            &clock,
+        );
+        assert_eq!(price, 102 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::PythWorking);
+
+        // a broken pyth can not be used
+        price_feed.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+        let price = price_feed.update_price(SourceResponse::Broken, SourceResponse::Broken, &clock);
+        assert_eq!(price, 102 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::BothUntrusted);
+    }
+}
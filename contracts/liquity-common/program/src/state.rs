@@ -5,6 +5,9 @@
 use {
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     solana_program::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        program_error::ProgramError,
         pubkey::{Pubkey},
     },
     num_traits::FromPrimitive,
@@ -12,7 +15,9 @@ use {
 };
 use crate::{
     constant::*,
-    liquity_math::*
+    error::LiquityError,
+    liquity_math::*,
+    utils::PoolWsolAccounts,
 };
 use std::str::FromStr;
 use std::convert::TryFrom;
@@ -33,6 +38,9 @@ pub struct StabilityPool {
     /// solUSD token's mint address
     pub sol_usd_pool_token_pubkey: Pubkey,
 
+    /// wSOL token account holding the SOL gains of the depositors
+    pub sol_pool_token_pubkey: Pubkey,
+
     /// Borrower Operations pubkey
     pub borrower_operations_pubkey: Pubkey,
 
@@ -68,27 +76,55 @@ pub struct StabilityPool {
     /// e.g. "USD" null padded (`*b"USD\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0"`) or a SPL token mint pubkey
     pub quote_currency: [u8; 32],
 
+    /// key allowed to pause the deposits
+    pub guardian: Pubkey,
+    /// bitmap of the paused operation classes
+    pub paused_operations: u8,
 }
 impl StabilityPool{
-    pub fn offset(&mut self, debt_to_offset: u128, coll_to_add: u128, solid_issuance: u128, active_pool:&mut ActivePool, epoch_to_scale:&mut EpochToScale){
+    pub fn offset<'a>(
+        &mut self,
+        debt_to_offset: u128,
+        coll_to_add: u128,
+        solid_issuance: u128,
+        active_pool:&mut ActivePool,
+        epoch_to_scale:&mut EpochToScale,
+        pool_wsol:&PoolWsolAccounts<'a>,
+        stability_pool_wsol:&AccountInfo<'a>,
+        stability_pool_solusd:&AccountInfo<'a>,
+        solusd_mint:&AccountInfo<'a>,
+    )->ProgramResult{
         let total_solusd = self.total_sol_usd_deposits;
         if total_solusd == 0 || debt_to_offset == 0 {
-            return;
+            return Ok(());
         }
         self.trigger_solid_issuance(solid_issuance, epoch_to_scale);
         let (sol_gain_per_unit_staked, solusd_loss_per_unit_staked) = self.compute_rewards_per_unit_staked(coll_to_add, debt_to_offset, total_solusd);
         self.update_reward_sum_and_product(sol_gain_per_unit_staked, solusd_loss_per_unit_staked, epoch_to_scale);
 
-        self.move_offset_coll_and_debt(coll_to_add, debt_to_offset, active_pool);
+        self.move_offset_coll_and_debt(coll_to_add, debt_to_offset, active_pool, pool_wsol, stability_pool_wsol, stability_pool_solusd, solusd_mint)
     }
-    pub fn move_offset_coll_and_debt(&mut self, coll_to_add: u128, debt_to_offset: u128, active_pool:&mut ActivePool){
+    pub fn move_offset_coll_and_debt<'a>(
+        &mut self,
+        coll_to_add: u128,
+        debt_to_offset: u128,
+        active_pool:&mut ActivePool,
+        pool_wsol:&PoolWsolAccounts<'a>,
+        stability_pool_wsol:&AccountInfo<'a>,
+        stability_pool_solusd:&AccountInfo<'a>,
+        solusd_mint:&AccountInfo<'a>,
+    )->ProgramResult{
         active_pool.decrease_solusd_debt(debt_to_offset);
         self.decrease_solusd(debt_to_offset);
 
         // Burn the debt that was successfully offset
-        //lusdToken.burn(address(this), _debtToOffset);
+        //lusdToken.burn(address(this), _debtToOffset); -- implemented, the trove manager authority is the delegate of the pool solUSD token account
+        pool_wsol.burn_solusd(solusd_mint, stability_pool_solusd, debt_to_offset)?;
 
-        //activePoolCached.sendETH(address(this), _collToAdd);
+        //activePoolCached.sendETH(address(this), _collToAdd); --implemented
+        active_pool.send_sol(coll_to_add);
+        self.sol += coll_to_add;
+        pool_wsol.send_sol(&pool_wsol.active_pool, stability_pool_wsol, coll_to_add)
     }
     pub fn decrease_solusd(&mut self, amount: u128){
         let new_total_solusd_deposits = self.total_sol_usd_deposits - amount;
@@ -570,6 +606,10 @@ pub struct TroveManager {
     pub last_sol_error_redistribution:u128,
     pub last_solusd_debt_error_redistribution:u128,
 
+    /// key allowed to pause the trove operations
+    pub guardian: Pubkey,
+    /// bitmap of the paused operation classes
+    pub paused_operations: u8,
 } 
 impl TroveManager{
     pub fn check_recovery_mode(&self, price: u128, active_pool_data: &ActivePool, default_pool_data: &DefaultPool)->u8{
@@ -592,16 +632,13 @@ pub enum Status {
 #[repr(C)]
 #[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub struct Trove {
-<<<<<<< HEAD
-    /// account type
-    pub account_type: u8, 
-    
-    /// pool pubkey
+    /// layout version of this trove account, see `TROVE_VERSION`
+    pub version: u8,
+
+    /// BorrowerOperations pubkey this trove was opened with
     pub pool_id_pubkey:Pubkey,
 
     /// owner pubkey
-=======
->>>>>>> 5b9b54a89629943e2cd6930c9216f96b19c19c67
     pub owner_pubkey:Pubkey,
     pub status:u8,
     pub debt:u128,
@@ -609,6 +646,10 @@ pub struct Trove {
     pub stake:u128,
 }
 impl Trove {
+    /// a zeroed account was never opened, any other version than the current one is an old layout
+    pub fn is_valid_version(&self)->bool {
+        self.version == 0 || self.version == TROVE_VERSION
+    }
     pub fn is_active(&self)->bool {
         let status = Status::from_u8(self.status).unwrap();
         match status {
@@ -845,6 +886,8 @@ pub struct ActivePool {
     pub trove_manager_address: Pubkey,
     pub stability_pool_address: Pubkey,
     pub default_pool_address: Pubkey,
+    /// wSOL token account holding the pool's SOL, owned by the trove manager authority
+    pub wsol_pubkey: Pubkey,
     pub sol: u128,
     pub solusd_debt: u128,
 }
@@ -873,8 +916,8 @@ impl ActivePool{
     pub fn decrease_solusd_debt(&mut self, amount:u128){
         self.solusd_debt -= amount;
     }
-    pub fn send_sol(&mut self, _amount:u128){
-
+    pub fn send_sol(&mut self, amount:u128){
+        self.sol -= amount;
     }
 
 }
@@ -886,14 +929,49 @@ pub struct CollSurplusPool {
     pub borrower_operations_address: Pubkey,
     pub trove_manager_address: Pubkey,
     pub active_pool_address: Pubkey,
+    /// wSOL token account holding the pool's SOL, owned by the trove manager authority
+    pub wsol_pubkey: Pubkey,
     pub sol: u128,
 }
+impl CollSurplusPool{
+    /// record the collateral surplus of a borrower, the SOL itself is sent to the pool by the caller
+    pub fn account_surplus(&mut self, balance:&mut CollSurplus, amount:u128){
+        self.sol += amount;
+        balance.amount += amount;
+    }
+    /// clear the claimable collateral of a borrower, the SOL is sent to the borrower by the caller
+    pub fn claim_coll(&mut self, balance:&mut CollSurplus)->Result<u128, ProgramError>{
+        let claimable_coll = balance.amount;
+        if claimable_coll == 0 {
+            return Err(LiquityError::NoCollAvailableToClaim.into());
+        }
+        balance.amount = 0;
+        self.sol -= claimable_coll;
+        Ok(claimable_coll)
+    }
+}
+
+/// Collateral surplus of a borrower, left by a liquidation in recovery mode or a full redemption
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct CollSurplus {
+    /// pool pubkey
+    pub pool_id_pubkey:Pubkey,
+
+    /// owner pubkey
+    pub owner_pubkey:Pubkey,
+
+    /// claimable SOL
+    pub amount:u128,
+}
 
 #[repr(C)]
 #[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub struct DefaultPool {
     pub trove_manager_address: Pubkey,
     pub active_pool_address: Pubkey,
+    /// wSOL token account holding the pool's SOL, owned by the trove manager authority
+    pub wsol_pubkey: Pubkey,
     pub sol: u128,
     pub solusd_debt: u128,
 }
@@ -1087,6 +1165,12 @@ pub struct SOLIDStaking {
     /// SOLID pool token account
     pub solid_pool_token_pubkey: Pubkey,
 
+    /// solUSD fee pool token account
+    pub solusd_pool_token_pubkey: Pubkey,
+
+    /// SOL fee pool token account
+    pub sol_pool_token_pubkey: Pubkey,
+
     /// TroveManager account
     pub trove_manager_id: Pubkey,
 
@@ -1120,6 +1204,18 @@ impl SOLIDStaking{
         }
         self.f_solusd += solusd_fee_per_solid_staked;
     }
+    pub fn get_pending_sol_gain(&self, user_deposit:&UserDeposit, snapshot:&Snapshot)->u128{
+        let f_sol_snapshot = snapshot.f_sol_snapshot;
+        user_deposit.deposit_amount as u128 * (self.f_sol - f_sol_snapshot) / DECIMAL_PRECISION
+    }
+    pub fn get_pending_solusd_gain(&self, user_deposit:&UserDeposit, snapshot:&Snapshot)->u128{
+        let f_solusd_snapshot = snapshot.f_solusd_snapshot;
+        user_deposit.deposit_amount as u128 * (self.f_solusd - f_solusd_snapshot) / DECIMAL_PRECISION
+    }
+    pub fn update_user_snapshot(&self, snapshot:&mut Snapshot){
+        snapshot.f_sol_snapshot = self.f_sol;
+        snapshot.f_solusd_snapshot = self.f_solusd;
+    }
 }
 
 #[repr(C)]
@@ -1146,10 +1242,10 @@ pub struct Snapshot {
     pub owner_pubkey:Pubkey,
 
     /// SOL snapshot
-    pub f_sol_snapshot:u64,
+    pub f_sol_snapshot:u128,
 
     /// solUSD snapshot
-    pub f_solusd_snapshot:u64,
+    pub f_solusd_snapshot:u128,
 }
 
 
@@ -1166,3 +1262,53 @@ pub struct EpochToScale {
     pub epoch_to_scale_to_g:u128,
 }
 
+/// Status of a price feed, mirroring the status machine of the liquity PriceFeed with pyth as the primary source
+/// and the pushed fallback price as the secondary one
+#[repr(C)]
+#[derive(FromPrimitive, Clone, Copy, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub enum PriceFeedStatus {
+    PythWorking,
+    UsingFallbackPythUntrusted,
+    BothUntrusted,
+    UsingFallbackPythFrozen,
+    UsingPythFallbackUntrusted,
+}
+impl Default for PriceFeedStatus {
+    fn default() -> Self {
+        PriceFeedStatus::PythWorking
+    }
+}
+
+/// Price source of the troves, falls back to a pushed price or to the last good price when pyth can not be trusted
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct PriceFeed {
+    /// TroveManager account the feed prices the troves of
+    pub trove_manager_id: Pubkey,
+
+    /// authority pushing the fallback price and setting the limits
+    pub authority: Pubkey,
+
+    pub oracle_program_id: Pubkey,
+    pub pyth_product_id: Pubkey,
+    pub pyth_price_id: Pubkey,
+    /// Currency market prices are quoted in
+    pub quote_currency: [u8; 32],
+
+    pub status: PriceFeedStatus,
+
+    /// last price returned by a trusted source, used while no source can be trusted
+    pub last_good_price: u128,
+    pub last_good_price_slot: u64,
+
+    /// fallback price pushed by the authority
+    pub fallback_price: u128,
+    pub fallback_price_slot: u64,
+
+    /// max relative change of the pyth price from the last good price
+    pub max_price_deviation: u128,
+    /// max relative difference between the two sources to trust both again
+    pub max_price_difference_between_sources: u128,
+    /// max age of the fallback price in slots
+    pub max_fallback_price_age_slots: u64,
+}
@@ -26,7 +26,7 @@ use {
         sysvar::{rent::Rent, Sysvar},
         program_pack::Pack,
     },
-    spl_token::state::Mint, 
+    spl_token::state::{Mint, Account}, 
 };
 use std::str::FromStr;
 use std::convert::TryInto;
@@ -161,6 +161,156 @@ pub fn token_burn<'a>(
     invoke_signed(&ix, &[mint, destination, authority, token_program], signers)
 }
 
+/// issue a spl_token `Approve` instruction.
+pub fn token_approve<'a>(
+    owner: &Pubkey,
+    token_program: AccountInfo<'a>,
+    source: AccountInfo<'a>,
+    delegate: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    nonce: u8,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    let owner_bytes = owner.to_bytes();
+    let authority_signature_seeds = [&owner_bytes[..32], &[nonce]];
+    let signers = &[&authority_signature_seeds[..]];
+
+    let ix = spl_token::instruction::approve(
+        token_program.key,
+        source.key,
+        delegate.key,
+        authority.key,
+        &[],
+        amount
+    )?;
+
+    invoke_signed(&ix, &[source, delegate, authority, token_program], signers)
+}
+
+/*
+* The SOL of the active, default and coll surplus pools is held as wSOL in token accounts owned by the trove manager
+* authority. Every change of a pool's `sol` ledger is paired with a transfer between these accounts.
+*/
+pub struct PoolWsolAccounts<'a> {
+    pub trove_manager: Pubkey,
+    pub nonce: u8,
+    pub token_program: AccountInfo<'a>,
+    pub authority: AccountInfo<'a>,
+    pub active_pool: AccountInfo<'a>,
+    pub default_pool: AccountInfo<'a>,
+    pub coll_surplus_pool: AccountInfo<'a>,
+}
+impl<'a> PoolWsolAccounts<'a> {
+    /// check the given wSOL token accounts against the ones recorded in the pools
+    pub fn new(
+        trove_manager: &Pubkey,
+        nonce: u8,
+        token_program: &AccountInfo<'a>,
+        authority: &AccountInfo<'a>,
+        active_pool_data: &ActivePool,
+        active_pool: &AccountInfo<'a>,
+        default_pool_data: &DefaultPool,
+        default_pool: &AccountInfo<'a>,
+        coll_surplus_pool_data: &CollSurplusPool,
+        coll_surplus_pool: &AccountInfo<'a>,
+    ) -> Result<PoolWsolAccounts<'a>, ProgramError> {
+        if *active_pool.key != active_pool_data.wsol_pubkey ||
+            *default_pool.key != default_pool_data.wsol_pubkey ||
+            *coll_surplus_pool.key != coll_surplus_pool_data.wsol_pubkey
+        {
+            msg!("Pool wSOL token account does not match the pool");
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        Ok(PoolWsolAccounts {
+            trove_manager: *trove_manager,
+            nonce,
+            token_program: token_program.clone(),
+            authority: authority.clone(),
+            active_pool: active_pool.clone(),
+            default_pool: default_pool.clone(),
+            coll_surplus_pool: coll_surplus_pool.clone(),
+        })
+    }
+
+    /// burn `amount` solUSD from a token account the trove manager authority is the delegate of
+    pub fn burn_solusd(&self, solusd_mint: &AccountInfo<'a>, source: &AccountInfo<'a>, amount: u128) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+        token_burn(
+            &self.trove_manager,
+            self.token_program.clone(),
+            solusd_mint.clone(),
+            source.clone(),
+            self.authority.clone(),
+            self.nonce,
+            amount.try_into().map_err(|_| LiquityError::MathOverflow)?
+        )
+    }
+
+    /// transfer `amount` wSOL out of one of the pool token accounts
+    pub fn send_sol(&self, source: &AccountInfo<'a>, destination: &AccountInfo<'a>, amount: u128) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+        token_transfer(
+            &self.trove_manager,
+            self.token_program.clone(),
+            source.clone(),
+            destination.clone(),
+            self.authority.clone(),
+            self.nonce,
+            amount.try_into().map_err(|_| LiquityError::InvalidAccountInput)?
+        )
+    }
+}
+
+/// check that a pool token account is a wSOL account held by the given authority
+pub fn assert_wsol_account(token_account_info: &AccountInfo, authority: &Pubkey) -> ProgramResult {
+    let token_account = Account::unpack(&token_account_info.data.borrow())?;
+    if token_account.mint != spl_token::native_mint::id() {
+        msg!("Pool token account is not a wSOL account");
+        return Err(LiquityError::InvalidAccountInput.into());
+    }
+    if token_account.owner != *authority {
+        return Err(LiquityError::InvalidOwner.into());
+    }
+    Ok(())
+}
+
+/// check that a token account is held by the given owner
+pub fn assert_token_account_owner(token_account_info: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    let token_account = Account::unpack(&token_account_info.data.borrow())?;
+    if token_account.owner != *owner {
+        return Err(LiquityError::InvalidOwner.into());
+    }
+    Ok(())
+}
+
+/// fail when the operation class is set in the pause bitmap
+pub fn require_not_paused(paused_operations: u8, operation: u8) -> ProgramResult {
+    if paused_operations & operation != 0 {
+        return Err(LiquityError::ProtocolPaused.into());
+    }
+    Ok(())
+}
+
+/// set the pause bitmap of a guardian protected account, the guardian must sign
+pub fn set_pause_state(guardian: &Pubkey, guardian_info: &AccountInfo, paused_operations: &mut u8, new_paused_operations: u8) -> ProgramResult {
+    if !guardian_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *guardian_info.key != *guardian {
+        return Err(LiquityError::InvalidOwner.into());
+    }
+    if new_paused_operations & !PAUSE_ALL != 0 {
+        return Err(LiquityError::InvalidAccountInput.into());
+    }
+    *paused_operations = new_paused_operations;
+    msg!("PauseStateChanged: guardian {}, paused operations {:#07b}", guardian, new_paused_operations);
+    Ok(())
+}
+
 pub fn get_redemption_fee(trove_manager: &TroveManager, sol_drawn:u128)->u128{
     calc_redemption_fee(get_redemption_rate(trove_manager), sol_drawn)
 }
@@ -175,13 +325,11 @@ pub fn get_redemption_rate(trove_manager:&TroveManager)->u128{
     calc_redemption_rate(trove_manager.base_rate)
 }
 
-pub fn  decay_base_rate_from_borrowing(trove_manager:&TroveManager, borrower_operation_info: &AccountInfo) -> Result<(), ProgramError>{
-
-    if trove_manager.borrower_operations_id != *borrower_operation_info.key
-    {
-        return Err(LiquityError::NotTroveManagerSigner.into());
-    }
-    Ok(())
+/// decay the base rate for the time passed since the last fee operation, a borrowing fee is charged at the decayed rate
+pub fn decay_base_rate_from_borrowing(trove_manager: &mut TroveManager, current_timestamp: u128){
+    let decayed_base_rate = calc_decayed_base_rate(trove_manager, current_timestamp);
+    trove_manager.base_rate = decayed_base_rate;
+    update_last_fee_op_time(trove_manager, current_timestamp);
 }
 
 pub fn calc_borrowing_rate(rate:u128)->u128{
@@ -249,8 +397,6 @@ pub fn get_pyth_product_quote_currency(pyth_product: &pyth::Product) -> Result<[
 }
 
 pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decimal, ProgramError> {
-    const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
-
     let pyth_price_data = pyth_price_info.try_borrow_data()?;
     let pyth_price = pyth::load::<pyth::Price>(&pyth_price_data)
         .map_err(|_| ProgramError::InvalidAccountData)?;
@@ -264,9 +410,14 @@ pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<De
         .slot
         .checked_sub(pyth_price.valid_slot)
         .ok_or(LiquityError::MathOverflow)?;
-    if slots_elapsed >= STALE_AFTER_SLOTS_ELAPSED {
+    if slots_elapsed >= MAX_PRICE_AGE_SLOTS {
         msg!("Oracle price is stale");
-        return Err(LiquityError::InvalidOracleConfig.into());
+        return Err(LiquityError::StalePrice.into());
+    }
+
+    if pyth_price.agg.status != pyth::PriceStatus::Trading {
+        msg!("Oracle price is not trading");
+        return Err(LiquityError::PriceNotTrading.into());
     }
 
     let price: u64 = pyth_price.agg.price.try_into().map_err(|_| {
@@ -274,6 +425,12 @@ pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<De
         LiquityError::InvalidOracleConfig
     })?;
 
+    // the confidence interval has the same exponent as the price
+    if price == 0 || pyth_price.agg.conf as u128 * DECIMAL_PRECISION / price as u128 > MAX_PRICE_CONF_RATIO {
+        msg!("Oracle price confidence interval is too wide");
+        return Err(LiquityError::PriceUncertain.into());
+    }
+
     let market_price = if pyth_price.expo >= 0 {
         let exponent = pyth_price
             .expo
@@ -345,7 +502,7 @@ pub fn get_market_price(
     let _quote_currency = get_pyth_product_quote_currency(pyth_product)?;
     if quote_currency != _quote_currency {
         msg!("Lending market quote currency does not match the oracle quote currency");
-        return Err(LiquityError::InvalidOracleConfig.into());
+        return Err(LiquityError::InvalidQuoteCurrency.into());
     }
 
     let market_price = get_pyth_price(pyth_price_info, clock)?;
@@ -407,38 +564,40 @@ pub fn update_system_snapshots_exclude_coll_reminder(
     trove_manager.total_collateral_snapshot = active_coll - coll_remainder + liquidate_coll;
 }
 
-pub fn get_total_from_batch_liquidate_normal_mode(
+/*
+* Tally the liquidation values of the given troves in normal mode. Troves that are not active or have ICR >= MCR are skipped.
+*/
+pub fn get_total_from_batch_liquidate_normal_mode<'a>(
     trove_manager_data:&mut TroveManager,
     active_pool:&mut ActivePool,
     default_pool:&mut DefaultPool,
     price:u128,
     solusd_in_stab_pool: u128,
-    borrower_address:&Pubkey,
-    borrower_trove:&mut Trove,
-    reward_snapshot:&mut RewardSnapshot
-)->LiquidationTotals{
+    troves:&mut [(Trove, RewardSnapshot, CollSurplus)],
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->Result<LiquidationTotals, ProgramError>{
     let mut vars = LocalVariablesLiquidationSequence::new();
-    let mut single_liquidation = LiquidationValues::new();
+    let mut totals = LiquidationTotals::new();
 
     vars.remaining_solusd_in_stab_pool = solusd_in_stab_pool;
-    
-    vars.user = Option::from(*borrower_address);
 
-    let mut totals = LiquidationTotals::new();
-
-    if borrower_trove.is_active() {
+    for (borrower_trove, reward_snapshot, _) in troves.iter_mut() {
+        if !borrower_trove.is_active() {
+            continue;
+        }
         vars.icr = get_current_icr(trove_manager_data, borrower_trove, reward_snapshot, price);
-        
+
         if vars.icr < MCR {
-            single_liquidation = liquidate_normal_mode(
+            let single_liquidation = liquidate_normal_mode(
                 trove_manager_data, 
                 active_pool, 
                 default_pool, 
                 borrower_trove, 
                 reward_snapshot, 
                 vars.remaining_solusd_in_stab_pool, 
-                );
-        
+                pool_wsol,
+                )?;
+
             vars.remaining_solusd_in_stab_pool -= single_liquidation.debt_to_offset;
 
             // Add liquidation values to their respective running totals
@@ -446,17 +605,22 @@ pub fn get_total_from_batch_liquidate_normal_mode(
         }
     }
 
-    totals
+    Ok(totals)
 }
-pub fn redistribute_debt_and_coll(
+pub fn redistribute_debt_and_coll<'a>(
     trove_manager:&mut TroveManager,
     active_pool:&mut ActivePool,
     default_pool:&mut DefaultPool,
     debt:u128,
-    coll:u128
-){
+    coll:u128,
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->ProgramResult{
     if debt == 0 {
-        return;
+        return Ok(());
+    }
+    // the last trove can not be liquidated by redistribution, there is no stake left to receive its debt
+    if trove_manager.total_stakes == 0 {
+        return Err(LiquityError::OnlyOneTroveInSystem.into());
     }
 
     /*
@@ -487,71 +651,84 @@ pub fn redistribute_debt_and_coll(
     active_pool.decrease_solusd_debt(debt);
     default_pool.increase_solusd_debt(debt);
 
-    active_pool.sol -= coll;
+    active_pool.send_sol(coll);
     default_pool.sol += coll;
-
+    pool_wsol.send_sol(&pool_wsol.active_pool, &pool_wsol.default_pool, coll)
 }
-pub fn get_total_from_batch_liquidate_recovery_mode(
+/*
+* Tally the liquidation values of the given troves in recovery mode. The system TCR is tracked through the sequence,
+* and once the liquidations bring the system back to normal mode, only troves with ICR < MCR are liquidated.
+*
+* The collateral surplus of a capped liquidation is recorded for the borrower in the CollSurplusPool, the caller
+* moves the total surplus to the pool.
+*/
+pub fn get_total_from_batch_liquidate_recovery_mode<'a>(
     trove_manager_data:&mut TroveManager,
     active_pool:&mut ActivePool,
     default_pool:&mut DefaultPool,
+    coll_surplus_pool:&mut CollSurplusPool,
     price:u128,
     solusd_in_stab_pool: u128,
-    borrower_address:&Pubkey,
-    borrower_trove:&mut Trove,
-    reward_snapshot:&mut RewardSnapshot
-)->LiquidationTotals{
+    troves:&mut [(Trove, RewardSnapshot, CollSurplus)],
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->Result<LiquidationTotals, ProgramError>{
     let mut vars = LocalVariablesLiquidationSequence::new();
-    let mut single_liquidation = LiquidationValues::new();
+    let mut totals = LiquidationTotals::new();
 
     vars.remaining_solusd_in_stab_pool = solusd_in_stab_pool;
     vars.back_to_normal_mode = 0;
     vars.entire_system_debt = active_pool.solusd_debt + default_pool.solusd_debt;
     vars.entire_system_coll = active_pool.sol + default_pool.sol;
-    vars.user = Option::from(*borrower_address);
 
-    let mut totals = LiquidationTotals::new();
-
-    if borrower_trove.is_active() {
+    for (borrower_trove, reward_snapshot, coll_surplus) in troves.iter_mut() {
+        if !borrower_trove.is_active() {
+            continue;
+        }
         vars.icr = get_current_icr(trove_manager_data, borrower_trove, reward_snapshot, price);
-        
+
         if vars.back_to_normal_mode == 0 {
-            if vars.icr < MCR || vars.remaining_solusd_in_stab_pool > 0 {
-                let tcr = compute_cr(vars.entire_system_coll, vars.entire_system_debt, price);
-                single_liquidation = liquidate_recovery_mode(
-                    trove_manager_data, 
-                    active_pool, 
-                    default_pool, 
-                    borrower_trove, 
-                    reward_snapshot, 
-                    vars.icr, 
-                    vars.remaining_solusd_in_stab_pool, 
-                    tcr, 
-                    price);
-                
-                    // update aggregate trackers
-                    vars.remaining_solusd_in_stab_pool -= single_liquidation.debt_to_offset;
-                    vars.entire_system_debt -= single_liquidation.debt_to_offset;
-                    vars.entire_system_coll -= vars.entire_system_coll - single_liquidation.coll_to_send_to_sp - single_liquidation.coll_gas_compensation - single_liquidation.coll_surplus;
-
-                    // Add liquidation values to their respective running totals
-                    
-                    add_liquidation_values_to_totals(&mut totals, &single_liquidation);
-                    vars.back_to_normal_mode = check_potential_not_recovery_mode(trove_manager_data, vars.entire_system_coll, vars.entire_system_debt, price);
-                    
+            // Skip this trove if ICR is greater than MCR and Stability Pool is empty
+            if vars.icr >= MCR && vars.remaining_solusd_in_stab_pool == 0 {
+                continue;
             }
-            else if vars.back_to_normal_mode == 1 && vars.icr < MCR {
-                single_liquidation = liquidate_normal_mode(trove_manager_data, active_pool, default_pool, borrower_trove, reward_snapshot, vars.remaining_solusd_in_stab_pool);
-                vars.remaining_solusd_in_stab_pool -= single_liquidation.debt_to_offset;
 
-                // Add liquidation values to their respective running totals
-                add_liquidation_values_to_totals(&mut totals, &single_liquidation);
+            let tcr = compute_cr(vars.entire_system_coll, vars.entire_system_debt, price);
+            let single_liquidation = liquidate_recovery_mode(
+                trove_manager_data, 
+                active_pool, 
+                default_pool, 
+                borrower_trove, 
+                reward_snapshot, 
+                vars.icr, 
+                vars.remaining_solusd_in_stab_pool, 
+                tcr, 
+                price,
+                pool_wsol)?;
+
+            // update aggregate trackers
+            vars.remaining_solusd_in_stab_pool -= single_liquidation.debt_to_offset;
+            vars.entire_system_debt -= single_liquidation.debt_to_offset;
+            vars.entire_system_coll -= single_liquidation.coll_to_send_to_sp + single_liquidation.coll_gas_compensation + single_liquidation.coll_surplus;
 
+            if single_liquidation.coll_surplus > 0 {
+                coll_surplus_pool.account_surplus(coll_surplus, single_liquidation.coll_surplus);
             }
+
+            // Add liquidation values to their respective running totals
+            add_liquidation_values_to_totals(&mut totals, &single_liquidation);
+
+            vars.back_to_normal_mode = check_potential_not_recovery_mode(trove_manager_data, vars.entire_system_coll, vars.entire_system_debt, price);
+        }
+        else if vars.back_to_normal_mode == 1 && vars.icr < MCR {
+            let single_liquidation = liquidate_normal_mode(trove_manager_data, active_pool, default_pool, borrower_trove, reward_snapshot, vars.remaining_solusd_in_stab_pool, pool_wsol)?;
+            vars.remaining_solusd_in_stab_pool -= single_liquidation.debt_to_offset;
+
+            // Add liquidation values to their respective running totals
+            add_liquidation_values_to_totals(&mut totals, &single_liquidation);
         }
     }
 
-    totals
+    Ok(totals)
 }
 
 pub fn check_potential_not_recovery_mode(trove_manager:&TroveManager, entire_system_coll:u128, entire_system_debt:u128, price:u128)->u8{
@@ -569,14 +746,15 @@ pub fn add_liquidation_values_to_totals(totals:&mut LiquidationTotals, single_li
     totals.total_coll_to_redistribute += single_liquidation.coll_to_redistribute;
     totals.total_coll_surplus += single_liquidation.coll_surplus;
 }
-pub fn liquidate_normal_mode(
+pub fn liquidate_normal_mode<'a>(
     trove_manager: &mut TroveManager,
     active_pool:&mut ActivePool,
     default_pool:&mut DefaultPool,
     borrower_trove:&mut Trove,
     reward_snapshots:&mut RewardSnapshot,
     _solusd_in_stab_pool:u128,
-)->LiquidationValues{
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->Result<LiquidationValues, ProgramError>{
     let mut vars = LocalVariablesInnerSingleLiquidateFunction::new();
     let mut single_liquidation = LiquidationValues::new();
 
@@ -587,7 +765,7 @@ pub fn liquidate_normal_mode(
     vars.pending_debt_reward = pending_debt_reward;
     vars.pending_coll_reward = pending_coll_reward;
 
-    move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool);
+    move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool, pool_wsol)?;
     remove_stake(trove_manager,borrower_trove);
 
     single_liquidation.coll_gas_compensation = get_coll_gas_compensation(single_liquidation.entire_trove_coll);
@@ -600,11 +778,12 @@ pub fn liquidate_normal_mode(
     single_liquidation.debt_to_redistribute = _debt_to_redistribute;
     single_liquidation.coll_to_redistribute = _coll_to_liquidate;
 
-    //_closeTrove(_borrower, Status.closedByLiquidation); --in frontend
-    return single_liquidation;
+    close_trove(borrower_trove, reward_snapshots);
+    borrower_trove.status = Status::ClosedByLiquidation as u8;
+    Ok(single_liquidation)
 
 }
-pub fn liquidate_recovery_mode(
+pub fn liquidate_recovery_mode<'a>(
     trove_manager: &mut TroveManager,
     active_pool:&mut ActivePool,
     default_pool:&mut DefaultPool,
@@ -613,9 +792,9 @@ pub fn liquidate_recovery_mode(
     _icr:u128,
     _solusd_in_stab_pool:u128,
     _tcr:u128,
-    _price:u128
-
-)->LiquidationValues{
+    _price:u128,
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->Result<LiquidationValues, ProgramError>{
     let mut vars = LocalVariablesInnerSingleLiquidateFunction::new();
     let mut single_liquidation = LiquidationValues::new();
 
@@ -632,7 +811,7 @@ pub fn liquidate_recovery_mode(
 
     // If ICR <= 100%, purely redistribute the Trove across all active Troves
     if _icr <= _100PCT {
-        move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool);
+        move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool, pool_wsol)?;
         remove_stake(trove_manager,borrower_trove);
 
         single_liquidation.debt_to_offset = 0;
@@ -641,13 +820,20 @@ pub fn liquidate_recovery_mode(
         single_liquidation.coll_to_redistribute = vars.coll_to_liquidate;
 
         close_trove(borrower_trove, reward_snapshots);
+        borrower_trove.status = Status::ClosedByLiquidation as u8;
     }
     else if (_icr > _100PCT) && (_icr < MCR) {
-        move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool);
+        move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool, pool_wsol)?;
         remove_stake(trove_manager,borrower_trove);
 
         let (_debt_to_offset, _coll_to_send_to_sp, _debt_to_redistribute, _coll_to_liquidate) = get_offset_and_redistribution_vals(single_liquidation.entire_trove_debt, vars.coll_to_liquidate, _solusd_in_stab_pool);
-        //_closeTrove(_borrower, Status.closedByLiquidation); -- in frontend
+        single_liquidation.debt_to_offset = _debt_to_offset;
+        single_liquidation.coll_to_send_to_sp = _coll_to_send_to_sp;
+        single_liquidation.debt_to_redistribute = _debt_to_redistribute;
+        single_liquidation.coll_to_redistribute = _coll_to_liquidate;
+
+        close_trove(borrower_trove, reward_snapshots);
+        borrower_trove.status = Status::ClosedByLiquidation as u8;
     }
     /*
     * If 110% <= ICR < current TCR (accounting for the preceding liquidations in the current sequence)
@@ -656,21 +842,22 @@ pub fn liquidate_recovery_mode(
     * The remainder due to the capped rate will be claimable as collateral surplus.
     */
     else if (_icr >= MCR) && (_icr < _tcr) && (single_liquidation.entire_trove_debt <= _solusd_in_stab_pool) {
-        move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool);
+        move_pending_trove_reward_to_active_pool(trove_manager, vars.pending_debt_reward, vars.pending_coll_reward, default_pool, active_pool, pool_wsol)?;
         //assert(_LUSDInStabPool != 0);
         remove_stake(trove_manager,borrower_trove);
         get_capped_offset_vals(&mut single_liquidation, _price);
 
-        //_closeTrove(_borrower, Status.closedByLiquidation); -- in frontend
+        close_trove(borrower_trove, reward_snapshots);
+        borrower_trove.status = Status::ClosedByLiquidation as u8;
         if single_liquidation.coll_surplus > 0 {
             //collSurplusPool.accountSurplus(_borrower, singleLiquidation.collSurplus); --in frontend
         }
     }
     else{
         let zero_vals = LiquidationValues::new();
-        return zero_vals;
+        return Ok(zero_vals);
     }
-    return single_liquidation;
+    Ok(single_liquidation)
 
 }
 pub fn get_capped_offset_vals(single_liquidation:&mut LiquidationValues, price:u128){
@@ -715,6 +902,22 @@ pub fn get_offset_and_redistribution_vals(debt:u128, coll:u128, solusd_in_stab_p
     }
 }
 
+/*
+* Check the ICR of a trove passed to a redemption. Troves with ICR < MCR can not be redeemed from and are skipped (returns false),
+* the others must come in ascending ICR order. There is no sorted troves list on-chain, so the order is only enforced among the
+* troves passed by the redeemer, a trove with a lower ICR that is left out is not redeemed from.
+*/
+pub fn check_redemption_order(icr: u128, last_icr: &mut u128) -> Result<bool, ProgramError> {
+    if icr < MCR {
+        return Ok(false);
+    }
+    if icr < *last_icr {
+        return Err(LiquityError::InvalidRedemptionOrder.into());
+    }
+    *last_icr = icr;
+    Ok(true)
+}
+
 pub fn get_current_icr(
     trove_manager_data:&TroveManager, 
     borrower_trove:&mut Trove, 
@@ -743,9 +946,120 @@ pub fn remove_stake(trove_manager:&mut TroveManager, borrower_trove:&mut Trove){
     trove_manager.total_stakes = trove_manager.total_stakes - stake;
     borrower_trove.stake = 0;
 }
+pub fn compute_new_stake(trove_manager:&TroveManager, coll:u128)->u128{
+    if trove_manager.total_collateral_snapshot == 0 {
+        return coll;
+    }
+    /*
+    * The following holds true because:
+    * - The system always contains >= 1 trove
+    * - When we close or liquidate a trove, we redistribute the pending rewards, so if all troves were closed/liquidated,
+    * rewards would've been emptied and total_collateral_snapshot would be zero too.
+    */
+    coll * trove_manager.total_stakes_snapshot / trove_manager.total_collateral_snapshot
+}
+pub fn update_stake_and_total_stakes(trove_manager:&mut TroveManager, borrower_trove:&mut Trove)->u128{
+    let new_stake = compute_new_stake(trove_manager, borrower_trove.coll);
+    let old_stake = borrower_trove.stake;
+    borrower_trove.stake = new_stake;
+    trove_manager.total_stakes = trove_manager.total_stakes - old_stake + new_stake;
+    new_stake
+}
+/*
+* Redeem as much collateral as possible from the given trove in exchange for SOLUSD up to `max_solusd_amount`.
+* A fully redeemed trove is closed, its liquidation reserve is left for the caller to burn from the gas pool
+* and its remaining collateral is moved to the CollSurplusPool, where the borrower can claim it.
+*/
+pub fn redeem_collateral_from_trove<'a>(
+    trove_manager:&mut TroveManager,
+    borrower_trove:&mut Trove,
+    reward_snapshot:&mut RewardSnapshot,
+    coll_surplus:&mut CollSurplus,
+    active_pool:&mut ActivePool,
+    coll_surplus_pool:&mut CollSurplusPool,
+    max_solusd_amount:u128,
+    price:u128,
+    partial_redemption_hint_nicr:u128,
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->Result<SingleRedemptionValues, ProgramError>{
+    let mut single_redemption = SingleRedemptionValues::default();
+
+    // Determine the remaining amount (lot) to be redeemed, capped by the entire debt of the Trove minus the liquidation reserve
+    let net_debt = borrower_trove.debt.checked_sub(SOLUSD_GAS_COMPENSATION).ok_or(LiquityError::MathOverflow)?;
+    single_redemption.solusd_lot = min(max_solusd_amount, net_debt);
+
+    // Get the SOLLot of equivalent value in USD
+    single_redemption.sol_lot = single_redemption.solusd_lot * DECIMAL_PRECISION / price;
+
+    // Decrease the debt and collateral of the current Trove according to the SOLUSD lot and corresponding SOL to send
+    let new_debt = borrower_trove.debt - single_redemption.solusd_lot;
+    let new_coll = borrower_trove.coll - single_redemption.sol_lot;
+
+    if new_debt == SOLUSD_GAS_COMPENSATION {
+        // No debt left in the Trove (except for the liquidation reserve), therefore the trove gets closed
+        remove_stake(trove_manager, borrower_trove);
+        close_trove(borrower_trove, reward_snapshot);
+        borrower_trove.status = Status::ClosedByRedemption as u8;
+
+        // Update Active Pool SOLUSD for the liquidation reserve, and send the remaining SOL to the CollSurplusPool
+        active_pool.decrease_solusd_debt(SOLUSD_GAS_COMPENSATION);
+        active_pool.send_sol(new_coll);
+        coll_surplus_pool.account_surplus(coll_surplus, new_coll);
+        pool_wsol.send_sol(&pool_wsol.active_pool, &pool_wsol.coll_surplus_pool, new_coll)?;
+    }
+    else {
+        let new_nicr = compute_nominal_cr(new_coll, new_debt);
+        /*
+        * If the provided hint is out of date, we bail since trying to reinsert without a good hint will almost
+        * certainly result in running out of gas.
+        *
+        * If the resultant net debt of the partial is less than the minimum, net debt we bail.
+        */
+        if new_nicr != partial_redemption_hint_nicr || new_debt - SOLUSD_GAS_COMPENSATION < MIN_NET_DEBT {
+            single_redemption.cancelled_partial = 1;
+            return Ok(single_redemption);
+        }
+
+        borrower_trove.debt = new_debt;
+        borrower_trove.coll = new_coll;
+        update_stake_and_total_stakes(trove_manager, borrower_trove);
+    }
+    Ok(single_redemption)
+}
 pub fn get_coll_gas_compensation(entire_coll:u128)->u128{
     entire_coll / PERCENT_DIVISOR
 }
+/*
+* Pay the gas compensation of a liquidation to the liquidator. The SOLUSD compensation comes from the gas pool, the
+* SOL compensation from the wSOL account of the active pool. Both are held by the trove manager authority.
+*/
+pub fn send_gas_compensation<'a>(
+    active_pool: &mut ActivePool,
+    pool_wsol: &PoolWsolAccounts<'a>,
+    gas_pool: AccountInfo<'a>,
+    liquidator_solusd: AccountInfo<'a>,
+    liquidator_wsol: AccountInfo<'a>,
+    solusd: u128,
+    sol: u128,
+) -> ProgramResult {
+    if solusd > 0 {
+        token_transfer(
+            &pool_wsol.trove_manager,
+            pool_wsol.token_program.clone(),
+            gas_pool,
+            liquidator_solusd,
+            pool_wsol.authority.clone(),
+            pool_wsol.nonce,
+            solusd.try_into().map_err(|_| LiquityError::MathOverflow)?
+        )?;
+    }
+
+    if sol > 0 {
+        active_pool.send_sol(sol);
+        pool_wsol.send_sol(&pool_wsol.active_pool, &liquidator_wsol, sol)?;
+    }
+    Ok(())
+}
 pub fn get_entire_debt_and_coll(trove_manager:&TroveManager, borrower_trove:&Trove, reward_snapshots:&RewardSnapshot)->(u128,u128,u128,u128){
     let mut debt = borrower_trove.debt;
     let mut coll = borrower_trove.coll;
@@ -771,19 +1085,21 @@ pub fn get_pending_sol_reward(trove_manager_data:&TroveManager, borrower_trove:&
     let pending_sol_reward = stake * reward_per_unit_staked / DECIMAL_PRECISION;
     return pending_sol_reward;
 }
-pub fn move_pending_trove_reward_to_active_pool(
+pub fn move_pending_trove_reward_to_active_pool<'a>(
     trove_manager_data:&TroveManager,
     _solusd:u128, 
     _sol:u128,
     default_pool_data:&mut DefaultPool,
-    active_pool_data:&mut ActivePool
-){
+    active_pool_data:&mut ActivePool,
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->ProgramResult{
     
     default_pool_data.decrease_solusd_debt(_solusd);
     active_pool_data.increase_solusd_debt(_solusd);
 
     default_pool_data.sol -= _sol;
     active_pool_data.sol += _sol;
+    pool_wsol.send_sol(&pool_wsol.default_pool, &pool_wsol.active_pool, _sol)
 }
 
 pub fn get_pending_solusd_debt_reward(trove_manager_data:&TroveManager, borrower_trove:&Trove, reward_snapshot:&RewardSnapshot)->u128{
@@ -863,12 +1179,14 @@ pub fn payout_solid_gains<'a>(
 
 }
 
-pub fn apply_pending_rewards(
+pub fn apply_pending_rewards<'a>(
     trove_manager_data:&TroveManager, 
     borrower_trove:&mut Trove, 
     reward_snapshot:&mut RewardSnapshot, 
     default_pool_data:&mut DefaultPool, 
-    active_pool_data:&mut ActivePool)
+    active_pool_data:&mut ActivePool,
+    pool_wsol:&PoolWsolAccounts<'a>,
+)->ProgramResult
 {
     if has_pending_rewards(trove_manager_data, borrower_trove, reward_snapshot) {
         if borrower_trove.is_active() {
@@ -884,13 +1202,15 @@ pub fn apply_pending_rewards(
             // Transfer from DefaultPool to ActivePool
             move_pending_trove_reward_to_active_pool(
                 trove_manager_data, 
-                pending_sol_reward, 
                 pending_solusd_debt_reward,
+                pending_sol_reward, 
                 default_pool_data,
-                active_pool_data
-            );
+                active_pool_data,
+                pool_wsol
+            )?;
         }
     }
+    Ok(())
 }
 pub fn close_trove(borrower_trove:&mut Trove, reward_snapshots:&mut RewardSnapshot){
     borrower_trove.coll = 0;
@@ -898,4 +1218,274 @@ pub fn close_trove(borrower_trove:&mut Trove, reward_snapshots:&mut RewardSnapsh
 
     reward_snapshots.sol = 0;
     reward_snapshots.solusd_debt = 0;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const UNIT: u128 = 1_000_000_000;
+
+    /// token account without data, the tests check the ledgers so the token transfers only reach the syscall stubs
+    fn account(key: Pubkey) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0)),
+            Box::leak(Vec::new().into_boxed_slice()),
+            &spl_token::ID,
+            false,
+            0,
+        )
+    }
+
+    /// wSOL accounts of the pools
+    fn pool_wsol() -> PoolWsolAccounts<'static> {
+        PoolWsolAccounts {
+            trove_manager: Pubkey::new_unique(),
+            nonce: 0,
+            token_program: account(spl_token::id()),
+            authority: account(Pubkey::new_unique()),
+            active_pool: account(Pubkey::new_unique()),
+            default_pool: account(Pubkey::new_unique()),
+            coll_surplus_pool: account(Pubkey::new_unique()),
+        }
+    }
+
+    fn active_trove(coll: u128, debt: u128) -> (Trove, RewardSnapshot, CollSurplus) {
+        let trove = Trove {
+            status: Status::Active as u8,
+            coll,
+            debt,
+            stake: coll,
+            ..Trove::default()
+        };
+        (trove, RewardSnapshot::default(), CollSurplus::default())
+    }
+
+    #[test]
+    fn liquidate_in_recovery_mode_and_claim_surplus() {
+        let price = 100 * DECIMAL_PRECISION;
+        let mut trove_manager = TroveManager {
+            total_stakes: 110 * UNIT,
+            ..TroveManager::default()
+        };
+        let mut active_pool = ActivePool {
+            sol: 110 * UNIT,
+            solusd_debt: 7_800 * UNIT,
+            ..ActivePool::default()
+        };
+        let mut default_pool = DefaultPool::default();
+        let mut coll_surplus_pool = CollSurplusPool::default();
+        // TCR is 141%, the trove ICR of 125% is between MCR and TCR so its debt is offset at the capped rate of MCR
+        let mut troves = [active_trove(10 * UNIT, 800 * UNIT)];
+        assert_eq!(trove_manager.check_recovery_mode(price, &active_pool, &default_pool), 1);
+
+        let totals = get_total_from_batch_liquidate_recovery_mode(
+            &mut trove_manager,
+            &mut active_pool,
+            &mut default_pool,
+            &mut coll_surplus_pool,
+            price,
+            1_000 * UNIT,
+            &mut troves,
+            &pool_wsol(),
+        ).unwrap();
+
+        assert_eq!(totals.total_debt_to_offset, 800 * UNIT);
+        assert_eq!(totals.total_coll_gas_compensation, 44_000_000);
+        assert_eq!(totals.total_coll_to_send_to_sp, 8_756_000_000);
+        assert_eq!(totals.total_coll_surplus, 1_200_000_000);
+        assert_eq!(totals.total_debt_to_redistribute, 0);
+        assert_eq!(totals.total_coll_to_redistribute, 0);
+
+        let (trove, _, coll_surplus) = &mut troves[0];
+        assert_eq!(trove.status, Status::ClosedByLiquidation as u8);
+        assert_eq!(trove_manager.total_stakes, 100 * UNIT);
+        assert_eq!(coll_surplus.amount, 1_200_000_000);
+        assert_eq!(coll_surplus_pool.sol, 1_200_000_000);
+
+        // the borrower claims the surplus once
+        assert_eq!(coll_surplus_pool.claim_coll(coll_surplus), Ok(1_200_000_000));
+        assert_eq!(coll_surplus.amount, 0);
+        assert_eq!(coll_surplus_pool.sol, 0);
+        assert_eq!(coll_surplus_pool.claim_coll(coll_surplus), Err(LiquityError::NoCollAvailableToClaim.into()));
+    }
+
+    #[test]
+    fn liquidate_batch_in_recovery_mode() {
+        let price = 100 * DECIMAL_PRECISION;
+        let mut trove_manager = TroveManager {
+            total_stakes: 150 * UNIT,
+            ..TroveManager::default()
+        };
+        let mut active_pool = ActivePool {
+            sol: 150 * UNIT,
+            solusd_debt: 11_000 * UNIT,
+            ..ActivePool::default()
+        };
+        let mut default_pool = DefaultPool::default();
+        let mut coll_surplus_pool = CollSurplusPool::default();
+        // TCR is 136%. The first trove has an ICR of 100% and is redistributed, the second one has an ICR of 125%
+        // below the TCR and is offset at the capped rate, the last one has an ICR of 250% above the TCR and is kept
+        let mut troves = [
+            active_trove(10 * UNIT, 1_000 * UNIT),
+            active_trove(10 * UNIT, 800 * UNIT),
+            active_trove(20 * UNIT, 800 * UNIT),
+        ];
+
+        let totals = get_total_from_batch_liquidate_recovery_mode(
+            &mut trove_manager,
+            &mut active_pool,
+            &mut default_pool,
+            &mut coll_surplus_pool,
+            price,
+            1_000 * UNIT,
+            &mut troves,
+            &pool_wsol(),
+        ).unwrap();
+
+        assert_eq!(totals.total_debt_in_sequence, 1_800 * UNIT);
+        assert_eq!(totals.total_coll_in_sequence, 20 * UNIT);
+        assert_eq!(totals.total_coll_gas_compensation, 94_000_000);
+        assert_eq!(totals.total_solusd_gas_compensation, 2 * SOLUSD_GAS_COMPENSATION);
+        assert_eq!(totals.total_debt_to_offset, 800 * UNIT);
+        assert_eq!(totals.total_coll_to_send_to_sp, 8_756_000_000);
+        assert_eq!(totals.total_debt_to_redistribute, 1_000 * UNIT);
+        assert_eq!(totals.total_coll_to_redistribute, 9_950_000_000);
+        assert_eq!(totals.total_coll_surplus, 1_200_000_000);
+
+        assert_eq!(troves[0].0.status, Status::ClosedByLiquidation as u8);
+        assert_eq!(troves[1].0.status, Status::ClosedByLiquidation as u8);
+        assert_eq!(troves[1].2.amount, 1_200_000_000);
+        assert!(troves[2].0.is_active());
+        assert_eq!(troves[2].0.coll, 20 * UNIT);
+        assert_eq!(trove_manager.total_stakes, 130 * UNIT);
+    }
+
+    #[test]
+    fn redistribute_to_the_stakes() {
+        let mut trove_manager = TroveManager {
+            total_stakes: 100 * UNIT,
+            ..TroveManager::default()
+        };
+        let mut active_pool = ActivePool {
+            sol: 115 * UNIT,
+            solusd_debt: 10_500 * UNIT,
+            ..ActivePool::default()
+        };
+        let mut default_pool = DefaultPool::default();
+        let pool_wsol = pool_wsol();
+
+        redistribute_debt_and_coll(&mut trove_manager, &mut active_pool, &mut default_pool, 500 * UNIT, 5 * UNIT, &pool_wsol).unwrap();
+        assert_eq!(trove_manager.l_sol, 5 * DECIMAL_PRECISION / 100);
+        assert_eq!(trove_manager.l_solusd_debt, 5 * DECIMAL_PRECISION);
+        assert_eq!(trove_manager.last_sol_error_redistribution, 0);
+        assert_eq!(trove_manager.last_solusd_debt_error_redistribution, 0);
+        assert_eq!((active_pool.sol, active_pool.solusd_debt), (110 * UNIT, 10_000 * UNIT));
+        assert_eq!((default_pool.sol, default_pool.solusd_debt), (5 * UNIT, 500 * UNIT));
+
+        // the remaining troves receive the rewards in proportion to their stakes
+        let (mut first, mut first_snapshot, _) = active_trove(40 * UNIT, 4_000 * UNIT);
+        let (mut second, mut second_snapshot, _) = active_trove(60 * UNIT, 6_000 * UNIT);
+        assert_eq!(get_pending_sol_reward(&trove_manager, &first, &first_snapshot), 2 * UNIT);
+        assert_eq!(get_pending_solusd_debt_reward(&trove_manager, &first, &first_snapshot), 200 * UNIT);
+
+        apply_pending_rewards(&trove_manager, &mut first, &mut first_snapshot, &mut default_pool, &mut active_pool, &pool_wsol).unwrap();
+        apply_pending_rewards(&trove_manager, &mut second, &mut second_snapshot, &mut default_pool, &mut active_pool, &pool_wsol).unwrap();
+        assert_eq!((first.coll, first.debt), (42 * UNIT, 4_200 * UNIT));
+        assert_eq!((second.coll, second.debt), (63 * UNIT, 6_300 * UNIT));
+        assert!(!has_pending_rewards(&trove_manager, &first, &first_snapshot));
+        assert_eq!((active_pool.sol, active_pool.solusd_debt), (115 * UNIT, 10_500 * UNIT));
+        assert_eq!((default_pool.sol, default_pool.solusd_debt), (0, 0));
+    }
+
+    #[test]
+    fn redistribute_without_stakes() {
+        let mut trove_manager = TroveManager::default();
+        let mut active_pool = ActivePool {
+            sol: 10 * UNIT,
+            solusd_debt: 1_000 * UNIT,
+            ..ActivePool::default()
+        };
+        let mut default_pool = DefaultPool::default();
+
+        assert_eq!(
+            redistribute_debt_and_coll(&mut trove_manager, &mut active_pool, &mut default_pool, 1_000 * UNIT, 10 * UNIT, &pool_wsol()),
+            Err(LiquityError::OnlyOneTroveInSystem.into())
+        );
+        assert_eq!(active_pool.solusd_debt, 1_000 * UNIT);
+        assert_eq!(default_pool.solusd_debt, 0);
+    }
+
+    #[test]
+    fn decay_base_rate() {
+        let price = 100 * DECIMAL_PRECISION;
+        let mut trove_manager = TroveManager::default();
+
+        // redeeming 1000 of the 10000 SOLUSD supply raises the base rate by half the redeemed fraction
+        let base_rate = update_base_rate_from_redemption(&mut trove_manager, 3_600, 10 * UNIT, price, 10_000 * UNIT);
+        assert_eq!(base_rate, DECIMAL_PRECISION / 20);
+        assert_eq!(trove_manager.last_fee_operation_time, 3_600);
+
+        // less than a minute passed, the base rate and the last fee operation time are kept
+        decay_base_rate_from_borrowing(&mut trove_manager, 3_630);
+        assert_eq!(trove_manager.base_rate, DECIMAL_PRECISION / 20);
+        assert_eq!(trove_manager.last_fee_operation_time, 3_600);
+
+        // the base rate is halved after 12 hours
+        decay_base_rate_from_borrowing(&mut trove_manager, 3_600 + 720 * SECONDS_IN_ONE_MINUTE);
+        assert_eq!(trove_manager.base_rate, 24_999_999_999_993_004);
+        assert_eq!(trove_manager.last_fee_operation_time, 3_600 + 720 * SECONDS_IN_ONE_MINUTE);
+        assert_eq!(get_redemption_fee(&trove_manager, 10 * UNIT), 299_999_999);
+    }
+
+    #[test]
+    fn pay_gas_compensation() {
+        let pool_wsol = pool_wsol();
+        let gas_pool = account(Pubkey::new_unique());
+        let liquidator_solusd = account(Pubkey::new_unique());
+        let liquidator_wsol = account(Pubkey::new_unique());
+        let mut active_pool = ActivePool {
+            sol: 10 * UNIT,
+            ..ActivePool::default()
+        };
+
+        let coll_gas_compensation = get_coll_gas_compensation(10 * UNIT);
+        assert_eq!(coll_gas_compensation, 50_000_000);
+        send_gas_compensation(
+            &mut active_pool,
+            &pool_wsol,
+            gas_pool.clone(),
+            liquidator_solusd.clone(),
+            liquidator_wsol.clone(),
+            200 * UNIT,
+            coll_gas_compensation,
+        ).unwrap();
+        assert_eq!(active_pool.sol, 9_950_000_000);
+
+        // a SOLUSD amount that does not fit a token amount is rejected
+        assert_eq!(
+            send_gas_compensation(&mut active_pool, &pool_wsol, gas_pool, liquidator_solusd, liquidator_wsol, u64::MAX as u128 + 1, 0),
+            Err(LiquityError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn redemption_order() {
+        let mut last_icr = 0;
+        // under-collateralized troves are skipped wherever they are passed
+        assert_eq!(check_redemption_order(MCR - 1, &mut last_icr), Ok(false));
+        assert_eq!(check_redemption_order(MCR, &mut last_icr), Ok(true));
+        assert_eq!(check_redemption_order(2 * MCR, &mut last_icr), Ok(true));
+        assert_eq!(check_redemption_order(MCR / 2, &mut last_icr), Ok(false));
+        assert_eq!(last_icr, 2 * MCR);
+        // the same ICR is still in order, a lower one is not
+        assert_eq!(check_redemption_order(2 * MCR, &mut last_icr), Ok(true));
+        assert_eq!(
+            check_redemption_order(2 * MCR - 1, &mut last_icr),
+            Err(LiquityError::InvalidRedemptionOrder.into())
+        );
+    }
+}
@@ -9,7 +9,8 @@ use {
     solana_program::{
         instruction::{AccountMeta, Instruction},
         pubkey::Pubkey,
-        // sysvar
+        system_program,
+        sysvar,
     },
 };
 
@@ -24,9 +25,11 @@ pub enum SOLIDStakingInstruction {
     ///   0. `[w]` New SOLID Staking account to create.
     ///   1. `[]` authority to initialize this pool account
     ///   2. `[]` SOLID pool token account
-    ///   3. `[]` Token program id
-    ///   4. `[]` nonce
-    ///   5. `[]` SOLIDStaking program id
+    ///   3. `[]` solUSD fee pool token account
+    ///   4. `[]` SOL fee pool token account
+    ///   5. `[]` Token program id
+    ///   6. `[]` nonce
+    ///   7. `[]` SOLIDStaking program id
     Initialize {
         #[allow(dead_code)]
         /// nonce
@@ -44,9 +47,15 @@ pub enum SOLIDStakingInstruction {
     ///   4. `[]` user transfer authority
     ///   5. `[]` user deposit data account
     ///   6. `[]` snapshot account
-    ///   7. `[]` Token program id
-    ///   8. `[]` SOLIDStaking program id
-    ///   9. `[]` amount
+    ///   7. `[]` solUSD fee pool token account
+    ///   8. `[]` solUSD user token account
+    ///   9. `[]` SOL fee pool token account
+    ///   10. `[]` SOL user token account
+    ///   11. `[]` Token program id
+    ///   12. `[]` Rent sysvar
+    ///   13. `[]` System program id
+    ///   14. `[]` SOLIDStaking program id
+    ///   15. `[]` amount
     Stake(u64),
 
     /// Withdraw from SOLID Staking
@@ -60,11 +69,33 @@ pub enum SOLIDStakingInstruction {
     ///   3. `[]` SOLID user token account account
     ///   4. `[]` user transfer authority
     ///   5. `[]` user deposit data account
-    ///   6. `[]` Token program id
-    ///   7. `[]` SOLIDStaking program id
-    ///   8. `[]` amount
+    ///   6. `[]` snapshot account
+    ///   7. `[]` solUSD fee pool token account
+    ///   8. `[]` solUSD user token account
+    ///   9. `[]` SOL fee pool token account
+    ///   10. `[]` SOL user token account
+    ///   11. `[]` Token program id
+    ///   12. `[]` SOLIDStaking program id
+    ///   13. `[]` amount
     Unstake(u64),
 
+    /// Claim SOLID Staking gains
+    ///
+    /// Send the accumulated solUSD & SOL gains to the caller without changing their stake.
+    /// 
+    ///   0. `[]` SOLIDStaking account
+    ///   1. `[]` authority of this pool account
+    ///   2. `[s]` user wallet
+    ///   3. `[]` user deposit data account
+    ///   4. `[w]` snapshot account
+    ///   5. `[w]` solUSD fee pool token account
+    ///   6. `[w]` solUSD user token account
+    ///   7. `[w]` SOL fee pool token account
+    ///   8. `[w]` SOL user token account
+    ///   9. `[]` Token program id
+    ///   10. `[]` SOLIDStaking program id
+    ClaimGains,
+
 }
 
 // below functions are used to test above instructions in the rust test side
@@ -75,6 +106,8 @@ pub fn initialize(
     pool_id: &Pubkey,
     authority: &Pubkey,
     solid_pool_token_pubkey: &Pubkey,
+    solusd_pool_token_pubkey: &Pubkey,
+    sol_pool_token_pubkey: &Pubkey,
     token_program_id: &Pubkey,
     nonce: u8,
     solid_staking_program_id: &Pubkey,
@@ -89,6 +122,8 @@ pub fn initialize(
         AccountMeta::new(*pool_id, false),
         AccountMeta::new_readonly(*authority, false),
         AccountMeta::new(*solid_pool_token_pubkey, false),
+        AccountMeta::new_readonly(*solusd_pool_token_pubkey, false),
+        AccountMeta::new_readonly(*sol_pool_token_pubkey, false),
         AccountMeta::new_readonly(*token_program_id, false),
     ];
     Instruction {
@@ -107,6 +142,10 @@ pub fn stake(
     user_transfer_authority_pubkey: &Pubkey,
     user_deposit_pubkey: &Pubkey,
     snapshot_pubkey: &Pubkey,
+    solusd_pool_token_pubkey: &Pubkey,
+    solusd_user_token_pubkey: &Pubkey,
+    sol_pool_token_pubkey: &Pubkey,
+    sol_user_token_pubkey: &Pubkey,
     token_program_id: &Pubkey,
     solid_staking_program_id: &Pubkey,
     amount: u64,
@@ -116,10 +155,16 @@ pub fn stake(
         AccountMeta::new_readonly(*authority, false),
         AccountMeta::new(*solid_pool_token_pubkey, false),
         AccountMeta::new(*solid_user_token_pubkey, false),
-        AccountMeta::new(*user_transfer_authority_pubkey, false),
+        AccountMeta::new(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*user_deposit_pubkey, false),
         AccountMeta::new(*snapshot_pubkey, false),
+        AccountMeta::new(*solusd_pool_token_pubkey, false),
+        AccountMeta::new(*solusd_user_token_pubkey, false),
+        AccountMeta::new(*sol_pool_token_pubkey, false),
+        AccountMeta::new(*sol_user_token_pubkey, false),
         AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
     Instruction {
         program_id: *solid_staking_program_id,
@@ -136,6 +181,11 @@ pub fn unstake(
     solid_user_token_pubkey: &Pubkey,
     user_transfer_authority_pubkey: &Pubkey,
     user_deposit_pubkey: &Pubkey,
+    snapshot_pubkey: &Pubkey,
+    solusd_pool_token_pubkey: &Pubkey,
+    solusd_user_token_pubkey: &Pubkey,
+    sol_pool_token_pubkey: &Pubkey,
+    sol_user_token_pubkey: &Pubkey,
     token_program_id: &Pubkey,
     solid_staking_program_id: &Pubkey,
     amount: u64,
@@ -145,8 +195,13 @@ pub fn unstake(
         AccountMeta::new_readonly(*authority, false),
         AccountMeta::new(*solid_pool_token_pubkey, false),
         AccountMeta::new(*solid_user_token_pubkey, false),
-        AccountMeta::new(*user_transfer_authority_pubkey, false),
+        AccountMeta::new(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*user_deposit_pubkey, false),
+        AccountMeta::new(*snapshot_pubkey, false),
+        AccountMeta::new(*solusd_pool_token_pubkey, false),
+        AccountMeta::new(*solusd_user_token_pubkey, false),
+        AccountMeta::new(*sol_pool_token_pubkey, false),
+        AccountMeta::new(*sol_user_token_pubkey, false),
         AccountMeta::new_readonly(*token_program_id, false),
     ];
     Instruction {
@@ -155,3 +210,36 @@ pub fn unstake(
         data: SOLIDStakingInstruction::Unstake(amount).try_to_vec().unwrap(),
     }
 }
+
+/// Creates instructions required to claim the SOLID Staking gains
+pub fn claim_gains(
+    pool_id: &Pubkey,
+    authority: &Pubkey,
+    owner_pubkey: &Pubkey,
+    user_deposit_pubkey: &Pubkey,
+    snapshot_pubkey: &Pubkey,
+    solusd_pool_token_pubkey: &Pubkey,
+    solusd_user_token_pubkey: &Pubkey,
+    sol_pool_token_pubkey: &Pubkey,
+    sol_user_token_pubkey: &Pubkey,
+    token_program_id: &Pubkey,
+    solid_staking_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*pool_id, false),
+        AccountMeta::new_readonly(*authority, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+        AccountMeta::new_readonly(*user_deposit_pubkey, false),
+        AccountMeta::new(*snapshot_pubkey, false),
+        AccountMeta::new(*solusd_pool_token_pubkey, false),
+        AccountMeta::new(*solusd_user_token_pubkey, false),
+        AccountMeta::new(*sol_pool_token_pubkey, false),
+        AccountMeta::new(*sol_user_token_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *solid_staking_program_id,
+        accounts,
+        data: SOLIDStakingInstruction::ClaimGains.try_to_vec().unwrap(),
+    }
+}
@@ -4,9 +4,9 @@
 use {
     liquity_common::{
         error::LiquityError,
-        state::{SOLIDStaking,UserDeposit},
+        state::{SOLIDStaking,UserDeposit,Snapshot},
         utils::{
-            authority_id,token_transfer,create_or_allocate_account_raw
+            authority_id,token_transfer,create_or_allocate_account_raw,assert_token_account_owner
         }
     },
     crate::{
@@ -26,7 +26,10 @@ use {
     },
 };
 
+use std::convert::TryInto;
+
 const PREFIX:&str = "liquity-solid-staking";
+const SNAPSHOT_PREFIX:&str = "liquity-solid-staking-snapshot";
 
 /// Program state handler.
 /// Main logic of this program
@@ -52,6 +55,10 @@ impl Processor {
                 // Instruction: Unstake
                 Self::process_unstake(program_id, accounts, amount)
             }
+            SOLIDStakingInstruction::ClaimGains => {
+                // Instruction: ClaimGains
+                Self::process_claim_gains(program_id, accounts)
+            }
         }
     }
 
@@ -76,6 +83,12 @@ impl Processor {
         // pool SOLID token account
         let solid_pool_info = next_account_info(account_info_iter)?;
 
+        // pool solUSD fee token account
+        let solusd_pool_info = next_account_info(account_info_iter)?;
+
+        // pool SOL fee token account
+        let sol_pool_info = next_account_info(account_info_iter)?;
+
         // spl-token program account information
         let token_program_info = next_account_info(account_info_iter)?;
 
@@ -90,6 +103,8 @@ impl Processor {
 
         pool_data.token_program_pubkey = *token_program_info.key;
         pool_data.solid_pool_token_pubkey = *solid_pool_info.key;
+        pool_data.solusd_pool_token_pubkey = *solusd_pool_info.key;
+        pool_data.sol_pool_token_pubkey = *sol_pool_info.key;
         pool_data.nonce = nonce;
         
         // serialize/store this initialized SOLID staking pool again
@@ -98,7 +113,8 @@ impl Processor {
             .map_err(|e| e.into())
     } 
 
-    /// process ProvideToSP instruction
+    /// process Stake instruction
+    /// If the caller has a pre-existing stake, their accumulated SOL and solUSD gains are sent to them.
     pub fn process_stake(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
@@ -126,23 +142,45 @@ impl Processor {
         // user deposit info
         let user_deposit_info = next_account_info(account_info_iter)?;
 
+        // user snapshot info
+        let snapshot_info = next_account_info(account_info_iter)?;
+
+        // pool solUSD fee token account
+        let solusd_pool_info = next_account_info(account_info_iter)?;
+
+        // user solUSD token account
+        let solusd_user_info = next_account_info(account_info_iter)?;
+
+        // pool SOL fee token account
+        let sol_pool_info = next_account_info(account_info_iter)?;
+
+        // user SOL token account
+        let sol_user_info = next_account_info(account_info_iter)?;
+
         // spl-token program address
         let token_program_info = next_account_info(account_info_iter)?;
         let rent_info = next_account_info(account_info_iter)?;
         let system_info = next_account_info(account_info_iter)?;
 
         // borrow pool account data
-        let pool_data = try_from_slice_unchecked::<SOLIDStaking>(&pool_id_info.data.borrow())?;
+        let mut pool_data = try_from_slice_unchecked::<SOLIDStaking>(&pool_id_info.data.borrow())?;
 
         // check if this SOLID staking pool account was created by this program with authority and nonce
         // if fail, returns InvalidProgramAddress error
         if *authority_info.key != authority_id(program_id, pool_id_info.key, pool_data.nonce)? {
             return Err(LiquityError::InvalidProgramAddress.into());
         }
+        Self::assert_pool_accounts(&pool_data, solid_pool_info, solusd_pool_info, sol_pool_info)?;
 
-        let bump = Self::assert_pda(program_id, pool_id_info.key, user_deposit_info.key, depositor_info.key, PREFIX)?;
+        // only the staker can stake and receive the gains of the current stake
+        if !depositor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        assert_token_account_owner(solusd_user_info, depositor_info.key)?;
+        assert_token_account_owner(sol_user_info, depositor_info.key)?;
 
-        let size = std::mem::size_of::<UserDeposit>();
+        let bump = Self::assert_pda(program_id, pool_id_info.key, user_deposit_info.key, depositor_info.key, PREFIX)?;
+        let snapshot_bump = Self::assert_pda(program_id, pool_id_info.key, snapshot_info.key, depositor_info.key, SNAPSHOT_PREFIX)?;
 
         let user_data_is_empty = user_deposit_info.data_is_empty();
 
@@ -154,7 +192,7 @@ impl Processor {
                 rent_info,
                 system_info,
                 depositor_info,
-                size,
+                std::mem::size_of::<UserDeposit>(),
                 &[
                     PREFIX.as_bytes(),
                     depositor_info.key.as_ref(),
@@ -163,12 +201,50 @@ impl Processor {
                 ],
             )?;
         }
+        if snapshot_info.data_is_empty() {
+            create_or_allocate_account_raw(
+                *program_id,
+                snapshot_info,
+                rent_info,
+                system_info,
+                depositor_info,
+                std::mem::size_of::<Snapshot>(),
+                &[
+                    SNAPSHOT_PREFIX.as_bytes(),
+                    depositor_info.key.as_ref(),
+                    pool_id_info.key.as_ref(),
+                    &[snapshot_bump],
+                ],
+            )?;
+        }
 
         // borrow user deposit data
         let mut user_deposit = try_from_slice_unchecked::<UserDeposit>(&user_deposit_info.data.borrow())?;
+        let mut snapshot = try_from_slice_unchecked::<Snapshot>(&snapshot_info.data.borrow())?;
+
+        if user_data_is_empty {
+            user_deposit.pool_id_pubkey = *pool_id_info.key;
+            user_deposit.owner_pubkey = *depositor_info.key;
+        }
+        if snapshot.owner_pubkey == Pubkey::default() {
+            snapshot.pool_id_pubkey = *pool_id_info.key;
+            snapshot.owner_pubkey = *depositor_info.key;
+            // a new snapshot starts at the current running totals, so a deposit made before it existed
+            // does not receive the fees accumulated since the pool was created
+            pool_data.update_user_snapshot(&mut snapshot);
+        }
+
+        // grab any accumulated SOL and solUSD gains from the current stake
+        let mut sol_gain = 0;
+        let mut solusd_gain = 0;
+        if user_deposit.deposit_amount != 0 {
+            sol_gain = pool_data.get_pending_sol_gain(&user_deposit, &snapshot);
+            solusd_gain = pool_data.get_pending_solusd_gain(&user_deposit, &snapshot);
+        }
+        pool_data.update_user_snapshot(&mut snapshot);
 
         if amount > 0 {
-            // transfer solUSD token amount from user's solUSD token account to pool's solUSD token pool
+            // transfer SOLID token amount from user's SOLID token account to pool's SOLID token pool
             token_transfer(
                 pool_id_info.key,
                 token_program_info.clone(), 
@@ -180,16 +256,29 @@ impl Processor {
             )?;
 
             user_deposit.deposit_amount += amount;
-
-            if user_data_is_empty {
-                user_deposit.pool_id_pubkey = *pool_id_info.key;
-                user_deposit.owner_pubkey = *depositor_info.key;
-            }
-            // serialize/store user info again
-            user_deposit
-                .serialize(&mut *user_deposit_info.data.borrow_mut())?;
+            pool_data.total_staked_amount += amount as u128;
         }
 
+        // send the accumulated gains to the staker
+        Self::send_gains(
+            pool_id_info,
+            &pool_data,
+            authority_info,
+            solusd_pool_info,
+            solusd_user_info,
+            sol_pool_info,
+            sol_user_info,
+            token_program_info,
+            sol_gain,
+            solusd_gain,
+        )?;
+
+        // serialize/store user info again
+        user_deposit
+            .serialize(&mut *user_deposit_info.data.borrow_mut())?;
+        snapshot
+            .serialize(&mut *snapshot_info.data.borrow_mut())?;
+
         // serialize/store this initialized SOLID staking pool again
         pool_data
             .serialize(&mut *pool_id_info.data.borrow_mut())
@@ -197,7 +286,7 @@ impl Processor {
         
     }
 
-    /// process WithdrawFromSP instruction
+    /// process Unstake instruction
     pub fn process_unstake(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
@@ -226,22 +315,54 @@ impl Processor {
         // user deposit info
         let user_deposit_info = next_account_info(account_info_iter)?;
 
+        // user snapshot info
+        let snapshot_info = next_account_info(account_info_iter)?;
+
+        // pool solUSD fee token account
+        let solusd_pool_info = next_account_info(account_info_iter)?;
+
+        // user solUSD token account
+        let solusd_user_info = next_account_info(account_info_iter)?;
+
+        // pool SOL fee token account
+        let sol_pool_info = next_account_info(account_info_iter)?;
+
+        // user SOL token account
+        let sol_user_info = next_account_info(account_info_iter)?;
+
         // spl-token program address
         let token_program_info = next_account_info(account_info_iter)?;
 
         // borrow pool account data to initialize 
-        let pool_data = try_from_slice_unchecked::<SOLIDStaking>(&pool_id_info.data.borrow())?;
+        let mut pool_data = try_from_slice_unchecked::<SOLIDStaking>(&pool_id_info.data.borrow())?;
 
         // check if this SOLID staking pool account was created by this program with authority and nonce
         // if fail, returns InvalidProgramAddress error
         if *authority_info.key != authority_id(program_id, pool_id_info.key, pool_data.nonce)? {
             return Err(LiquityError::InvalidProgramAddress.into());
         }
+        Self::assert_pool_accounts(&pool_data, solid_pool_info, solusd_pool_info, sol_pool_info)?;
+
+        // only the staker can unstake and receive the gains
+        if !withdrawer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
         Self::assert_pda(program_id,pool_id_info.key, user_deposit_info.key, withdrawer_info.key, PREFIX)?;
+        Self::assert_pda(program_id,pool_id_info.key, snapshot_info.key, withdrawer_info.key, SNAPSHOT_PREFIX)?;
 
         // borrow user deposit data
         let mut user_deposit = try_from_slice_unchecked::<UserDeposit>(&user_deposit_info.data.borrow())?;
+        let mut snapshot = try_from_slice_unchecked::<Snapshot>(&snapshot_info.data.borrow())?;
+
+        if user_deposit.deposit_amount == 0 {
+            return Err(LiquityError::NoStake.into());
+        }
+
+        // grab any accumulated SOL and solUSD gains from the current stake
+        let sol_gain = pool_data.get_pending_sol_gain(&user_deposit, &snapshot);
+        let solusd_gain = pool_data.get_pending_solusd_gain(&user_deposit, &snapshot);
+        pool_data.update_user_snapshot(&mut snapshot);
 
         // check if given amount is small than deposit amount
         let mut _amount = amount;
@@ -250,7 +371,7 @@ impl Processor {
         }
 
         if _amount > 0 {
-            // transfer solUSD token amount from user's solUSD token account to pool's solUSD token pool
+            // transfer SOLID token amount from pool's SOLID token pool to user's SOLID token account
             token_transfer(
                 pool_id_info.key,
                 token_program_info.clone(),
@@ -261,11 +382,28 @@ impl Processor {
                 _amount
             )?;
             user_deposit.deposit_amount -= _amount;
+            pool_data.total_staked_amount -= _amount as u128;
         }
 
+        // send the accumulated gains to the staker
+        Self::send_gains(
+            pool_id_info,
+            &pool_data,
+            authority_info,
+            solusd_pool_info,
+            solusd_user_info,
+            sol_pool_info,
+            sol_user_info,
+            token_program_info,
+            sol_gain,
+            solusd_gain,
+        )?;
+
         // serialize/store user info again
         user_deposit
             .serialize(&mut *user_deposit_info.data.borrow_mut())?;
+        snapshot
+            .serialize(&mut *snapshot_info.data.borrow_mut())?;
 
         // serialize/store this initialized SOLID staking pool again
         pool_data
@@ -273,6 +411,146 @@ impl Processor {
             .map_err(|e| e.into())
         
     }
+
+    /// process ClaimGains instruction
+    /// Send the accumulated SOL and solUSD gains to the staker without changing their stake.
+    pub fn process_claim_gains(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        msg!("claiming staking gains ...");
+
+        // get account informations
+        let account_info_iter = &mut accounts.iter();
+
+        // pool account information
+        let pool_id_info = next_account_info(account_info_iter)?;
+
+        // authority information of this farm account
+        let authority_info = next_account_info(account_info_iter)?;
+
+        // user wallet
+        let owner_info = next_account_info(account_info_iter)?;
+
+        // user deposit info
+        let user_deposit_info = next_account_info(account_info_iter)?;
+
+        // user snapshot info
+        let snapshot_info = next_account_info(account_info_iter)?;
+
+        // pool solUSD fee token account
+        let solusd_pool_info = next_account_info(account_info_iter)?;
+
+        // user solUSD token account
+        let solusd_user_info = next_account_info(account_info_iter)?;
+
+        // pool SOL fee token account
+        let sol_pool_info = next_account_info(account_info_iter)?;
+
+        // user SOL token account
+        let sol_user_info = next_account_info(account_info_iter)?;
+
+        // spl-token program address
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let pool_data = try_from_slice_unchecked::<SOLIDStaking>(&pool_id_info.data.borrow())?;
+
+        if *authority_info.key != authority_id(program_id, pool_id_info.key, pool_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+        if *solusd_pool_info.key != pool_data.solusd_pool_token_pubkey || *sol_pool_info.key != pool_data.sol_pool_token_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+
+        // only the staker can claim the gains
+        if !owner_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        Self::assert_pda(program_id,pool_id_info.key, user_deposit_info.key, owner_info.key, PREFIX)?;
+        Self::assert_pda(program_id,pool_id_info.key, snapshot_info.key, owner_info.key, SNAPSHOT_PREFIX)?;
+
+        let user_deposit = try_from_slice_unchecked::<UserDeposit>(&user_deposit_info.data.borrow())?;
+        let mut snapshot = try_from_slice_unchecked::<Snapshot>(&snapshot_info.data.borrow())?;
+
+        if user_deposit.deposit_amount == 0 {
+            return Err(LiquityError::NoStake.into());
+        }
+
+        let sol_gain = pool_data.get_pending_sol_gain(&user_deposit, &snapshot);
+        let solusd_gain = pool_data.get_pending_solusd_gain(&user_deposit, &snapshot);
+        pool_data.update_user_snapshot(&mut snapshot);
+
+        Self::send_gains(
+            pool_id_info,
+            &pool_data,
+            authority_info,
+            solusd_pool_info,
+            solusd_user_info,
+            sol_pool_info,
+            sol_user_info,
+            token_program_info,
+            sol_gain,
+            solusd_gain,
+        )?;
+
+        snapshot
+            .serialize(&mut *snapshot_info.data.borrow_mut())
+            .map_err(|e| e.into())
+    }
+
+    /// send the SOL and solUSD gains from the pool's fee token accounts to the staker
+    pub fn send_gains<'a>(
+        pool_id_info: &AccountInfo<'a>,
+        pool_data: &SOLIDStaking,
+        authority_info: &AccountInfo<'a>,
+        solusd_pool_info: &AccountInfo<'a>,
+        solusd_user_info: &AccountInfo<'a>,
+        sol_pool_info: &AccountInfo<'a>,
+        sol_user_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        sol_gain: u128,
+        solusd_gain: u128,
+    ) -> ProgramResult {
+        if solusd_gain > 0 {
+            token_transfer(
+                pool_id_info.key,
+                token_program_info.clone(),
+                solusd_pool_info.clone(),
+                solusd_user_info.clone(),
+                authority_info.clone(),
+                pool_data.nonce,
+                solusd_gain.try_into().map_err(|_| LiquityError::MathOverflow)?
+            )?;
+        }
+        if sol_gain > 0 {
+            token_transfer(
+                pool_id_info.key,
+                token_program_info.clone(),
+                sol_pool_info.clone(),
+                sol_user_info.clone(),
+                authority_info.clone(),
+                pool_data.nonce,
+                sol_gain.try_into().map_err(|_| LiquityError::MathOverflow)?
+            )?;
+        }
+        Ok(())
+    }
+
+    /// check if the pool token accounts are the ones stored in the SOLID staking pool
+    pub fn assert_pool_accounts(
+        pool_data: &SOLIDStaking,
+        solid_pool_info: &AccountInfo,
+        solusd_pool_info: &AccountInfo,
+        sol_pool_info: &AccountInfo,
+    ) -> ProgramResult {
+        if *solid_pool_info.key != pool_data.solid_pool_token_pubkey
+            || *solusd_pool_info.key != pool_data.solusd_pool_token_pubkey
+            || *sol_pool_info.key != pool_data.sol_pool_token_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        Ok(())
+    }
     
     /// check if pda address is correct
     pub fn assert_pda(program_id:&Pubkey, pool_key:&Pubkey, key: &Pubkey,authority: &Pubkey, tag: &str)->Result<u8, ProgramError>{
@@ -17,16 +17,18 @@ use {
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub enum StabilityPoolInstruction {
-    ///   Initializes a new Stability Pool.
+    ///   Initializes a new Stability Pool, fails if it is already initialized.
     ///   These represent the parameters that will be included from client side
     ///   [w] - writable, [s] - signer
     /// 
     ///   0. `[w]` New Stability Pool account to create.
     ///   1. `[]` authority to initialize this pool account
-    ///   2. `[]` solUSD pool token account account
-    ///   3. `[]` Token program id
-    ///   4. `[]` nonce
-    ///   5. `[]` StabilityPool program id
+    ///   2. `[w]` solUSD pool token account account, the TroveManager authority is approved to burn the offset debt
+    ///   3. `[]` CommunityIssuance account
+    ///   4. `[]` wSOL gain token account held by the pool authority, receives the collateral of offset liquidations
+    ///   5. `[]` Token program id
+    ///   6. `[s]` guardian allowed to pause the deposits
+    ///   7. `[]` authority of the TroveManager account
     Initialize {
         #[allow(dead_code)]
         /// nonce
@@ -102,4 +104,23 @@ pub enum StabilityPoolInstruction {
     
     RegisterFrontEnd(u64),
 
+    /// Set the guardian of the stability pool. Only callable by the current guardian
+    ///
+    ///   0. `[w]` StabilityPool account
+    ///   1. `[s]` guardian
+    SetGuardian{
+        #[allow(dead_code)]
+        guardian: Pubkey,
+    },
+
+    /// Set the bitmap of the paused operation classes, see the `PAUSE_*` constants.
+    /// Withdrawals stay available. Only callable by the guardian
+    ///
+    ///   0. `[w]` StabilityPool account
+    ///   1. `[s]` guardian
+    SetPauseState{
+        #[allow(dead_code)]
+        paused_operations: u8,
+    },
+
 }
@@ -18,10 +18,12 @@ use {
         constant::{
             DECIMAL_PRECISION,
             MCR,
+            PAUSE_SP_DEPOSIT,
         },
         pyth,
         math::{Decimal, TryDiv, TryMul},
         utils::*,
+        price_feed::read_price,
     },
     crate::{
         instruction::{StabilityPoolInstruction},
@@ -93,6 +95,18 @@ impl Processor {
                 // Instruction: RegisterFrontEnd
                 Self::process_register_frontend(program_id, accounts, kickback_rate)
             }
+            StabilityPoolInstruction::SetGuardian{
+                guardian,
+            } => {
+                // Instruction: SetGuardian
+                Self::process_set_guardian(accounts, guardian)
+            }
+            StabilityPoolInstruction::SetPauseState{
+                paused_operations,
+            } => {
+                // Instruction: SetPauseState
+                Self::process_set_pause_state(accounts, paused_operations)
+            }
         }
     }
 
@@ -119,9 +133,18 @@ impl Processor {
         // pool solUsd token account
         let community_issuance_info = next_account_info(account_info_iter)?;
 
+        // pool wsol gain token account
+        let wsol_pool_gain_info = next_account_info(account_info_iter)?;
+
         // spl-token program account information
         let token_program_info = next_account_info(account_info_iter)?;
 
+        // guardian allowed to pause the deposits
+        let guardian_info = next_account_info(account_info_iter)?;
+
+        // authority of the trove manager, burns the debt offset by liquidations from the pool solUSD token account
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+
         // check if this stability pool account was created by this program with authority and nonce
         // if fail, returns InvalidProgramAddress error
         if *authority_info.key != authority_id(program_id, pool_id_info.key, nonce)? {
@@ -134,12 +157,37 @@ impl Processor {
         //     return Err(LiquityError::InvalidOwner.into());
         // }
 
+        // the guardian signs the initialization
+        if !guardian_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
         // borrow pool account data to initialize (mutable)
         let mut pool_data = try_from_slice_unchecked::<StabilityPool>(&pool_id_info.data.borrow())?;
 
+        // the pool can only be initialized once, a second initialization would replace its guardian
+        if pool_data.token_program_pubkey != Pubkey::default() {
+            return Err(LiquityError::AlreadyInUse.into());
+        }
+
+        // the SOL gains are paid out of the wSOL gain token account by the pool authority
+        assert_wsol_account(wsol_pool_gain_info, authority_info.key)?;
+
+        token_approve(
+            pool_id_info.key,
+            token_program_info.clone(),
+            solusd_pool_info.clone(),
+            trove_manager_authority_info.clone(),
+            authority_info.clone(),
+            nonce,
+            u64::MAX
+        )?;
+
         pool_data.token_program_pubkey = *token_program_info.key;
         pool_data.sol_usd_pool_token_pubkey = *solusd_pool_info.key;
         pool_data.community_issuance_pubkey = *community_issuance_info.key;
+        pool_data.sol_pool_token_pubkey = *wsol_pool_gain_info.key;
+        pool_data.guardian = *guardian_info.key;
         
         // serialize/store this initialized stability pool again
         pool_data
@@ -217,6 +265,9 @@ impl Processor {
         // borrow pool account data
         let mut pool_data = try_from_slice_unchecked::<StabilityPool>(&pool_id_info.data.borrow())?;
 
+        // deposits can be paused by the guardian, withdrawals can not
+        require_not_paused(pool_data.paused_operations, PAUSE_SP_DEPOSIT)?;
+
         let mut community_issuance_data = try_from_slice_unchecked::<CommunityIssuance>(&community_issuance_info.data.borrow())?;
 
         let mut epoch_to_scale = try_from_slice_unchecked::<EpochToScale>(&epoch_to_scale_info.data.borrow())?;
@@ -243,6 +294,11 @@ impl Processor {
             return Err(LiquityError::InvalidOwner.into());
         }
 
+        // the SOL gains are paid out of the pool wSOL gain token account only
+        if *wsol_pool_gain_info.key != pool_data.sol_pool_token_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+
         // borrow user deposit data
         let mut user_deposit = try_from_slice_unchecked::<Deposit>(&user_deposit_info.data.borrow())?;
 
@@ -411,6 +467,7 @@ impl Processor {
 
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
+        let price_feed_info = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
 
         // get current timestamp(second)
@@ -445,9 +502,15 @@ impl Processor {
             return Err(LiquityError::InvalidOwner.into());
         }
 
-        let market_price = get_market_price(
-            pool_data.oracle_program_id,
-            pool_data.quote_currency,
+        // the SOL gains are paid out of the pool wSOL gain token account only
+        if *wsol_gain_pool_info.key != pool_data.sol_pool_token_pubkey {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+
+        let market_price = read_price(
+            price_feed_info,
+            trove_manager_info,
+            &pool_data.trove_manager_pubkey,
             pyth_product_info,
             pyth_price_info,
             clock
@@ -541,7 +604,7 @@ impl Processor {
                 token_program_info.clone(),
                 wsol_gain_pool_info.clone(),
                 wsol_gain_user_info.clone(),
-                user_transfer_authority_info.clone(),
+                authority_info.clone(),
                 pool_data.nonce,
                 depositor_sol_gain as u64
             )?;
@@ -737,4 +800,56 @@ impl Processor {
             .map_err(|e| e.into())
         
     }
+
+    /// process SetGuardian instruction
+    pub fn process_set_guardian(
+        accounts: &[AccountInfo],
+        guardian: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        // pool account information
+        let pool_id_info = next_account_info(account_info_iter)?;
+
+        // current guardian of the pool
+        let guardian_info = next_account_info(account_info_iter)?;
+
+        let mut pool_data = try_from_slice_unchecked::<StabilityPool>(&pool_id_info.data.borrow())?;
+
+        if !guardian_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *guardian_info.key != pool_data.guardian {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+
+        pool_data.guardian = guardian;
+
+        pool_data
+            .serialize(&mut *pool_id_info.data.borrow_mut())
+            .map_err(|e| e.into())
+    }
+
+    /// process SetPauseState instruction
+    pub fn process_set_pause_state(
+        accounts: &[AccountInfo],
+        paused_operations: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+
+        // pool account information
+        let pool_id_info = next_account_info(account_info_iter)?;
+
+        // guardian of the pool
+        let guardian_info = next_account_info(account_info_iter)?;
+
+        let mut pool_data = try_from_slice_unchecked::<StabilityPool>(&pool_id_info.data.borrow())?;
+
+        let guardian = pool_data.guardian;
+        set_pause_state(&guardian, guardian_info, &mut pool_data.paused_operations, paused_operations)?;
+
+        pool_data
+            .serialize(&mut *pool_id_info.data.borrow_mut())
+            .map_err(|e| e.into())
+    }
 }
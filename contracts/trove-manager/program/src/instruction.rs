@@ -9,7 +9,8 @@ use {
     solana_program::{
         instruction::{AccountMeta, Instruction},
         pubkey::Pubkey,
-        // sysvar
+        system_program,
+        sysvar,
     },
 };
 
@@ -17,13 +18,52 @@ use {
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
 pub enum TroveManagerInstruction {
+    /// Initialize the TroveManager account, fails if it is already initialized
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[w]` DefaultPool account
+    ///   2. `[w]` ActivePool account
+    ///   3. `[]` StabilityPool account
+    ///   4. `[]` GasPool account
+    ///   5. `[w]` CollSurplusPool account
+    ///   6. `[]` BorrowerOperations account
+    ///   7. `[]` oracle program id
+    ///   8. `[]` pyth product account
+    ///   9. `[]` pyth price account
+    ///   10. `[]` SOLUSD token mint
+    ///   11. `[]` SOLID staking account
+    ///   12. `[]` authority of the TroveManager account
+    ///   13. `[]` Token program id
+    ///   14. `[]` active pool wSOL token account
+    ///   15. `[]` default pool wSOL token account
+    ///   16. `[]` coll surplus pool wSOL token account
+    ///   17. `[s]` guardian
     Initialize {
         #[allow(dead_code)]
         /// nonce
         nonce: u8,
     },
+    /// Apply the pending redistribution rewards of a trove, moving the rewarded SOL from the default pool to the active pool.
+    /// Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[]` TroveManager account
+    ///   1. `[]` authority of the TroveManager account
+    ///   2. `[]` BorrowerOperations account
+    ///   3. `[s]` authority of the BorrowerOperations account
+    ///   4. `[w]` borrower trove account
+    ///   5. `[w]` borrower reward snapshot account
+    ///   6. `[w]` ActivePool account
+    ///   7. `[w]` DefaultPool account
+    ///   8. `[]` CollSurplusPool account
+    ///   9. `[w]` active pool wSOL token account
+    ///   10. `[w]` default pool wSOL token account
+    ///   11. `[]` coll surplus pool wSOL token account
+    ///   12. `[]` Token program id
     ApplyPendingRewards,
     Liquidate,
+    /// Redeem SOLUSD for SOL. The troves to redeem from are passed after the fixed accounts
+    /// as (trove, reward snapshot, coll surplus) triples, in ascending ICR order. Troves with ICR < MCR are skipped.
+    /// The order is only checked among the passed troves, there is no on-chain sorted list to check it against
     RedeemCollateral{
         #[allow(dead_code)]
         solusd_amount: u128,
@@ -38,26 +78,392 @@ pub enum TroveManagerInstruction {
         max_fee_percentage: u128,
 
         #[allow(dead_code)]
-        total_sol_drawn: u128,
+        nonce: u8,
+    },
+    /// Liquidate up to `number` troves. The troves are passed after the fixed accounts
+    /// as (trove, reward snapshot, owner, coll surplus) accounts, starting from the lowest ICR.
+    /// The offset debt is burnt from the stability pool solUSD token account, the last fixed accounts
+    /// are that `[w]` token account and the `[w]` SOLUSD token mint
+    LiquidateTroves{
+        #[allow(dead_code)]
+        number:u128,
+    },
 
+    /// Send SOL of the active pool to a wSOL token account. Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[]` TroveManager account
+    ///   1. `[]` authority of the TroveManager account
+    ///   2. `[]` BorrowerOperations account
+    ///   3. `[s]` authority of the BorrowerOperations account
+    ///   4. `[w]` ActivePool account
+    ///   5. `[]` DefaultPool account
+    ///   6. `[]` CollSurplusPool account
+    ///   7. `[w]` active pool wSOL token account
+    ///   8. `[]` default pool wSOL token account
+    ///   9. `[]` coll surplus pool wSOL token account
+    ///   10. `[w]` destination wSOL token account
+    ///   11. `[]` Token program id
+    SendSol{
         #[allow(dead_code)]
-        total_solusd_to_redeem: u128,
+        amount:u128,
+    },
 
+    /// Send the whole collateral surplus of a borrower from the coll surplus pool to a wSOL token account.
+    /// Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[]` TroveManager account
+    ///   1. `[]` authority of the TroveManager account
+    ///   2. `[]` BorrowerOperations account
+    ///   3. `[s]` authority of the BorrowerOperations account
+    ///   4. `[w]` borrower coll surplus account
+    ///   5. `[]` ActivePool account
+    ///   6. `[]` DefaultPool account
+    ///   7. `[w]` CollSurplusPool account
+    ///   8. `[]` active pool wSOL token account
+    ///   9. `[]` default pool wSOL token account
+    ///   10. `[w]` coll surplus pool wSOL token account
+    ///   11. `[w]` destination wSOL token account
+    ///   12. `[]` Token program id
+    ClaimCollSurplus,
+
+    /// Initialize the price feed of the troves, the current pyth price becomes its first good price
+    ///
+    ///   0. `[w]` PriceFeed account, owned by this program
+    ///   1. `[]` TroveManager account
+    ///   2. `[s]` price feed authority
+    ///   3. `[]` oracle program id
+    ///   4. `[]` pyth product account
+    ///   5. `[]` pyth price account
+    ///   6. `[]` clock sysvar
+    InitializePriceFeed,
+
+    /// Set the authority and the limits of the price feed. Only callable by the price feed authority
+    ///
+    ///   0. `[w]` PriceFeed account
+    ///   1. `[s]` price feed authority
+    SetPriceFeedConfig{
         #[allow(dead_code)]
-        nonce: u8,
+        authority: Pubkey,
+        #[allow(dead_code)]
+        max_price_deviation: u128,
+        #[allow(dead_code)]
+        max_price_difference_between_sources: u128,
+        #[allow(dead_code)]
+        max_fallback_price_age_slots: u64,
     },
-    LiquidateTroves{
-        number:u128,
-        total_coll_in_sequence:u128,
-        total_debt_in_sequence:u128,
-        total_coll_gas_compensation:u128,
-        total_solusd_gas_compensation:u128,
-        total_debt_to_offset:u128,
-        total_coll_to_send_to_sp:u128,
-        total_debt_to_redistribute:u128,
-        total_coll_to_redistribute:u128,
-        total_coll_surplus:u128,
-    },
-    
 
+    /// Push the fallback price of the price feed. Only callable by the price feed authority
+    ///
+    ///   0. `[w]` PriceFeed account
+    ///   1. `[s]` price feed authority
+    ///   2. `[]` clock sysvar
+    PushFallbackPrice{
+        #[allow(dead_code)]
+        price: u128,
+    },
+
+    /// Set the guardian of the trove manager. Only callable by the current guardian
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[s]` guardian
+    SetGuardian{
+        #[allow(dead_code)]
+        guardian: Pubkey,
+    },
+
+    /// Set the bitmap of the paused operation classes, see the `PAUSE_*` constants.
+    /// Repaying and closing troves stay available. Only callable by the guardian
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[s]` guardian
+    SetPauseState{
+        #[allow(dead_code)]
+        paused_operations: u8,
+    },
+
+    /// Create the trove, reward snapshot and coll surplus accounts of a borrower when they don't exist yet and open
+    /// the trove with the given collateral and composite debt, adding both to the active pool.
+    /// Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[]` BorrowerOperations account
+    ///   2. `[s]` authority of the BorrowerOperations account
+    ///   3. `[w, s]` borrower wallet, pays for the new accounts
+    ///   4. `[w]` borrower trove account
+    ///   5. `[w]` borrower reward snapshot account
+    ///   6. `[w]` borrower coll surplus account
+    ///   7. `[w]` ActivePool account
+    ///   8. `[]` rent sysvar
+    ///   9. `[]` system program
+    OpenTrove{
+        #[allow(dead_code)]
+        coll: u128,
+        #[allow(dead_code)]
+        debt: u128,
+    },
+
+    /// Apply a collateral and debt change to an active trove and to the active pool.
+    /// Withdrawn collateral leaves the active pool with `SendSol`. Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[]` BorrowerOperations account
+    ///   2. `[s]` authority of the BorrowerOperations account
+    ///   3. `[w]` borrower trove account
+    ///   4. `[w]` ActivePool account
+    AdjustTrove{
+        #[allow(dead_code)]
+        coll_change: u128,
+        #[allow(dead_code)]
+        is_coll_increase: u8,
+        #[allow(dead_code)]
+        debt_change: u128,
+        #[allow(dead_code)]
+        is_debt_increase: u8,
+    },
+
+    /// Close an active trove and remove its debt from the active pool.
+    /// Its collateral leaves the active pool with `SendSol`. Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[]` BorrowerOperations account
+    ///   2. `[s]` authority of the BorrowerOperations account
+    ///   3. `[w]` borrower trove account
+    ///   4. `[w]` borrower reward snapshot account
+    ///   5. `[w]` ActivePool account
+    CloseTrove,
+
+    /// Decay the base rate for the time passed since the last fee operation, before a borrowing fee is charged.
+    /// Only callable by the BorrowerOperations authority
+    ///
+    ///   0. `[w]` TroveManager account
+    ///   1. `[]` BorrowerOperations account
+    ///   2. `[s]` authority of the BorrowerOperations account
+    ///   3. `[]` clock sysvar
+    DecayBaseRateFromBorrowing,
+}
+
+/// Creates an `ApplyPendingRewards` instruction
+pub fn apply_pending_rewards(
+    trove_manager_id: &Pubkey,
+    trove_manager_authority: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    borrower_trove: &Pubkey,
+    reward_snapshot: &Pubkey,
+    active_pool_id: &Pubkey,
+    default_pool_id: &Pubkey,
+    coll_surplus_pool_id: &Pubkey,
+    active_pool_wsol: &Pubkey,
+    default_pool_wsol: &Pubkey,
+    coll_surplus_pool_wsol: &Pubkey,
+    token_program_id: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*trove_manager_id, false),
+        AccountMeta::new_readonly(*trove_manager_authority, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new(*borrower_trove, false),
+        AccountMeta::new(*reward_snapshot, false),
+        AccountMeta::new(*active_pool_id, false),
+        AccountMeta::new(*default_pool_id, false),
+        AccountMeta::new_readonly(*coll_surplus_pool_id, false),
+        AccountMeta::new(*active_pool_wsol, false),
+        AccountMeta::new(*default_pool_wsol, false),
+        AccountMeta::new_readonly(*coll_surplus_pool_wsol, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::ApplyPendingRewards.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a `SendSol` instruction
+pub fn send_sol(
+    trove_manager_id: &Pubkey,
+    trove_manager_authority: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    active_pool_id: &Pubkey,
+    default_pool_id: &Pubkey,
+    coll_surplus_pool_id: &Pubkey,
+    active_pool_wsol: &Pubkey,
+    default_pool_wsol: &Pubkey,
+    coll_surplus_pool_wsol: &Pubkey,
+    destination: &Pubkey,
+    token_program_id: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+    amount: u128,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*trove_manager_id, false),
+        AccountMeta::new_readonly(*trove_manager_authority, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new(*active_pool_id, false),
+        AccountMeta::new_readonly(*default_pool_id, false),
+        AccountMeta::new_readonly(*coll_surplus_pool_id, false),
+        AccountMeta::new(*active_pool_wsol, false),
+        AccountMeta::new_readonly(*default_pool_wsol, false),
+        AccountMeta::new_readonly(*coll_surplus_pool_wsol, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::SendSol{amount}.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a `ClaimCollSurplus` instruction
+pub fn claim_coll_surplus(
+    trove_manager_id: &Pubkey,
+    trove_manager_authority: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    coll_surplus: &Pubkey,
+    active_pool_id: &Pubkey,
+    default_pool_id: &Pubkey,
+    coll_surplus_pool_id: &Pubkey,
+    active_pool_wsol: &Pubkey,
+    default_pool_wsol: &Pubkey,
+    coll_surplus_pool_wsol: &Pubkey,
+    destination: &Pubkey,
+    token_program_id: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*trove_manager_id, false),
+        AccountMeta::new_readonly(*trove_manager_authority, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new(*coll_surplus, false),
+        AccountMeta::new_readonly(*active_pool_id, false),
+        AccountMeta::new_readonly(*default_pool_id, false),
+        AccountMeta::new(*coll_surplus_pool_id, false),
+        AccountMeta::new_readonly(*active_pool_wsol, false),
+        AccountMeta::new_readonly(*default_pool_wsol, false),
+        AccountMeta::new(*coll_surplus_pool_wsol, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::ClaimCollSurplus.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates an `OpenTrove` instruction
+pub fn open_trove(
+    trove_manager_id: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    borrower: &Pubkey,
+    borrower_trove: &Pubkey,
+    reward_snapshot: &Pubkey,
+    coll_surplus: &Pubkey,
+    active_pool_id: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+    coll: u128,
+    debt: u128,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*trove_manager_id, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new(*borrower, true),
+        AccountMeta::new(*borrower_trove, false),
+        AccountMeta::new(*reward_snapshot, false),
+        AccountMeta::new(*coll_surplus, false),
+        AccountMeta::new(*active_pool_id, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::OpenTrove{coll, debt}.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates an `AdjustTrove` instruction
+pub fn adjust_trove(
+    trove_manager_id: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    borrower_trove: &Pubkey,
+    active_pool_id: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+    coll_change: u128,
+    is_coll_increase: bool,
+    debt_change: u128,
+    is_debt_increase: bool,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*trove_manager_id, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new(*borrower_trove, false),
+        AccountMeta::new(*active_pool_id, false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::AdjustTrove{
+            coll_change,
+            is_coll_increase: is_coll_increase as u8,
+            debt_change,
+            is_debt_increase: is_debt_increase as u8,
+        }.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a `CloseTrove` instruction
+pub fn close_trove(
+    trove_manager_id: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    borrower_trove: &Pubkey,
+    reward_snapshot: &Pubkey,
+    active_pool_id: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*trove_manager_id, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new(*borrower_trove, false),
+        AccountMeta::new(*reward_snapshot, false),
+        AccountMeta::new(*active_pool_id, false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::CloseTrove.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a `DecayBaseRateFromBorrowing` instruction
+pub fn decay_base_rate_from_borrowing(
+    trove_manager_id: &Pubkey,
+    borrower_operations_id: &Pubkey,
+    borrower_operations_authority: &Pubkey,
+    trove_manager_program_id: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*trove_manager_id, false),
+        AccountMeta::new_readonly(*borrower_operations_id, false),
+        AccountMeta::new_readonly(*borrower_operations_authority, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    Instruction {
+        program_id: *trove_manager_program_id,
+        accounts,
+        data: TroveManagerInstruction::DecayBaseRateFromBorrowing.try_to_vec().unwrap(),
+    }
 }
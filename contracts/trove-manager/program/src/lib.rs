@@ -16,6 +16,7 @@ pub mod instruction;
 pub mod processor;
 
 // Declare and export the program's entrypoint
+#[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
 
 // Program entrypoint's implementation
@@ -22,8 +22,12 @@ use {
             StabilityPool,
             CommunityIssuance,
             CollSurplusPool,
+            CollSurplus,
             EpochToScale,
-            SOLIDStaking
+            SOLIDStaking,
+            BorrowerOperations,
+            PriceFeed,
+            PriceFeedStatus
         },
         constant::{
             DECIMAL_PRECISION,
@@ -35,7 +39,17 @@ use {
             MINUTE_DECAY_FACTOR,
             REDEMPTION_FEE_FLOOR,
             SECONDS_IN_ONE_MINUTE,
-            BETA
+            BETA,
+            USD_QUOTE_CURRENCY,
+            DEFAULT_MAX_PRICE_DEVIATION,
+            DEFAULT_MAX_PRICE_DIFFERENCE_BETWEEN_SOURCES,
+            DEFAULT_MAX_FALLBACK_PRICE_AGE_SLOTS,
+            PAUSE_LIQUIDATE,
+            PAUSE_REDEEM,
+            TROVE_PREFIX,
+            REWARD_SNAPSHOT_PREFIX,
+            COLL_SURPLUS_PREFIX,
+            TROVE_VERSION
         },
         liquity_math::{
             compute_cr,
@@ -46,6 +60,7 @@ use {
         pyth,
         math::{Decimal,  TryDiv, TryMul},
         utils::*,
+        price_feed::fetch_price,
     },
     crate::{
         instruction::{TroveManagerInstruction},
@@ -103,35 +118,81 @@ impl Processor {
                 partial_redemption_hint_nicr,
                 max_iterations,
                 max_fee_percentage,
-                total_sol_drawn,
-                total_solusd_to_redeem,
                 nonce
             } => {
                 // Instruction: Initialize
-                Self::process_redeem_collateral(program_id, accounts, solusd_amount, partial_redemption_hint_nicr, max_iterations, max_fee_percentage, total_sol_drawn,total_solusd_to_redeem, nonce)
+                Self::process_redeem_collateral(program_id, accounts, solusd_amount, partial_redemption_hint_nicr, max_iterations, max_fee_percentage, nonce)
             }
             TroveManagerInstruction::LiquidateTroves{
                 number,
-                total_coll_in_sequence,
-                total_debt_in_sequence,
-                total_coll_gas_compensation,
-                total_solusd_gas_compensation,
-                total_debt_to_offset,
-                total_coll_to_send_to_sp,
-                total_debt_to_redistribute,
-                total_coll_to_redistribute,
-                total_coll_surplus,
             } => {
                 // Instruction: Initialize
-                Self::process_liquidate_troves(program_id, accounts, number, total_coll_in_sequence,
-                    total_debt_in_sequence,
-                    total_coll_gas_compensation,
-                    total_solusd_gas_compensation,
-                    total_debt_to_offset,
-                    total_coll_to_send_to_sp,
-                    total_debt_to_redistribute,
-                    total_coll_to_redistribute,
-                    total_coll_surplus)
+                Self::process_liquidate_troves(program_id, accounts, number)
+            }
+            TroveManagerInstruction::SendSol{
+                amount,
+            } => {
+                // Instruction: SendSol
+                Self::process_send_sol(program_id, accounts, amount)
+            }
+            TroveManagerInstruction::ClaimCollSurplus => {
+                // Instruction: ClaimCollSurplus
+                Self::process_claim_coll_surplus(program_id, accounts)
+            }
+            TroveManagerInstruction::InitializePriceFeed => {
+                // Instruction: InitializePriceFeed
+                Self::process_initialize_price_feed(program_id, accounts)
+            }
+            TroveManagerInstruction::SetPriceFeedConfig{
+                authority,
+                max_price_deviation,
+                max_price_difference_between_sources,
+                max_fallback_price_age_slots,
+            } => {
+                // Instruction: SetPriceFeedConfig
+                Self::process_set_price_feed_config(accounts, authority, max_price_deviation, max_price_difference_between_sources, max_fallback_price_age_slots)
+            }
+            TroveManagerInstruction::PushFallbackPrice{
+                price,
+            } => {
+                // Instruction: PushFallbackPrice
+                Self::process_push_fallback_price(accounts, price)
+            }
+            TroveManagerInstruction::SetGuardian{
+                guardian,
+            } => {
+                // Instruction: SetGuardian
+                Self::process_set_guardian(accounts, guardian)
+            }
+            TroveManagerInstruction::SetPauseState{
+                paused_operations,
+            } => {
+                // Instruction: SetPauseState
+                Self::process_set_pause_state(accounts, paused_operations)
+            }
+            TroveManagerInstruction::OpenTrove{
+                coll,
+                debt,
+            } => {
+                // Instruction: OpenTrove
+                Self::process_open_trove(program_id, accounts, coll, debt)
+            }
+            TroveManagerInstruction::AdjustTrove{
+                coll_change,
+                is_coll_increase,
+                debt_change,
+                is_debt_increase,
+            } => {
+                // Instruction: AdjustTrove
+                Self::process_adjust_trove(program_id, accounts, coll_change, is_coll_increase != 0, debt_change, is_debt_increase != 0)
+            }
+            TroveManagerInstruction::CloseTrove => {
+                // Instruction: CloseTrove
+                Self::process_close_trove(program_id, accounts)
+            }
+            TroveManagerInstruction::DecayBaseRateFromBorrowing => {
+                // Instruction: DecayBaseRateFromBorrowing
+                Self::process_decay_base_rate_from_borrowing(accounts)
             }
         }
     }
@@ -161,6 +222,10 @@ impl Processor {
         let solid_staking_id_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let guardian_info = next_account_info(account_info_iter)?;
 
         // check if this SOLID staking pool account was created by this program with authority and nonce
         // if fail, returns InvalidProgramAddress error
@@ -168,8 +233,38 @@ impl Processor {
             return Err(LiquityError::InvalidProgramAddress.into());
         }
 
+        if trove_manager_id_info.owner != program_id {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        // the guardian is the admin of the trove manager, it signs the initialization
+        if !guardian_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the trove manager can only be initialized once, a second initialization would replace its ids and guardian
         let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+        if trove_manager_data.nonce != 0 || trove_manager_data.borrower_operations_id != Pubkey::default() {
+            return Err(LiquityError::AlreadyInUse.into());
+        }
+
+        // the SOL of the pools is held in wSOL token accounts owned by the trove manager authority
+        assert_wsol_account(active_pool_wsol_info, authority_info.key)?;
+        assert_wsol_account(default_pool_wsol_info, authority_info.key)?;
+        assert_wsol_account(coll_surplus_pool_wsol_info, authority_info.key)?;
+
+        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_id_info.data.borrow())?;
+        let mut default_pool_data = try_from_slice_unchecked::<DefaultPool>(&default_pool_id_info.data.borrow())?;
+        let mut coll_surplus_pool_data = try_from_slice_unchecked::<CollSurplusPool>(&coll_surplus_pool_id_info.data.borrow())?;
 
+        active_pool_data.wsol_pubkey = *active_pool_wsol_info.key;
+        default_pool_data.wsol_pubkey = *default_pool_wsol_info.key;
+        coll_surplus_pool_data.wsol_pubkey = *coll_surplus_pool_wsol_info.key;
+
+        active_pool_data.serialize(&mut &mut active_pool_id_info.data.borrow_mut()[..])?;
+        default_pool_data.serialize(&mut &mut default_pool_id_info.data.borrow_mut()[..])?;
+        coll_surplus_pool_data.serialize(&mut &mut coll_surplus_pool_id_info.data.borrow_mut()[..])?;
+
+        trove_manager_data.nonce = nonce;
         trove_manager_data.borrower_operations_id = *borrow_operations_id_info.key;
         trove_manager_data.default_pool_id = *default_pool_id_info.key;
         trove_manager_data.active_pool_id = *active_pool_id_info.key;
@@ -182,8 +277,9 @@ impl Processor {
         trove_manager_data.solusd_token_pubkey = *solusd_token_id_info.key;
         trove_manager_data.solid_staking_pubkey = *solid_staking_id_info.key;
         trove_manager_data.token_program_id = *token_program_info.key;
+        trove_manager_data.guardian = *guardian_info.key;
 
-        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..]);
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
 
         Ok(())
     } 
@@ -194,36 +290,642 @@ impl Processor {
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let trove_manager_id_info = next_account_info(account_info_iter)?;
-        let borrower_info = next_account_info(account_info_iter)?;
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
         let borrower_trove_info = next_account_info(account_info_iter)?;
         let reward_snapshots_info = next_account_info(account_info_iter)?;
-        let default_pool_info = next_account_info(account_info_iter)?;
         let active_pool_info = next_account_info(account_info_iter)?;
-        let caller_info = next_account_info(account_info_iter)?;
-        let authority_info = next_account_info(account_info_iter)?;
+        let default_pool_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
 
         let trove_manager_data = try_from_slice_unchecked::<TroveManager>(&mut trove_manager_id_info.data.borrow())?;
         let mut borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
         let mut reward_snapshot = try_from_slice_unchecked::<RewardSnapshot>(&reward_snapshots_info.data.borrow())?;
 
-        if *caller_info.key != trove_manager_data.borrower_operations_id {
-            return Err(LiquityError::InvalidBorrwerOperations.into());
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+        if *trove_manager_authority_info.key != authority_id(program_id, trove_manager_id_info.key, trove_manager_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+        Self::assert_pool_accounts(&trove_manager_data, active_pool_info, default_pool_info, coll_surplus_pool_info)?;
+        Self::assert_trove_account(program_id, &trove_manager_data, &borrower_trove.owner_pubkey, borrower_trove_info, TROVE_PREFIX)?;
+        Self::assert_trove_account(program_id, &trove_manager_data, &borrower_trove.owner_pubkey, reward_snapshots_info, REWARD_SNAPSHOT_PREFIX)?;
+        if reward_snapshot.owner_pubkey != borrower_trove.owner_pubkey {
+            return Err(LiquityError::InvalidRewardSnapshot.into());
         }
 
-        let mut default_pool_data = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow()).unwrap();
-        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow()).unwrap();
+        let mut default_pool_data = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow())?;
+        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let coll_surplus_pool_data = try_from_slice_unchecked::<CollSurplusPool>(&coll_surplus_pool_info.data.borrow())?;
+
+        let pool_wsol = PoolWsolAccounts::new(
+            trove_manager_id_info.key,
+            trove_manager_data.nonce,
+            token_program_info,
+            trove_manager_authority_info,
+            &active_pool_data,
+            active_pool_wsol_info,
+            &default_pool_data,
+            default_pool_wsol_info,
+            &coll_surplus_pool_data,
+            coll_surplus_pool_wsol_info,
+        )?;
 
         apply_pending_rewards(
             &trove_manager_data, 
             &mut borrower_trove,
             &mut reward_snapshot, 
             &mut default_pool_data, 
-            &mut active_pool_data
-        );
+            &mut active_pool_data,
+            &pool_wsol
+        )?;
+
+        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+        reward_snapshot.serialize(&mut &mut reward_snapshots_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        default_pool_data.serialize(&mut &mut default_pool_info.data.borrow_mut()[..])?;
 
         Ok(())
     } 
 
+    /// process `SendSol` instruction.
+    /// BorrowerOperations can not sign for the pool wSOL accounts, it sends collateral out of the active pool through here.
+    pub fn process_send_sol(
+        program_id: &Pubkey,        // this program id
+        accounts: &[AccountInfo],   // all account informations
+        amount: u128,               // SOL amount to send
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let active_pool_info = next_account_info(account_info_iter)?;
+        let default_pool_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let trove_manager_data = try_from_slice_unchecked::<TroveManager>(&mut trove_manager_id_info.data.borrow())?;
+
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+        if *trove_manager_authority_info.key != authority_id(program_id, trove_manager_id_info.key, trove_manager_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+        Self::assert_pool_accounts(&trove_manager_data, active_pool_info, default_pool_info, coll_surplus_pool_info)?;
+
+        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let default_pool_data = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow())?;
+        let coll_surplus_pool_data = try_from_slice_unchecked::<CollSurplusPool>(&coll_surplus_pool_info.data.borrow())?;
+
+        let pool_wsol = PoolWsolAccounts::new(
+            trove_manager_id_info.key,
+            trove_manager_data.nonce,
+            token_program_info,
+            trove_manager_authority_info,
+            &active_pool_data,
+            active_pool_wsol_info,
+            &default_pool_data,
+            default_pool_wsol_info,
+            &coll_surplus_pool_data,
+            coll_surplus_pool_wsol_info,
+        )?;
+
+        active_pool_data.send_sol(amount);
+        pool_wsol.send_sol(&pool_wsol.active_pool, destination_info, amount)?;
+
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `ClaimCollSurplus` instruction, send the whole collateral surplus of a borrower to the destination
+    pub fn process_claim_coll_surplus(
+        program_id: &Pubkey,        // this program id
+        accounts: &[AccountInfo],   // all account informations
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let coll_surplus_info = next_account_info(account_info_iter)?;
+        let active_pool_info = next_account_info(account_info_iter)?;
+        let default_pool_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let trove_manager_data = try_from_slice_unchecked::<TroveManager>(&mut trove_manager_id_info.data.borrow())?;
+
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+        if *trove_manager_authority_info.key != authority_id(program_id, trove_manager_id_info.key, trove_manager_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+        Self::assert_pool_accounts(&trove_manager_data, active_pool_info, default_pool_info, coll_surplus_pool_info)?;
+
+        let active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let default_pool_data = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow())?;
+        let mut coll_surplus_pool_data = try_from_slice_unchecked::<CollSurplusPool>(&coll_surplus_pool_info.data.borrow())?;
+        let mut coll_surplus = try_from_slice_unchecked::<CollSurplus>(&coll_surplus_info.data.borrow())?;
+        Self::assert_trove_account(program_id, &trove_manager_data, &coll_surplus.owner_pubkey, coll_surplus_info, COLL_SURPLUS_PREFIX)?;
+
+        let pool_wsol = PoolWsolAccounts::new(
+            trove_manager_id_info.key,
+            trove_manager_data.nonce,
+            token_program_info,
+            trove_manager_authority_info,
+            &active_pool_data,
+            active_pool_wsol_info,
+            &default_pool_data,
+            default_pool_wsol_info,
+            &coll_surplus_pool_data,
+            coll_surplus_pool_wsol_info,
+        )?;
+
+        let claimable_coll = coll_surplus_pool_data.claim_coll(&mut coll_surplus)?;
+        pool_wsol.send_sol(&pool_wsol.coll_surplus_pool, destination_info, claimable_coll)?;
+
+        coll_surplus.serialize(&mut &mut coll_surplus_info.data.borrow_mut()[..])?;
+        coll_surplus_pool_data.serialize(&mut &mut coll_surplus_pool_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `OpenTrove` instruction.
+    /// The trove accounts are PDAs of this program, so they are created here and only written by this program.
+    pub fn process_open_trove(
+        program_id: &Pubkey,        // this program id
+        accounts: &[AccountInfo],   // all account informations
+        coll: u128,                 // collateral of the new trove
+        debt: u128,                 // composite debt of the new trove
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let borrower_info = next_account_info(account_info_iter)?;
+        let borrower_trove_info = next_account_info(account_info_iter)?;
+        let reward_snapshot_info = next_account_info(account_info_iter)?;
+        let coll_surplus_info = next_account_info(account_info_iter)?;
+        let active_pool_info = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let system_info = next_account_info(account_info_iter)?;
+
+        let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+        if *active_pool_info.key != trove_manager_data.active_pool_id {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+
+        let trove_accounts = [
+            (borrower_trove_info, TROVE_PREFIX, std::mem::size_of::<Trove>()),
+            (reward_snapshot_info, REWARD_SNAPSHOT_PREFIX, std::mem::size_of::<RewardSnapshot>()),
+            (coll_surplus_info, COLL_SURPLUS_PREFIX, std::mem::size_of::<CollSurplus>()),
+        ];
+        for (info, prefix, size) in trove_accounts.iter() {
+            let bump = Self::assert_trove_account_key(program_id, &trove_manager_data, borrower_info.key, info, prefix)?;
+            if info.data_is_empty() {
+                create_or_allocate_account_raw(
+                    *program_id,
+                    info,
+                    rent_info,
+                    system_info,
+                    borrower_info,
+                    *size,
+                    &[
+                        prefix.as_bytes(),
+                        borrower_info.key.as_ref(),
+                        trove_manager_data.borrower_operations_id.as_ref(),
+                        &[bump],
+                    ],
+                )?;
+            }
+            else if info.owner != program_id {
+                return Err(LiquityError::InvalidOwner.into());
+            }
+        }
+
+        let mut borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+        let mut reward_snapshot = try_from_slice_unchecked::<RewardSnapshot>(&reward_snapshot_info.data.borrow())?;
+        let mut coll_surplus = try_from_slice_unchecked::<CollSurplus>(&coll_surplus_info.data.borrow())?;
+        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+
+        if !borrower_trove.is_valid_version() {
+            return Err(LiquityError::InvalidTroveVersion.into());
+        }
+        if borrower_trove.is_active() {
+            return Err(LiquityError::ErrorTroveisActive.into());
+        }
+
+        borrower_trove.version = TROVE_VERSION;
+        borrower_trove.pool_id_pubkey = trove_manager_data.borrower_operations_id;
+        borrower_trove.owner_pubkey = *borrower_info.key;
+        borrower_trove.status = Status::Active as u8;
+        borrower_trove.coll = coll;
+        borrower_trove.debt = debt;
+
+        reward_snapshot.pool_id_pubkey = trove_manager_data.borrower_operations_id;
+        reward_snapshot.owner_pubkey = *borrower_info.key;
+        reward_snapshot.update_trove_reward_snapshots(&trove_manager_data);
+        update_stake_and_total_stakes(&mut trove_manager_data, &mut borrower_trove);
+
+        // the collateral surplus account outlives the trove, liquidations and redemptions record the surplus in it
+        coll_surplus.pool_id_pubkey = trove_manager_data.borrower_operations_id;
+        coll_surplus.owner_pubkey = *borrower_info.key;
+
+        active_pool_data.increase_coll(coll);
+        active_pool_data.increase_solusd_debt(debt);
+
+        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+        reward_snapshot.serialize(&mut &mut reward_snapshot_info.data.borrow_mut()[..])?;
+        coll_surplus.serialize(&mut &mut coll_surplus_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `AdjustTrove` instruction.
+    pub fn process_adjust_trove(
+        program_id: &Pubkey,        // this program id
+        accounts: &[AccountInfo],   // all account informations
+        coll_change: u128,
+        is_coll_increase: bool,
+        debt_change: u128,
+        is_debt_increase: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let borrower_trove_info = next_account_info(account_info_iter)?;
+        let active_pool_info = next_account_info(account_info_iter)?;
+
+        let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+        let mut borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+        if *active_pool_info.key != trove_manager_data.active_pool_id {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        Self::assert_trove_account(program_id, &trove_manager_data, &borrower_trove.owner_pubkey, borrower_trove_info, TROVE_PREFIX)?;
+        if !borrower_trove.is_active() {
+            return Err(LiquityError::TroveNotActive.into());
+        }
+
+        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+
+        if is_coll_increase {
+            borrower_trove.increase_trove_coll(coll_change);
+            active_pool_data.increase_coll(coll_change);
+        }
+        else {
+            borrower_trove.decrease_trove_coll(coll_change);
+        }
+        if is_debt_increase {
+            borrower_trove.increase_trove_debt(debt_change);
+            active_pool_data.increase_solusd_debt(debt_change);
+        }
+        else {
+            borrower_trove.decrease_trove_debt(debt_change);
+            active_pool_data.decrease_solusd_debt(debt_change);
+        }
+        update_stake_and_total_stakes(&mut trove_manager_data, &mut borrower_trove);
+
+        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `CloseTrove` instruction.
+    pub fn process_close_trove(
+        program_id: &Pubkey,        // this program id
+        accounts: &[AccountInfo],   // all account informations
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let borrower_trove_info = next_account_info(account_info_iter)?;
+        let reward_snapshot_info = next_account_info(account_info_iter)?;
+        let active_pool_info = next_account_info(account_info_iter)?;
+
+        let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+        let mut borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+        let mut reward_snapshot = try_from_slice_unchecked::<RewardSnapshot>(&reward_snapshot_info.data.borrow())?;
+
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+        if *active_pool_info.key != trove_manager_data.active_pool_id {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        Self::assert_trove_account(program_id, &trove_manager_data, &borrower_trove.owner_pubkey, borrower_trove_info, TROVE_PREFIX)?;
+        Self::assert_trove_account(program_id, &trove_manager_data, &borrower_trove.owner_pubkey, reward_snapshot_info, REWARD_SNAPSHOT_PREFIX)?;
+        if !borrower_trove.is_active() {
+            return Err(LiquityError::TroveNotActive.into());
+        }
+
+        let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+
+        active_pool_data.decrease_solusd_debt(borrower_trove.debt);
+        remove_stake(&mut trove_manager_data, &mut borrower_trove);
+        borrower_trove.close_trove();
+        reward_snapshot.reset();
+
+        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+        reward_snapshot.serialize(&mut &mut reward_snapshot_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `DecayBaseRateFromBorrowing` instruction.
+    pub fn process_decay_base_rate_from_borrowing(
+        accounts: &[AccountInfo],   // all account informations
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let borrower_operations_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+        let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+
+        Self::assert_borrower_operations_caller(&trove_manager_data, borrower_operations_info, caller_info)?;
+
+        decay_base_rate_from_borrowing(&mut trove_manager_data, clock.unix_timestamp as u128);
+
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `InitializePriceFeed` instruction.
+    pub fn process_initialize_price_feed(
+        program_id: &Pubkey,        // this program id
+        accounts: &[AccountInfo],   // all account informations
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let price_feed_info = next_account_info(account_info_iter)?;
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let oracle_program_id_info = next_account_info(account_info_iter)?;
+        let pyth_product_info = next_account_info(account_info_iter)?;
+        let pyth_price_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+        if price_feed_info.owner != program_id {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        if !authority_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut price_feed = try_from_slice_unchecked::<PriceFeed>(&price_feed_info.data.borrow())?;
+        if price_feed.trove_manager_id != Pubkey::default() {
+            return Err(LiquityError::AlreadyInUse.into());
+        }
+
+        price_feed.oracle_program_id = *oracle_program_id_info.key;
+        price_feed.quote_currency = USD_QUOTE_CURRENCY;
+        let market_price = get_market_price(
+            price_feed.oracle_program_id,
+            price_feed.quote_currency,
+            pyth_product_info,
+            pyth_price_info,
+            clock
+        )?;
+
+        price_feed.trove_manager_id = *trove_manager_id_info.key;
+        price_feed.authority = *authority_info.key;
+        price_feed.pyth_product_id = *pyth_product_info.key;
+        price_feed.pyth_price_id = *pyth_price_info.key;
+        price_feed.status = PriceFeedStatus::PythWorking;
+        price_feed.last_good_price = market_price;
+        price_feed.last_good_price_slot = clock.slot;
+        price_feed.max_price_deviation = DEFAULT_MAX_PRICE_DEVIATION;
+        price_feed.max_price_difference_between_sources = DEFAULT_MAX_PRICE_DIFFERENCE_BETWEEN_SOURCES;
+        price_feed.max_fallback_price_age_slots = DEFAULT_MAX_FALLBACK_PRICE_AGE_SLOTS;
+
+        price_feed.serialize(&mut &mut price_feed_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `SetPriceFeedConfig` instruction.
+    pub fn process_set_price_feed_config(
+        accounts: &[AccountInfo],   // all account informations
+        authority: Pubkey,
+        max_price_deviation: u128,
+        max_price_difference_between_sources: u128,
+        max_fallback_price_age_slots: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let price_feed_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let mut price_feed = try_from_slice_unchecked::<PriceFeed>(&price_feed_info.data.borrow())?;
+        Self::assert_price_feed_authority(&price_feed, authority_info)?;
+
+        if max_price_deviation == 0 || max_price_deviation > DECIMAL_PRECISION ||
+            max_price_difference_between_sources == 0 || max_price_difference_between_sources > DECIMAL_PRECISION ||
+            max_fallback_price_age_slots == 0
+        {
+            return Err(LiquityError::InvalidPriceFeedConfig.into());
+        }
+
+        price_feed.authority = authority;
+        price_feed.max_price_deviation = max_price_deviation;
+        price_feed.max_price_difference_between_sources = max_price_difference_between_sources;
+        price_feed.max_fallback_price_age_slots = max_fallback_price_age_slots;
+
+        price_feed.serialize(&mut &mut price_feed_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `PushFallbackPrice` instruction.
+    pub fn process_push_fallback_price(
+        accounts: &[AccountInfo],   // all account informations
+        price: u128,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let price_feed_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+        let mut price_feed = try_from_slice_unchecked::<PriceFeed>(&price_feed_info.data.borrow())?;
+        Self::assert_price_feed_authority(&price_feed, authority_info)?;
+
+        if price == 0 {
+            return Err(LiquityError::InvalidFallbackPrice.into());
+        }
+
+        price_feed.fallback_price = price;
+        price_feed.fallback_price_slot = clock.slot;
+
+        price_feed.serialize(&mut &mut price_feed_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `SetGuardian` instruction.
+    pub fn process_set_guardian(
+        accounts: &[AccountInfo],   // all account informations
+        guardian: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let guardian_info = next_account_info(account_info_iter)?;
+
+        let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+        if !guardian_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *guardian_info.key != trove_manager_data.guardian {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+
+        trove_manager_data.guardian = guardian;
+
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// process `SetPauseState` instruction.
+    pub fn process_set_pause_state(
+        accounts: &[AccountInfo],   // all account informations
+        paused_operations: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let trove_manager_id_info = next_account_info(account_info_iter)?;
+        let guardian_info = next_account_info(account_info_iter)?;
+
+        let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&trove_manager_id_info.data.borrow())?;
+        let guardian = trove_manager_data.guardian;
+        set_pause_state(&guardian, guardian_info, &mut trove_manager_data.paused_operations, paused_operations)?;
+
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    /// check that the signer is the authority of an initialized price feed
+    fn assert_price_feed_authority(
+        price_feed: &PriceFeed,
+        authority_info: &AccountInfo,
+    ) -> ProgramResult {
+        if price_feed.trove_manager_id == Pubkey::default() {
+            return Err(LiquityError::InvalidPriceFeed.into());
+        }
+        if !authority_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *authority_info.key != price_feed.authority {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        Ok(())
+    }
+
+    /// check that the caller is the authority of the BorrowerOperations account registered in the trove manager
+    fn assert_borrower_operations_caller(
+        trove_manager_data: &TroveManager,
+        borrower_operations_info: &AccountInfo,
+        caller_info: &AccountInfo,
+    ) -> ProgramResult {
+        if *borrower_operations_info.key != trove_manager_data.borrower_operations_id {
+            return Err(LiquityError::InvalidBorrwerOperations.into());
+        }
+        if !caller_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let borrower_operations = try_from_slice_unchecked::<BorrowerOperations>(&borrower_operations_info.data.borrow())?;
+        if *caller_info.key != authority_id(borrower_operations_info.owner, borrower_operations_info.key, borrower_operations.nonce)? {
+            return Err(LiquityError::InvalidBorrwerOperations.into());
+        }
+        Ok(())
+    }
+
+    /// check that the pool accounts are the ones registered in the trove manager
+    fn assert_pool_accounts(
+        trove_manager_data: &TroveManager,
+        active_pool_info: &AccountInfo,
+        default_pool_info: &AccountInfo,
+        coll_surplus_pool_info: &AccountInfo,
+    ) -> ProgramResult {
+        if *active_pool_info.key != trove_manager_data.active_pool_id ||
+            *default_pool_info.key != trove_manager_data.default_pool_id ||
+            *coll_surplus_pool_info.key != trove_manager_data.coll_surplus_pool_id
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        Ok(())
+    }
+
+    /// check that the account is the `prefix` PDA of this program for `owner`, returns its bump
+    fn assert_trove_account_key(
+        program_id: &Pubkey,
+        trove_manager_data: &TroveManager,
+        owner: &Pubkey,
+        info: &AccountInfo,
+        prefix: &str,
+    ) -> Result<u8, ProgramError> {
+        let seeds = [
+            prefix.as_bytes(),
+            owner.as_ref(),
+            trove_manager_data.borrower_operations_id.as_ref(),
+        ];
+        let (pda_key, bump) = Pubkey::find_program_address(&seeds, program_id);
+        if pda_key != *info.key {
+            return Err(LiquityError::InvalidPdaAddress.into());
+        }
+        Ok(bump)
+    }
+
+    /// check that the account is owned by this program and is the `prefix` PDA for `owner`
+    fn assert_trove_account(
+        program_id: &Pubkey,
+        trove_manager_data: &TroveManager,
+        owner: &Pubkey,
+        info: &AccountInfo,
+        prefix: &str,
+    ) -> ProgramResult {
+        if info.owner != program_id {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        Self::assert_trove_account_key(program_id, trove_manager_data, owner, info, prefix)?;
+        Ok(())
+    }
+
+    /// check that the trove, reward snapshot and collateral surplus accounts are owned by this program and derived for `owner`
+    fn assert_trove_accounts(
+        program_id: &Pubkey,
+        trove_manager_data: &TroveManager,
+        owner: &Pubkey,
+        borrower_trove_info: &AccountInfo,
+        reward_snapshot_info: &AccountInfo,
+        coll_surplus_info: &AccountInfo,
+    ) -> ProgramResult {
+        Self::assert_trove_account(program_id, trove_manager_data, owner, borrower_trove_info, TROVE_PREFIX)?;
+        Self::assert_trove_account(program_id, trove_manager_data, owner, reward_snapshot_info, REWARD_SNAPSHOT_PREFIX)?;
+        Self::assert_trove_account(program_id, trove_manager_data, owner, coll_surplus_info, COLL_SURPLUS_PREFIX)
+    }
+
     /*
     * Attempt to liquidate a custom list of troves provided by the caller.
     */
@@ -244,7 +946,20 @@ impl Processor {
         let epoch_to_scale_info = next_account_info(account_info_iter)?;
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
+        let price_feed_info = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+        let gas_pool_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let liquidator_solusd_info = next_account_info(account_info_iter)?;
+        let liquidator_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let stability_pool_wsol_info = next_account_info(account_info_iter)?;
+        let borrower_coll_surplus_info = next_account_info(account_info_iter)?;
+        let stability_pool_solusd_info = next_account_info(account_info_iter)?;
+        let solusd_token_info = next_account_info(account_info_iter)?;
         let cur_timestamp = clock.unix_timestamp as u64;
 
         let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&mut trove_manager_id_info.data.borrow())?;
@@ -256,14 +971,56 @@ impl Processor {
         let mut stability_pool_data = try_from_slice_unchecked::<StabilityPool>(&stability_pool_info.data.borrow())?;
         let mut community_issuance_data = try_from_slice_unchecked::<CommunityIssuance>(&community_issuance_id_info.data.borrow())?;
         let mut epoch_to_scale = try_from_slice_unchecked::<EpochToScale>(&epoch_to_scale_info.data.borrow())?;
+        let borrower_coll_surplus = try_from_slice_unchecked::<CollSurplus>(&borrower_coll_surplus_info.data.borrow())?;
+
+        require_not_paused(trove_manager_data.paused_operations, PAUSE_LIQUIDATE)?;
+
+        if *gas_pool_info.key != trove_manager_data.gas_pool_id {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        if *trove_manager_authority_info.key != authority_id(program_id, trove_manager_id_info.key, trove_manager_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+        Self::assert_trove_accounts(program_id, &trove_manager_data, borrower_info.key, borrower_trove_info, reward_snapshots_info, borrower_coll_surplus_info)?;
+        if borrower_trove.owner_pubkey != *borrower_info.key {
+            return Err(LiquityError::InvalidOwner.into());
+        }
+        if reward_snapshots_data.owner_pubkey != borrower_trove.owner_pubkey {
+            return Err(LiquityError::InvalidRewardSnapshot.into());
+        }
+        if borrower_coll_surplus.owner_pubkey != borrower_trove.owner_pubkey {
+            return Err(LiquityError::InvalidCollSurplus.into());
+        }
 
         if !borrower_trove.is_active() {
             return Err(LiquityError::TroveNotActive.into());
         }
 
-        let market_price = get_market_price(
-            stability_pool_data.oracle_program_id,
-            stability_pool_data.quote_currency,
+        Self::assert_pool_accounts(&trove_manager_data, active_pool_info, default_pool_info, coll_surplus_pool_info)?;
+        if *stability_pool_info.key != trove_manager_data.stability_pool_id ||
+            *stability_pool_wsol_info.key != stability_pool_data.sol_pool_token_pubkey ||
+            *stability_pool_solusd_info.key != stability_pool_data.sol_usd_pool_token_pubkey ||
+            *solusd_token_info.key != trove_manager_data.solusd_token_pubkey
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        let pool_wsol = PoolWsolAccounts::new(
+            trove_manager_id_info.key,
+            trove_manager_data.nonce,
+            token_program_info,
+            trove_manager_authority_info,
+            &active_pool_data,
+            active_pool_wsol_info,
+            &default_pool_data,
+            default_pool_wsol_info,
+            &coll_surplus_pool_data,
+            coll_surplus_pool_wsol_info,
+        )?;
+
+        let market_price = fetch_price(
+            program_id,
+            price_feed_info,
+            trove_manager_id_info.key,
             pyth_product_info,
             pyth_price_info,
             clock
@@ -280,7 +1037,8 @@ impl Processor {
         vars.price = market_price;
         vars.solusd_in_stab_pool = stability_pool_data.total_sol_usd_deposits as u128;
         vars.recovery_mode_at_start = trove_manager_data.check_recovery_mode(vars.price, &active_pool_data, &default_pool_data);
-        let mut totals = LiquidationTotals::new();
+        let mut troves = [(borrower_trove, reward_snapshots_data, borrower_coll_surplus)];
+        let totals;
         
         // Perform the appropriate liquidation sequence - tally values and obtain their totals.
         if vars.recovery_mode_at_start == 1 {
@@ -289,11 +1047,11 @@ impl Processor {
                 &mut trove_manager_data, 
                 &mut active_pool_data, 
                 &mut default_pool_data, 
+                &mut coll_surplus_pool_data,
                 vars.price, 
                 vars.solusd_in_stab_pool, 
-                borrower_info.key, 
-                &mut borrower_trove, 
-                &mut reward_snapshots_data);
+                &mut troves,
+                &pool_wsol)?;
         }
         else {//  if !vars.recoveryModeAtStart
             totals = get_total_from_batch_liquidate_normal_mode(
@@ -302,9 +1060,8 @@ impl Processor {
                 &mut default_pool_data, 
                 vars.price, 
                 vars.solusd_in_stab_pool, 
-                borrower_info.key, 
-                &mut borrower_trove, 
-                &mut reward_snapshots_data);
+                &mut troves,
+                &pool_wsol)?;
         }
 
         if totals.total_debt_in_sequence <= 0 {
@@ -313,13 +1070,13 @@ impl Processor {
  
         // Move liquidated SOL and SOLUSD to the appropriate pools
         //stabilityPoolCached.offset(totals.totalDebtToOffset, totals.totalCollToSendToSP); -- implemented
-        stability_pool_data.offset(totals.total_debt_to_offset, totals.total_coll_to_send_to_sp, community_issuance_data.issue_solid(cur_timestamp as u128), &mut active_pool_data, &mut epoch_to_scale);
-        redistribute_debt_and_coll(&mut trove_manager_data, &mut active_pool_data, &mut default_pool_data, totals.total_debt_to_redistribute, totals.total_coll_to_redistribute);
+        stability_pool_data.offset(totals.total_debt_to_offset, totals.total_coll_to_send_to_sp, community_issuance_data.issue_solid(cur_timestamp as u128), &mut active_pool_data, &mut epoch_to_scale, &pool_wsol, stability_pool_wsol_info, stability_pool_solusd_info, solusd_token_info)?;
+        redistribute_debt_and_coll(&mut trove_manager_data, &mut active_pool_data, &mut default_pool_data, totals.total_debt_to_redistribute, totals.total_coll_to_redistribute, &pool_wsol)?;
 
         if totals.total_coll_surplus > 0 {
             //activePoolCached.sendETH(address(collSurplusPool), totals.totalCollSurplus); -- implemented
-            active_pool_data.sol -= totals.total_coll_surplus;
-            coll_surplus_pool_data.sol += totals.total_coll_surplus;
+            active_pool_data.send_sol(totals.total_coll_surplus);
+            pool_wsol.send_sol(&pool_wsol.active_pool, &pool_wsol.coll_surplus_pool, totals.total_coll_surplus)?;
         }
 
         // update system snapshots
@@ -329,8 +1086,27 @@ impl Processor {
         vars.liquidated_coll = totals.total_coll_in_sequence - totals.total_coll_gas_compensation - totals.total_coll_surplus;
 
         // Send gas compensation to caller
+        send_gas_compensation(
+            &mut active_pool_data,
+            &pool_wsol,
+            gas_pool_info.clone(),
+            liquidator_solusd_info.clone(),
+            liquidator_wsol_info.clone(),
+            totals.total_solusd_gas_compensation,
+            totals.total_coll_gas_compensation
+        )?;
 
-        // _sendGasCompensation(activePoolCached, msg.sender, totals.totalLUSDGasCompensation, totals.totalCollGasCompensation);
+        let (borrower_trove, reward_snapshots_data, borrower_coll_surplus) = &troves[0];
+        borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+        reward_snapshots_data.serialize(&mut &mut reward_snapshots_info.data.borrow_mut()[..])?;
+        borrower_coll_surplus.serialize(&mut &mut borrower_coll_surplus_info.data.borrow_mut()[..])?;
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        default_pool_data.serialize(&mut &mut default_pool_info.data.borrow_mut()[..])?;
+        coll_surplus_pool_data.serialize(&mut &mut coll_surplus_pool_info.data.borrow_mut()[..])?;
+        stability_pool_data.serialize(&mut &mut stability_pool_info.data.borrow_mut()[..])?;
+        community_issuance_data.serialize(&mut &mut community_issuance_id_info.data.borrow_mut()[..])?;
+        epoch_to_scale.serialize(&mut &mut epoch_to_scale_info.data.borrow_mut()[..])?;
 
         Ok(())
     }
@@ -362,9 +1138,6 @@ impl Processor {
         partial_redemption_hint_nicr:u128,
         max_iterations:u128,
         max_fee_percentage:u128,
-
-        total_sol_drawn: u128,
-        total_solusd_to_redeem:u128,
         nonce:u8,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
@@ -376,6 +1149,7 @@ impl Processor {
         let stability_pool_info = next_account_info(account_info_iter)?;
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
+        let price_feed_info = next_account_info(account_info_iter)?;
 
         let owner_info = next_account_info(account_info_iter)?;
         let solusd_token_mint_info = next_account_info(account_info_iter)?;
@@ -383,22 +1157,65 @@ impl Processor {
         let authority_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+        let gas_pool_info = next_account_info(account_info_iter)?;
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let staking_sol_pool_info = next_account_info(account_info_iter)?;
+        let redeemer_wsol_info = next_account_info(account_info_iter)?;
         let cur_timestamp = clock.unix_timestamp as u128;
 
+        // the redeemer burns its SOLUSD and receives the redeemed SOL in its own wSOL account
+        if !owner_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        assert_token_account_owner(redeemer_wsol_info, owner_info.key)?;
+
         let mut trove_manager_data = try_from_slice_unchecked::<TroveManager>(&mut trove_manager_id_info.data.borrow())?;
         let mut default_pool_data = try_from_slice_unchecked::<DefaultPool>(&default_pool_info.data.borrow())?;
         let mut active_pool_data = try_from_slice_unchecked::<ActivePool>(&active_pool_info.data.borrow())?;
+        let mut coll_surplus_pool_data = try_from_slice_unchecked::<CollSurplusPool>(&coll_surplus_pool_info.data.borrow())?;
         let mut solid_staring_data = try_from_slice_unchecked::<SOLIDStaking>(&solid_staking_id_info.data.borrow())?;
         let stability_pool_data = try_from_slice_unchecked::<StabilityPool>(&stability_pool_info.data.borrow())?;
 
+        require_not_paused(trove_manager_data.paused_operations, PAUSE_REDEEM)?;
+
+        if *gas_pool_info.key != trove_manager_data.gas_pool_id {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        if *trove_manager_authority_info.key != authority_id(program_id, trove_manager_id_info.key, trove_manager_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+
+        Self::assert_pool_accounts(&trove_manager_data, active_pool_info, default_pool_info, coll_surplus_pool_info)?;
+        if *solid_staking_id_info.key != trove_manager_data.solid_staking_pubkey ||
+            *staking_sol_pool_info.key != solid_staring_data.sol_pool_token_pubkey
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        let pool_wsol = PoolWsolAccounts::new(
+            trove_manager_id_info.key,
+            trove_manager_data.nonce,
+            token_program_info,
+            trove_manager_authority_info,
+            &active_pool_data,
+            active_pool_wsol_info,
+            &default_pool_data,
+            default_pool_wsol_info,
+            &coll_surplus_pool_data,
+            coll_surplus_pool_wsol_info,
+        )?;
+
         if max_fee_percentage < REDEMPTION_FEE_FLOOR || max_fee_percentage > DECIMAL_PRECISION {
             return Err(LiquityError::ExceedMaxFeePercentage.into());
         }
         //_requireAfterBootstrapPeriod();
 
-        let market_price = get_market_price(
-            stability_pool_data.oracle_program_id,
-            stability_pool_data.quote_currency,
+        let market_price = fetch_price(
+            program_id,
+            price_feed_info,
+            trove_manager_id_info.key,
             pyth_product_info,
             pyth_price_info,
             clock
@@ -427,53 +1244,94 @@ impl Processor {
         totals.remaining_solusd = solusd_amount;
 
         /*
-        address currentBorrower;
-
-        if (_isValidFirstRedemptionHint(contractsCache.sortedTroves, _firstRedemptionHint, totals.price)) {
-            currentBorrower = _firstRedemptionHint;
-        } else {
-            currentBorrower = contractsCache.sortedTroves.getLast();
-            // Find the first trove with ICR >= MCR
-            while (currentBorrower != address(0) && getCurrentICR(currentBorrower, totals.price) < MCR) {
-                currentBorrower = contractsCache.sortedTroves.getPrev(currentBorrower);
+        * There is no sorted troves list on-chain, so the caller passes the troves to redeem from. They must be active,
+        * troves with ICR < MCR are skipped and the others must be in ascending ICR order, so the lowest collateralized
+        * troves among the passed ones are redeemed from first.
+        */
+        let mut iterations: u128 = 0;
+        let mut last_icr: u128 = 0;
+        let mut redeemed_troves: Vec<Pubkey> = Vec::new();
+
+        // Loop through the Troves starting from the one with lowest collateral ratio until solusd_amount of SOLUSD is exchanged for collateral
+        while totals.remaining_solusd > 0 && (max_iterations == 0 || iterations < max_iterations) {
+            let borrower_trove_info = match next_account_info(account_info_iter) {
+                Ok(info) => info,
+                Err(_) => break,
+            };
+            let reward_snapshot_info = next_account_info(account_info_iter)?;
+            let coll_surplus_info = next_account_info(account_info_iter)?;
+
+            let mut borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+            let mut reward_snapshot = try_from_slice_unchecked::<RewardSnapshot>(&reward_snapshot_info.data.borrow())?;
+            let mut coll_surplus = try_from_slice_unchecked::<CollSurplus>(&coll_surplus_info.data.borrow())?;
+
+            Self::assert_trove_accounts(program_id, &trove_manager_data, &borrower_trove.owner_pubkey, borrower_trove_info, reward_snapshot_info, coll_surplus_info)?;
+            if redeemed_troves.contains(borrower_trove_info.key) {
+                return Err(LiquityError::InvalidAccountInput.into());
             }
-        }
+            redeemed_troves.push(*borrower_trove_info.key);
 
-        // Loop through the Troves starting from the one with lowest collateral ratio until _amount of LUSD is exchanged for collateral
-        if (_maxIterations == 0) { _maxIterations = uint(-1); }
-        while (currentBorrower != address(0) && totals.remainingLUSD > 0 && _maxIterations > 0) {
-            _maxIterations--;
-            // Save the address of the Trove preceding the current one, before potentially modifying the list
-            address nextUserToCheck = contractsCache.sortedTroves.getPrev(currentBorrower);
-
-            _applyPendingRewards(contractsCache.activePool, contractsCache.defaultPool, currentBorrower);
+            if !borrower_trove.is_active() {
+                return Err(LiquityError::TroveNotActive.into());
+            }
+            if reward_snapshot.owner_pubkey != borrower_trove.owner_pubkey {
+                return Err(LiquityError::InvalidRewardSnapshot.into());
+            }
+            if coll_surplus.owner_pubkey != borrower_trove.owner_pubkey {
+                return Err(LiquityError::InvalidCollSurplus.into());
+            }
 
-            SingleRedemptionValues memory singleRedemption = _redeemCollateralFromTrove(
-                contractsCache,
-                currentBorrower,
-                totals.remainingLUSD,
+            let icr = get_current_icr(&trove_manager_data, &mut borrower_trove, &mut reward_snapshot, totals.price);
+            if !check_redemption_order(icr, &mut last_icr)? {
+                continue;
+            }
+            iterations += 1;
+
+            apply_pending_rewards(&trove_manager_data, &mut borrower_trove, &mut reward_snapshot, &mut default_pool_data, &mut active_pool_data, &pool_wsol)?;
+
+            let single_redemption = redeem_collateral_from_trove(
+                &mut trove_manager_data,
+                &mut borrower_trove,
+                &mut reward_snapshot,
+                &mut coll_surplus,
+                &mut active_pool_data,
+                &mut coll_surplus_pool_data,
+                totals.remaining_solusd,
                 totals.price,
-                _upperPartialRedemptionHint,
-                _lowerPartialRedemptionHint,
-                _partialRedemptionHintNICR
-            );
+                partial_redemption_hint_nicr,
+                &pool_wsol
+            )?;
 
-            if (singleRedemption.cancelledPartial) break; // Partial redemption was cancelled (out-of-date hint, or new net debt < minimum), therefore we could not redeem from the last Trove
+            borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+            reward_snapshot.serialize(&mut &mut reward_snapshot_info.data.borrow_mut()[..])?;
+            coll_surplus.serialize(&mut &mut coll_surplus_info.data.borrow_mut()[..])?;
 
-            totals.totalLUSDToRedeem  = totals.totalLUSDToRedeem.add(singleRedemption.LUSDLot);
-            totals.totalETHDrawn = totals.totalETHDrawn.add(singleRedemption.ETHLot);
+            // Partial redemption was cancelled (out-of-date hint, or new net debt < minimum), therefore we could not redeem from the last Trove
+            if single_redemption.cancelled_partial == 1 {
+                break;
+            }
 
-            totals.remainingLUSD = totals.remainingLUSD.sub(singleRedemption.LUSDLot);
-            currentBorrower = nextUserToCheck;
-        } 
-        -------------implemented below
-        */
-        totals.total_sol_drawn = total_sol_drawn;
-        totals.total_solusd_to_redeem = total_solusd_to_redeem;
-        totals.remaining_solusd = 0;
+            if borrower_trove.status == Status::ClosedByRedemption as u8 {
+                // Burn the liquidation reserve of the closed trove from the gas pool
+                token_burn(
+                    trove_manager_id_info.key,
+                    token_program_info.clone(),
+                    solusd_token_mint_info.clone(),
+                    gas_pool_info.clone(),
+                    trove_manager_authority_info.clone(),
+                    trove_manager_data.nonce,
+                    SOLUSD_GAS_COMPENSATION.try_into().map_err(|_| LiquityError::MathOverflow)?
+                )?;
+            }
+
+            totals.total_solusd_to_redeem += single_redemption.solusd_lot;
+            totals.total_sol_drawn += single_redemption.sol_lot;
+
+            totals.remaining_solusd -= single_redemption.solusd_lot;
+        }
 
         if totals.total_sol_drawn <= 0 {
-            return Err(LiquityError::ZeroAmount.into());
+            return Err(LiquityError::UnableToRedeemAnyAmount.into());
         }
 
         // Decay the baseRate due to time passed, and then increase it according to the size of this redemption.
@@ -489,7 +1347,8 @@ impl Processor {
 
         // send the sol fee to the SOLID staking contract
         //contractsCache.activePool.sendETH(address(contractsCache.lqtyStaking), totals.ETHFee); --implemented
-        active_pool_data.sol -= totals.sol_fee;
+        active_pool_data.send_sol(totals.sol_fee);
+        pool_wsol.send_sol(&pool_wsol.active_pool, staking_sol_pool_info, totals.sol_fee)?;
 
         //contractsCache.lqtyStaking.increaseF_ETH(totals.ETHFee); --implemented
         solid_staring_data.increase_f_sol(totals.sol_fee);
@@ -505,13 +1364,20 @@ impl Processor {
             solusd_dest_info.clone(), 
             authority_info.clone(), 
             nonce, 
-            totals.total_solusd_to_redeem.try_into().unwrap()
-        ).unwrap();    
+            totals.total_solusd_to_redeem.try_into().map_err(|_| LiquityError::MathOverflow)?
+        )?;    
 
         // Update Active Pool LUSD, and send ETH to account
         active_pool_data.decrease_solusd_debt(totals.total_solusd_to_redeem);
         //contractsCache.activePool.sendETH(msg.sender, totals.ETHToSendToRedeemer); --implemented
-        active_pool_data.sol -= totals.sol_to_send_to_redeemer;
+        active_pool_data.send_sol(totals.sol_to_send_to_redeemer);
+        pool_wsol.send_sol(&pool_wsol.active_pool, redeemer_wsol_info, totals.sol_to_send_to_redeemer)?;
+
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        default_pool_data.serialize(&mut &mut default_pool_info.data.borrow_mut()[..])?;
+        coll_surplus_pool_data.serialize(&mut &mut coll_surplus_pool_info.data.borrow_mut()[..])?;
+        solid_staring_data.serialize(&mut &mut solid_staking_id_info.data.borrow_mut()[..])?;
 
         Ok(())
     } 
@@ -523,17 +1389,7 @@ impl Processor {
     pub fn process_liquidate_troves(
         program_id: &Pubkey,        // this program id
         accounts: &[AccountInfo],   // all account informations
-        number: u128,                  // nonce for authorizing
-
-        total_coll_in_sequence:u128,
-        total_debt_in_sequence:u128,
-        total_coll_gas_compensation:u128,
-        total_solusd_gas_compensation:u128,
-        total_debt_to_offset:u128,
-        total_coll_to_send_to_sp:u128,
-        total_debt_to_redistribute:u128,
-        total_coll_to_redistribute:u128,
-        total_coll_surplus:u128,
+        number: u128,                  // maximum number of troves to liquidate
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let trove_manager_id_info = next_account_info(account_info_iter)?;
@@ -545,7 +1401,19 @@ impl Processor {
         let coll_surplus_pool_info = next_account_info(account_info_iter)?;
         let pyth_product_info = next_account_info(account_info_iter)?;
         let pyth_price_info = next_account_info(account_info_iter)?;
+        let price_feed_info = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+        let gas_pool_info = next_account_info(account_info_iter)?;
+        let active_pool_wsol_info = next_account_info(account_info_iter)?;
+        let trove_manager_authority_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let liquidator_solusd_info = next_account_info(account_info_iter)?;
+        let liquidator_wsol_info = next_account_info(account_info_iter)?;
+        let default_pool_wsol_info = next_account_info(account_info_iter)?;
+        let coll_surplus_pool_wsol_info = next_account_info(account_info_iter)?;
+        let stability_pool_wsol_info = next_account_info(account_info_iter)?;
+        let stability_pool_solusd_info = next_account_info(account_info_iter)?;
+        let solusd_token_info = next_account_info(account_info_iter)?;
 
         let cur_timestamp = clock.unix_timestamp as u64;
 
@@ -557,52 +1425,125 @@ impl Processor {
         let mut epoch_to_scale = try_from_slice_unchecked::<EpochToScale>(&epoch_to_scale_info.data.borrow())?;
         let mut coll_surplus_pool_data = try_from_slice_unchecked::<CollSurplusPool>(&coll_surplus_pool_info.data.borrow())?;
 
-        let market_price = get_market_price(
-            stability_pool_data.oracle_program_id,
-            stability_pool_data.quote_currency,
+        require_not_paused(trove_manager_data.paused_operations, PAUSE_LIQUIDATE)?;
+
+        if *gas_pool_info.key != trove_manager_data.gas_pool_id {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        if *trove_manager_authority_info.key != authority_id(program_id, trove_manager_id_info.key, trove_manager_data.nonce)? {
+            return Err(LiquityError::InvalidProgramAddress.into());
+        }
+
+        Self::assert_pool_accounts(&trove_manager_data, active_pool_info, default_pool_info, coll_surplus_pool_info)?;
+        if *stability_pool_info.key != trove_manager_data.stability_pool_id ||
+            *stability_pool_wsol_info.key != stability_pool_data.sol_pool_token_pubkey ||
+            *stability_pool_solusd_info.key != stability_pool_data.sol_usd_pool_token_pubkey ||
+            *solusd_token_info.key != trove_manager_data.solusd_token_pubkey
+        {
+            return Err(LiquityError::InvalidAccountInput.into());
+        }
+        let pool_wsol = PoolWsolAccounts::new(
+            trove_manager_id_info.key,
+            trove_manager_data.nonce,
+            token_program_info,
+            trove_manager_authority_info,
+            &active_pool_data,
+            active_pool_wsol_info,
+            &default_pool_data,
+            default_pool_wsol_info,
+            &coll_surplus_pool_data,
+            coll_surplus_pool_wsol_info,
+        )?;
+
+        let market_price = fetch_price(
+            program_id,
+            price_feed_info,
+            trove_manager_id_info.key,
             pyth_product_info,
             pyth_price_info,
             clock
         )?;
 
+        /*
+        * There is no sorted troves list on-chain, so the caller passes the troves to liquidate with their reward snapshots,
+        * owners and collateral surplus accounts. Each trove, snapshot and surplus account must belong to the owner passed with it.
+        */
+        let mut trove_infos = Vec::new();
+        let mut troves = Vec::new();
+        while (trove_infos.len() as u128) < number {
+            let borrower_trove_info = match next_account_info(account_info_iter) {
+                Ok(info) => info,
+                Err(_) => break,
+            };
+            let reward_snapshot_info = next_account_info(account_info_iter)?;
+            let borrower_info = next_account_info(account_info_iter)?;
+            let coll_surplus_info = next_account_info(account_info_iter)?;
+
+            let borrower_trove = try_from_slice_unchecked::<Trove>(&borrower_trove_info.data.borrow())?;
+            let reward_snapshot = try_from_slice_unchecked::<RewardSnapshot>(&reward_snapshot_info.data.borrow())?;
+            let coll_surplus = try_from_slice_unchecked::<CollSurplus>(&coll_surplus_info.data.borrow())?;
+
+            Self::assert_trove_accounts(program_id, &trove_manager_data, borrower_info.key, borrower_trove_info, reward_snapshot_info, coll_surplus_info)?;
+            if borrower_trove.owner_pubkey != *borrower_info.key {
+                return Err(LiquityError::InvalidOwner.into());
+            }
+            if reward_snapshot.owner_pubkey != *borrower_info.key {
+                return Err(LiquityError::InvalidRewardSnapshot.into());
+            }
+            if coll_surplus.owner_pubkey != *borrower_info.key {
+                return Err(LiquityError::InvalidCollSurplus.into());
+            }
+            // the accounts are derived from the owner, so a repeated owner is a repeated trove
+            if trove_infos.iter().any(|(info, _, _)| info.key == borrower_trove_info.key) {
+                return Err(LiquityError::InvalidAccountInput.into());
+            }
+
+            trove_infos.push((borrower_trove_info, reward_snapshot_info, coll_surplus_info));
+            troves.push((borrower_trove, reward_snapshot, coll_surplus));
+        }
+
         let mut vars = LocalVariablesOuterLiquidationFunction::new();
-        let mut totals = LiquidationTotals::new();
+        let totals;
 
         vars.price = market_price;
         vars.solusd_in_stab_pool = stability_pool_data.total_sol_usd_deposits as u128;
         vars.recovery_mode_at_start = trove_manager_data.check_recovery_mode(vars.price, &active_pool_data, &default_pool_data);
 
-        /*
         // Perform the appropriate liquidation sequence - tally the values, and obtain their totals
         if vars.recovery_mode_at_start == 1 {
-            totals = _getTotalsFromLiquidateTrovesSequence_RecoveryMode(contractsCache, vars.price, vars.LUSDInStabPool, _n);
+            totals = get_total_from_batch_liquidate_recovery_mode(
+                &mut trove_manager_data, 
+                &mut active_pool_data, 
+                &mut default_pool_data, 
+                &mut coll_surplus_pool_data,
+                vars.price, 
+                vars.solusd_in_stab_pool, 
+                &mut troves,
+                &pool_wsol)?;
         }
         else {// if !vars.recoveryModeAtStart
-            totals = _getTotalsFromLiquidateTrovesSequence_NormalMode(contractsCache.activePool, contractsCache.defaultPool, vars.price, vars.LUSDInStabPool, _n);
-        } ---implemented
-        */
-        totals.total_coll_in_sequence = total_coll_in_sequence;
-        totals.total_debt_in_sequence = total_debt_in_sequence;
-        totals.total_coll_gas_compensation = total_coll_gas_compensation;
-        totals.total_solusd_gas_compensation = total_solusd_gas_compensation;
-        totals.total_debt_to_offset = total_debt_to_offset;
-        totals.total_coll_to_send_to_sp = total_coll_to_send_to_sp;
-        totals.total_debt_to_redistribute = total_debt_to_redistribute;
-        totals.total_coll_to_redistribute = total_coll_to_redistribute;
-        totals.total_coll_surplus = total_coll_surplus;
+            totals = get_total_from_batch_liquidate_normal_mode(
+                &mut trove_manager_data, 
+                &mut active_pool_data, 
+                &mut default_pool_data, 
+                vars.price, 
+                vars.solusd_in_stab_pool, 
+                &mut troves,
+                &pool_wsol)?;
+        }
 
         if totals.total_debt_in_sequence <= 0 {
-            return Err(LiquityError::ZeroAmount.into());
+            return Err(LiquityError::NothingToLiquidate.into());
         }
         // Move liquidated SOL and SOLUSD to the appropriate pools
         //stabilityPoolCached.offset(totals.totalDebtToOffset, totals.totalCollToSendToSP); --implemented
-        stability_pool_data.offset(totals.total_debt_to_offset, totals.total_coll_to_send_to_sp, community_issuance_data.issue_solid(cur_timestamp as u128), &mut active_pool_data, &mut epoch_to_scale);
-        redistribute_debt_and_coll(&mut trove_manager_data, &mut active_pool_data, &mut default_pool_data, totals.total_debt_to_redistribute, totals.total_coll_to_redistribute);
+        stability_pool_data.offset(totals.total_debt_to_offset, totals.total_coll_to_send_to_sp, community_issuance_data.issue_solid(cur_timestamp as u128), &mut active_pool_data, &mut epoch_to_scale, &pool_wsol, stability_pool_wsol_info, stability_pool_solusd_info, solusd_token_info)?;
+        redistribute_debt_and_coll(&mut trove_manager_data, &mut active_pool_data, &mut default_pool_data, totals.total_debt_to_redistribute, totals.total_coll_to_redistribute, &pool_wsol)?;
 
         if totals.total_coll_surplus > 0 {
             //contractsCache.activePool.sendETH(address(collSurplusPool), totals.totalCollSurplus); --implemented
-            active_pool_data.sol -= totals.total_coll_surplus;
-            coll_surplus_pool_data.sol += totals.total_coll_surplus;
+            active_pool_data.send_sol(totals.total_coll_surplus);
+            pool_wsol.send_sol(&pool_wsol.active_pool, &pool_wsol.coll_surplus_pool, totals.total_coll_surplus)?;
         }
         update_system_snapshots_exclude_coll_reminder(&mut trove_manager_data, &active_pool_data, &default_pool_data, totals.total_coll_gas_compensation);
 
@@ -610,7 +1551,28 @@ impl Processor {
         vars.liquidated_coll = totals.total_coll_in_sequence - totals.total_coll_gas_compensation - totals.total_coll_surplus;
 
         // Send gas compensation to caller
-        //_sendGasCompensation(contractsCache.activePool, msg.sender, totals.totalLUSDGasCompensation, totals.totalCollGasCompensation);
+        send_gas_compensation(
+            &mut active_pool_data,
+            &pool_wsol,
+            gas_pool_info.clone(),
+            liquidator_solusd_info.clone(),
+            liquidator_wsol_info.clone(),
+            totals.total_solusd_gas_compensation,
+            totals.total_coll_gas_compensation
+        )?;
+
+        for ((borrower_trove_info, reward_snapshot_info, coll_surplus_info), (borrower_trove, reward_snapshot, coll_surplus)) in trove_infos.iter().zip(troves.iter()) {
+            borrower_trove.serialize(&mut &mut borrower_trove_info.data.borrow_mut()[..])?;
+            reward_snapshot.serialize(&mut &mut reward_snapshot_info.data.borrow_mut()[..])?;
+            coll_surplus.serialize(&mut &mut coll_surplus_info.data.borrow_mut()[..])?;
+        }
+        trove_manager_data.serialize(&mut &mut trove_manager_id_info.data.borrow_mut()[..])?;
+        active_pool_data.serialize(&mut &mut active_pool_info.data.borrow_mut()[..])?;
+        default_pool_data.serialize(&mut &mut default_pool_info.data.borrow_mut()[..])?;
+        coll_surplus_pool_data.serialize(&mut &mut coll_surplus_pool_info.data.borrow_mut()[..])?;
+        stability_pool_data.serialize(&mut &mut stability_pool_info.data.borrow_mut()[..])?;
+        community_issuance_data.serialize(&mut &mut community_issuance_info.data.borrow_mut()[..])?;
+        epoch_to_scale.serialize(&mut &mut epoch_to_scale_info.data.borrow_mut()[..])?;
 
         Ok(())
     }
@@ -1,26 +1,15 @@
 use anchor_lang::prelude::*;
 
 #[error]
-pub enum StablePoolError {
+pub enum SolidStakingError {
     #[msg("You are not authorized to perform this action.")]
     Unauthorized,
-    #[msg("AlreadyInUse")]
-    AlreadyInUse,
-    #[msg("InvalidProgramAddress")]
-    InvalidProgramAddress,
-    #[msg("InvalidState")]
-    InvalidState,
     #[msg("InvalidOwner")]
     InvalidOwner,
-    #[msg("NotAllowed")]
-    NotAllowed,
-    #[msg("Math operation overflow")]
-    MathOverflow,
-    #[msg("InvalidOracleConfig")]
-    InvalidOracleConfig,
     #[msg("InvalidAccountInput")]
     InvalidAccountInput,
-    
-    #[msg("PreciseError")]
-    PreciseError,
-}
\ No newline at end of file
+    #[msg("All the fee vaults must be passed when the stake changes")]
+    InvalidFeeVaultCount,
+    #[msg("Nothing to unstake")]
+    NothingToUnstake,
+}
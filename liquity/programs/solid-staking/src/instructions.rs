@@ -7,70 +7,72 @@ use crate::{
 };
 
 #[derive(Accounts)]
-#[instruction(global_state_nonce:u8, mint_usd_nonce:u8, stability_pool_nonce:u8)]
-pub struct CreateGlobalState <'info>{
+#[instruction(staking_state_nonce:u8, solid_pool_nonce:u8, solusd_fee_pool_nonce:u8)]
+pub struct CreateStakingState <'info>{
     pub super_owner:  Signer<'info>,
 
     #[account(
     init,
-    seeds = [GLOBAL_STATE_TAG],
-    bump = global_state_nonce,
+    seeds = [STAKING_STATE_TAG],
+    bump = staking_state_nonce,
     payer = super_owner,
     )]
-    pub global_state:ProgramAccount<'info, GlobalState>,
+    pub staking_state:ProgramAccount<'info, StakingState>,
+
+    pub mint_solid:Account<'info, Mint>,
+    pub mint_usd:Account<'info, Mint>,
 
     #[account(init,
-        mint::decimals = SOLUSD_DECIMALS,
-        mint::authority = global_state,
-        seeds = [SOLUSD_MINT_TAG],
-        bump = mint_usd_nonce,
+        token::mint = mint_solid,
+        token::authority = staking_state,
+        seeds = [SOLID_POOL_TAG],
+        bump = solid_pool_nonce,
         payer = super_owner)]
-    pub mint_usd:Account<'info, Mint>,
+    pub solid_pool:Account<'info, TokenAccount>,
 
     #[account(init,
         token::mint = mint_usd,
-        token::authority = global_state,
-        seeds = [STABILITY_POOL_TAG],
-        bump = stability_pool_nonce,
+        token::authority = staking_state,
+        seeds = [SOLUSD_FEE_POOL_TAG],
+        bump = solusd_fee_pool_nonce,
         payer = super_owner)]
-    pub stability_solusd_pool:Account<'info, TokenAccount>,
+    pub solusd_fee_pool:Account<'info, TokenAccount>,
+
+    // global state of the stable pool
+    pub fee_authority: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
-    pub clock: Sysvar<'info, Clock>,
 }
 
 #[derive(Accounts)]
-#[instruction(token_vault_nonce:u8, global_state_nonce:u8, token_coll_nonce:u8)]
-pub struct CreateTokenVault<'info> {
+#[instruction(fee_vault_nonce:u8, staking_state_nonce:u8, fee_vault_pool_nonce:u8)]
+pub struct CreateFeeVault<'info> {
     pub payer:  Signer<'info>,
     #[account(
         init,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
+        seeds = [FEE_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = fee_vault_nonce,
         payer = payer,
-        constraint = payer.key() == global_state.super_owner
+        constraint = payer.key() == staking_state.super_owner
     )]
-    pub token_vault: ProgramAccount<'info, TokenVault>,
+    pub fee_vault: ProgramAccount<'info, FeeVault>,
 
-    #[account(seeds = [GLOBAL_STATE_TAG],
-        bump = global_state_nonce)]
-    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [STAKING_STATE_TAG],
+        bump = staking_state_nonce)]
+    pub staking_state: ProgramAccount<'info, StakingState>,
 
     pub mint_coll:Account<'info, Mint>,
 
     #[account(init,
         token::mint = mint_coll,
-        token::authority = token_vault,
-        seeds = [TOKEN_VAULT_POOL_TAG, token_vault.key().as_ref()],
-        bump = token_coll_nonce,
+        token::authority = fee_vault,
+        seeds = [FEE_VAULT_POOL_TAG, fee_vault.key().as_ref()],
+        bump = fee_vault_pool_nonce,
         payer = payer)]
     pub token_coll:Account<'info, TokenAccount>,
-    
-    pub oracle_program: AccountInfo<'info>,
-    pub pyth_product: AccountInfo<'info>,
-    pub pyth_price: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
@@ -78,247 +80,145 @@ pub struct CreateTokenVault<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(user_trove_nonce:u8, token_vault_nonce:u8)]
-pub struct CreateUserTrove<'info> {
-    pub trove_owner:  Signer<'info>,
+#[instruction(staker_info_nonce:u8)]
+pub struct CreateStakerInfo<'info> {
+    pub owner:  Signer<'info>,
     #[account(
     init,
-    seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), trove_owner.key().as_ref()],
-    bump = user_trove_nonce,
-    payer = trove_owner,
+    seeds = [STAKER_INFO_TAG, owner.key().as_ref()],
+    bump = staker_info_nonce,
+    payer = owner,
     )]
-    pub user_trove:ProgramAccount<'info, UserTrove>,
-    #[account(mut,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
-    )]
-    pub token_vault:ProgramAccount<'info, TokenVault>,
-    #[account(mut,
-        constraint = mint_coll.key() == token_vault.mint_coll)]
-    pub mint_coll:Account<'info, Mint>,
-    
+    pub staker_info:ProgramAccount<'info, StakerInfo>,
+
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8)]
-pub struct DepositCollateral<'info> {
+#[instruction(staker_coll_snapshot_nonce:u8)]
+pub struct CreateStakerCollSnapshot<'info> {
     pub owner:  Signer<'info>,
-    #[account(mut,
-        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
-        bump = user_trove_nonce)]
-    pub user_trove:ProgramAccount<'info, UserTrove>,
-    #[account(mut,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
-    )]
-    pub token_vault:ProgramAccount<'info, TokenVault>,
-    #[account(mut,
-        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
-        bump = token_coll_nonce,
+    #[account(
+    init,
+    seeds = [STAKER_COLL_SNAPSHOT_TAG, fee_vault.key().as_ref(), owner.key().as_ref()],
+    bump = staker_coll_snapshot_nonce,
+    payer = owner,
     )]
-    pub pool_token_coll:Account<'info, TokenAccount>,
-    #[account(mut,
-        constraint = user_token_coll.owner == owner.key(),
-        constraint = user_token_coll.mint == token_vault.mint_coll)]
-    pub user_token_coll:Account<'info, TokenAccount>,
-    #[account(mut,
-        constraint = mint_coll.key() == token_vault.mint_coll)]
-    pub mint_coll:Account<'info, Mint>,
-    pub token_program:Program<'info, Token>,
+    pub staker_coll_snapshot:ProgramAccount<'info, StakerCollSnapshot>,
+    pub fee_vault:ProgramAccount<'info, FeeVault>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8)]
-pub struct WithdrawCollateral<'info> {
-    pub owner:  Signer<'info>,
-    #[account(mut,
-        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
-        bump = user_trove_nonce)]
-    pub user_trove:ProgramAccount<'info, UserTrove>,
-    #[account(mut,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
-    )]
-    pub token_vault:ProgramAccount<'info, TokenVault>,
-    #[account(mut,
-        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
-        bump = token_coll_nonce,
-    )]
-    pub pool_token_coll:Account<'info, TokenAccount>,
+#[instruction(amount: u64)]
+pub struct IncreaseFSolusd<'info> {
+    pub fee_authority:  Signer<'info>,
     #[account(mut,
-        constraint = user_token_coll.owner == owner.key(),
-        constraint = user_token_coll.mint == token_vault.mint_coll)]
-    pub user_token_coll:Account<'info, TokenAccount>,
-    #[account(mut,
-        constraint = mint_coll.key() == token_vault.mint_coll)]
-    pub mint_coll:Account<'info, Mint>,
-    pub token_program:Program<'info, Token>,
+        constraint = staking_state.fee_authority == fee_authority.key())]
+    pub staking_state: ProgramAccount<'info, StakingState>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8)]
-pub struct BorrowUsd<'info> {
-    pub owner:  Signer<'info>,
-    #[account(mut,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
-    )]
-    pub token_vault:ProgramAccount<'info, TokenVault>,
-    #[account(mut,
-        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
-        bump = user_trove_nonce)]
-    pub user_trove:ProgramAccount<'info, UserTrove>,
-    
-    #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
-        bump = global_state_nonce)]
-    pub global_state: ProgramAccount<'info, GlobalState>,
-    #[account(mut,
-        seeds = [SOLUSD_MINT_TAG],
-        bump = mint_usd_nonce,
-        constraint = mint_usd.key() == global_state.mint_usd
-    )]
-    pub mint_usd:Account<'info, Mint>,
-    #[account(mut,
-        constraint = user_token_usd.owner == owner.key(),
-        constraint = user_token_usd.mint == mint_usd.key())]
-    pub user_token_usd:Account<'info, TokenAccount>,
+#[instruction(amount: u64)]
+pub struct IncreaseFColl<'info> {
+    pub fee_authority:  Signer<'info>,
     #[account(
-        constraint = mint_coll.key() == token_vault.mint_coll)]
-    pub mint_coll:Account<'info, Mint>,
-    pub token_program:Program<'info, Token>,
-    
-    pub oracle_program: AccountInfo<'info>,
-    pub pyth_product: AccountInfo<'info>,
-    pub pyth_price: AccountInfo<'info>,
-    pub clock: Sysvar<'info, Clock>,
+        constraint = staking_state.fee_authority == fee_authority.key())]
+    pub staking_state: ProgramAccount<'info, StakingState>,
+    #[account(mut)]
+    pub fee_vault: ProgramAccount<'info, FeeVault>,
 }
- 
+
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8)]
-pub struct RepayUsd<'info> {
+#[instruction(amount: u64, staking_state_nonce: u8, staker_info_nonce: u8)]
+pub struct Stake<'info> {
     pub owner:  Signer<'info>,
+
     #[account(mut,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
-    )]
-    pub token_vault:ProgramAccount<'info, TokenVault>,
-    #[account(mut,
-        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
-        bump = user_trove_nonce)]
-    pub user_trove:ProgramAccount<'info, UserTrove>,
-    #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
-        bump = global_state_nonce)]
-    pub global_state: ProgramAccount<'info, GlobalState>,
-    #[account(mut,
-        seeds = [SOLUSD_MINT_TAG],
-        bump = mint_usd_nonce,
-        constraint = mint_usd.key() == global_state.mint_usd
-    )]
-    pub mint_usd:Account<'info, Mint>,
-    #[account(mut,
-        constraint = user_token_usd.owner == owner.key(),
-        constraint = user_token_usd.mint == mint_usd.key())]
-    pub user_token_usd:Account<'info, TokenAccount>,
+        seeds = [STAKING_STATE_TAG],
+        bump = staking_state_nonce)]
+    pub staking_state: ProgramAccount<'info, StakingState>,
     #[account(mut,
-        constraint = mint_coll.key() == token_vault.mint_coll)]
-    pub mint_coll:Account<'info, Mint>,
-    pub token_program:Program<'info, Token>,
-}
-
-#[derive(Accounts)]
-#[instruction(global_state_nonce: u8, token_vault_nonce: u8, user_trove_nonce: u8)]
-pub struct LiquidateTrove<'info> {
-    pub liquidator:  Signer<'info>,
+        seeds = [STAKER_INFO_TAG, owner.key().as_ref()],
+        bump = staker_info_nonce)]
+    pub staker_info:ProgramAccount<'info, StakerInfo>,
 
     #[account(mut,
-        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
-        bump = token_vault_nonce,
-    )]
-    pub token_vault:ProgramAccount<'info, TokenVault>,
+        constraint = solid_pool.key() == staking_state.solid_pool)]
+    pub solid_pool:Account<'info, TokenAccount>,
     #[account(mut,
-        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), user_trove_owner.key.as_ref()],
-        bump = user_trove_nonce)]
-    pub user_trove:ProgramAccount<'info, UserTrove>,
-    pub user_trove_owner: AccountInfo<'info>,
-    
+        constraint = user_token_solid.owner == owner.key(),
+        constraint = user_token_solid.mint == staking_state.mint_solid)]
+    pub user_token_solid:Account<'info, TokenAccount>,
+
     #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
-        bump = global_state_nonce)]
-    pub global_state: ProgramAccount<'info, GlobalState>,
-    
+        constraint = solusd_fee_pool.key() == staking_state.solusd_fee_pool)]
+    pub solusd_fee_pool:Account<'info, TokenAccount>,
     #[account(mut,
-        constraint = mint_coll.key() == token_vault.mint_coll)]
-    pub mint_coll:Account<'info, Mint>,
-
-    pub stability_solusd_pool:Account<'info, TokenAccount>,
-    
-    pub oracle_program: AccountInfo<'info>,
-    pub pyth_product: AccountInfo<'info>,
-    pub pyth_price: AccountInfo<'info>,
-    pub clock: Sysvar<'info, Clock>,
+        constraint = user_token_usd.owner == owner.key(),
+        constraint = user_token_usd.mint == staking_state.mint_usd)]
+    pub user_token_usd:Account<'info, TokenAccount>,
 
     pub token_program:Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, global_state_nonce: u8, sp_user_info_nonce: u8, stability_pool_nonce: u8)]
-pub struct SPDeposit<'info> {
+#[instruction(amount: u64, staking_state_nonce: u8, staker_info_nonce: u8)]
+pub struct Unstake<'info> {
     pub owner:  Signer<'info>,
 
     #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
-        bump = global_state_nonce)]
-    pub global_state: ProgramAccount<'info, GlobalState>,
-
+        seeds = [STAKING_STATE_TAG],
+        bump = staking_state_nonce)]
+    pub staking_state: ProgramAccount<'info, StakingState>,
     #[account(mut,
-        seeds = [SP_USER_INFO],
-        bump = sp_user_info_nonce)]
-    pub sp_user_info: ProgramAccount<'info, SPUserInfo>,
+        seeds = [STAKER_INFO_TAG, owner.key().as_ref()],
+        bump = staker_info_nonce)]
+    pub staker_info:ProgramAccount<'info, StakerInfo>,
 
     #[account(mut,
-        seeds = [STABILITY_POOL_TAG],
-        bump = stability_pool_nonce,
-    )]
-    pub stability_solusd_pool:Account<'info, TokenAccount>,
+        constraint = solid_pool.key() == staking_state.solid_pool)]
+    pub solid_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_solid.owner == owner.key(),
+        constraint = user_token_solid.mint == staking_state.mint_solid)]
+    pub user_token_solid:Account<'info, TokenAccount>,
 
     #[account(mut,
-        constraint = user_solusd_token.owner == owner.key(),
-        constraint = user_solusd_token.mint == global_state.mint_usd)]
-    pub user_solusd_token:Account<'info, TokenAccount>,
-    
+        constraint = solusd_fee_pool.key() == staking_state.solusd_fee_pool)]
+    pub solusd_fee_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_usd.owner == owner.key(),
+        constraint = user_token_usd.mint == staking_state.mint_usd)]
+    pub user_token_usd:Account<'info, TokenAccount>,
+
     pub token_program:Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, global_state_nonce: u8, sp_user_info_nonce: u8, stability_pool_nonce: u8)]
-pub struct SPWithdraw<'info> {
+#[instruction(staking_state_nonce: u8, staker_info_nonce: u8)]
+pub struct ClaimGains<'info> {
     pub owner:  Signer<'info>,
 
+    #[account(
+        seeds = [STAKING_STATE_TAG],
+        bump = staking_state_nonce)]
+    pub staking_state: ProgramAccount<'info, StakingState>,
     #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
-        bump = global_state_nonce)]
-    pub global_state: ProgramAccount<'info, GlobalState>,
+        seeds = [STAKER_INFO_TAG, owner.key().as_ref()],
+        bump = staker_info_nonce)]
+    pub staker_info:ProgramAccount<'info, StakerInfo>,
 
     #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
-        bump = sp_user_info_nonce)]
-    pub sp_user_info: ProgramAccount<'info, SPUserInfo>,
-
+        constraint = solusd_fee_pool.key() == staking_state.solusd_fee_pool)]
+    pub solusd_fee_pool:Account<'info, TokenAccount>,
     #[account(mut,
-        seeds = [STABILITY_POOL_TAG],
-        bump = stability_pool_nonce,
-    )]
-    pub stability_solusd_pool:Account<'info, TokenAccount>,
+        constraint = user_token_usd.owner == owner.key(),
+        constraint = user_token_usd.mint == staking_state.mint_usd)]
+    pub user_token_usd:Account<'info, TokenAccount>,
 
-    #[account(mut,
-        constraint = user_solusd_token.owner == owner.key(),
-        constraint = user_solusd_token.mint == global_state.mint_usd)]
-    pub user_solusd_token:Account<'info, TokenAccount>,
-    
     pub token_program:Program<'info, Token>,
 }
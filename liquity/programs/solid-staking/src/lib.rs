@@ -12,48 +12,43 @@ pub mod constant;
 pub mod instructions;
 /// utils
 pub mod utils;
-/// pyth
-pub mod pyth;
 
 use crate::{
     instructions::*,
     processor::*,
 };
 
-declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+declare_id!("Ve3QUUFsr6n2W1GVWkUY7BaYLptQEkYPj6WXPQVX1Q9");
 
 #[program]
-pub mod stable_pool {
+pub mod solid_staking {
     use super::*;
 
-    pub fn create_global_state(ctx: Context<CreateGlobalState>, global_state_nonce:u8, mint_usd_nonce:u8, stability_pool_nonce:u8) -> ProgramResult { 
-        process_create_global_state(ctx, global_state_nonce, mint_usd_nonce, stability_pool_nonce) 
+    pub fn create_staking_state(ctx: Context<CreateStakingState>, staking_state_nonce:u8, solid_pool_nonce:u8, solusd_fee_pool_nonce:u8) -> ProgramResult { 
+        process_create_staking_state(ctx, staking_state_nonce, solid_pool_nonce, solusd_fee_pool_nonce) 
     }
-    pub fn create_token_vault(ctx: Context<CreateTokenVault>, token_vault_nonce:u8, global_state_nonce:u8, token_coll_nonce:u8) -> ProgramResult { 
-        process_create_token_vault(ctx, token_vault_nonce, global_state_nonce, token_coll_nonce)
+    pub fn create_fee_vault(ctx: Context<CreateFeeVault>, fee_vault_nonce:u8, staking_state_nonce:u8, fee_vault_pool_nonce:u8) -> ProgramResult { 
+        process_create_fee_vault(ctx, fee_vault_nonce, staking_state_nonce, fee_vault_pool_nonce)
     }
-    pub fn create_user_trove(ctx: Context<CreateUserTrove>, user_trove_nonce:u8, token_vault_nonce:u8) -> ProgramResult { 
-        process_create_user_trove(ctx, user_trove_nonce, token_vault_nonce) 
+    pub fn create_staker_info(ctx: Context<CreateStakerInfo>, staker_info_nonce:u8) -> ProgramResult { 
+        process_create_staker_info(ctx, staker_info_nonce) 
     }
-    pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
-        process_deposit_collateral(ctx, amount, token_vault_nonce, user_trove_nonce, token_coll_nonce) 
+    pub fn create_staker_coll_snapshot(ctx: Context<CreateStakerCollSnapshot>, staker_coll_snapshot_nonce:u8) -> ProgramResult { 
+        process_create_staker_coll_snapshot(ctx, staker_coll_snapshot_nonce) 
     }
-    pub fn withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
-        process_withdraw_collateral(ctx, amount, token_vault_nonce, user_trove_nonce, token_coll_nonce) 
+    pub fn increase_f_solusd(ctx: Context<IncreaseFSolusd>, amount: u64) -> ProgramResult { 
+        process_increase_f_solusd(ctx, amount) 
     }
-    pub fn borrow_usd(ctx: Context<BorrowUsd>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8) -> ProgramResult { 
-        process_borrow_usd(ctx, amount, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce) 
+    pub fn increase_f_coll(ctx: Context<IncreaseFColl>, amount: u64) -> ProgramResult { 
+        process_increase_f_coll(ctx, amount) 
     }
-    pub fn repay_usd(ctx: Context<RepayUsd>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8) -> ProgramResult { 
-        process_repay_usd(ctx, amount, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce) 
+    pub fn stake(ctx: Context<Stake>, amount: u64, staking_state_nonce: u8, staker_info_nonce: u8) -> ProgramResult { 
+        process_stake(ctx, amount, staking_state_nonce, staker_info_nonce) 
     }
-    pub fn liquidate_trove(ctx: Context<LiquidateTrove>, global_state_nonce: u8, token_vault_nonce: u8, user_trove_nonce: u8) -> ProgramResult { 
-        process_liquidate_trove(ctx, global_state_nonce, token_vault_nonce, user_trove_nonce) 
+    pub fn unstake(ctx: Context<Unstake>, amount: u64, staking_state_nonce: u8, staker_info_nonce: u8) -> ProgramResult { 
+        process_unstake(ctx, amount, staking_state_nonce, staker_info_nonce) 
     }
-    pub fn sp_deposit(ctx: Context<SPDeposit>, amount: u64, global_state_nonce: u8, sp_user_info_nonce: u8, stability_pool_nonce: u8) -> ProgramResult { 
-        process_sp_deposit(ctx, amount, global_state_nonce, sp_user_info_nonce, stability_pool_nonce) 
+    pub fn claim_gains(ctx: Context<ClaimGains>, staking_state_nonce: u8, staker_info_nonce: u8) -> ProgramResult { 
+        process_claim_gains(ctx, staking_state_nonce, staker_info_nonce) 
     }
-    pub fn sp_withdraw(ctx: Context<SPWithdraw>, amount: u64, global_state_nonce: u8, sp_user_info_nonce: u8, stability_pool_nonce: u8) -> ProgramResult { 
-        process_sp_withdraw(ctx, amount, global_state_nonce, sp_user_info_nonce, stability_pool_nonce) 
-    }
-}
\ No newline at end of file
+}
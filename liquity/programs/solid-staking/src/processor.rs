@@ -1,31 +1,22 @@
 
 
-pub mod process_create_global_state;
-pub use process_create_global_state::*;
+pub mod process_create_staking;
+pub use process_create_staking::*;
 
-pub mod process_create_token_vault;
-pub use process_create_token_vault::*;
+pub mod process_create_fee_vault;
+pub use process_create_fee_vault::*;
 
-pub mod process_create_user_trove;
-pub use process_create_user_trove::*;
+pub mod process_create_staker_info;
+pub use process_create_staker_info::*;
 
-pub mod process_deposit_collateral;
-pub use process_deposit_collateral::*;
+pub mod process_increase_fee;
+pub use process_increase_fee::*;
 
-pub mod process_withdraw_collateral;
-pub use process_withdraw_collateral::*;
+pub mod process_stake;
+pub use process_stake::*;
 
-pub mod process_borrow_usd;
-pub use process_borrow_usd::*;
+pub mod process_unstake;
+pub use process_unstake::*;
 
-pub mod process_repay_usd;
-pub use process_repay_usd::*;
-
-pub mod process_liquidate_trove;
-pub use process_liquidate_trove::*;
-
-pub mod process_sp_deposit;
-pub use process_sp_deposit::*;
-
-pub mod process_sp_withdraw;
-pub use process_sp_withdraw::*;
\ No newline at end of file
+pub mod process_claim_gains;
+pub use process_claim_gains::*;
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::*,
+    utils::*,
+};
+
+/// claim the fees earned by the stake, for the SOLUSD fee pool and the fee vaults passed as remaining accounts
+pub fn process_claim_gains(ctx: Context<ClaimGains>, staking_state_nonce: u8, _staker_info_nonce: u8) -> ProgramResult {
+    pay_staking_gains(
+        ctx.program_id,
+        &ctx.accounts.staking_state,
+        &mut ctx.accounts.staker_info,
+        &ctx.accounts.solusd_fee_pool,
+        &ctx.accounts.user_token_usd,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+        staking_state_nonce,
+        false,
+    )
+}
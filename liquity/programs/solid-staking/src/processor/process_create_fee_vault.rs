@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::*
+};
+
+pub fn process_create_fee_vault(ctx: Context<CreateFeeVault>, fee_vault_nonce:u8, _staking_state_nonce:u8, _fee_vault_pool_nonce:u8) -> ProgramResult {
+    ctx.accounts.fee_vault.mint_coll = ctx.accounts.mint_coll.key();
+    ctx.accounts.fee_vault.token_coll = ctx.accounts.token_coll.key();
+    ctx.accounts.fee_vault.nonce = fee_vault_nonce;
+    ctx.accounts.fee_vault.f_coll = 0;
+
+    ctx.accounts.staking_state.fee_vault_count += 1;
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::*
+};
+
+pub fn process_create_staker_info(ctx: Context<CreateStakerInfo>, _staker_info_nonce:u8) -> ProgramResult {
+    ctx.accounts.staker_info.owner = ctx.accounts.owner.key();
+    ctx.accounts.staker_info.stake = 0;
+    Ok(())
+}
+
+/*
+* A snapshot starts at zero: a staker whose stake changed after the fee vault was created already has a snapshot,
+* so a new snapshot is only for a stake that earned all the fees of the vault.
+*/
+pub fn process_create_staker_coll_snapshot(ctx: Context<CreateStakerCollSnapshot>, _staker_coll_snapshot_nonce:u8) -> ProgramResult {
+    ctx.accounts.staker_coll_snapshot.owner = ctx.accounts.owner.key();
+    ctx.accounts.staker_coll_snapshot.fee_vault = ctx.accounts.fee_vault.key();
+    ctx.accounts.staker_coll_snapshot.f_coll_snapshot = 0;
+    Ok(())
+}
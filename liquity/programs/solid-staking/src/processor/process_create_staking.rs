@@ -4,9 +4,12 @@ use crate::{
     instructions::*
 };
 
-pub fn process_create_global_state(ctx: Context<CreateGlobalState>, _global_state_nonce:u8, _mint_usd_nonce:u8, _stability_pool_nonce:u8) -> ProgramResult {
-    ctx.accounts.global_state.super_owner = ctx.accounts.super_owner.key();
-    ctx.accounts.global_state.mint_usd = ctx.accounts.mint_usd.key();
-    ctx.accounts.global_state.stability_solusd_pool = ctx.accounts.stability_solusd_pool.key();
+pub fn process_create_staking_state(ctx: Context<CreateStakingState>, _staking_state_nonce:u8, _solid_pool_nonce:u8, _solusd_fee_pool_nonce:u8) -> ProgramResult {
+    ctx.accounts.staking_state.super_owner = ctx.accounts.super_owner.key();
+    ctx.accounts.staking_state.fee_authority = ctx.accounts.fee_authority.key();
+    ctx.accounts.staking_state.mint_solid = ctx.accounts.mint_solid.key();
+    ctx.accounts.staking_state.mint_usd = ctx.accounts.mint_usd.key();
+    ctx.accounts.staking_state.solid_pool = ctx.accounts.solid_pool.key();
+    ctx.accounts.staking_state.solusd_fee_pool = ctx.accounts.solusd_fee_pool.key();
     Ok(())
 }
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::*
+};
+
+/// called by the stable pool after sending a borrowing fee to the SOLUSD fee pool
+pub fn process_increase_f_solusd(ctx: Context<IncreaseFSolusd>, amount: u64) -> ProgramResult {
+    ctx.accounts.staking_state.increase_f_solusd(amount);
+    Ok(())
+}
+
+/// called by the stable pool after sending a redemption fee to the fee vault of the collateral
+pub fn process_increase_f_coll(ctx: Context<IncreaseFColl>, amount: u64) -> ProgramResult {
+    let total_staked_amount = ctx.accounts.staking_state.total_staked_amount;
+    ctx.accounts.fee_vault.increase_f_coll(amount, total_staked_amount);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Transfer};
+
+use crate::{
+    instructions::*,
+    utils::*,
+};
+
+pub fn process_stake(ctx: Context<Stake>, amount: u64, staking_state_nonce: u8, _staker_info_nonce: u8) -> ProgramResult {
+    pay_staking_gains(
+        ctx.program_id,
+        &ctx.accounts.staking_state,
+        &mut ctx.accounts.staker_info,
+        &ctx.accounts.solusd_fee_pool,
+        &ctx.accounts.user_token_usd,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+        staking_state_nonce,
+        true,
+    )?;
+
+    // transfer from user to pool
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.user_token_solid.to_account_info(),
+        to: ctx.accounts.solid_pool.to_account_info(),
+        authority: ctx.accounts.owner.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::transfer(cpi_ctx, amount)?;
+
+    ctx.accounts.staker_info.stake += amount;
+    ctx.accounts.staking_state.total_staked_amount += amount;
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Transfer};
+
+use crate::{
+    constant::*,
+    error::*,
+    instructions::*,
+    utils::*,
+};
+
+pub fn process_unstake(ctx: Context<Unstake>, amount: u64, staking_state_nonce: u8, _staker_info_nonce: u8) -> ProgramResult {
+    if ctx.accounts.staker_info.stake == 0 {
+        return Err(SolidStakingError::NothingToUnstake.into());
+    }
+
+    pay_staking_gains(
+        ctx.program_id,
+        &ctx.accounts.staking_state,
+        &mut ctx.accounts.staker_info,
+        &ctx.accounts.solusd_fee_pool,
+        &ctx.accounts.user_token_usd,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+        staking_state_nonce,
+        true,
+    )?;
+
+    let mut _amount = amount;
+    if amount > ctx.accounts.staker_info.stake {
+        _amount = ctx.accounts.staker_info.stake;
+    }
+
+    // transfer from pool to user
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.solid_pool.to_account_info(),
+        to: ctx.accounts.user_token_solid.to_account_info(),
+        authority: ctx.accounts.staking_state.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let signer_seeds = &[
+        STAKING_STATE_TAG,
+        &[staking_state_nonce]
+    ];
+    let signer = &[&signer_seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, _amount)?;
+
+    ctx.accounts.staker_info.stake -= _amount;
+    ctx.accounts.staking_state.total_staked_amount -= _amount;
+
+    Ok(())
+}
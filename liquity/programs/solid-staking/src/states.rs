@@ -1,94 +1,76 @@
 use anchor_lang::prelude::*;
 use crate::{
     constant::*,
-    utils::*
 };
 #[account]
 #[derive(Default)]
-pub struct GlobalState {
+pub struct StakingState {
     pub super_owner: Pubkey,
+    // global state of the stable pool, the only signer allowed to add fees
+    pub fee_authority: Pubkey,
+
+    pub mint_solid: Pubkey,
     pub mint_usd: Pubkey,
+    // staked SOLID
+    pub solid_pool: Pubkey,
+    // borrowing fees in SOLUSD
+    pub solusd_fee_pool: Pubkey,
 
-    pub stability_solusd_pool: Pubkey,
-    pub sp_solusd_amount: u64,
-    pub sp_sol_amount: u64,
-    
+    pub total_staked_amount: u64,
+    // number of collateral fee vaults, a stake change has to update the snapshots of all of them
+    pub fee_vault_count: u64,
+    // running sum of the SOLUSD fees per staked SOLID
+    pub f_solusd: u128,
+}
+impl StakingState {
+    pub fn increase_f_solusd(&mut self, solusd_fee: u64) {
+        let mut solusd_fee_per_solid_staked = 0;
+        if self.total_staked_amount > 0 {
+            solusd_fee_per_solid_staked = solusd_fee as u128 * DECIMAL_PRECISION as u128 / self.total_staked_amount as u128;
+        }
+        self.f_solusd += solusd_fee_per_solid_staked;
+    }
+    pub fn get_pending_solusd_gain(&self, staker_info: &StakerInfo) -> u64 {
+        (staker_info.stake as u128 * (self.f_solusd - staker_info.f_solusd_snapshot) / DECIMAL_PRECISION as u128) as u64
+    }
 }
 
+/// redemption fees of one collateral
 #[account]
 #[derive(Default)]
-pub struct TokenVault {
+pub struct FeeVault {
     pub mint_coll: Pubkey,
     pub token_coll: Pubkey,
-
-    pub total_coll: u64,
-    pub total_debt: u64,
-
-    pub active_total_coll: u64,
-    pub default_total_coll: u64,
-    pub active_total_debt: u64,
-    pub default_total_debt: u64,
-
-    pub oracle_program: Pubkey,
-    pub pyth_product: Pubkey,
-    pub pyth_price: Pubkey,
+    pub nonce: u8,
+    // running sum of the collateral fees per staked SOLID
+    pub f_coll: u128,
 }
-impl TokenVault {
-    pub fn check_recovery_mode(&self, market_price: u64) -> bool {
-        let tcr = compute_cr(self.total_coll, self.total_debt, market_price);
-        return tcr < CCR ;
+impl FeeVault {
+    pub fn increase_f_coll(&mut self, coll_fee: u64, total_staked_amount: u64) {
+        let mut coll_fee_per_solid_staked = 0;
+        if total_staked_amount > 0 {
+            coll_fee_per_solid_staked = coll_fee as u128 * DECIMAL_PRECISION as u128 / total_staked_amount as u128;
+        }
+        self.f_coll += coll_fee_per_solid_staked;
+    }
+    pub fn get_pending_coll_gain(&self, stake: u64, snapshot: &StakerCollSnapshot) -> u64 {
+        (stake as u128 * (self.f_coll - snapshot.f_coll_snapshot) / DECIMAL_PRECISION as u128) as u64
     }
 }
 
 #[account]
 #[derive(Default)]
-pub struct UserTrove {
+pub struct StakerInfo {
     pub owner: Pubkey,
-    pub state: u8,
-    pub coll: u64,
-    pub debt: u64
-}
-impl UserTrove {
-    pub fn is_closed(&self)->bool{
-        self.state == 0
-    }
-    pub fn close(&mut self) {
-        self.state = 0;
-        self.coll = 0;
-        self.debt = 0;
-    }
+    pub stake: u64,
+    pub f_solusd_snapshot: u128,
 }
+
+/// snapshot of the collateral fees of one fee vault, for a staker
 #[account]
 #[derive(Default)]
-pub struct SPUserInfo {
+pub struct StakerCollSnapshot {
     pub owner: Pubkey,
-    pub deposit_balance: u64,
+    pub fee_vault: Pubkey,
+    pub f_coll_snapshot: u128,
 }
-
-
-pub struct LiquidationTotals {
-    pub total_coll_in_sequence:u64,
-    pub total_debt_in_sequence:u64,
-    pub total_coll_gas_compensation:u64,
-    pub total_solusd_gas_compensation:u64,
-    pub total_debt_to_offset:u64,
-    pub total_coll_to_send_to_sp:u64,
-    pub total_debt_to_redistribute:u64,
-    pub total_coll_to_redistribute:u64,
-    pub total_coll_surplus:u64,
-}
-impl LiquidationTotals{
-    pub fn new()->LiquidationTotals{
-        LiquidationTotals{
-            total_coll_in_sequence:0,
-            total_debt_in_sequence:0,
-            total_coll_gas_compensation:0,
-            total_solusd_gas_compensation:0,
-            total_debt_to_offset:0,
-            total_coll_to_send_to_sp:0,
-            total_debt_to_redistribute:0,
-            total_coll_to_redistribute:0,
-            total_coll_surplus:0,
-        }
-    }
-}
\ No newline at end of file
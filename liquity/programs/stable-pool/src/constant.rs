@@ -5,16 +5,78 @@ pub const SOLUSD_MINT_TAG:&[u8] = b"solusd-mint";
 pub const TOKEN_VAULT_POOL_TAG:&[u8] = b"token-vault-pool";
 pub const STABILITY_POOL_TAG:&[u8] = b"stability-pool";
 pub const SP_USER_INFO:&[u8] = b"sp-user-info";
+pub const EPOCH_TO_SCALE_TAG:&[u8] = b"epoch-to-scale";
+pub const PRICE_FEED_TAG:&[u8] = b"price-feed";
+pub const GAS_POOL_TAG:&[u8] = b"gas-pool";
 
 pub const SOLUSD_DECIMALS: u8 = 6;
 
-// Minimum collateral ratio for individual troves
-pub const MCR: u64 = 1_100_000_000; // 110%
+// Default minimum collateral ratio for individual troves of a token vault
+pub const DEFAULT_MCR: u64 = 1_100_000_000; // 110%
 
 pub const _100PCT: u64 = 1_000_000_000; // 110%
 
-// Critical system collateral ratio. If the system's total collateral ratio (TCR) falls below the CCR, Recovery Mode is triggered.
-pub const CCR: u64 = 1_500_000_000; // 150%
+// Default critical system collateral ratio of a token vault. If the vault's total collateral ratio (TCR) falls below the CCR, Recovery Mode is triggered.
+pub const DEFAULT_CCR: u64 = 1_500_000_000; // 150%
+// Upper bound of the CCR a token vault can be configured with
+pub const MAX_CCR: u64 = 5_000_000_000; // 500%
+
+// Default minimum SOLUSD debt of a trove with debt, governance can lower it but not to zero
+pub const DEFAULT_MIN_NET_DEBT: u64 = 1_800_000_000; // 1800 SOLUSD
+// Default max total debt of a token vault, and max SOLUSD supply across all the vaults. Governance lowers them
+// when onboarding a collateral
+pub const DEFAULT_DEBT_CEILING: u64 = u64::MAX;
+pub const DEFAULT_MAX_TOTAL_SUPPLY: u64 = u64::MAX;
+
+// Share of a liquidated trove's collateral paid to the liquidator, dividing by 200 yields 0.5%
+pub const PERCENT_DIVISOR: u64 = 200;
+// Default SOLUSD reserved in the gas pool when a trove takes on debt, added to its debt and paid to the liquidator
+pub const DEFAULT_SOLUSD_GAS_COMPENSATION: u64 = 200_000_000; // 200 SOLUSD
+// Upper bound of the SOLUSD gas compensation a token vault can be configured with
+pub const MAX_SOLUSD_GAS_COMPENSATION: u64 = 2_000_000_000; // 2000 SOLUSD
 
 pub const DECIMAL_PRECISION:u64 = 1_000_000_000;
-pub const NICR_PRECISION:u64 = 100_000_000;
\ No newline at end of file
+pub const NICR_PRECISION:u64 = 100_000_000;
+
+// Precision of the stability pool running product P and of the SOLUSD loss per unit staked
+pub const SP_PRECISION: u128 = 1_000_000_000_000_000_000;
+// P is multiplied by SCALE_FACTOR when it would drop below it, which starts a new scale
+pub const SCALE_FACTOR: u128 = 1_000_000_000;
+
+// Default oracle guards of a token vault: max age of the aggregate pyth price in slots, and max confidence interval
+// relative to the price (2%)
+pub const DEFAULT_MAX_PRICE_AGE_SLOTS: u64 = 5;
+pub const DEFAULT_MAX_PRICE_CONF_RATIO: u64 = DECIMAL_PRECISION / 100 * 2;
+// Currency the collateral prices are quoted in, "USD" null padded
+pub const USD_QUOTE_CURRENCY: [u8; 32] = *b"USD\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+// Default price feed limits: max relative change of the pyth price from the last good price (50%), max relative
+// difference between the pyth and the fallback prices to trust both (5%), and max age of the fallback price in slots
+pub const DEFAULT_MAX_PRICE_DEVIATION: u64 = DECIMAL_PRECISION / 2;
+pub const DEFAULT_MAX_PRICE_DIFFERENCE_BETWEEN_SOURCES: u64 = DECIMAL_PRECISION / 100 * 5;
+pub const DEFAULT_MAX_FALLBACK_PRICE_AGE_SLOTS: u64 = 150;
+
+// Operation classes the guardian can pause, as bits of the pause bitmap. Repaying and closing troves can not be paused
+pub const PAUSE_BORROW: u8 = 1 << 0;
+pub const PAUSE_WITHDRAW_COLL: u8 = 1 << 1;
+pub const PAUSE_LIQUIDATE: u8 = 1 << 2;
+pub const PAUSE_REDEEM: u8 = 1 << 3;
+pub const PAUSE_SP_DEPOSIT: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 = PAUSE_BORROW | PAUSE_WITHDRAW_COLL | PAUSE_LIQUIDATE | PAUSE_REDEEM | PAUSE_SP_DEPOSIT;
+
+pub const SECONDS_IN_ONE_MINUTE: i64 = 60;
+/*
+ * Half-life of 12h. 12h = 720 min
+ * (1/2) = d^720 => d = (1/2)^(1/720)
+ */
+pub const MINUTE_DECAY_FACTOR: u64 = 999_037_759;
+pub const DEFAULT_REDEMPTION_FEE_FLOOR: u64 = DECIMAL_PRECISION / 1000 * 5; // 0.5%
+pub const DEFAULT_BORROWING_FEE_FLOOR: u64 = DECIMAL_PRECISION / 1000 * 5; // 0.5%
+pub const MAX_BORROWING_FEE: u64 = DECIMAL_PRECISION / 100 * 5; // 5%
+// Upper bound of the redemption fee floor a token vault can be configured with
+pub const MAX_REDEMPTION_FEE_FLOOR: u64 = DECIMAL_PRECISION / 100 * 5; // 5%
+/*
+ * BETA: 18 digit decimal. Parameter by which to divide the redeemed fraction, in order to calc the new base rate from a redemption.
+ * Corresponds to (1 / ALPHA) in the white paper.
+ */
+pub const BETA: u64 = 2;
@@ -23,4 +23,80 @@ pub enum StablePoolError {
     
     #[msg("PreciseError")]
     PreciseError,
+    #[msg("InvalidSortedTrovesHint")]
+    InvalidSortedTrovesHint,
+    #[msg("Max fee percentage must be between 0.5% and 100%")]
+    InvalidMaxFeePercentage,
+    #[msg("Fee exceeded provided maximum")]
+    FeeExceededMaxPercentage,
+    #[msg("Cannot redeem when TCR < MCR")]
+    RedemptionBelowMCR,
+    #[msg("Unable to redeem any amount")]
+    UnableToRedeemAnyAmount,
+    #[msg("Requested redemption amount must be <= user's SOLUSD token balance")]
+    InsufficientSolusdBalance,
+    #[msg("Nothing to liquidate")]
+    NothingToLiquidate,
+    #[msg("Only one trove in the system")]
+    OnlyOneTroveInSystem,
+    #[msg("Epoch to scale account does not match the stability pool snapshot")]
+    InvalidEpochToScale,
+    #[msg("Stability pool product P can not be zero")]
+    InvalidProductP,
+    #[msg("No collateral gain to claim")]
+    NoCollGain,
+    #[msg("Collateral withdrawal not permitted in Recovery Mode")]
+    CollWithdrawalInRecoveryMode,
+    #[msg("An operation that would result in ICR < MCR is not permitted")]
+    ICRBelowMCR,
+    #[msg("Operation must leave trove with ICR >= CCR")]
+    ICRBelowCCR,
+    #[msg("Cannot decrease your Trove's ICR in Recovery Mode")]
+    ICRDecreaseInRecoveryMode,
+    #[msg("An operation that would result in TCR < CCR is not permitted")]
+    TCRBelowCCR,
+    #[msg("Trove does not exist or is closed")]
+    TroveNotActive,
+    #[msg("There must be either a collateral change or a debt change")]
+    NoneZeroAdjustment,
+    #[msg("Amount repaid must not be larger than the Trove's debt")]
+    RepaymentExceedsDebt,
+    #[msg("Amount withdrawn must not be larger than the Trove's collateral")]
+    WithdrawalExceedsColl,
+    #[msg("Operation not permitted during Recovery Mode")]
+    NotPermittedInRecoveryMode,
+    #[msg("No collateral available to claim")]
+    NoCollAvailableToClaim,
+    #[msg("Oracle price is stale")]
+    StalePrice,
+    #[msg("Oracle price confidence interval is too wide")]
+    PriceUncertain,
+    #[msg("Oracle price is not trading")]
+    PriceNotTrading,
+    #[msg("Oracle quote currency does not match the token vault quote currency")]
+    InvalidQuoteCurrency,
+    #[msg("Oracle guard limits are invalid")]
+    InvalidOracleGuards,
+    #[msg("Price feed limits are invalid")]
+    InvalidPriceFeedConfig,
+    #[msg("Fallback price must be positive")]
+    InvalidFallbackPrice,
+    #[msg("Trove's net debt must be greater than minimum")]
+    NetDebtBelowMinimum,
+    #[msg("Token vault risk parameters are invalid")]
+    InvalidVaultParams,
+    #[msg("Operation is paused by the guardian")]
+    ProtocolPaused,
+    #[msg("Pause state has unknown operation bits")]
+    InvalidPauseState,
+    #[msg("Borrow would exceed the debt ceiling of the token vault")]
+    DebtCeilingExceeded,
+    #[msg("Borrow would exceed the max total SOLUSD supply")]
+    MaxTotalSupplyExceeded,
+    #[msg("Division by zero")]
+    DivisionByZero,
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+    #[msg("Cannot withdraw while there are troves with ICR < MCR")]
+    UnderCollateralizedTroves,
 }
\ No newline at end of file
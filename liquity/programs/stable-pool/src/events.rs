@@ -0,0 +1,188 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    states::*,
+};
+
+/// operation that changed a trove, mirroring the BorrowerOperation and TroveManagerOperation of liquity
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TroveOperation {
+    OpenTrove,
+    CloseTrove,
+    AdjustTrove,
+    DepositCollateral,
+    WithdrawCollateral,
+    BorrowUsd,
+    RepayUsd,
+    RedeemCollateral,
+    LiquidateInNormalMode,
+    LiquidateInRecoveryMode,
+}
+
+#[event]
+pub struct TroveUpdated {
+    pub owner: Pubkey,
+    pub token_vault: Pubkey,
+    pub coll: u64,
+    pub debt: u64,
+    pub stake: u64,
+    pub operation: TroveOperation,
+}
+
+/// coll and debt of the trove when it was liquidated, including its pending rewards
+#[event]
+pub struct TroveLiquidated {
+    pub owner: Pubkey,
+    pub token_vault: Pubkey,
+    pub coll: u64,
+    pub debt: u64,
+    pub operation: TroveOperation,
+}
+
+/// totals of a liquidation sequence of a token vault
+#[event]
+pub struct Liquidation {
+    pub token_vault: Pubkey,
+    pub liquidated_debt: u64,
+    pub liquidated_coll: u64,
+    pub coll_gas_compensation: u64,
+    pub solusd_gas_compensation: u64,
+    pub coll_surplus: u64,
+}
+
+#[event]
+pub struct Redemption {
+    pub token_vault: Pubkey,
+    pub redeemer: Pubkey,
+    pub attempted_solusd_amount: u64,
+    pub actual_solusd_amount: u64,
+    pub coll_sent: u64,
+    pub coll_fee: u64,
+}
+
+#[event]
+pub struct CollSurplusClaimed {
+    pub owner: Pubkey,
+    pub token_vault: Pubkey,
+    pub coll_surplus: u64,
+}
+
+/// `deposit_balance` is the compounded deposit of the depositor after the deposit
+#[event]
+pub struct StabilityPoolDeposit {
+    pub depositor: Pubkey,
+    pub token_vault: Pubkey,
+    pub amount: u64,
+    pub deposit_balance: u64,
+}
+
+/// `deposit_balance` is the compounded deposit of the depositor after the withdrawal
+#[event]
+pub struct StabilityPoolWithdraw {
+    pub depositor: Pubkey,
+    pub token_vault: Pubkey,
+    pub amount: u64,
+    pub deposit_balance: u64,
+}
+
+#[event]
+pub struct SPGainsClaimed {
+    pub depositor: Pubkey,
+    pub token_vault: Pubkey,
+    pub coll_gain: u64,
+}
+
+#[event]
+pub struct BaseRateUpdated {
+    pub base_rate: u64,
+    pub last_fee_operation_time: i64,
+}
+
+#[event]
+pub struct TokenVaultCreated {
+    pub token_vault: Pubkey,
+    pub mint_coll: Pubkey,
+}
+
+#[event]
+pub struct VaultParamsUpdated {
+    pub token_vault: Pubkey,
+    pub mcr: u64,
+    pub ccr: u64,
+    pub min_net_debt: u64,
+    pub solusd_gas_compensation: u64,
+    pub debt_ceiling: u64,
+    pub borrowing_fee_floor: u64,
+    pub redemption_fee_floor: u64,
+}
+
+#[event]
+pub struct OracleConfigUpdated {
+    pub token_vault: Pubkey,
+    pub quote_currency: [u8; 32],
+    pub max_price_age_slots: u64,
+    pub max_price_conf_ratio: u64,
+    pub require_trading_status: bool,
+}
+
+#[event]
+pub struct PriceFeedConfigUpdated {
+    pub price_feed: Pubkey,
+    pub fallback_authority: Pubkey,
+    pub max_price_deviation: u64,
+    pub max_price_difference_between_sources: u64,
+    pub max_fallback_price_age_slots: u64,
+}
+
+#[event]
+pub struct FallbackPriceUpdated {
+    pub price_feed: Pubkey,
+    pub price: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct MaxTotalSupplyUpdated {
+    pub max_total_supply: u64,
+}
+
+#[event]
+pub struct StakingStateUpdated {
+    pub staking_state: Pubkey,
+}
+
+#[event]
+pub struct SuperOwnerProposed {
+    pub super_owner: Pubkey,
+    pub pending_super_owner: Pubkey,
+}
+
+#[event]
+pub struct SuperOwnerTransferred {
+    pub super_owner: Pubkey,
+}
+
+#[event]
+pub struct RolesUpdated {
+    pub vault_creator: Pubkey,
+    pub params_admin: Pubkey,
+    pub oracle_admin: Pubkey,
+    pub guardian: Pubkey,
+}
+
+#[event]
+pub struct PauseStateChanged {
+    pub paused_operations: u8,
+    pub guardian: Pubkey,
+}
+
+pub fn emit_trove_updated(user_trove: &UserTrove, operation: TroveOperation) {
+    emit!(TroveUpdated {
+        owner: user_trove.owner,
+        token_vault: user_trove.token_vault,
+        coll: user_trove.coll,
+        debt: user_trove.debt,
+        stake: user_trove.stake,
+        operation,
+    });
+}
@@ -1,13 +1,18 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount,Mint};
 
+use solid_staking_v1::{
+    program::SolidStaking,
+    states::{StakingState, FeeVault},
+};
+
 use crate::{
     states::*,
     constant::*,
 };
 
 #[derive(Accounts)]
-#[instruction(global_state_nonce:u8, mint_usd_nonce:u8, stability_pool_nonce:u8)]
+#[instruction(global_state_nonce:u8, mint_usd_nonce:u8, stability_pool_nonce:u8, gas_pool_nonce:u8)]
 pub struct CreateGlobalState <'info>{
     pub super_owner:  Signer<'info>,
 
@@ -35,6 +40,14 @@ pub struct CreateGlobalState <'info>{
         payer = super_owner)]
     pub stability_solusd_pool:Account<'info, TokenAccount>,
 
+    #[account(init,
+        token::mint = mint_usd,
+        token::authority = global_state,
+        seeds = [GAS_POOL_TAG],
+        bump = gas_pool_nonce,
+        payer = super_owner)]
+    pub gas_pool:Account<'info, TokenAccount>,
+
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
@@ -50,7 +63,7 @@ pub struct CreateTokenVault<'info> {
         seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
         bump = token_vault_nonce,
         payer = payer,
-        constraint = payer.key() == global_state.super_owner
+        constraint = payer.key() == global_state.vault_creator
     )]
     pub token_vault: ProgramAccount<'info, TokenVault>,
 
@@ -103,7 +116,7 @@ pub struct CreateUserTrove<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8)]
+#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey)]
 pub struct DepositCollateral<'info> {
     pub owner:  Signer<'info>,
     #[account(mut,
@@ -131,7 +144,7 @@ pub struct DepositCollateral<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8)]
+#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey)]
 pub struct WithdrawCollateral<'info> {
     pub owner:  Signer<'info>,
     #[account(mut,
@@ -156,10 +169,29 @@ pub struct WithdrawCollateral<'info> {
         constraint = mint_coll.key() == token_vault.mint_coll)]
     pub mint_coll:Account<'info, Mint>,
     pub token_program:Program<'info, Token>,
+
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(
+        constraint = mint_usd.key() == global_state.mint_usd)]
+    pub mint_usd:Account<'info, Mint>,
+
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8)]
+#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64)]
 pub struct BorrowUsd<'info> {
     pub owner:  Signer<'info>,
     #[account(mut,
@@ -186,19 +218,39 @@ pub struct BorrowUsd<'info> {
         constraint = user_token_usd.owner == owner.key(),
         constraint = user_token_usd.mint == mint_usd.key())]
     pub user_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+
+    pub staking_program: Program<'info, SolidStaking>,
+    #[account(mut,
+        constraint = staking_state.key() == global_state.staking_state)]
+    pub staking_state: Account<'info, StakingState>,
+    #[account(mut,
+        constraint = staking_solusd_fee_pool.key() == staking_state.solusd_fee_pool)]
+    pub staking_solusd_fee_pool:Account<'info, TokenAccount>,
     #[account(
         constraint = mint_coll.key() == token_vault.mint_coll)]
     pub mint_coll:Account<'info, Mint>,
     pub token_program:Program<'info, Token>,
     
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
     pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
     pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
     pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
     pub clock: Sysvar<'info, Clock>,
 }
  
 #[derive(Accounts)]
-#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8)]
+#[instruction(amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey)]
 pub struct RepayUsd<'info> {
     pub owner:  Signer<'info>,
     #[account(mut,
@@ -224,6 +276,9 @@ pub struct RepayUsd<'info> {
         constraint = user_token_usd.owner == owner.key(),
         constraint = user_token_usd.mint == mint_usd.key())]
     pub user_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
     #[account(mut,
         constraint = mint_coll.key() == token_vault.mint_coll)]
     pub mint_coll:Account<'info, Mint>,
@@ -231,7 +286,7 @@ pub struct RepayUsd<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(global_state_nonce: u8, token_vault_nonce: u8, user_trove_nonce: u8)]
+#[instruction(global_state_nonce: u8, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8)]
 pub struct LiquidateTrove<'info> {
     pub liquidator:  Signer<'info>,
 
@@ -255,11 +310,113 @@ pub struct LiquidateTrove<'info> {
         constraint = mint_coll.key() == token_vault.mint_coll)]
     pub mint_coll:Account<'info, Mint>,
 
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = liquidator_token_coll.owner == liquidator.key(),
+        constraint = liquidator_token_coll.mint == token_vault.mint_coll)]
+    pub liquidator_token_coll:Account<'info, TokenAccount>,
+
+    #[account(mut,
+        constraint = mint_usd.key() == global_state.mint_usd)]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(mut,
+        constraint = stability_solusd_pool.key() == global_state.stability_solusd_pool)]
     pub stability_solusd_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = liquidator_token_usd.owner == liquidator.key(),
+        constraint = liquidator_token_usd.mint == mint_usd.key())]
+    pub liquidator_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = epoch_to_scale.token_vault == token_vault.key(),
+        constraint = epoch_to_scale.epoch == token_vault.sp_current_epoch,
+        constraint = epoch_to_scale.scale == token_vault.sp_current_scale)]
+    pub epoch_to_scale: ProgramAccount<'info, EpochToScale>,
     
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
     pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
     pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
     pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+
+    pub token_program:Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8, token_vault_nonce: u8, token_coll_nonce: u8)]
+pub struct BatchLiquidateTroves<'info> {
+    pub liquidator:  Signer<'info>,
+
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = liquidator_token_coll.owner == liquidator.key(),
+        constraint = liquidator_token_coll.mint == token_vault.mint_coll)]
+    pub liquidator_token_coll:Account<'info, TokenAccount>,
+
+    #[account(mut,
+        constraint = mint_usd.key() == global_state.mint_usd)]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(mut,
+        constraint = stability_solusd_pool.key() == global_state.stability_solusd_pool)]
+    pub stability_solusd_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = liquidator_token_usd.owner == liquidator.key(),
+        constraint = liquidator_token_usd.mint == mint_usd.key())]
+    pub liquidator_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = epoch_to_scale.token_vault == token_vault.key(),
+        constraint = epoch_to_scale.epoch == token_vault.sp_current_epoch,
+        constraint = epoch_to_scale.scale == token_vault.sp_current_scale)]
+    pub epoch_to_scale: ProgramAccount<'info, EpochToScale>,
+
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
     pub clock: Sysvar<'info, Clock>,
 
     pub token_program:Program<'info, Token>,
@@ -275,11 +432,30 @@ pub struct SPDeposit<'info> {
         bump = global_state_nonce)]
     pub global_state: ProgramAccount<'info, GlobalState>,
 
+    #[account(mut)]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+
     #[account(mut,
-        seeds = [SP_USER_INFO],
+        seeds = [SP_USER_INFO, token_vault.key().as_ref(), owner.key().as_ref()],
         bump = sp_user_info_nonce)]
     pub sp_user_info: ProgramAccount<'info, SPUserInfo>,
 
+    #[account(
+        constraint = epoch_to_scale_snapshot.token_vault == token_vault.key(),
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_snapshot.epoch == sp_user_info.epoch_snapshot,
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_snapshot.scale == sp_user_info.scale_snapshot)]
+    pub epoch_to_scale_snapshot: ProgramAccount<'info, EpochToScale>,
+    #[account(
+        constraint = epoch_to_scale_next.token_vault == token_vault.key(),
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_next.epoch == sp_user_info.epoch_snapshot,
+        constraint = sp_user_info.deposit_balance == 0 || sp_user_info.scale_snapshot.checked_add(1) == Some(epoch_to_scale_next.scale))]
+    pub epoch_to_scale_next: ProgramAccount<'info, EpochToScale>,
+    #[account(
+        constraint = epoch_to_scale_current.token_vault == token_vault.key(),
+        constraint = epoch_to_scale_current.epoch == token_vault.sp_current_epoch,
+        constraint = epoch_to_scale_current.scale == token_vault.sp_current_scale)]
+    pub epoch_to_scale_current: ProgramAccount<'info, EpochToScale>,
+
     #[account(mut,
         seeds = [STABILITY_POOL_TAG],
         bump = stability_pool_nonce,
@@ -304,11 +480,30 @@ pub struct SPWithdraw<'info> {
         bump = global_state_nonce)]
     pub global_state: ProgramAccount<'info, GlobalState>,
 
+    #[account(mut)]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+
     #[account(mut,
-        seeds = [GLOBAL_STATE_TAG],
+        seeds = [SP_USER_INFO, token_vault.key().as_ref(), owner.key().as_ref()],
         bump = sp_user_info_nonce)]
     pub sp_user_info: ProgramAccount<'info, SPUserInfo>,
 
+    #[account(
+        constraint = epoch_to_scale_snapshot.token_vault == token_vault.key(),
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_snapshot.epoch == sp_user_info.epoch_snapshot,
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_snapshot.scale == sp_user_info.scale_snapshot)]
+    pub epoch_to_scale_snapshot: ProgramAccount<'info, EpochToScale>,
+    #[account(
+        constraint = epoch_to_scale_next.token_vault == token_vault.key(),
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_next.epoch == sp_user_info.epoch_snapshot,
+        constraint = sp_user_info.deposit_balance == 0 || sp_user_info.scale_snapshot.checked_add(1) == Some(epoch_to_scale_next.scale))]
+    pub epoch_to_scale_next: ProgramAccount<'info, EpochToScale>,
+    #[account(
+        constraint = epoch_to_scale_current.token_vault == token_vault.key(),
+        constraint = epoch_to_scale_current.epoch == token_vault.sp_current_epoch,
+        constraint = epoch_to_scale_current.scale == token_vault.sp_current_scale)]
+    pub epoch_to_scale_current: ProgramAccount<'info, EpochToScale>,
+
     #[account(mut,
         seeds = [STABILITY_POOL_TAG],
         bump = stability_pool_nonce,
@@ -321,4 +516,559 @@ pub struct SPWithdraw<'info> {
     pub user_solusd_token:Account<'info, TokenAccount>,
     
     pub token_program:Program<'info, Token>,
+
+    #[account(
+        constraint = mint_usd.key() == global_state.mint_usd)]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+    #[account(constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, token_vault_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8, partial_redemption_hint_nicr: u64, upper_partial_redemption_hint: Pubkey, lower_partial_redemption_hint: Pubkey, max_iterations: u64, max_fee_percentage: u64)]
+pub struct RedeemCollateral<'info> {
+    pub redeemer:  Signer<'info>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [SOLUSD_MINT_TAG],
+        bump = mint_usd_nonce,
+        constraint = mint_usd.key() == global_state.mint_usd
+    )]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(mut,
+        constraint = user_token_usd.owner == redeemer.key(),
+        constraint = user_token_usd.mint == mint_usd.key())]
+    pub user_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_coll.owner == redeemer.key(),
+        constraint = user_token_coll.mint == token_vault.mint_coll)]
+    pub user_token_coll:Account<'info, TokenAccount>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+    pub token_program:Program<'info, Token>,
+
+    pub staking_program: Program<'info, SolidStaking>,
+    #[account(mut,
+        constraint = staking_state.key() == global_state.staking_state)]
+    pub staking_state: Account<'info, StakingState>,
+    #[account(mut,
+        constraint = staking_fee_vault.mint_coll == token_vault.mint_coll)]
+    pub staking_fee_vault: Account<'info, FeeVault>,
+    #[account(mut,
+        constraint = staking_fee_vault_pool.key() == staking_fee_vault.token_coll)]
+    pub staking_fee_vault_pool:Account<'info, TokenAccount>,
+
+    #[account(constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(sp_user_info_nonce: u8)]
+pub struct CreateSPUserInfo<'info> {
+    pub owner:  Signer<'info>,
+    #[account(
+    init,
+    seeds = [SP_USER_INFO, token_vault.key().as_ref(), owner.key().as_ref()],
+    bump = sp_user_info_nonce,
+    payer = owner,
+    )]
+    pub sp_user_info: ProgramAccount<'info, SPUserInfo>,
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64, scale: u64, epoch_to_scale_nonce: u8)]
+pub struct CreateEpochToScale<'info> {
+    pub payer:  Signer<'info>,
+    #[account(
+    init,
+    seeds = [EPOCH_TO_SCALE_TAG, token_vault.key().as_ref(), epoch.to_le_bytes().as_ref(), scale.to_le_bytes().as_ref()],
+    bump = epoch_to_scale_nonce,
+    payer = payer,
+    )]
+    pub epoch_to_scale: ProgramAccount<'info, EpochToScale>,
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8, sp_user_info_nonce: u8, token_vault_nonce: u8, token_coll_nonce: u8)]
+pub struct SPClaimGains<'info> {
+    pub owner:  Signer<'info>,
+
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+
+    #[account(mut,
+        seeds = [SP_USER_INFO, token_vault.key().as_ref(), owner.key().as_ref()],
+        bump = sp_user_info_nonce)]
+    pub sp_user_info: ProgramAccount<'info, SPUserInfo>,
+
+    #[account(
+        constraint = epoch_to_scale_snapshot.token_vault == token_vault.key(),
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_snapshot.epoch == sp_user_info.epoch_snapshot,
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_snapshot.scale == sp_user_info.scale_snapshot)]
+    pub epoch_to_scale_snapshot: ProgramAccount<'info, EpochToScale>,
+    #[account(
+        constraint = epoch_to_scale_next.token_vault == token_vault.key(),
+        constraint = sp_user_info.deposit_balance == 0 || epoch_to_scale_next.epoch == sp_user_info.epoch_snapshot,
+        constraint = sp_user_info.deposit_balance == 0 || sp_user_info.scale_snapshot.checked_add(1) == Some(epoch_to_scale_next.scale))]
+    pub epoch_to_scale_next: ProgramAccount<'info, EpochToScale>,
+    #[account(
+        constraint = epoch_to_scale_current.token_vault == token_vault.key(),
+        constraint = epoch_to_scale_current.epoch == token_vault.sp_current_epoch,
+        constraint = epoch_to_scale_current.scale == token_vault.sp_current_scale)]
+    pub epoch_to_scale_current: ProgramAccount<'info, EpochToScale>,
+
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_coll.owner == owner.key(),
+        constraint = user_token_coll.mint == token_vault.mint_coll)]
+    pub user_token_coll:Account<'info, TokenAccount>,
+
+    pub token_program:Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct SetStakingState<'info> {
+    pub super_owner:  Signer<'info>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = super_owner.key() == global_state.super_owner)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(
+        constraint = staking_state.fee_authority == global_state.key(),
+        constraint = staking_state.mint_usd == global_state.mint_usd)]
+    pub staking_state: Account<'info, StakingState>,
+}
+
+#[derive(Accounts)]
+#[instruction(coll_amount: u64, debt_amount: u64, user_trove_nonce: u8, token_vault_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64)]
+pub struct OpenTrove<'info> {
+    pub owner:  Signer<'info>,
+    #[account(
+    init,
+    seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
+    bump = user_trove_nonce,
+    payer = owner,
+    )]
+    pub user_trove:ProgramAccount<'info, UserTrove>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [SOLUSD_MINT_TAG],
+        bump = mint_usd_nonce,
+        constraint = mint_usd.key() == global_state.mint_usd
+    )]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(mut,
+        constraint = user_token_usd.owner == owner.key(),
+        constraint = user_token_usd.mint == mint_usd.key())]
+    pub user_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_coll.owner == owner.key(),
+        constraint = user_token_coll.mint == token_vault.mint_coll)]
+    pub user_token_coll:Account<'info, TokenAccount>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+
+    pub staking_program: Program<'info, SolidStaking>,
+    #[account(mut,
+        constraint = staking_state.key() == global_state.staking_state)]
+    pub staking_state: Account<'info, StakingState>,
+    #[account(mut,
+        constraint = staking_solusd_fee_pool.key() == staking_state.solusd_fee_pool)]
+    pub staking_solusd_fee_pool:Account<'info, TokenAccount>,
+    pub token_program:Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(coll_change: u64, is_coll_increase: bool, debt_change: u64, is_debt_increase: bool, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64)]
+pub struct AdjustTrove<'info> {
+    pub owner:  Signer<'info>,
+    #[account(mut,
+        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
+        bump = user_trove_nonce)]
+    pub user_trove:ProgramAccount<'info, UserTrove>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [SOLUSD_MINT_TAG],
+        bump = mint_usd_nonce,
+        constraint = mint_usd.key() == global_state.mint_usd
+    )]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(mut,
+        constraint = user_token_usd.owner == owner.key(),
+        constraint = user_token_usd.mint == mint_usd.key())]
+    pub user_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_coll.owner == owner.key(),
+        constraint = user_token_coll.mint == token_vault.mint_coll)]
+    pub user_token_coll:Account<'info, TokenAccount>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+
+    pub staking_program: Program<'info, SolidStaking>,
+    #[account(mut,
+        constraint = staking_state.key() == global_state.staking_state)]
+    pub staking_state: Account<'info, StakingState>,
+    #[account(mut,
+        constraint = staking_solusd_fee_pool.key() == staking_state.solusd_fee_pool)]
+    pub staking_solusd_fee_pool:Account<'info, TokenAccount>,
+    pub token_program:Program<'info, Token>,
+
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8)]
+pub struct CloseTrove<'info> {
+    #[account(mut)]
+    pub owner:  Signer<'info>,
+    // the trove account is closed and its rent sent back to the owner
+    #[account(mut,
+        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
+        bump = user_trove_nonce,
+        close = owner)]
+    pub user_trove:ProgramAccount<'info, UserTrove>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [SOLUSD_MINT_TAG],
+        bump = mint_usd_nonce,
+        constraint = mint_usd.key() == global_state.mint_usd
+    )]
+    pub mint_usd:Account<'info, Mint>,
+    #[account(mut,
+        constraint = user_token_usd.owner == owner.key(),
+        constraint = user_token_usd.mint == mint_usd.key())]
+    pub user_token_usd:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = gas_pool.key() == global_state.gas_pool)]
+    pub gas_pool:Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_coll.owner == owner.key(),
+        constraint = user_token_coll.mint == token_vault.mint_coll)]
+    pub user_token_coll:Account<'info, TokenAccount>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+    pub token_program:Program<'info, Token>,
+
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    #[account(mut,
+        constraint = price_feed.token_vault == token_vault.key())]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8)]
+pub struct ClaimCollSurplus<'info> {
+    pub owner:  Signer<'info>,
+
+    #[account(mut,
+        seeds = [USER_TROVE_TAG,token_vault.key().as_ref(), owner.key().as_ref()],
+        bump = user_trove_nonce)]
+    pub user_trove:ProgramAccount<'info, UserTrove>,
+
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,mint_coll.key().as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(
+        constraint = mint_coll.key() == token_vault.mint_coll)]
+    pub mint_coll:Account<'info, Mint>,
+
+    #[account(mut,
+        seeds = [TOKEN_VAULT_POOL_TAG,token_vault.key().as_ref()],
+        bump = token_coll_nonce,
+    )]
+    pub pool_token_coll:Account<'info, TokenAccount>,
+    #[account(mut,
+        constraint = user_token_coll.owner == owner.key(),
+        constraint = user_token_coll.mint == token_vault.mint_coll)]
+    pub user_token_coll:Account<'info, TokenAccount>,
+
+    pub token_program:Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8, token_vault_nonce: u8)]
+pub struct SetOracleConfig<'info> {
+    pub oracle_admin:  Signer<'info>,
+    #[account(
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = oracle_admin.key() == global_state.oracle_admin)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,token_vault.mint_coll.as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+}
+
+#[derive(Accounts)]
+#[instruction(price_feed_nonce: u8, global_state_nonce: u8)]
+pub struct CreatePriceFeed<'info> {
+    pub oracle_admin:  Signer<'info>,
+    #[account(
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = oracle_admin.key() == global_state.oracle_admin)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+    #[account(
+        init,
+        seeds = [PRICE_FEED_TAG, token_vault.key().as_ref()],
+        bump = price_feed_nonce,
+        payer = oracle_admin)]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+
+    #[account(
+        constraint = oracle_program.key() == token_vault.oracle_program)]
+    pub oracle_program: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_product.key() == token_vault.pyth_product)]
+    pub pyth_product: AccountInfo<'info>,
+    #[account(
+        constraint = pyth_price.key() == token_vault.pyth_price)]
+    pub pyth_price: AccountInfo<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct SetPriceFeedConfig<'info> {
+    pub oracle_admin:  Signer<'info>,
+    #[account(
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = oracle_admin.key() == global_state.oracle_admin)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut)]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+}
+
+#[derive(Accounts)]
+pub struct PushFallbackPrice<'info> {
+    pub fallback_authority:  Signer<'info>,
+    #[account(mut,
+        constraint = fallback_authority.key() == price_feed.fallback_authority)]
+    pub price_feed: ProgramAccount<'info, PriceFeed>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8, token_vault_nonce: u8)]
+pub struct UpdateVaultParams<'info> {
+    pub params_admin:  Signer<'info>,
+    #[account(
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = params_admin.key() == global_state.params_admin)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+    #[account(mut,
+        seeds = [TOKEN_VAULT_TAG,token_vault.mint_coll.as_ref()],
+        bump = token_vault_nonce,
+    )]
+    pub token_vault:ProgramAccount<'info, TokenVault>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct SetMaxTotalSupply<'info> {
+    pub params_admin:  Signer<'info>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = params_admin.key() == global_state.params_admin)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct ProposeSuperOwner<'info> {
+    pub super_owner:  Signer<'info>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = super_owner.key() == global_state.super_owner)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct AcceptSuperOwner<'info> {
+    pub pending_super_owner:  Signer<'info>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = pending_super_owner.key() == global_state.pending_super_owner)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct SetRoles<'info> {
+    pub super_owner:  Signer<'info>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = super_owner.key() == global_state.super_owner)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+#[instruction(global_state_nonce: u8)]
+pub struct SetPauseState<'info> {
+    pub guardian:  Signer<'info>,
+    #[account(mut,
+        seeds = [GLOBAL_STATE_TAG],
+        bump = global_state_nonce,
+        constraint = guardian.key() == global_state.guardian)]
+    pub global_state: ProgramAccount<'info, GlobalState>,
 }
@@ -12,8 +12,16 @@ pub mod constant;
 pub mod instructions;
 /// utils
 pub mod utils;
+/// checked math
+pub mod math;
 /// pyth
 pub mod pyth;
+/// sorted troves
+pub mod sorted_troves;
+/// price feed
+pub mod price_feed;
+/// events
+pub mod events;
 
 use crate::{
     instructions::*,
@@ -26,8 +34,11 @@ declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 pub mod stable_pool {
     use super::*;
 
-    pub fn create_global_state(ctx: Context<CreateGlobalState>, global_state_nonce:u8, mint_usd_nonce:u8, stability_pool_nonce:u8) -> ProgramResult { 
-        process_create_global_state(ctx, global_state_nonce, mint_usd_nonce, stability_pool_nonce) 
+    pub fn create_global_state(ctx: Context<CreateGlobalState>, global_state_nonce:u8, mint_usd_nonce:u8, stability_pool_nonce:u8, gas_pool_nonce:u8) -> ProgramResult { 
+        process_create_global_state(ctx, global_state_nonce, mint_usd_nonce, stability_pool_nonce, gas_pool_nonce) 
+    }
+    pub fn set_staking_state(ctx: Context<SetStakingState>, global_state_nonce:u8) -> ProgramResult { 
+        process_set_staking_state(ctx, global_state_nonce) 
     }
     pub fn create_token_vault(ctx: Context<CreateTokenVault>, token_vault_nonce:u8, global_state_nonce:u8, token_coll_nonce:u8) -> ProgramResult { 
         process_create_token_vault(ctx, token_vault_nonce, global_state_nonce, token_coll_nonce)
@@ -35,20 +46,38 @@ pub mod stable_pool {
     pub fn create_user_trove(ctx: Context<CreateUserTrove>, user_trove_nonce:u8, token_vault_nonce:u8) -> ProgramResult { 
         process_create_user_trove(ctx, user_trove_nonce, token_vault_nonce) 
     }
-    pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
-        process_deposit_collateral(ctx, amount, token_vault_nonce, user_trove_nonce, token_coll_nonce) 
+    pub fn open_trove(ctx: Context<OpenTrove>, coll_amount: u64, debt_amount: u64, user_trove_nonce: u8, token_vault_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64) -> ProgramResult { 
+        process_open_trove(ctx, coll_amount, debt_amount, user_trove_nonce, token_vault_nonce, global_state_nonce, mint_usd_nonce, token_coll_nonce, upper_hint, lower_hint, max_fee_percentage) 
+    }
+    pub fn adjust_trove(ctx: Context<AdjustTrove>, coll_change: u64, is_coll_increase: bool, debt_change: u64, is_debt_increase: bool, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64) -> ProgramResult { 
+        process_adjust_trove(ctx, coll_change, is_coll_increase, debt_change, is_debt_increase, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce, token_coll_nonce, upper_hint, lower_hint, max_fee_percentage) 
+    }
+    pub fn close_trove(ctx: Context<CloseTrove>, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
+        process_close_trove(ctx, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce, token_coll_nonce) 
+    }
+    pub fn create_sp_user_info(ctx: Context<CreateSPUserInfo>, sp_user_info_nonce:u8) -> ProgramResult { 
+        process_create_sp_user_info(ctx, sp_user_info_nonce) 
+    }
+    pub fn create_epoch_to_scale(ctx: Context<CreateEpochToScale>, epoch: u64, scale: u64, epoch_to_scale_nonce: u8) -> ProgramResult { 
+        process_create_epoch_to_scale(ctx, epoch, scale, epoch_to_scale_nonce) 
     }
-    pub fn withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
-        process_withdraw_collateral(ctx, amount, token_vault_nonce, user_trove_nonce, token_coll_nonce) 
+    pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey) -> ProgramResult { 
+        process_deposit_collateral(ctx, amount, token_vault_nonce, user_trove_nonce, token_coll_nonce, upper_hint, lower_hint) 
     }
-    pub fn borrow_usd(ctx: Context<BorrowUsd>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8) -> ProgramResult { 
-        process_borrow_usd(ctx, amount, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce) 
+    pub fn withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey) -> ProgramResult { 
+        process_withdraw_collateral(ctx, amount, token_vault_nonce, user_trove_nonce, token_coll_nonce, upper_hint, lower_hint) 
     }
-    pub fn repay_usd(ctx: Context<RepayUsd>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8) -> ProgramResult { 
-        process_repay_usd(ctx, amount, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce) 
+    pub fn borrow_usd(ctx: Context<BorrowUsd>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64) -> ProgramResult { 
+        process_borrow_usd(ctx, amount, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce, upper_hint, lower_hint, max_fee_percentage) 
     }
-    pub fn liquidate_trove(ctx: Context<LiquidateTrove>, global_state_nonce: u8, token_vault_nonce: u8, user_trove_nonce: u8) -> ProgramResult { 
-        process_liquidate_trove(ctx, global_state_nonce, token_vault_nonce, user_trove_nonce) 
+    pub fn repay_usd(ctx: Context<RepayUsd>, amount: u64, token_vault_nonce: u8, user_trove_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey) -> ProgramResult { 
+        process_repay_usd(ctx, amount, token_vault_nonce, user_trove_nonce, global_state_nonce, mint_usd_nonce, upper_hint, lower_hint) 
+    }
+    pub fn liquidate_trove(ctx: Context<LiquidateTrove>, global_state_nonce: u8, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
+        process_liquidate_trove(ctx, global_state_nonce, token_vault_nonce, user_trove_nonce, token_coll_nonce) 
+    }
+    pub fn batch_liquidate_troves(ctx: Context<BatchLiquidateTroves>, global_state_nonce: u8, token_vault_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
+        process_batch_liquidate_troves(ctx, global_state_nonce, token_vault_nonce, token_coll_nonce) 
     }
     pub fn sp_deposit(ctx: Context<SPDeposit>, amount: u64, global_state_nonce: u8, sp_user_info_nonce: u8, stability_pool_nonce: u8) -> ProgramResult { 
         process_sp_deposit(ctx, amount, global_state_nonce, sp_user_info_nonce, stability_pool_nonce) 
@@ -56,4 +85,43 @@ pub mod stable_pool {
     pub fn sp_withdraw(ctx: Context<SPWithdraw>, amount: u64, global_state_nonce: u8, sp_user_info_nonce: u8, stability_pool_nonce: u8) -> ProgramResult { 
         process_sp_withdraw(ctx, amount, global_state_nonce, sp_user_info_nonce, stability_pool_nonce) 
     }
+    pub fn sp_claim_gains(ctx: Context<SPClaimGains>, global_state_nonce: u8, sp_user_info_nonce: u8, token_vault_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
+        process_sp_claim_gains(ctx, global_state_nonce, sp_user_info_nonce, token_vault_nonce, token_coll_nonce) 
+    }
+    pub fn redeem_collateral(ctx: Context<RedeemCollateral>, amount: u64, token_vault_nonce: u8, global_state_nonce: u8, mint_usd_nonce: u8, token_coll_nonce: u8, partial_redemption_hint_nicr: u64, upper_partial_redemption_hint: Pubkey, lower_partial_redemption_hint: Pubkey, max_iterations: u64, max_fee_percentage: u64) -> ProgramResult { 
+        process_redeem_collateral(ctx, amount, token_vault_nonce, global_state_nonce, mint_usd_nonce, token_coll_nonce, partial_redemption_hint_nicr, upper_partial_redemption_hint, lower_partial_redemption_hint, max_iterations, max_fee_percentage) 
+    }
+    pub fn claim_coll_surplus(ctx: Context<ClaimCollSurplus>, token_vault_nonce: u8, user_trove_nonce: u8, token_coll_nonce: u8) -> ProgramResult { 
+        process_claim_coll_surplus(ctx, token_vault_nonce, user_trove_nonce, token_coll_nonce) 
+    }
+    pub fn set_oracle_config(ctx: Context<SetOracleConfig>, global_state_nonce: u8, token_vault_nonce: u8, quote_currency: [u8; 32], max_price_age_slots: u64, max_price_conf_ratio: u64, require_trading_status: bool) -> ProgramResult { 
+        process_set_oracle_config(ctx, global_state_nonce, token_vault_nonce, quote_currency, max_price_age_slots, max_price_conf_ratio, require_trading_status) 
+    }
+    pub fn create_price_feed(ctx: Context<CreatePriceFeed>, price_feed_nonce: u8, global_state_nonce: u8) -> ProgramResult { 
+        process_create_price_feed(ctx, price_feed_nonce, global_state_nonce) 
+    }
+    pub fn set_price_feed_config(ctx: Context<SetPriceFeedConfig>, global_state_nonce: u8, fallback_authority: Pubkey, max_price_deviation: u64, max_price_difference_between_sources: u64, max_fallback_price_age_slots: u64) -> ProgramResult { 
+        process_set_price_feed_config(ctx, global_state_nonce, fallback_authority, max_price_deviation, max_price_difference_between_sources, max_fallback_price_age_slots) 
+    }
+    pub fn push_fallback_price(ctx: Context<PushFallbackPrice>, price: u64) -> ProgramResult { 
+        process_push_fallback_price(ctx, price) 
+    }
+    pub fn update_vault_params(ctx: Context<UpdateVaultParams>, global_state_nonce: u8, token_vault_nonce: u8, mcr: u64, ccr: u64, min_net_debt: u64, solusd_gas_compensation: u64, debt_ceiling: u64, borrowing_fee_floor: u64, redemption_fee_floor: u64) -> ProgramResult { 
+        process_update_vault_params(ctx, global_state_nonce, token_vault_nonce, mcr, ccr, min_net_debt, solusd_gas_compensation, debt_ceiling, borrowing_fee_floor, redemption_fee_floor) 
+    }
+    pub fn set_max_total_supply(ctx: Context<SetMaxTotalSupply>, global_state_nonce: u8, max_total_supply: u64) -> ProgramResult { 
+        process_set_max_total_supply(ctx, global_state_nonce, max_total_supply) 
+    }
+    pub fn propose_super_owner(ctx: Context<ProposeSuperOwner>, global_state_nonce: u8, new_super_owner: Pubkey) -> ProgramResult { 
+        process_propose_super_owner(ctx, global_state_nonce, new_super_owner) 
+    }
+    pub fn accept_super_owner(ctx: Context<AcceptSuperOwner>, global_state_nonce: u8) -> ProgramResult { 
+        process_accept_super_owner(ctx, global_state_nonce) 
+    }
+    pub fn set_roles(ctx: Context<SetRoles>, global_state_nonce: u8, vault_creator: Pubkey, params_admin: Pubkey, oracle_admin: Pubkey, guardian: Pubkey) -> ProgramResult { 
+        process_set_roles(ctx, global_state_nonce, vault_creator, params_admin, oracle_admin, guardian) 
+    }
+    pub fn set_pause_state(ctx: Context<SetPauseState>, global_state_nonce: u8, paused_operations: u8) -> ProgramResult { 
+        process_set_pause_state(ctx, global_state_nonce, paused_operations) 
+    }
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use anchor_lang::prelude::*;
+use crate::error::*;
+use std::convert::TryFrom;
+
+/*
+* Checked arithmetic of the stable pool. An overflow, an underflow or a division by zero returns an error instead of
+* panicking or wrapping, so every processor can propagate it with `?`.
+*/
+pub trait SafeMath: Sized {
+    fn try_add(self, rhs: Self) -> Result<Self>;
+    fn try_sub(self, rhs: Self) -> Result<Self>;
+    fn try_mul(self, rhs: Self) -> Result<Self>;
+    fn try_div(self, rhs: Self) -> Result<Self>;
+}
+
+macro_rules! impl_safe_math {
+    ($($t:ty),*) => {
+        $(
+            impl SafeMath for $t {
+                fn try_add(self, rhs: Self) -> Result<Self> {
+                    self.checked_add(rhs).ok_or_else(|| StablePoolError::MathOverflow.into())
+                }
+                fn try_sub(self, rhs: Self) -> Result<Self> {
+                    self.checked_sub(rhs).ok_or_else(|| StablePoolError::MathOverflow.into())
+                }
+                fn try_mul(self, rhs: Self) -> Result<Self> {
+                    self.checked_mul(rhs).ok_or_else(|| StablePoolError::MathOverflow.into())
+                }
+                fn try_div(self, rhs: Self) -> Result<Self> {
+                    if rhs == 0 {
+                        return Err(StablePoolError::DivisionByZero.into());
+                    }
+                    self.checked_div(rhs).ok_or_else(|| StablePoolError::MathOverflow.into())
+                }
+            }
+        )*
+    };
+}
+impl_safe_math!(u64, u128, i64);
+
+/// `a * b / c` with a u128 intermediate product, the quotient must fit in a u64
+pub fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    to_u64((a as u128).try_mul(b as u128)?.try_div(c as u128)?)
+}
+
+pub fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| StablePoolError::MathOverflow.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn safe_math() {
+        let half = u32::MAX as u64 + 1;
+        // a, b, a + b, a - b, a * b and a / b, None where the operation fails
+        let cases = [
+            (0, 0, Some(0), Some(0), Some(0), None),
+            (7, 2, Some(9), Some(5), Some(14), Some(3)),
+            (2, 7, Some(9), None, Some(14), Some(0)),
+            (u64::MAX, 1, None, Some(u64::MAX - 1), Some(u64::MAX), Some(u64::MAX)),
+            (half - 1, half, Some(2 * half - 1), None, Some((half - 1) << 32), Some(0)),
+            (half, half, Some(2 * half), Some(0), None, Some(1)),
+        ];
+        for &(a, b, sum, difference, product, quotient) in cases.iter() {
+            assert_eq!(a.try_add(b).ok(), sum);
+            assert_eq!(a.try_sub(b).ok(), difference);
+            assert_eq!(a.try_mul(b).ok(), product);
+            assert_eq!(a.try_div(b).ok(), quotient);
+        }
+    }
+
+    #[test]
+    fn math_errors_are_typed() {
+        assert_eq!(u64::MAX.try_add(1).unwrap_err(), StablePoolError::MathOverflow.into());
+        assert_eq!(0u64.try_sub(1).unwrap_err(), StablePoolError::MathOverflow.into());
+        assert_eq!(u64::MAX.try_mul(2).unwrap_err(), StablePoolError::MathOverflow.into());
+        assert_eq!(1u64.try_div(0).unwrap_err(), StablePoolError::DivisionByZero.into());
+        assert_eq!(i64::MIN.try_div(-1).unwrap_err(), StablePoolError::MathOverflow.into());
+        assert_eq!(to_u64(u64::MAX as u128 + 1).unwrap_err(), StablePoolError::MathOverflow.into());
+    }
+
+    #[test]
+    fn mul_div_and_to_u64() {
+        // the intermediate product may exceed a u64 as long as the quotient fits
+        assert_eq!(mul_div(10, 1_000_000_000, 4).ok(), Some(2_500_000_000));
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX).ok(), Some(u64::MAX));
+        assert_eq!(mul_div(1, 2, 3).ok(), Some(0));
+        assert_eq!(mul_div(u64::MAX, 2, 1).ok(), None);
+        assert_eq!(mul_div(1, 1, 0).ok(), None);
+
+        assert_eq!(to_u64(u64::MAX as u128).ok(), Some(u64::MAX));
+        assert_eq!(to_u64(u128::MAX).ok(), None);
+    }
+}
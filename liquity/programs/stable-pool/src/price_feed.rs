@@ -0,0 +1,232 @@
+use anchor_lang::prelude::*;
+use crate::{
+    constant::*,
+    states::*,
+    error::*,
+    utils::*,
+};
+
+/*
+* Price feed of a token vault, with pyth as the primary source and a fallback price pushed by the fallback authority.
+*
+* Pyth is broken when its price can not be read or fails the oracle guards of the token vault, and frozen when its
+* aggregate price is stale. The fallback is broken while no price was pushed, and frozen when the pushed price is older
+* than the max fallback age. A pyth price moving more than the max deviation from the last good price is not trusted
+* either. Depending on the status, the feed switches between the sources and freezes on the last good price when
+* neither of them can be trusted, until both sources are live again and report similar prices.
+*/
+
+enum SourceResponse {
+    Working(u64),
+    Frozen,
+    Broken,
+}
+impl SourceResponse {
+    fn is_broken(&self) -> bool {
+        matches!(self, SourceResponse::Broken)
+    }
+    fn is_frozen(&self) -> bool {
+        matches!(self, SourceResponse::Frozen)
+    }
+}
+
+impl PriceFeed {
+    /// price of the collateral of the token vault, updates the status and the last good price of the feed
+    pub fn fetch_price(&mut self, token_vault: &TokenVault, pyth_product_info: &AccountInfo, pyth_price_info: &AccountInfo, clock: &Clock) -> Result<u64> {
+        let pyth = match get_market_price(token_vault, pyth_product_info, pyth_price_info, clock) {
+            Ok(price) => SourceResponse::Working(price),
+            Err(err) if err == StablePoolError::StalePrice.into() => SourceResponse::Frozen,
+            Err(_) => SourceResponse::Broken,
+        };
+        let fallback = self.get_fallback_response(clock);
+        Ok(self.update_price(pyth, fallback, clock))
+    }
+
+    /// switch the status of the feed depending on the responses of the sources, returns the price to use
+    fn update_price(&mut self, pyth: SourceResponse, fallback: SourceResponse, clock: &Clock) -> u64 {
+        match self.status {
+            PriceFeedStatus::PythWorking => {
+                if pyth.is_broken() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::BothUntrusted;
+                        return self.last_good_price;
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                    return self.store_price(&fallback, clock);
+                }
+                if pyth.is_frozen() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                        return self.last_good_price;
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythFrozen;
+                    return self.store_price(&fallback, clock);
+                }
+                if self.is_price_change_above_max(&pyth) {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::BothUntrusted;
+                        return self.last_good_price;
+                    }
+                    if fallback.is_frozen() {
+                        self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                        return self.last_good_price;
+                    }
+                    if self.are_both_sources_similar(&pyth, &fallback) {
+                        return self.store_price(&pyth, clock);
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                    return self.store_price(&fallback, clock);
+                }
+                if fallback.is_broken() {
+                    self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                }
+                self.store_price(&pyth, clock)
+            }
+            PriceFeedStatus::UsingFallbackPythUntrusted => {
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                if fallback.is_broken() {
+                    self.status = PriceFeedStatus::BothUntrusted;
+                    return self.last_good_price;
+                }
+                self.store_price(&fallback, clock)
+            }
+            PriceFeedStatus::BothUntrusted => {
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                self.last_good_price
+            }
+            PriceFeedStatus::UsingFallbackPythFrozen => {
+                if pyth.is_broken() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::BothUntrusted;
+                        return self.last_good_price;
+                    }
+                    self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                    return self.store_price(&fallback, clock);
+                }
+                if pyth.is_frozen() {
+                    if fallback.is_broken() {
+                        self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                        return self.last_good_price;
+                    }
+                    return self.store_price(&fallback, clock);
+                }
+                // pyth is live again
+                if fallback.is_broken() {
+                    self.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+                    return self.store_price(&pyth, clock);
+                }
+                if fallback.is_frozen() {
+                    return self.last_good_price;
+                }
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                self.status = PriceFeedStatus::UsingFallbackPythUntrusted;
+                self.store_price(&fallback, clock)
+            }
+            PriceFeedStatus::UsingPythFallbackUntrusted => {
+                if self.are_both_sources_similar(&pyth, &fallback) {
+                    self.status = PriceFeedStatus::PythWorking;
+                    return self.store_price(&pyth, clock);
+                }
+                if pyth.is_broken() || self.is_price_change_above_max(&pyth) {
+                    self.status = PriceFeedStatus::BothUntrusted;
+                    return self.last_good_price;
+                }
+                // a frozen pyth keeps the status and the last good price, the feed uses pyth again once it is live
+                self.store_price(&pyth, clock)
+            }
+        }
+    }
+
+    fn get_fallback_response(&self, clock: &Clock) -> SourceResponse {
+        if self.fallback_price == 0 || self.fallback_price_slot == 0 {
+            return SourceResponse::Broken;
+        }
+        if clock.slot.saturating_sub(self.fallback_price_slot) > self.max_fallback_price_age_slots {
+            return SourceResponse::Frozen;
+        }
+        SourceResponse::Working(self.fallback_price)
+    }
+
+    /// store the price of a live source as the last good price, frozen or broken sources keep the last good price
+    fn store_price(&mut self, source: &SourceResponse, clock: &Clock) -> u64 {
+        if let SourceResponse::Working(price) = source {
+            self.last_good_price = *price;
+            self.last_good_price_slot = clock.slot;
+        }
+        self.last_good_price
+    }
+
+    fn is_price_change_above_max(&self, pyth: &SourceResponse) -> bool {
+        match pyth {
+            SourceResponse::Working(price) => {
+                let change = get_absolute_difference(*price, self.last_good_price);
+                change as u128 * DECIMAL_PRECISION as u128 > self.max_price_deviation as u128 * self.last_good_price as u128
+            }
+            _ => false,
+        }
+    }
+
+    /// both sources are live and their prices are within the max difference between sources
+    fn are_both_sources_similar(&self, pyth: &SourceResponse, fallback: &SourceResponse) -> bool {
+        match (pyth, fallback) {
+            (SourceResponse::Working(pyth_price), SourceResponse::Working(fallback_price)) => {
+                let min_price = min(*pyth_price, *fallback_price);
+                let difference = get_absolute_difference(*pyth_price, *fallback_price);
+                difference as u128 * DECIMAL_PRECISION as u128 <= self.max_price_difference_between_sources as u128 * min_price as u128
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frozen_pyth_recovers_without_fallback() {
+        let clock = Clock { slot: 10, ..Clock::default() };
+        let mut price_feed = PriceFeed {
+            status: PriceFeedStatus::UsingPythFallbackUntrusted,
+            last_good_price: 100 * DECIMAL_PRECISION,
+            max_price_deviation: DEFAULT_MAX_PRICE_DEVIATION,
+            max_price_difference_between_sources: DEFAULT_MAX_PRICE_DIFFERENCE_BETWEEN_SOURCES,
+            ..PriceFeed::default()
+        };
+
+        // pyth is frozen and the fallback is broken, the feed keeps the last good price and waits for pyth
+        let price = price_feed.update_price(SourceResponse::Frozen, SourceResponse::Broken, &clock);
+        assert_eq!(price, 100 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::UsingPythFallbackUntrusted);
+
+        // pyth is live again
+        let price = price_feed.update_price(SourceResponse::Working(101 * DECIMAL_PRECISION), SourceResponse::Broken, &clock);
+        assert_eq!(price, 101 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::UsingPythFallbackUntrusted);
+        assert_eq!(price_feed.last_good_price_slot, 10);
+
+        // the fallback is back with a similar price
+        let price = price_feed.update_price(
+            SourceResponse::Working(102 * DECIMAL_PRECISION),
+            SourceResponse::Working(100 * DECIMAL_PRECISION),
+            &clock,
+        );
+        assert_eq!(price, 102 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::PythWorking);
+
+        // a broken pyth can not be used
+        price_feed.status = PriceFeedStatus::UsingPythFallbackUntrusted;
+        let price = price_feed.update_price(SourceResponse::Broken, SourceResponse::Broken, &clock);
+        assert_eq!(price, 102 * DECIMAL_PRECISION);
+        assert_eq!(price_feed.status, PriceFeedStatus::BothUntrusted);
+    }
+}
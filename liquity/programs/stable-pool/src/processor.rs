@@ -3,12 +3,30 @@
 pub mod process_create_global_state;
 pub use process_create_global_state::*;
 
+pub mod process_set_staking_state;
+pub use process_set_staking_state::*;
+
 pub mod process_create_token_vault;
 pub use process_create_token_vault::*;
 
 pub mod process_create_user_trove;
 pub use process_create_user_trove::*;
 
+pub mod process_open_trove;
+pub use process_open_trove::*;
+
+pub mod process_adjust_trove;
+pub use process_adjust_trove::*;
+
+pub mod process_close_trove;
+pub use process_close_trove::*;
+
+pub mod process_create_sp_user_info;
+pub use process_create_sp_user_info::*;
+
+pub mod process_create_epoch_to_scale;
+pub use process_create_epoch_to_scale::*;
+
 pub mod process_deposit_collateral;
 pub use process_deposit_collateral::*;
 
@@ -24,8 +42,50 @@ pub use process_repay_usd::*;
 pub mod process_liquidate_trove;
 pub use process_liquidate_trove::*;
 
+pub mod process_batch_liquidate_troves;
+pub use process_batch_liquidate_troves::*;
+
 pub mod process_sp_deposit;
 pub use process_sp_deposit::*;
 
 pub mod process_sp_withdraw;
-pub use process_sp_withdraw::*;
\ No newline at end of file
+pub use process_sp_withdraw::*;
+
+pub mod process_sp_claim_gains;
+pub use process_sp_claim_gains::*;
+
+pub mod process_redeem_collateral;
+pub use process_redeem_collateral::*;
+
+pub mod process_claim_coll_surplus;
+pub use process_claim_coll_surplus::*;
+
+pub mod process_set_oracle_config;
+pub use process_set_oracle_config::*;
+
+pub mod process_create_price_feed;
+pub use process_create_price_feed::*;
+
+pub mod process_set_price_feed_config;
+pub use process_set_price_feed_config::*;
+
+pub mod process_push_fallback_price;
+pub use process_push_fallback_price::*;
+
+pub mod process_update_vault_params;
+pub use process_update_vault_params::*;
+
+pub mod process_set_max_total_supply;
+pub use process_set_max_total_supply::*;
+
+pub mod process_propose_super_owner;
+pub use process_propose_super_owner::*;
+
+pub mod process_accept_super_owner;
+pub use process_accept_super_owner::*;
+
+pub mod process_set_roles;
+pub use process_set_roles::*;
+
+pub mod process_set_pause_state;
+pub use process_set_pause_state::*;
\ No newline at end of file
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::*,
+    instructions::*
+};
+
+/// second step of the super owner transfer, signed by the proposed super owner
+pub fn process_accept_super_owner(ctx: Context<AcceptSuperOwner>, _global_state_nonce: u8) -> ProgramResult {
+    let global_state = &mut ctx.accounts.global_state;
+    global_state.super_owner = global_state.pending_super_owner;
+    global_state.pending_super_owner = Pubkey::default();
+
+    emit!(SuperOwnerTransferred {
+        super_owner: global_state.super_owner,
+    });
+    Ok(())
+}
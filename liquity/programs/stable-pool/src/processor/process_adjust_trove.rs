@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Burn, Transfer};
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*,
+    math::*,
+    utils::*,
+    sorted_troves::*,
+    processor::*,
+};
+
+/*
+* Change the collateral and the debt of a trove in one call. The collateral is deposited when `is_coll_increase` and
+* withdrawn otherwise, the debt is borrowed when `is_debt_increase` and repaid otherwise.
+*
+* The first borrow of a trove reserves the SOLUSD gas compensation, and repaying the whole net debt releases it.
+*
+* The adjustment must follow the recovery mode rules, see `require_valid_adjustment`.
+*/
+pub fn process_adjust_trove(ctx: Context<AdjustTrove>, coll_change: u64, is_coll_increase: bool, debt_change: u64, is_debt_increase: bool, token_vault_nonce: u8, _user_trove_nonce: u8, global_state_nonce: u8, _mint_usd_nonce: u8, _token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64) -> ProgramResult {
+    if !ctx.accounts.user_trove.is_active() {
+        return Err(StablePoolError::TroveNotActive.into());
+    }
+    if coll_change == 0 && debt_change == 0 {
+        return Err(StablePoolError::NoneZeroAdjustment.into());
+    }
+    if is_debt_increase && debt_change > 0 {
+        ctx.accounts.global_state.require_not_paused(PAUSE_BORROW)?;
+    }
+    if !is_coll_increase && coll_change > 0 {
+        ctx.accounts.global_state.require_not_paused(PAUSE_WITHDRAW_COLL)?;
+    }
+
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
+        &ctx.accounts.pyth_product,
+        &ctx.accounts.pyth_price,
+        &ctx.accounts.clock
+    )?;
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
+
+    let recovery_mode = ctx.accounts.token_vault.is_recovery_mode(market_price, coll_decimals, usd_decimals)?;
+    let is_borrow = is_debt_increase && debt_change > 0;
+
+    let now = ctx.accounts.clock.unix_timestamp;
+    let borrowing_fee = if is_borrow {
+        trigger_borrowing_fee(&mut ctx.accounts.global_state, ctx.accounts.token_vault.borrowing_fee_floor, recovery_mode, debt_change, max_fee_percentage, now)?
+    } else {0};
+
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
+
+    let token_vault = &ctx.accounts.token_vault;
+    let user_trove = &ctx.accounts.user_trove;
+    if !is_debt_increase && debt_change > user_trove.net_debt()? {
+        return Err(StablePoolError::RepaymentExceedsDebt.into());
+    }
+    let gas_compensation = if is_borrow && user_trove.debt == 0 {token_vault.solusd_gas_compensation} else {0};
+    let released_gas_compensation = if !is_debt_increase && debt_change > 0 && debt_change == user_trove.net_debt()? {
+        user_trove.solusd_gas_compensation
+    } else {0};
+    let net_debt_change = debt_change.try_add(borrowing_fee)?.try_add(gas_compensation)?;
+    let repaid_debt = debt_change.try_add(released_gas_compensation)?;
+    if !is_coll_increase && coll_change > user_trove.coll {
+        return Err(StablePoolError::WithdrawalExceedsColl.into());
+    }
+    if !is_debt_increase && ctx.accounts.user_token_usd.amount < debt_change {
+        return Err(StablePoolError::InsufficientSolusdBalance.into());
+    }
+
+    let (new_coll, new_total_coll) = if is_coll_increase {
+        (user_trove.coll.try_add(coll_change)?, token_vault.total_coll.try_add(coll_change)?)
+    } else {
+        (user_trove.coll.try_sub(coll_change)?, token_vault.total_coll.try_sub(coll_change)?)
+    };
+    let (new_debt, new_total_debt) = if is_debt_increase {
+        (user_trove.debt.try_add(net_debt_change)?, token_vault.total_debt.try_add(net_debt_change)?)
+    } else {
+        (user_trove.debt.try_sub(repaid_debt)?, token_vault.total_debt.try_sub(repaid_debt)?)
+    };
+    let new_gas_compensation = user_trove.solusd_gas_compensation.try_add(gas_compensation)?.try_sub(released_gas_compensation)?;
+
+    // the trove and the system must stay healthy after the adjustment
+    let coll_withdrawal = if is_coll_increase {0} else {coll_change};
+    let old_icr = compute_icr(user_trove.coll, user_trove.debt, market_price, coll_decimals, usd_decimals)?;
+    let new_icr = compute_icr(new_coll, new_debt, market_price, coll_decimals, usd_decimals)?;
+    let new_tcr = compute_icr(new_total_coll, new_total_debt, market_price, coll_decimals, usd_decimals)?;
+    require_valid_adjustment(token_vault, recovery_mode, coll_withdrawal, is_borrow, old_icr, new_icr, new_tcr)?;
+    token_vault.require_at_least_min_net_debt(new_debt.try_sub(new_gas_compensation)?)?;
+    if is_borrow {
+        token_vault.require_within_debt_ceiling(net_debt_change)?;
+    }
+
+    // move the collateral
+    if coll_change > 0 {
+        if is_coll_increase {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.user_token_coll.to_account_info(),
+                to: ctx.accounts.pool_token_coll.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, coll_change)?;
+        }
+        else {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.pool_token_coll.to_account_info(),
+                to: ctx.accounts.user_token_coll.to_account_info(),
+                authority: ctx.accounts.token_vault.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let signer_seeds = &[
+                TOKEN_VAULT_TAG,
+                ctx.accounts.token_vault.mint_coll.as_ref(),
+                &[token_vault_nonce]
+            ];
+            let signer = &[&signer_seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, coll_change)?;
+        }
+    }
+
+    // move the debt
+    if debt_change > 0 {
+        if is_debt_increase {
+            mint_solusd_and_fee(
+                &mut ctx.accounts.global_state,
+                &ctx.accounts.mint_usd,
+                &ctx.accounts.user_token_usd,
+                &ctx.accounts.gas_pool,
+                &ctx.accounts.staking_program,
+                &ctx.accounts.staking_state,
+                &ctx.accounts.staking_solusd_fee_pool,
+                &ctx.accounts.token_program,
+                global_state_nonce,
+                debt_change,
+                borrowing_fee,
+                gas_compensation,
+            )?;
+        }
+        else {
+            let cpi_accounts = Burn {
+                mint: ctx.accounts.mint_usd.to_account_info(),
+                to: ctx.accounts.user_token_usd.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::burn(cpi_ctx, debt_change)?;
+
+            burn_gas_compensation(
+                &ctx.accounts.global_state,
+                &ctx.accounts.mint_usd,
+                &ctx.accounts.gas_pool,
+                &ctx.accounts.token_program,
+                global_state_nonce,
+                released_gas_compensation,
+            )?;
+        }
+    }
+
+    ctx.accounts.token_vault.total_coll = new_total_coll;
+    ctx.accounts.token_vault.total_debt = new_total_debt;
+    ctx.accounts.user_trove.coll = new_coll;
+    ctx.accounts.user_trove.debt = new_debt;
+    ctx.accounts.user_trove.solusd_gas_compensation = new_gas_compensation;
+
+    ctx.accounts.token_vault.update_stake_and_total_stakes(&mut ctx.accounts.user_trove)?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &upper_hint,
+        &lower_hint,
+    )?;
+
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::AdjustTrove);
+    Ok(())
+}
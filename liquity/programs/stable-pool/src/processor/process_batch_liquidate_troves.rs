@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*,
+    utils::*,
+    states::*,
+    sorted_troves::*,
+    processor::*,
+};
+
+/*
+* Liquidate the troves passed as remaining accounts, in the given order. The caller should pass them starting from the
+* tail of the sorted list (lowest ICR), followed by the neighbour troves whose links change when the liquidated troves
+* leave the list. Troves that can not be liquidated are left untouched.
+*
+* The totals are accumulated across all troves, and the stability pool offset and the redistribution are applied once
+* at the end. In recovery mode the TCR is tracked through the sequence, and once the liquidations bring the vault back
+* to normal mode only troves with ICR < MCR are liquidated.
+*/
+pub fn process_batch_liquidate_troves(ctx: Context<BatchLiquidateTroves>, global_state_nonce: u8, token_vault_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
+    ctx.accounts.global_state.require_not_paused(PAUSE_LIQUIDATE)?;
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
+        &ctx.accounts.pyth_product,
+        &ctx.accounts.pyth_price,
+        &ctx.accounts.clock
+    )?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let mut nodes = load_trove_nodes(ctx.remaining_accounts, &token_vault_key, &Pubkey::default())?;
+    let trove_keys: Vec<Pubkey> = nodes.iter().map(|node| node.key()).collect();
+
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
+    let mut recovery_mode = ctx.accounts.token_vault.is_recovery_mode(market_price, coll_decimals, usd_decimals)?;
+    let mut totals = LiquidationTotals::new();
+
+    for trove_key in trove_keys.iter() {
+        let index = nodes.iter().position(|node| node.key() == *trove_key).ok_or(StablePoolError::InvalidAccountInput)?;
+        let mut trove = nodes.remove(index);
+
+        if trove.is_listed && trove.debt > 0 {
+            ctx.accounts.token_vault.apply_pending_rewards(&mut trove)?;
+            let (coll, debt) = (trove.coll, trove.debt);
+            let liquidated_in_recovery_mode = recovery_mode;
+            if recovery_mode {
+                get_total_from_batch_liquidate_recovery_mode(&ctx.accounts.token_vault, &mut trove, &mut totals, market_price, coll_decimals, usd_decimals)?;
+                recovery_mode = get_tcr_after_liquidations(&ctx.accounts.token_vault, &totals, market_price, coll_decimals, usd_decimals)? < ctx.accounts.token_vault.ccr;
+            }
+            else {
+                get_total_from_batch_liquidate_normal_mode(&ctx.accounts.token_vault, &mut trove, &mut totals, market_price, coll_decimals, usd_decimals)?;
+            }
+
+            // a liquidated trove leaves the sorted list
+            if trove.debt == 0 {
+                ctx.accounts.token_vault.remove_stake(&mut trove)?;
+                remove(&mut ctx.accounts.token_vault, trove_key, &mut trove, &mut nodes)?;
+                emit_trove_liquidated(&trove, coll, debt, liquidated_in_recovery_mode);
+            }
+        }
+        nodes.push(trove);
+    }
+
+    if totals.total_debt_in_sequence == 0 {
+        return Err(StablePoolError::NothingToLiquidate.into());
+    }
+
+    apply_liquidation_totals(
+        &ctx.accounts.global_state,
+        &mut ctx.accounts.token_vault,
+        &mut ctx.accounts.epoch_to_scale,
+        &ctx.accounts.mint_usd,
+        &ctx.accounts.stability_solusd_pool,
+        &ctx.accounts.gas_pool,
+        &ctx.accounts.liquidator_token_usd,
+        &ctx.accounts.pool_token_coll,
+        &ctx.accounts.liquidator_token_coll,
+        &ctx.accounts.token_program,
+        global_state_nonce,
+        token_vault_nonce,
+        &totals,
+    )?;
+
+    save_trove_nodes(ctx.program_id, &nodes)
+}
@@ -1,43 +1,212 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self,  MintTo};
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount};
+use solid_staking_v1::{
+    program::SolidStaking,
+    states::StakingState,
+};
 
 use crate::{
     constant::*,
+    error::*,
+    events::*,
     instructions::*,
+    math::*,
     utils::*,
+    states::*,
+    sorted_troves::*,
 };
 
-pub fn process_borrow_usd(ctx: Context<BorrowUsd>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _global_state_nonce: u8, _mint_usd_nonce: u8) -> ProgramResult {
-    let market_price = get_market_price(
-        *ctx.accounts.oracle_program.key,
+pub fn process_borrow_usd(ctx: Context<BorrowUsd>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _global_state_nonce: u8, _mint_usd_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64) -> ProgramResult {
+    if !ctx.accounts.user_trove.is_active() {
+        return Err(StablePoolError::TroveNotActive.into());
+    }
+    if amount == 0 {
+        return Err(StablePoolError::ZeroAmount.into());
+    }
+    ctx.accounts.global_state.require_not_paused(PAUSE_BORROW)?;
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
         &ctx.accounts.pyth_product,
         &ctx.accounts.pyth_price,
         &ctx.accounts.clock
     )?;
 
-    assert_debt_allowed(ctx.accounts.user_trove.coll, ctx.accounts.user_trove.debt, amount, market_price, ctx.accounts.mint_coll.decimals, ctx.accounts.mint_usd.decimals)?;
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
 
-    // mint to user
-    let cpi_accounts = MintTo {
-        mint: ctx.accounts.mint_usd.to_account_info().clone(),
-        to: ctx.accounts.user_token_usd.to_account_info().clone(),
-        authority: ctx.accounts.global_state.to_account_info().clone(),
-    };
+    let recovery_mode = ctx.accounts.token_vault.is_recovery_mode(market_price, coll_decimals, usd_decimals)?;
+
+    let now = ctx.accounts.clock.unix_timestamp;
+    let borrowing_fee = trigger_borrowing_fee(&mut ctx.accounts.global_state, ctx.accounts.token_vault.borrowing_fee_floor, recovery_mode, amount, max_fee_percentage, now)?;
+
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
+
+    // the first borrow of a trove reserves the SOLUSD gas compensation
+    let gas_compensation = if ctx.accounts.user_trove.debt == 0 {ctx.accounts.token_vault.solusd_gas_compensation} else {0};
+    let debt_increase = amount.try_add(borrowing_fee)?.try_add(gas_compensation)?;
+
+    // the trove and the system must stay healthy after the borrow
+    let token_vault = &ctx.accounts.token_vault;
+    let user_trove = &ctx.accounts.user_trove;
+    let new_debt = user_trove.debt.try_add(debt_increase)?;
+    let old_icr = compute_icr(user_trove.coll, user_trove.debt, market_price, coll_decimals, usd_decimals)?;
+    let new_icr = compute_icr(user_trove.coll, new_debt, market_price, coll_decimals, usd_decimals)?;
+    let new_tcr = compute_icr(token_vault.total_coll, token_vault.total_debt.try_add(debt_increase)?, market_price, coll_decimals, usd_decimals)?;
+    require_valid_adjustment(token_vault, recovery_mode, 0, true, old_icr, new_icr, new_tcr)?;
+    token_vault.require_at_least_min_net_debt(new_debt.try_sub(user_trove.solusd_gas_compensation)?.try_sub(gas_compensation)?)?;
+    token_vault.require_within_debt_ceiling(debt_increase)?;
+
+    mint_solusd_and_fee(
+        &mut ctx.accounts.global_state,
+        &ctx.accounts.mint_usd,
+        &ctx.accounts.user_token_usd,
+        &ctx.accounts.gas_pool,
+        &ctx.accounts.staking_program,
+        &ctx.accounts.staking_state,
+        &ctx.accounts.staking_solusd_fee_pool,
+        &ctx.accounts.token_program,
+        _global_state_nonce,
+        amount,
+        borrowing_fee,
+        gas_compensation,
+    )?;
+
+    ctx.accounts.token_vault.total_debt = ctx.accounts.token_vault.total_debt.try_add(debt_increase)?;
+    ctx.accounts.user_trove.debt = ctx.accounts.user_trove.debt.try_add(debt_increase)?;
+    ctx.accounts.user_trove.solusd_gas_compensation = ctx.accounts.user_trove.solusd_gas_compensation.try_add(gas_compensation)?;
+
+    ctx.accounts.token_vault.update_stake_and_total_stakes(&mut ctx.accounts.user_trove)?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &upper_hint,
+        &lower_hint,
+    )?;
+
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::BorrowUsd);
+    Ok(())
+}
 
-    let cpi_program = ctx.accounts.token_program.to_account_info().clone();
+/*
+* Check the max fee percentage the borrower accepts and compute the borrowing fee of a debt increase, after decaying the
+* base rate. The fee is waived in recovery mode.
+*/
+pub fn trigger_borrowing_fee(global_state: &mut GlobalState, borrowing_fee_floor: u64, recovery_mode: bool, amount: u64, max_fee_percentage: u64, now: i64) -> Result<u64> {
+    if recovery_mode {
+        if max_fee_percentage > DECIMAL_PRECISION {
+            return Err(StablePoolError::InvalidMaxFeePercentage.into());
+        }
+        return Ok(0);
+    }
+    if max_fee_percentage < borrowing_fee_floor || max_fee_percentage > DECIMAL_PRECISION {
+        return Err(StablePoolError::InvalidMaxFeePercentage.into());
+    }
+
+    global_state.decay_base_rate_from_borrowing(now)?;
+    let borrowing_fee = global_state.get_borrowing_fee(borrowing_fee_floor, amount)?;
+    require_user_accepts_fee(borrowing_fee, amount, max_fee_percentage)?;
+    Ok(borrowing_fee)
+}
+
+/// mint `amount` SOLUSD to the borrower, the borrowing fee to the SOLID staking fee pool and the gas compensation to the gas pool
+pub fn mint_solusd_and_fee<'info>(
+    global_state: &mut ProgramAccount<'info, GlobalState>,
+    mint_usd: &Account<'info, Mint>,
+    user_token_usd: &Account<'info, TokenAccount>,
+    gas_pool: &Account<'info, TokenAccount>,
+    staking_program: &Program<'info, SolidStaking>,
+    staking_state: &Account<'info, StakingState>,
+    staking_solusd_fee_pool: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    global_state_nonce: u8,
+    amount: u64,
+    borrowing_fee: u64,
+    gas_compensation: u64,
+) -> ProgramResult {
+    global_state.require_within_max_total_supply(mint_usd.supply, amount.try_add(borrowing_fee)?.try_add(gas_compensation)?)?;
 
     let signer_seeds = &[
         GLOBAL_STATE_TAG,
-        &[_global_state_nonce],
+        &[global_state_nonce],
     ];
     let signer = &[&signer_seeds[..]];
 
+    // mint to user
+    let cpi_accounts = MintTo {
+        mint: mint_usd.to_account_info(),
+        to: user_token_usd.to_account_info(),
+        authority: global_state.to_account_info(),
+    };
+    let cpi_program = token_program.to_account_info();
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-
     token::mint_to(cpi_ctx, amount)?;
 
-    ctx.accounts.token_vault.total_debt += amount;
-    ctx.accounts.user_trove.debt += amount;
+    // mint the borrowing fee to the SOLID staking fee pool, it is added to the trove debt
+    if borrowing_fee > 0 {
+        let cpi_accounts = MintTo {
+            mint: mint_usd.to_account_info(),
+            to: staking_solusd_fee_pool.to_account_info(),
+            authority: global_state.to_account_info(),
+        };
+        let cpi_program = token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, borrowing_fee)?;
+
+        let cpi_accounts = solid_staking_v1::cpi::accounts::IncreaseFSolusd {
+            fee_authority: global_state.to_account_info(),
+            staking_state: staking_state.to_account_info(),
+        };
+        let cpi_program = staking_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        solid_staking_v1::cpi::increase_f_solusd(cpi_ctx, borrowing_fee)?;
+
+        global_state.total_borrowing_fee = global_state.total_borrowing_fee.try_add(borrowing_fee)?;
+    }
 
+    // reserve the gas compensation, it is added to the trove debt
+    if gas_compensation > 0 {
+        let cpi_accounts = MintTo {
+            mint: mint_usd.to_account_info(),
+            to: gas_pool.to_account_info(),
+            authority: global_state.to_account_info(),
+        };
+        let cpi_program = token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, gas_compensation)?;
+    }
     Ok(())
 }
+
+/// burn the gas compensation reserved by a trove whose debt is repaid, from the gas pool
+pub fn burn_gas_compensation<'info>(
+    global_state: &ProgramAccount<'info, GlobalState>,
+    mint_usd: &Account<'info, Mint>,
+    gas_pool: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    global_state_nonce: u8,
+    gas_compensation: u64,
+) -> ProgramResult {
+    if gas_compensation == 0 {
+        return Ok(());
+    }
+    let cpi_accounts = Burn {
+        mint: mint_usd.to_account_info(),
+        to: gas_pool.to_account_info(),
+        authority: global_state.to_account_info(),
+    };
+    let cpi_program = token_program.to_account_info();
+    let signer_seeds = &[
+        GLOBAL_STATE_TAG,
+        &[global_state_nonce],
+    ];
+    let signer = &[&signer_seeds[..]];
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::burn(cpi_ctx, gas_compensation)
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Transfer};
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*,
+    math::*
+};
+
+/*
+* Send the collateral surplus of a trove liquidated in recovery mode or fully redeemed back to its owner.
+* The trove account is kept, so the owner can open it again.
+*/
+pub fn process_claim_coll_surplus(ctx: Context<ClaimCollSurplus>, token_vault_nonce: u8, _user_trove_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
+    let coll_surplus = ctx.accounts.user_trove.coll_surplus;
+    if coll_surplus == 0 {
+        return Err(StablePoolError::NoCollAvailableToClaim.into());
+    }
+
+    // transfer the collateral surplus from the pool to the owner
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.pool_token_coll.to_account_info(),
+        to: ctx.accounts.user_token_coll.to_account_info(),
+        authority: ctx.accounts.token_vault.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let signer_seeds = &[
+        TOKEN_VAULT_TAG,
+        ctx.accounts.token_vault.mint_coll.as_ref(),
+        &[token_vault_nonce]
+    ];
+    let signer = &[&signer_seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, coll_surplus)?;
+
+    ctx.accounts.user_trove.coll_surplus = 0;
+    ctx.accounts.token_vault.total_coll_surplus = ctx.accounts.token_vault.total_coll_surplus.try_sub(coll_surplus)?;
+
+    emit!(CollSurplusClaimed {
+        owner: ctx.accounts.user_trove.owner,
+        token_vault: ctx.accounts.token_vault.key(),
+        coll_surplus,
+    });
+    Ok(())
+}
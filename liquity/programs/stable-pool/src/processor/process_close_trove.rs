@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Burn, Transfer};
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*,
+    math::*,
+    utils::*,
+    states::*,
+    sorted_troves::*,
+    processor::*,
+};
+
+/*
+* Close a trove: repay all its debt, send all its collateral back to the owner and close the trove account to reclaim
+* the rent. The neighbours of the trove in the sorted list are passed as remaining accounts.
+*
+* The owner repays the net debt, the SOLUSD gas compensation is burnt from the gas pool.
+*
+* An active trove can not be closed in recovery mode, nor when it would push the TCR below CCR. A trove closed by a
+* liquidation or a redemption has no debt left, closing it returns the collateral that is left, including the collateral
+* surplus of a recovery mode liquidation.
+*/
+pub fn process_close_trove(ctx: Context<CloseTrove>, token_vault_nonce: u8, _user_trove_nonce: u8, global_state_nonce: u8, _mint_usd_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
+    if ctx.accounts.user_trove.status == TroveStatus::NonExistent {
+        return Err(StablePoolError::TroveNotActive.into());
+    }
+
+    if ctx.accounts.user_trove.is_active() {
+        let market_price = ctx.accounts.price_feed.fetch_price(
+            &ctx.accounts.token_vault,
+            &ctx.accounts.pyth_product,
+            &ctx.accounts.pyth_price,
+            &ctx.accounts.clock
+        )?;
+        let coll_decimals = ctx.accounts.mint_coll.decimals;
+        let usd_decimals = ctx.accounts.mint_usd.decimals;
+
+        ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
+
+        let token_vault = &ctx.accounts.token_vault;
+        let user_trove = &ctx.accounts.user_trove;
+        if user_trove.debt > 0 {
+            if token_vault.is_recovery_mode(market_price, coll_decimals, usd_decimals)? {
+                return Err(StablePoolError::NotPermittedInRecoveryMode.into());
+            }
+            if token_vault.sorted_troves_size <= 1 {
+                return Err(StablePoolError::OnlyOneTroveInSystem.into());
+            }
+            let new_tcr = compute_icr(token_vault.total_coll.try_sub(user_trove.coll)?, token_vault.total_debt.try_sub(user_trove.debt)?, market_price, coll_decimals, usd_decimals)?;
+            if new_tcr < token_vault.ccr {
+                return Err(StablePoolError::TCRBelowCCR.into());
+            }
+            let net_debt = user_trove.net_debt()?;
+            if ctx.accounts.user_token_usd.amount < net_debt {
+                return Err(StablePoolError::InsufficientSolusdBalance.into());
+            }
+
+            // burn the repaid debt
+            let cpi_accounts = Burn {
+                mint: ctx.accounts.mint_usd.to_account_info(),
+                to: ctx.accounts.user_token_usd.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::burn(cpi_ctx, net_debt)?;
+
+            burn_gas_compensation(
+                &ctx.accounts.global_state,
+                &ctx.accounts.mint_usd,
+                &ctx.accounts.gas_pool,
+                &ctx.accounts.token_program,
+                global_state_nonce,
+                user_trove.solusd_gas_compensation,
+            )?;
+
+            ctx.accounts.token_vault.total_debt = ctx.accounts.token_vault.total_debt.try_sub(ctx.accounts.user_trove.debt)?;
+        }
+        ctx.accounts.user_trove.debt = 0;
+        ctx.accounts.user_trove.solusd_gas_compensation = 0;
+        ctx.accounts.token_vault.remove_stake(&mut ctx.accounts.user_trove)?;
+
+        // the trove leaves the sorted list
+        let token_vault_key = ctx.accounts.token_vault.key();
+        let user_trove_key = ctx.accounts.user_trove.key();
+        update_sorted_troves(
+            ctx.program_id,
+            &token_vault_key,
+            &mut ctx.accounts.token_vault,
+            &user_trove_key,
+            &mut ctx.accounts.user_trove,
+            ctx.remaining_accounts,
+            &Pubkey::default(),
+            &Pubkey::default(),
+        )?;
+    }
+
+    // send the collateral back to the owner, the account is closed so the collateral surplus goes with it
+    let coll = ctx.accounts.user_trove.coll;
+    let coll_surplus = ctx.accounts.user_trove.coll_surplus;
+    let coll_to_send = coll.try_add(coll_surplus)?;
+    if coll_to_send > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.pool_token_coll.to_account_info(),
+            to: ctx.accounts.user_token_coll.to_account_info(),
+            authority: ctx.accounts.token_vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let signer_seeds = &[
+            TOKEN_VAULT_TAG,
+            ctx.accounts.token_vault.mint_coll.as_ref(),
+            &[token_vault_nonce]
+        ];
+        let signer = &[&signer_seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, coll_to_send)?;
+
+        ctx.accounts.token_vault.total_coll = ctx.accounts.token_vault.total_coll.try_sub(coll)?;
+        ctx.accounts.token_vault.total_coll_surplus = ctx.accounts.token_vault.total_coll_surplus.try_sub(coll_surplus)?;
+        ctx.accounts.user_trove.coll_surplus = 0;
+    }
+
+    if ctx.accounts.user_trove.is_active() {
+        ctx.accounts.user_trove.close(TroveStatus::ClosedByOwner);
+    }
+    else {
+        ctx.accounts.user_trove.coll = 0;
+    }
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::CloseTrove);
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::*
+};
+
+/*
+* Create the account holding the sum S of a token vault's stability pool for an epoch and scale. Liquidations need the account of the current epoch and
+* scale, and depositors need the account of their snapshot scale and of the next one.
+*/
+pub fn process_create_epoch_to_scale(ctx: Context<CreateEpochToScale>, epoch: u64, scale: u64, _epoch_to_scale_nonce: u8) -> ProgramResult {
+    ctx.accounts.epoch_to_scale.token_vault = ctx.accounts.token_vault.key();
+    ctx.accounts.epoch_to_scale.epoch = epoch;
+    ctx.accounts.epoch_to_scale.scale = scale;
+    ctx.accounts.epoch_to_scale.sum = 0;
+    Ok(())
+}
@@ -1,12 +1,21 @@
 use anchor_lang::prelude::*;
 
 use crate::{
+    constant::*,
     instructions::*
 };
 
-pub fn process_create_global_state(ctx: Context<CreateGlobalState>, _global_state_nonce:u8, _mint_usd_nonce:u8, _stability_pool_nonce:u8) -> ProgramResult {
+pub fn process_create_global_state(ctx: Context<CreateGlobalState>, _global_state_nonce:u8, _mint_usd_nonce:u8, _stability_pool_nonce:u8, _gas_pool_nonce:u8) -> ProgramResult {
     ctx.accounts.global_state.super_owner = ctx.accounts.super_owner.key();
     ctx.accounts.global_state.mint_usd = ctx.accounts.mint_usd.key();
     ctx.accounts.global_state.stability_solusd_pool = ctx.accounts.stability_solusd_pool.key();
+    ctx.accounts.global_state.gas_pool = ctx.accounts.gas_pool.key();
+    ctx.accounts.global_state.max_total_supply = DEFAULT_MAX_TOTAL_SUPPLY;
+
+    // the super owner holds every role until it delegates them
+    ctx.accounts.global_state.vault_creator = ctx.accounts.super_owner.key();
+    ctx.accounts.global_state.params_admin = ctx.accounts.super_owner.key();
+    ctx.accounts.global_state.oracle_admin = ctx.accounts.super_owner.key();
+    ctx.accounts.global_state.guardian = ctx.accounts.super_owner.key();
     Ok(())
 }
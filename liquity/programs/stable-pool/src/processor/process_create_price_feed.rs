@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constant::*,
+    instructions::*,
+    states::*,
+    utils::*,
+};
+
+/// create the price feed of a token vault, the current pyth price becomes its first good price
+pub fn process_create_price_feed(ctx: Context<CreatePriceFeed>, _price_feed_nonce: u8, _global_state_nonce: u8) -> ProgramResult {
+    let market_price = get_market_price(
+        &ctx.accounts.token_vault,
+        &ctx.accounts.pyth_product,
+        &ctx.accounts.pyth_price,
+        &ctx.accounts.clock
+    )?;
+
+    let price_feed = &mut ctx.accounts.price_feed;
+    price_feed.token_vault = ctx.accounts.token_vault.key();
+    price_feed.status = PriceFeedStatus::PythWorking;
+    price_feed.last_good_price = market_price;
+    price_feed.last_good_price_slot = ctx.accounts.clock.slot;
+
+    price_feed.fallback_authority = ctx.accounts.oracle_admin.key();
+    price_feed.max_price_deviation = DEFAULT_MAX_PRICE_DEVIATION;
+    price_feed.max_price_difference_between_sources = DEFAULT_MAX_PRICE_DIFFERENCE_BETWEEN_SOURCES;
+    price_feed.max_fallback_price_age_slots = DEFAULT_MAX_FALLBACK_PRICE_AGE_SLOTS;
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::*
+};
+
+pub fn process_create_sp_user_info(ctx: Context<CreateSPUserInfo>, _sp_user_info_nonce:u8) -> ProgramResult {
+    ctx.accounts.sp_user_info.owner = ctx.accounts.owner.key();
+    ctx.accounts.sp_user_info.token_vault = ctx.accounts.token_vault.key();
+    ctx.accounts.sp_user_info.deposit_balance = 0;
+    ctx.accounts.sp_user_info.coll_gain = 0;
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
 
 use crate::{
+    constant::*,
+    events::*,
     instructions::*
 };
 
@@ -13,5 +15,25 @@ pub fn process_create_token_vault(ctx: Context<CreateTokenVault>, _token_vault_n
     ctx.accounts.token_vault.oracle_program = *ctx.accounts.oracle_program.key;
     ctx.accounts.token_vault.pyth_product = *ctx.accounts.pyth_product.key;
     ctx.accounts.token_vault.pyth_price = *ctx.accounts.pyth_price.key;
+
+    ctx.accounts.token_vault.mcr = DEFAULT_MCR;
+    ctx.accounts.token_vault.ccr = DEFAULT_CCR;
+    ctx.accounts.token_vault.min_net_debt = DEFAULT_MIN_NET_DEBT;
+    ctx.accounts.token_vault.solusd_gas_compensation = DEFAULT_SOLUSD_GAS_COMPENSATION;
+    ctx.accounts.token_vault.debt_ceiling = DEFAULT_DEBT_CEILING;
+    ctx.accounts.token_vault.borrowing_fee_floor = DEFAULT_BORROWING_FEE_FLOOR;
+    ctx.accounts.token_vault.redemption_fee_floor = DEFAULT_REDEMPTION_FEE_FLOOR;
+
+    ctx.accounts.token_vault.quote_currency = USD_QUOTE_CURRENCY;
+    ctx.accounts.token_vault.max_price_age_slots = DEFAULT_MAX_PRICE_AGE_SLOTS;
+    ctx.accounts.token_vault.max_price_conf_ratio = DEFAULT_MAX_PRICE_CONF_RATIO;
+    ctx.accounts.token_vault.require_trading_status = true;
+
+    ctx.accounts.token_vault.sp_p = SP_PRECISION;
+
+    emit!(TokenVaultCreated {
+        token_vault: ctx.accounts.token_vault.key(),
+        mint_coll: ctx.accounts.mint_coll.key(),
+    });
     Ok(())
 }
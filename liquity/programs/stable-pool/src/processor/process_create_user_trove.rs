@@ -1,12 +1,16 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    instructions::*
+    instructions::*,
+    states::*,
 };
 
 pub fn process_create_user_trove(ctx: Context<CreateUserTrove>, _user_trove_nonce:u8, _token_vault_nonce:u8) -> ProgramResult {
     ctx.accounts.user_trove.owner = ctx.accounts.trove_owner.key();
+    ctx.accounts.user_trove.token_vault = ctx.accounts.token_vault.key();
     ctx.accounts.user_trove.coll = 0;
     ctx.accounts.user_trove.debt = 0;
+    // an empty trove, filled by deposit_collateral and borrow_usd
+    ctx.accounts.user_trove.status = TroveStatus::Active;
     Ok(())
 }
@@ -2,11 +2,20 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{self,  Transfer};
 
 use crate::{
-    instructions::*
+    error::*,
+    events::*,
+    instructions::*,
+    math::*,
+    sorted_troves::*,
 };
 
-pub fn process_deposit_collateral(ctx: Context<DepositCollateral>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
-    
+pub fn process_deposit_collateral(ctx: Context<DepositCollateral>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey) -> ProgramResult {
+    if !ctx.accounts.user_trove.is_active() {
+        return Err(StablePoolError::TroveNotActive.into());
+    }
+
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
+
     // transfer from user to pool
     let cpi_accounts = Transfer {
         from: ctx.accounts.user_token_coll.to_account_info().clone(),
@@ -20,8 +29,24 @@ pub fn process_deposit_collateral(ctx: Context<DepositCollateral>, amount: u64,
 
     token::transfer(cpi_ctx, amount)?;
 
-    ctx.accounts.token_vault.total_coll += amount;
-    ctx.accounts.user_trove.coll += amount;
+    ctx.accounts.token_vault.total_coll = ctx.accounts.token_vault.total_coll.try_add(amount)?;
+    ctx.accounts.user_trove.coll = ctx.accounts.user_trove.coll.try_add(amount)?;
+
+    ctx.accounts.token_vault.update_stake_and_total_stakes(&mut ctx.accounts.user_trove)?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &upper_hint,
+        &lower_hint,
+    )?;
 
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::DepositCollateral);
     Ok(())
 }
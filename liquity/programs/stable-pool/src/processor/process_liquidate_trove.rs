@@ -1,105 +1,289 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount, Transfer};
 
 use crate::{
     constant::*,
+    error::*,
+    events::*,
     instructions::*,
+    math::*,
     utils::*,
-    states::*
+    states::*,
+    sorted_troves::*,
 };
 
-pub fn process_liquidate_trove(ctx: Context<LiquidateTrove>, _global_state_nonce: u8, _token_vault_nonce: u8, _user_trove_nonce: u8) -> ProgramResult {
+pub fn process_liquidate_trove(ctx: Context<LiquidateTrove>, global_state_nonce: u8, token_vault_nonce: u8, _user_trove_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
+    ctx.accounts.global_state.require_not_paused(PAUSE_LIQUIDATE)?;
 
-    let market_price = get_market_price(
-        *ctx.accounts.oracle_program.key,
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
         &ctx.accounts.pyth_product,
         &ctx.accounts.pyth_price,
         &ctx.accounts.clock
     )?;
-    let recovery_mode = ctx.accounts.token_vault.check_recovery_mode(market_price);
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
+    let recovery_mode = ctx.accounts.token_vault.is_recovery_mode(market_price, coll_decimals, usd_decimals)?;
     let mut totals = LiquidationTotals::new();
-    
+
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
+    let (coll, debt) = (ctx.accounts.user_trove.coll, ctx.accounts.user_trove.debt);
     if recovery_mode {
-        get_total_from_batch_liquidate_recovery_mode(&mut ctx.accounts.global_state, &mut ctx.accounts.token_vault, &mut ctx.accounts.user_trove, &mut totals, market_price)?;
+        get_total_from_batch_liquidate_recovery_mode(&ctx.accounts.token_vault, &mut ctx.accounts.user_trove, &mut totals, market_price, coll_decimals, usd_decimals)?;
     }
     else {
-        get_total_from_batch_liquidate_normal_mode(&mut ctx.accounts.global_state, &mut ctx.accounts.token_vault, &mut ctx.accounts.user_trove, &mut totals, market_price)?;
+        get_total_from_batch_liquidate_normal_mode(&ctx.accounts.token_vault, &mut ctx.accounts.user_trove, &mut totals, market_price, coll_decimals, usd_decimals)?;
+    }
+    if totals.total_debt_in_sequence == 0 {
+        return Err(StablePoolError::NothingToLiquidate.into());
+    }
+    ctx.accounts.token_vault.remove_stake(&mut ctx.accounts.user_trove)?;
+    emit_trove_liquidated(&ctx.accounts.user_trove, coll, debt, recovery_mode);
+
+    // a liquidated trove leaves the sorted list, its neighbours are passed as remaining accounts
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &Pubkey::default(),
+        &Pubkey::default(),
+    )?;
+
+    apply_liquidation_totals(
+        &ctx.accounts.global_state,
+        &mut ctx.accounts.token_vault,
+        &mut ctx.accounts.epoch_to_scale,
+        &ctx.accounts.mint_usd,
+        &ctx.accounts.stability_solusd_pool,
+        &ctx.accounts.gas_pool,
+        &ctx.accounts.liquidator_token_usd,
+        &ctx.accounts.pool_token_coll,
+        &ctx.accounts.liquidator_token_coll,
+        &ctx.accounts.token_program,
+        global_state_nonce,
+        token_vault_nonce,
+        &totals,
+    )
+}
+
+/*
+* Offset the liquidated debt and collateral with the stability pool, redistribute the rest to the remaining troves and
+* pay the collateral and SOLUSD gas compensations to the liquidator.
+*/
+pub fn apply_liquidation_totals<'info>(
+    global_state: &ProgramAccount<'info, GlobalState>,
+    token_vault: &mut ProgramAccount<'info, TokenVault>,
+    epoch_to_scale: &mut EpochToScale,
+    mint_usd: &Account<'info, Mint>,
+    stability_solusd_pool: &Account<'info, TokenAccount>,
+    gas_pool: &Account<'info, TokenAccount>,
+    liquidator_token_usd: &Account<'info, TokenAccount>,
+    pool_token_coll: &Account<'info, TokenAccount>,
+    liquidator_token_coll: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    global_state_nonce: u8,
+    token_vault_nonce: u8,
+    totals: &LiquidationTotals,
+) -> ProgramResult {
+    token_vault.offset(epoch_to_scale, totals.total_debt_to_offset, totals.total_coll_to_send_to_sp)?;
+
+    // burn the SOLUSD cancelled by the offset from the stability pool
+    if totals.total_debt_to_offset > 0 {
+        let cpi_accounts = Burn {
+            mint: mint_usd.to_account_info(),
+            to: stability_solusd_pool.to_account_info(),
+            authority: global_state.to_account_info(),
+        };
+        let cpi_program = token_program.to_account_info();
+        let signer_seeds = &[
+            GLOBAL_STATE_TAG,
+            &[global_state_nonce]
+        ];
+        let signer = &[&signer_seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::burn(cpi_ctx, totals.total_debt_to_offset)?;
+    }
+
+    // the offset debt is cancelled by the stability pool, and the collateral sent to it leaves the troves
+    token_vault.total_debt = token_vault.total_debt.try_sub(totals.total_debt_to_offset)?;
+    token_vault.total_coll = token_vault.total_coll.try_sub(totals.total_coll_to_send_to_sp)?;
+
+    // the debt the stability pool could not absorb is socialised over the remaining troves
+    token_vault.redistribute_debt_and_coll(totals.total_debt_to_redistribute, totals.total_coll_to_redistribute)?;
+
+    // send the collateral gas compensation to the liquidator
+    if totals.total_coll_gas_compensation > 0 {
+        let cpi_accounts = Transfer {
+            from: pool_token_coll.to_account_info(),
+            to: liquidator_token_coll.to_account_info(),
+            authority: token_vault.to_account_info(),
+        };
+        let cpi_program = token_program.to_account_info();
+        let signer_seeds = &[
+            TOKEN_VAULT_TAG,
+            token_vault.mint_coll.as_ref(),
+            &[token_vault_nonce]
+        ];
+        let signer = &[&signer_seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, totals.total_coll_gas_compensation)?;
+
+        token_vault.total_coll = token_vault.total_coll.try_sub(totals.total_coll_gas_compensation)?;
+    }
+
+    // send the SOLUSD gas compensation reserved by the liquidated troves to the liquidator
+    if totals.total_solusd_gas_compensation > 0 {
+        let cpi_accounts = Transfer {
+            from: gas_pool.to_account_info(),
+            to: liquidator_token_usd.to_account_info(),
+            authority: global_state.to_account_info(),
+        };
+        let cpi_program = token_program.to_account_info();
+        let signer_seeds = &[
+            GLOBAL_STATE_TAG,
+            &[global_state_nonce]
+        ];
+        let signer = &[&signer_seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, totals.total_solusd_gas_compensation)?;
     }
 
-    ctx.accounts.global_state.sp_solusd_amount -= totals.total_debt_to_offset;
-    ctx.accounts.token_vault.active_total_debt -= totals.total_debt_to_offset;
-    ctx.accounts.global_state.sp_sol_amount += totals.total_coll_to_send_to_sp;
+    // the collateral surplus stays in the pool token account, but no longer backs any trove
+    token_vault.total_coll = token_vault.total_coll.try_sub(totals.total_coll_surplus)?;
+    token_vault.total_coll_surplus = token_vault.total_coll_surplus.try_add(totals.total_coll_surplus)?;
+
+    token_vault.update_system_snapshots();
+
+    emit!(Liquidation {
+        token_vault: token_vault.key(),
+        liquidated_debt: totals.total_debt_in_sequence,
+        liquidated_coll: totals.total_coll_in_sequence,
+        coll_gas_compensation: totals.total_coll_gas_compensation,
+        solusd_gas_compensation: totals.total_solusd_gas_compensation,
+        coll_surplus: totals.total_coll_surplus,
+    });
+    Ok(())
+}
+
+/// emit the liquidation of a trove, with the coll and debt it had before being closed
+pub fn emit_trove_liquidated(user_trove: &UserTrove, coll: u64, debt: u64, recovery_mode: bool) {
+    let operation = if recovery_mode {TroveOperation::LiquidateInRecoveryMode} else {TroveOperation::LiquidateInNormalMode};
+    emit!(TroveLiquidated {
+        owner: user_trove.owner,
+        token_vault: user_trove.token_vault,
+        coll,
+        debt,
+        operation,
+    });
+    emit_trove_updated(user_trove, operation);
+}
 
+pub fn add_liquidation_values_to_totals(totals: &mut LiquidationTotals, single_liquidation: &LiquidationTotals) -> ProgramResult {
+    totals.total_coll_in_sequence = totals.total_coll_in_sequence.try_add(single_liquidation.total_coll_in_sequence)?;
+    totals.total_debt_in_sequence = totals.total_debt_in_sequence.try_add(single_liquidation.total_debt_in_sequence)?;
+    totals.total_coll_gas_compensation = totals.total_coll_gas_compensation.try_add(single_liquidation.total_coll_gas_compensation)?;
+    totals.total_solusd_gas_compensation = totals.total_solusd_gas_compensation.try_add(single_liquidation.total_solusd_gas_compensation)?;
+    totals.total_debt_to_offset = totals.total_debt_to_offset.try_add(single_liquidation.total_debt_to_offset)?;
+    totals.total_coll_to_send_to_sp = totals.total_coll_to_send_to_sp.try_add(single_liquidation.total_coll_to_send_to_sp)?;
+    totals.total_debt_to_redistribute = totals.total_debt_to_redistribute.try_add(single_liquidation.total_debt_to_redistribute)?;
+    totals.total_coll_to_redistribute = totals.total_coll_to_redistribute.try_add(single_liquidation.total_coll_to_redistribute)?;
+    totals.total_coll_surplus = totals.total_coll_surplus.try_add(single_liquidation.total_coll_surplus)?;
     Ok(())
 }
 
-pub fn get_total_from_batch_liquidate_recovery_mode(global_state:&mut GlobalState, token_vault:&mut TokenVault, user_trove:&mut UserTrove, totals:&mut LiquidationTotals, price: u64) -> ProgramResult {
+/// TCR of the token vault after the liquidations already tallied in `totals`
+pub fn get_tcr_after_liquidations(token_vault: &TokenVault, totals: &LiquidationTotals, price: u64, coll_decimals: u8, usd_decimals: u8) -> Result<u64> {
+    let entire_system_coll = token_vault.total_coll
+        .try_sub(totals.total_coll_to_send_to_sp)?
+        .try_sub(totals.total_coll_gas_compensation)?
+        .try_sub(totals.total_coll_surplus)?;
+    let entire_system_debt = token_vault.total_debt.try_sub(totals.total_debt_to_offset)?;
+    compute_icr(entire_system_coll, entire_system_debt, price, coll_decimals, usd_decimals)
+}
+
+pub fn get_total_from_batch_liquidate_recovery_mode(token_vault:&TokenVault, user_trove:&mut UserTrove, totals:&mut LiquidationTotals, price: u64, coll_decimals: u8, usd_decimals: u8) -> ProgramResult {
     let coll = user_trove.coll;
     let debt = user_trove.debt;
-    let solusd_in_stab_pool = global_state.sp_solusd_amount;
+    let solusd_in_stab_pool = token_vault.sp_solusd_amount.try_sub(totals.total_debt_to_offset)?;
+
+    let icr = compute_icr(coll, debt, price, coll_decimals, usd_decimals)?;
+    let tcr = get_tcr_after_liquidations(token_vault, totals, price, coll_decimals, usd_decimals)?;
 
-    let icr = compute_cr(coll, debt, price);
-    let tcr = compute_cr(token_vault.total_coll, token_vault.total_debt, price);
+    let coll_gas_compensation = get_coll_gas_compensation(coll);
+    let coll_to_liquidate = coll.try_sub(coll_gas_compensation)?;
+    let mut single_liquidation = LiquidationTotals::new();
+    single_liquidation.total_solusd_gas_compensation = user_trove.solusd_gas_compensation;
 
     if icr <= _100PCT {
-        totals.total_debt_to_offset = 0;
-        totals.total_coll_to_send_to_sp = 0;
-        totals.total_debt_to_redistribute = debt;
-        totals.total_coll_to_redistribute = coll;
+        single_liquidation.total_coll_gas_compensation = coll_gas_compensation;
+        single_liquidation.total_debt_to_offset = 0;
+        single_liquidation.total_coll_to_send_to_sp = 0;
+        single_liquidation.total_debt_to_redistribute = debt;
+        single_liquidation.total_coll_to_redistribute = coll_to_liquidate;
 
-        user_trove.close();
+        user_trove.close(TroveStatus::ClosedByLiquidation);
     }
-    else if icr > _100PCT && icr < MCR {
-        totals.total_debt_to_offset = if debt < solusd_in_stab_pool {debt} else {solusd_in_stab_pool};
-        totals.total_coll_to_send_to_sp = coll * totals.total_debt_to_offset / debt;
-        totals.total_debt_to_redistribute = debt - totals.total_debt_to_offset;
-        totals.total_coll_to_redistribute = coll - totals.total_coll_to_send_to_sp;
+    else if icr > _100PCT && icr < token_vault.mcr {
+        single_liquidation.total_coll_gas_compensation = coll_gas_compensation;
+        single_liquidation.total_debt_to_offset = if debt < solusd_in_stab_pool {debt} else {solusd_in_stab_pool};
+        single_liquidation.total_coll_to_send_to_sp = mul_div(coll_to_liquidate, single_liquidation.total_debt_to_offset, debt)?;
+        single_liquidation.total_debt_to_redistribute = debt.try_sub(single_liquidation.total_debt_to_offset)?;
+        single_liquidation.total_coll_to_redistribute = coll_to_liquidate.try_sub(single_liquidation.total_coll_to_send_to_sp)?;
 
-        user_trove.close();
+        user_trove.close(TroveStatus::ClosedByLiquidation);
     }
-    else if icr >= MCR && icr < tcr && solusd_in_stab_pool >= debt {
-        let capped_coll_portion = debt * MCR / price;
+    else if icr >= token_vault.mcr && icr < tcr && solusd_in_stab_pool >= debt {
+        // the collateral worth debt * MCR, the trove is liquidated as if its ICR was MCR
+        let capped_coll_portion = get_coll_amount_for_usd(mul_div(debt, token_vault.mcr, DECIMAL_PRECISION)?, price, coll_decimals, usd_decimals)?;
 
-        totals.total_debt_to_offset = debt;
-        totals.total_coll_to_send_to_sp = capped_coll_portion;
-        totals.total_coll_surplus = coll - capped_coll_portion;
-        totals.total_debt_to_redistribute = 0;
-        totals.total_coll_to_redistribute = 0;
+        single_liquidation.total_coll_gas_compensation = get_coll_gas_compensation(capped_coll_portion);
+        single_liquidation.total_debt_to_offset = debt;
+        single_liquidation.total_coll_to_send_to_sp = capped_coll_portion.try_sub(single_liquidation.total_coll_gas_compensation)?;
+        single_liquidation.total_coll_surplus = coll.try_sub(capped_coll_portion)?;
+        single_liquidation.total_debt_to_redistribute = 0;
+        single_liquidation.total_coll_to_redistribute = 0;
 
-        user_trove.close();
+        // the owner keeps the collateral above the capped portion
+        user_trove.coll_surplus = user_trove.coll_surplus.try_add(single_liquidation.total_coll_surplus)?;
+        user_trove.close(TroveStatus::ClosedByLiquidation);
     }
     else {
-        totals.total_debt_to_offset = 0;
-        totals.total_coll_to_send_to_sp = 0;
-        totals.total_debt_to_redistribute = 0;
-        totals.total_coll_to_redistribute = 0;
+        return Ok(());
     }
-    Ok(())
+
+    single_liquidation.total_coll_in_sequence = coll;
+    single_liquidation.total_debt_in_sequence = debt;
+    add_liquidation_values_to_totals(totals, &single_liquidation)
 
 }
-pub fn get_total_from_batch_liquidate_normal_mode(global_state:&mut GlobalState, token_vault:&mut TokenVault, user_trove:&mut UserTrove, totals:&mut LiquidationTotals, price: u64) -> ProgramResult {
+pub fn get_total_from_batch_liquidate_normal_mode(token_vault:&TokenVault, user_trove:&mut UserTrove, totals:&mut LiquidationTotals, price: u64, coll_decimals: u8, usd_decimals: u8) -> ProgramResult {
 
     let coll = user_trove.coll;
     let debt = user_trove.debt;
-    let solusd_in_stab_pool = global_state.sp_solusd_amount;
+    let solusd_in_stab_pool = token_vault.sp_solusd_amount.try_sub(totals.total_debt_to_offset)?;
 
-    let icr = compute_cr(coll, debt, price);
-    let _tcr = compute_cr(token_vault.total_coll, token_vault.total_debt, price);
+    let icr = compute_icr(coll, debt, price, coll_decimals, usd_decimals)?;
 
-    if icr < MCR {
-        let coll_to_liquidate = coll - totals.total_coll_gas_compensation;
-        
-        totals.total_debt_to_offset = if debt < solusd_in_stab_pool {debt} else {solusd_in_stab_pool};
-        totals.total_coll_to_send_to_sp = coll_to_liquidate * totals.total_debt_to_offset / debt;
-        totals.total_debt_to_redistribute = debt - totals.total_debt_to_offset;
-        totals.total_coll_to_redistribute = coll_to_liquidate - totals.total_coll_to_send_to_sp;
+    if icr < token_vault.mcr {
+        let mut single_liquidation = LiquidationTotals::new();
+        single_liquidation.total_solusd_gas_compensation = user_trove.solusd_gas_compensation;
+        single_liquidation.total_coll_gas_compensation = get_coll_gas_compensation(coll);
+        let coll_to_liquidate = coll.try_sub(single_liquidation.total_coll_gas_compensation)?;
 
-        user_trove.close();
-    }
-    else {
-        totals.total_debt_to_offset = 0;
-        totals.total_coll_to_send_to_sp = 0;
-        totals.total_debt_to_redistribute = 0;
-        totals.total_coll_to_redistribute = 0;
+        single_liquidation.total_debt_to_offset = if debt < solusd_in_stab_pool {debt} else {solusd_in_stab_pool};
+        single_liquidation.total_coll_to_send_to_sp = mul_div(coll_to_liquidate, single_liquidation.total_debt_to_offset, debt)?;
+        single_liquidation.total_debt_to_redistribute = debt.try_sub(single_liquidation.total_debt_to_offset)?;
+        single_liquidation.total_coll_to_redistribute = coll_to_liquidate.try_sub(single_liquidation.total_coll_to_send_to_sp)?;
+        single_liquidation.total_coll_in_sequence = coll;
+        single_liquidation.total_debt_in_sequence = debt;
+
+        user_trove.close(TroveStatus::ClosedByLiquidation);
+        add_liquidation_values_to_totals(totals, &single_liquidation)?;
     }
     Ok(())
 }
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Transfer};
+
+use crate::{
+    constant::*,
+    events::*,
+    instructions::*,
+    math::*,
+    utils::*,
+    states::*,
+    sorted_troves::*,
+    processor::*,
+};
+
+/*
+* Open a trove in one step: create the trove account, deposit `coll_amount` collateral and borrow `debt_amount` SOLUSD.
+* The borrowing fee and the SOLUSD gas compensation are added to the trove debt, and the trove is inserted in the sorted
+* list between the hints.
+*/
+pub fn process_open_trove(ctx: Context<OpenTrove>, coll_amount: u64, debt_amount: u64, _user_trove_nonce: u8, _token_vault_nonce: u8, global_state_nonce: u8, _mint_usd_nonce: u8, _token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey, max_fee_percentage: u64) -> ProgramResult {
+    if debt_amount > 0 {
+        ctx.accounts.global_state.require_not_paused(PAUSE_BORROW)?;
+    }
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
+        &ctx.accounts.pyth_product,
+        &ctx.accounts.pyth_price,
+        &ctx.accounts.clock
+    )?;
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
+
+    let recovery_mode = ctx.accounts.token_vault.is_recovery_mode(market_price, coll_decimals, usd_decimals)?;
+
+    let now = ctx.accounts.clock.unix_timestamp;
+    let borrowing_fee = if debt_amount > 0 {
+        trigger_borrowing_fee(&mut ctx.accounts.global_state, ctx.accounts.token_vault.borrowing_fee_floor, recovery_mode, debt_amount, max_fee_percentage, now)?
+    } else {0};
+    let gas_compensation = if debt_amount > 0 {ctx.accounts.token_vault.solusd_gas_compensation} else {0};
+    let net_debt = debt_amount.try_add(borrowing_fee)?;
+    let composite_debt = net_debt.try_add(gas_compensation)?;
+
+    // the new trove and the system must be healthy
+    let token_vault = &ctx.accounts.token_vault;
+    let new_icr = compute_icr(coll_amount, composite_debt, market_price, coll_decimals, usd_decimals)?;
+    let new_tcr = compute_icr(token_vault.total_coll.try_add(coll_amount)?, token_vault.total_debt.try_add(composite_debt)?, market_price, coll_decimals, usd_decimals)?;
+    require_valid_adjustment(token_vault, recovery_mode, 0, composite_debt > 0, 0, new_icr, new_tcr)?;
+    token_vault.require_at_least_min_net_debt(net_debt)?;
+    token_vault.require_within_debt_ceiling(composite_debt)?;
+
+    ctx.accounts.user_trove.owner = ctx.accounts.owner.key();
+    ctx.accounts.user_trove.token_vault = ctx.accounts.token_vault.key();
+    ctx.accounts.user_trove.status = TroveStatus::Active;
+    // take the reward snapshots, a new trove has no pending rewards
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
+
+    // transfer from user to pool
+    if coll_amount > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_coll.to_account_info(),
+            to: ctx.accounts.pool_token_coll.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, coll_amount)?;
+    }
+
+    if debt_amount > 0 {
+        mint_solusd_and_fee(
+            &mut ctx.accounts.global_state,
+            &ctx.accounts.mint_usd,
+            &ctx.accounts.user_token_usd,
+            &ctx.accounts.gas_pool,
+            &ctx.accounts.staking_program,
+            &ctx.accounts.staking_state,
+            &ctx.accounts.staking_solusd_fee_pool,
+            &ctx.accounts.token_program,
+            global_state_nonce,
+            debt_amount,
+            borrowing_fee,
+            gas_compensation,
+        )?;
+    }
+
+    ctx.accounts.token_vault.total_coll = ctx.accounts.token_vault.total_coll.try_add(coll_amount)?;
+    ctx.accounts.token_vault.total_debt = ctx.accounts.token_vault.total_debt.try_add(composite_debt)?;
+    ctx.accounts.user_trove.coll = coll_amount;
+    ctx.accounts.user_trove.debt = composite_debt;
+    ctx.accounts.user_trove.solusd_gas_compensation = gas_compensation;
+
+    ctx.accounts.token_vault.update_stake_and_total_stakes(&mut ctx.accounts.user_trove)?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &upper_hint,
+        &lower_hint,
+    )?;
+
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::OpenTrove);
+    Ok(())
+}
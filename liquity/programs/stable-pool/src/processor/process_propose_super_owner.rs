@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::*,
+    instructions::*
+};
+
+/*
+* First step of the super owner transfer. The new super owner takes over once it accepts, until then the current
+* super owner keeps all its rights. Proposing the default pubkey cancels a pending transfer.
+*/
+pub fn process_propose_super_owner(ctx: Context<ProposeSuperOwner>, _global_state_nonce: u8, new_super_owner: Pubkey) -> ProgramResult {
+    ctx.accounts.global_state.pending_super_owner = new_super_owner;
+
+    emit!(SuperOwnerProposed {
+        super_owner: ctx.accounts.super_owner.key(),
+        pending_super_owner: new_super_owner,
+    });
+    Ok(())
+}
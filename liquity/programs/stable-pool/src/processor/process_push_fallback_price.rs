@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::*,
+    events::*,
+    instructions::*
+};
+
+/// push the fallback price of a price feed, in the same unit as the market price of the token vault
+pub fn process_push_fallback_price(ctx: Context<PushFallbackPrice>, price: u64) -> ProgramResult {
+    if price == 0 {
+        return Err(StablePoolError::InvalidFallbackPrice.into());
+    }
+
+    ctx.accounts.price_feed.fallback_price = price;
+    ctx.accounts.price_feed.fallback_price_slot = ctx.accounts.clock.slot;
+
+    emit!(FallbackPriceUpdated {
+        price_feed: ctx.accounts.price_feed.key(),
+        price,
+        slot: ctx.accounts.clock.slot,
+    });
+    Ok(())
+}
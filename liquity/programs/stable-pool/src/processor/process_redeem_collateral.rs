@@ -0,0 +1,233 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Burn, Transfer};
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*,
+    math::*,
+    utils::*,
+    states::*,
+    sorted_troves::*,
+    processor::*,
+};
+
+/*
+* Send `amount` SOLUSD to the system and redeem the corresponding amount of collateral from as many troves as are needed to fill the redemption
+* request.
+*
+* A trove is redeemed up to its net debt. A fully redeemed trove is closed and its SOLUSD gas compensation is burnt from
+* the gas pool.
+*
+* Troves are redeemed from the tail of the sorted list (lowest ICR) upwards, skipping troves with ICR < MCR, which should be liquidated instead.
+* The walked troves and the partial redemption hints are passed as remaining accounts, the walk itself follows the on-chain list links.
+*
+* If a trove is not fully redeemed, it is re-inserted with `partial_redemption_hint_nicr` between `upper_partial_redemption_hint` and
+* `lower_partial_redemption_hint`. If its new NICR differs from the hint, the partial redemption is cancelled and the redemption stops there.
+*
+* A `max_iterations` of 0 means no limit.
+*/
+pub fn process_redeem_collateral(
+    ctx: Context<RedeemCollateral>,
+    amount: u64,
+    token_vault_nonce: u8,
+    global_state_nonce: u8,
+    _mint_usd_nonce: u8,
+    _token_coll_nonce: u8,
+    partial_redemption_hint_nicr: u64,
+    upper_partial_redemption_hint: Pubkey,
+    lower_partial_redemption_hint: Pubkey,
+    max_iterations: u64,
+    max_fee_percentage: u64,
+) -> ProgramResult {
+    ctx.accounts.global_state.require_not_paused(PAUSE_REDEEM)?;
+    if max_fee_percentage < ctx.accounts.token_vault.redemption_fee_floor || max_fee_percentage > DECIMAL_PRECISION {
+        return Err(StablePoolError::InvalidMaxFeePercentage.into());
+    }
+    if amount == 0 {
+        return Err(StablePoolError::NotAllowed.into());
+    }
+    if ctx.accounts.user_token_usd.amount < amount {
+        return Err(StablePoolError::InsufficientSolusdBalance.into());
+    }
+
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
+        &ctx.accounts.pyth_product,
+        &ctx.accounts.pyth_price,
+        &ctx.accounts.clock
+    )?;
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
+
+    let tcr = compute_icr(ctx.accounts.token_vault.total_coll, ctx.accounts.token_vault.total_debt, market_price, coll_decimals, usd_decimals)?;
+    if tcr < ctx.accounts.token_vault.mcr {
+        return Err(StablePoolError::RedemptionBelowMCR.into());
+    }
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let mut nodes = load_trove_nodes(ctx.remaining_accounts, &token_vault_key, &Pubkey::default())?;
+    let token_vault = &mut ctx.accounts.token_vault;
+
+    // find the first trove with ICR >= MCR, starting from the tail
+    let mut current_trove = token_vault.sorted_troves_tail;
+    while current_trove != Pubkey::default() {
+        let node = get_node(&nodes, &current_trove)?;
+        let (coll, debt) = token_vault.get_entire_coll_and_debt(node)?;
+        if compute_icr(coll, debt, market_price, coll_decimals, usd_decimals)? >= token_vault.mcr {
+            break;
+        }
+        current_trove = node.prev_trove;
+    }
+
+    let mut remaining_solusd = amount;
+    let mut total_solusd_to_redeem: u64 = 0;
+    let mut total_coll_drawn: u64 = 0;
+    let mut total_gas_compensation: u64 = 0;
+    let mut iterations: u64 = 0;
+
+    // loop through the troves starting from the one with lowest collateral ratio until `amount` of SOLUSD is exchanged for collateral
+    while current_trove != Pubkey::default() && remaining_solusd > 0 && (max_iterations == 0 || iterations < max_iterations) {
+        let index = nodes.iter().position(|node| node.key() == current_trove).ok_or(StablePoolError::InvalidAccountInput)?;
+        let mut trove = nodes.remove(index);
+        // save the address of the trove preceding the current one, before potentially modifying the list
+        let next_trove_to_check = trove.prev_trove;
+
+        token_vault.apply_pending_rewards(&mut trove)?;
+
+        // determine the remaining amount (lot) to be redeemed, capped by the debt of the trove minus the gas compensation
+        let solusd_lot = min(remaining_solusd, trove.net_debt()?);
+        // get the collateral lot of equivalent value in USD
+        let coll_lot = min(get_coll_amount_for_usd(solusd_lot, market_price, coll_decimals, usd_decimals)?, trove.coll);
+
+        let new_debt = trove.debt.try_sub(solusd_lot)?;
+        let new_coll = trove.coll.try_sub(coll_lot)?;
+
+        if new_debt == trove.solusd_gas_compensation {
+            // the trove leaves the list, its remaining collateral becomes a surplus the owner can claim
+            trove.coll_surplus = trove.coll_surplus.try_add(new_coll)?;
+            token_vault.total_coll = token_vault.total_coll.try_sub(new_coll)?;
+            token_vault.total_coll_surplus = token_vault.total_coll_surplus.try_add(new_coll)?;
+            total_gas_compensation = total_gas_compensation.try_add(trove.solusd_gas_compensation)?;
+            token_vault.remove_stake(&mut trove)?;
+            trove.close(TroveStatus::ClosedByRedemption);
+            remove(token_vault, &current_trove, &mut trove, &mut nodes)?;
+        }
+        else {
+            let new_nicr = compute_nominal_cr(new_coll, new_debt);
+            /*
+            * If the provided hint is out of date, we bail since trying to reinsert without a good hint will almost
+            * certainly result in running out of gas. A partial redemption must also leave the trove with at least the
+            * minimum net debt.
+            */
+            if new_nicr != partial_redemption_hint_nicr || new_debt.try_sub(trove.solusd_gas_compensation)? < token_vault.min_net_debt {
+                nodes.push(trove);
+                break;
+            }
+            trove.debt = new_debt;
+            trove.coll = new_coll;
+            token_vault.update_stake_and_total_stakes(&mut trove)?;
+            re_insert(token_vault, &current_trove, &mut trove, &mut nodes, new_nicr, &upper_partial_redemption_hint, &lower_partial_redemption_hint)?;
+        }
+        emit_trove_updated(&trove, TroveOperation::RedeemCollateral);
+        nodes.push(trove);
+
+        total_solusd_to_redeem = total_solusd_to_redeem.try_add(solusd_lot)?;
+        total_coll_drawn = total_coll_drawn.try_add(coll_lot)?;
+
+        remaining_solusd = remaining_solusd.try_sub(solusd_lot)?;
+        current_trove = next_trove_to_check;
+        iterations = iterations.try_add(1)?;
+    }
+    if total_coll_drawn == 0 {
+        return Err(StablePoolError::UnableToRedeemAnyAmount.into());
+    }
+
+    // decay the base rate due to time passed, and then increase it according to the size of this redemption.
+    let now = ctx.accounts.clock.unix_timestamp;
+    ctx.accounts.global_state.update_base_rate_from_redemption(total_solusd_to_redeem, ctx.accounts.mint_usd.supply, now)?;
+
+    // calculate the collateral fee
+    let coll_fee = get_redemption_fee(ctx.accounts.token_vault.redemption_fee_floor, ctx.accounts.global_state.base_rate, total_coll_drawn)?;
+    require_user_accepts_fee(coll_fee, total_coll_drawn, max_fee_percentage)?;
+
+    let coll_to_send_to_redeemer = total_coll_drawn.try_sub(coll_fee)?;
+
+    // burn the total SOLUSD that is cancelled with debt
+    let cpi_accounts = Burn {
+        mint: ctx.accounts.mint_usd.to_account_info().clone(),
+        to: ctx.accounts.user_token_usd.to_account_info().clone(),
+        authority: ctx.accounts.redeemer.to_account_info().clone(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info().clone();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::burn(cpi_ctx, total_solusd_to_redeem)?;
+
+    // burn the gas compensation of the closed troves
+    burn_gas_compensation(
+        &ctx.accounts.global_state,
+        &ctx.accounts.mint_usd,
+        &ctx.accounts.gas_pool,
+        &ctx.accounts.token_program,
+        global_state_nonce,
+        total_gas_compensation,
+    )?;
+
+    // send collateral to the redeemer
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.pool_token_coll.to_account_info(),
+        to: ctx.accounts.user_token_coll.to_account_info(),
+        authority: ctx.accounts.token_vault.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let signer_seeds = &[
+        TOKEN_VAULT_TAG,
+        ctx.accounts.token_vault.mint_coll.as_ref(),
+        &[token_vault_nonce]
+    ];
+    let signer = &[&signer_seeds[..]];
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, coll_to_send_to_redeemer)?;
+
+    // send the collateral fee to the SOLID staking fee vault
+    if coll_fee > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.pool_token_coll.to_account_info(),
+            to: ctx.accounts.staking_fee_vault_pool.to_account_info(),
+            authority: ctx.accounts.token_vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, coll_fee)?;
+
+        let cpi_accounts = solid_staking_v1::cpi::accounts::IncreaseFColl {
+            fee_authority: ctx.accounts.global_state.to_account_info(),
+            staking_state: ctx.accounts.staking_state.to_account_info(),
+            fee_vault: ctx.accounts.staking_fee_vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.staking_program.to_account_info();
+        let global_state_signer_seeds = &[
+            GLOBAL_STATE_TAG,
+            &[global_state_nonce]
+        ];
+        let global_state_signer = &[&global_state_signer_seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, global_state_signer);
+        solid_staking_v1::cpi::increase_f_coll(cpi_ctx, coll_fee)?;
+    }
+
+    ctx.accounts.token_vault.total_debt = ctx.accounts.token_vault.total_debt.try_sub(total_solusd_to_redeem)?.try_sub(total_gas_compensation)?;
+    ctx.accounts.token_vault.total_coll = ctx.accounts.token_vault.total_coll.try_sub(total_coll_drawn)?;
+    ctx.accounts.token_vault.total_redemption_fee = ctx.accounts.token_vault.total_redemption_fee.try_add(coll_fee)?;
+
+    emit!(Redemption {
+        token_vault: ctx.accounts.token_vault.key(),
+        redeemer: ctx.accounts.redeemer.key(),
+        attempted_solusd_amount: amount,
+        actual_solusd_amount: total_solusd_to_redeem,
+        coll_sent: coll_to_send_to_redeemer,
+        coll_fee,
+    });
+
+    save_trove_nodes(ctx.program_id, &nodes)
+}
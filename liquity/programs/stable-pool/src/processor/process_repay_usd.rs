@@ -3,15 +3,32 @@ use anchor_spl::token::{self,  Burn};
 
 use crate::{
     constant::*,
+    error::*,
+    events::*,
     instructions::*,
+    math::*,
+    sorted_troves::*,
+    processor::*,
 };
 
-pub fn process_repay_usd(ctx: Context<RepayUsd>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _global_state_nonce: u8, _mint_usd_nonce: u8) -> ProgramResult {
+/*
+* Repay up to `amount` SOLUSD of the trove debt. Repaying the whole net debt releases the SOLUSD gas compensation
+* reserved in the gas pool.
+*/
+pub fn process_repay_usd(ctx: Context<RepayUsd>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _global_state_nonce: u8, _mint_usd_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey) -> ProgramResult {
+    if !ctx.accounts.user_trove.is_active() {
+        return Err(StablePoolError::TroveNotActive.into());
+    }
+
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
 
+    let net_debt = ctx.accounts.user_trove.net_debt()?;
     let mut _amount = amount;
-    if ctx.accounts.user_trove.debt < amount {
-        _amount = ctx.accounts.user_trove.debt;
+    if net_debt < amount {
+        _amount = net_debt;
     }
+    let released_gas_compensation = if _amount == net_debt {ctx.accounts.user_trove.solusd_gas_compensation} else {0};
+    ctx.accounts.token_vault.require_at_least_min_net_debt(net_debt.try_sub(_amount)?)?;
     // burn
     let cpi_accounts = Burn {
         mint: ctx.accounts.mint_usd.to_account_info().clone(),
@@ -31,8 +48,35 @@ pub fn process_repay_usd(ctx: Context<RepayUsd>, amount: u64, _token_vault_nonce
     
     token::burn(cpi_ctx, _amount)?;
 
-    ctx.accounts.token_vault.total_debt -= _amount;
-    ctx.accounts.user_trove.debt -= _amount;
+    burn_gas_compensation(
+        &ctx.accounts.global_state,
+        &ctx.accounts.mint_usd,
+        &ctx.accounts.gas_pool,
+        &ctx.accounts.token_program,
+        _global_state_nonce,
+        released_gas_compensation,
+    )?;
+
+    let repaid_debt = _amount.try_add(released_gas_compensation)?;
+    ctx.accounts.token_vault.total_debt = ctx.accounts.token_vault.total_debt.try_sub(repaid_debt)?;
+    ctx.accounts.user_trove.debt = ctx.accounts.user_trove.debt.try_sub(repaid_debt)?;
+    ctx.accounts.user_trove.solusd_gas_compensation = ctx.accounts.user_trove.solusd_gas_compensation.try_sub(released_gas_compensation)?;
+
+    ctx.accounts.token_vault.update_stake_and_total_stakes(&mut ctx.accounts.user_trove)?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &upper_hint,
+        &lower_hint,
+    )?;
 
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::RepayUsd);
     Ok(())
 }
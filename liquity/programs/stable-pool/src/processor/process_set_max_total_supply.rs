@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::*,
+    instructions::*
+};
+
+/// set the max SOLUSD supply, it only limits new mints and can be set below the current supply to stop borrowing
+pub fn process_set_max_total_supply(ctx: Context<SetMaxTotalSupply>, _global_state_nonce: u8, max_total_supply: u64) -> ProgramResult {
+    ctx.accounts.global_state.max_total_supply = max_total_supply;
+
+    emit!(MaxTotalSupplyUpdated {
+        max_total_supply,
+    });
+    Ok(())
+}
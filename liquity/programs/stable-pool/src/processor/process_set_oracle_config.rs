@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*
+};
+
+/// set the oracle guards of a token vault, the prices read by every instruction of the vault are checked against them
+pub fn process_set_oracle_config(ctx: Context<SetOracleConfig>, _global_state_nonce: u8, _token_vault_nonce: u8, quote_currency: [u8; 32], max_price_age_slots: u64, max_price_conf_ratio: u64, require_trading_status: bool) -> ProgramResult {
+    if max_price_age_slots == 0 || max_price_conf_ratio == 0 || max_price_conf_ratio > DECIMAL_PRECISION {
+        return Err(StablePoolError::InvalidOracleGuards.into());
+    }
+
+    ctx.accounts.token_vault.quote_currency = quote_currency;
+    ctx.accounts.token_vault.max_price_age_slots = max_price_age_slots;
+    ctx.accounts.token_vault.max_price_conf_ratio = max_price_conf_ratio;
+    ctx.accounts.token_vault.require_trading_status = require_trading_status;
+
+    emit!(OracleConfigUpdated {
+        token_vault: ctx.accounts.token_vault.key(),
+        quote_currency,
+        max_price_age_slots,
+        max_price_conf_ratio,
+        require_trading_status,
+    });
+    Ok(())
+}
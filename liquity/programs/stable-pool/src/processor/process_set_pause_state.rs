@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*
+};
+
+/*
+* Set the bitmap of the paused operation classes. Only callable by the guardian.
+* Repaying and closing troves and withdrawing from the stability pool are never paused, so users can always de-risk.
+*/
+pub fn process_set_pause_state(ctx: Context<SetPauseState>, _global_state_nonce: u8, paused_operations: u8) -> ProgramResult {
+    if paused_operations & !PAUSE_ALL != 0 {
+        return Err(StablePoolError::InvalidPauseState.into());
+    }
+    ctx.accounts.global_state.paused_operations = paused_operations;
+
+    emit!(PauseStateChanged {
+        paused_operations,
+        guardian: ctx.accounts.guardian.key(),
+    });
+    Ok(())
+}
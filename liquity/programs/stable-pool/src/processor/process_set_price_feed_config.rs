@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*
+};
+
+/// set the fallback authority and the limits of a price feed
+pub fn process_set_price_feed_config(ctx: Context<SetPriceFeedConfig>, _global_state_nonce: u8, fallback_authority: Pubkey, max_price_deviation: u64, max_price_difference_between_sources: u64, max_fallback_price_age_slots: u64) -> ProgramResult {
+    if max_price_deviation == 0 || max_price_deviation > DECIMAL_PRECISION
+        || max_price_difference_between_sources == 0 || max_price_difference_between_sources > DECIMAL_PRECISION
+        || max_fallback_price_age_slots == 0 {
+        return Err(StablePoolError::InvalidPriceFeedConfig.into());
+    }
+
+    let price_feed = &mut ctx.accounts.price_feed;
+    price_feed.fallback_authority = fallback_authority;
+    price_feed.max_price_deviation = max_price_deviation;
+    price_feed.max_price_difference_between_sources = max_price_difference_between_sources;
+    price_feed.max_fallback_price_age_slots = max_fallback_price_age_slots;
+
+    emit!(PriceFeedConfigUpdated {
+        price_feed: price_feed.key(),
+        fallback_authority,
+        max_price_deviation,
+        max_price_difference_between_sources,
+        max_fallback_price_age_slots,
+    });
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::*,
+    instructions::*
+};
+
+/*
+* Delegate the admin roles: creating token vaults, updating the vault risk parameters, configuring the oracles and
+* price feeds, and pausing operations. Only the super owner can change the roles.
+*/
+pub fn process_set_roles(ctx: Context<SetRoles>, _global_state_nonce: u8, vault_creator: Pubkey, params_admin: Pubkey, oracle_admin: Pubkey, guardian: Pubkey) -> ProgramResult {
+    let global_state = &mut ctx.accounts.global_state;
+    global_state.vault_creator = vault_creator;
+    global_state.params_admin = params_admin;
+    global_state.oracle_admin = oracle_admin;
+    global_state.guardian = guardian;
+
+    emit!(RolesUpdated {
+        vault_creator,
+        params_admin,
+        oracle_admin,
+        guardian,
+    });
+    Ok(())
+}
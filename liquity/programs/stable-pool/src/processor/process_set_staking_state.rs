@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::*,
+    instructions::*
+};
+
+/// set the SOLID staking state receiving the protocol fees, its fee authority must be the global state
+pub fn process_set_staking_state(ctx: Context<SetStakingState>, _global_state_nonce:u8) -> ProgramResult {
+    ctx.accounts.global_state.staking_state = ctx.accounts.staking_state.key();
+
+    emit!(StakingStateUpdated {
+        staking_state: ctx.accounts.staking_state.key(),
+    });
+    Ok(())
+}
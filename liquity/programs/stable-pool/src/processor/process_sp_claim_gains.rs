@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self,  Transfer};
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*,
+    math::*
+};
+
+pub fn process_sp_claim_gains(ctx: Context<SPClaimGains>, _global_state_nonce: u8, _sp_user_info_nonce: u8, token_vault_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
+    ctx.accounts.token_vault.update_depositor(
+        &mut ctx.accounts.sp_user_info,
+        &ctx.accounts.epoch_to_scale_snapshot,
+        &ctx.accounts.epoch_to_scale_next,
+        &ctx.accounts.epoch_to_scale_current,
+    )?;
+
+    let coll_gain = ctx.accounts.sp_user_info.coll_gain;
+    if coll_gain == 0 {
+        return Err(StablePoolError::NoCollGain.into());
+    }
+
+    // transfer the collateral gain from the pool to the depositor
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.pool_token_coll.to_account_info(),
+        to: ctx.accounts.user_token_coll.to_account_info(),
+        authority: ctx.accounts.token_vault.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let signer_seeds = &[
+        TOKEN_VAULT_TAG,
+        ctx.accounts.token_vault.mint_coll.as_ref(),
+        &[token_vault_nonce]
+    ];
+    let signer = &[&signer_seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token::transfer(cpi_ctx, coll_gain)?;
+
+    ctx.accounts.sp_user_info.coll_gain = 0;
+    ctx.accounts.token_vault.sp_coll_amount = ctx.accounts.token_vault.sp_coll_amount.try_sub(coll_gain)?;
+
+    emit!(SPGainsClaimed {
+        depositor: ctx.accounts.sp_user_info.owner,
+        token_vault: ctx.accounts.token_vault.key(),
+        coll_gain,
+    });
+    Ok(())
+}
@@ -2,11 +2,23 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{self,  Transfer};
 
 use crate::{
-    instructions::*
+    constant::*,
+    events::*,
+    instructions::*,
+    math::*
 };
 
 pub fn process_sp_deposit(ctx: Context<SPDeposit>, amount: u64, _global_state_nonce: u8, _sp_user_info_nonce: u8, _stability_pool_nonce: u8) -> ProgramResult {
-    
+    ctx.accounts.global_state.require_not_paused(PAUSE_SP_DEPOSIT)?;
+
+    // take the collateral gain and the compounded deposit before the deposit changes
+    ctx.accounts.token_vault.update_depositor(
+        &mut ctx.accounts.sp_user_info,
+        &ctx.accounts.epoch_to_scale_snapshot,
+        &ctx.accounts.epoch_to_scale_next,
+        &ctx.accounts.epoch_to_scale_current,
+    )?;
+
     // transfer from user to pool
     let cpi_accounts = Transfer {
         from: ctx.accounts.user_solusd_token.to_account_info().clone(),
@@ -18,7 +30,14 @@ pub fn process_sp_deposit(ctx: Context<SPDeposit>, amount: u64, _global_state_no
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
     token::transfer(cpi_ctx, amount)?;
 
-    ctx.accounts.sp_user_info.deposit_balance += amount;
+    ctx.accounts.sp_user_info.deposit_balance = ctx.accounts.sp_user_info.deposit_balance.try_add(amount)?;
+    ctx.accounts.token_vault.sp_solusd_amount = ctx.accounts.token_vault.sp_solusd_amount.try_add(amount)?;
 
+    emit!(StabilityPoolDeposit {
+        depositor: ctx.accounts.owner.key(),
+        token_vault: ctx.accounts.token_vault.key(),
+        amount,
+        deposit_balance: ctx.accounts.sp_user_info.deposit_balance,
+    });
     Ok(())
 }
@@ -3,17 +3,48 @@ use anchor_spl::token::{self,  Transfer};
 
 use crate::{
     constant::*,
-    instructions::*
+    events::*,
+    error::*,
+    instructions::*,
+    math::*,
+    utils::*,
+    sorted_troves::*,
 };
 
 pub fn process_sp_withdraw(ctx: Context<SPWithdraw>, amount: u64, _global_state_nonce: u8, _sp_user_info_nonce: u8, _stability_pool_nonce: u8) -> ProgramResult {
-    msg!("withdrawing ...");
-    
+    // take the collateral gain and the compounded deposit, only the compounded deposit can be withdrawn
+    ctx.accounts.token_vault.update_depositor(
+        &mut ctx.accounts.sp_user_info,
+        &ctx.accounts.epoch_to_scale_snapshot,
+        &ctx.accounts.epoch_to_scale_next,
+        &ctx.accounts.epoch_to_scale_current,
+    )?;
+
     let mut _amount = amount;
     if amount > ctx.accounts.sp_user_info.deposit_balance {
         _amount = ctx.accounts.sp_user_info.deposit_balance;
     }
-    
+
+    /*
+    * Like liquity, no SOLUSD can leave the pool while a trove with ICR < MCR is waiting to be liquidated. The list is
+    * sorted by NICR, so checking the tail trove, passed as the remaining account, is enough.
+    */
+    let lowest_trove_key = ctx.accounts.token_vault.sorted_troves_tail;
+    if _amount > 0 && lowest_trove_key != Pubkey::default() {
+        let market_price = ctx.accounts.price_feed.fetch_price(
+            &ctx.accounts.token_vault,
+            &ctx.accounts.pyth_product,
+            &ctx.accounts.pyth_price,
+            &ctx.accounts.clock
+        )?;
+        let nodes = load_trove_nodes(ctx.remaining_accounts, &ctx.accounts.token_vault.key(), &Pubkey::default())?;
+        let (coll, debt) = ctx.accounts.token_vault.get_entire_coll_and_debt(get_node(&nodes, &lowest_trove_key)?)?;
+        let icr = compute_icr(coll, debt, market_price, ctx.accounts.mint_coll.decimals, ctx.accounts.mint_usd.decimals)?;
+        if icr < ctx.accounts.token_vault.mcr {
+            return Err(StablePoolError::UnderCollateralizedTroves.into());
+        }
+    }
+
     // transfer from pool to user
     let cpi_accounts = Transfer {
         from: ctx.accounts.stability_solusd_pool.to_account_info(),
@@ -30,11 +61,16 @@ pub fn process_sp_withdraw(ctx: Context<SPWithdraw>, amount: u64, _global_state_
     let signer = &[&signer_seeds[..]];
 
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-    msg!("transfering ...");
     token::transfer(cpi_ctx, _amount)?;
 
-    msg!("updating ...");
-    ctx.accounts.sp_user_info.deposit_balance -= _amount;
+    ctx.accounts.sp_user_info.deposit_balance = ctx.accounts.sp_user_info.deposit_balance.try_sub(_amount)?;
+    ctx.accounts.token_vault.sp_solusd_amount = ctx.accounts.token_vault.sp_solusd_amount.try_sub(_amount)?;
 
+    emit!(StabilityPoolWithdraw {
+        depositor: ctx.accounts.sp_user_info.owner,
+        token_vault: ctx.accounts.token_vault.key(),
+        amount: _amount,
+        deposit_balance: ctx.accounts.sp_user_info.deposit_balance,
+    });
     Ok(())
 }
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constant::*,
+    error::*,
+    events::*,
+    instructions::*
+};
+
+/*
+* Update the risk parameters of a token vault. The MCR must stay above 100% and below the CCR, the CCR is capped at
+* MAX_CCR, the SOLUSD gas compensation at MAX_SOLUSD_GAS_COMPENSATION, the min net debt can not be zero, and the fee
+* floors can not exceed the max borrowing fee and the max redemption fee floor.
+*
+* A new gas compensation only applies to the troves taking on debt afterwards, the troves with debt keep the amount they
+* reserved.
+*
+* The debt ceiling only limits new borrows, it can be set below the current total debt of the vault to stop borrowing.
+*/
+pub fn process_update_vault_params(ctx: Context<UpdateVaultParams>, _global_state_nonce: u8, _token_vault_nonce: u8, mcr: u64, ccr: u64, min_net_debt: u64, solusd_gas_compensation: u64, debt_ceiling: u64, borrowing_fee_floor: u64, redemption_fee_floor: u64) -> ProgramResult {
+    if mcr <= _100PCT || ccr <= mcr || ccr > MAX_CCR || min_net_debt == 0 || solusd_gas_compensation > MAX_SOLUSD_GAS_COMPENSATION
+        || borrowing_fee_floor > MAX_BORROWING_FEE || redemption_fee_floor > MAX_REDEMPTION_FEE_FLOOR {
+        return Err(StablePoolError::InvalidVaultParams.into());
+    }
+
+    let token_vault = &mut ctx.accounts.token_vault;
+    token_vault.mcr = mcr;
+    token_vault.ccr = ccr;
+    token_vault.min_net_debt = min_net_debt;
+    token_vault.solusd_gas_compensation = solusd_gas_compensation;
+    token_vault.debt_ceiling = debt_ceiling;
+    token_vault.borrowing_fee_floor = borrowing_fee_floor;
+    token_vault.redemption_fee_floor = redemption_fee_floor;
+
+    emit!(VaultParamsUpdated {
+        token_vault: token_vault.key(),
+        mcr,
+        ccr,
+        min_net_debt,
+        solusd_gas_compensation,
+        debt_ceiling,
+        borrowing_fee_floor,
+        redemption_fee_floor,
+    });
+    Ok(())
+}
@@ -3,16 +3,43 @@ use anchor_spl::token::{self,  Transfer};
 
 use crate::{
     constant::*,
-    instructions::*
+    error::*,
+    events::*,
+    instructions::*,
+    math::*,
+    utils::*,
+    sorted_troves::*,
 };
 
-pub fn process_withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _token_coll_nonce: u8) -> ProgramResult {
-    msg!("withdrawing ...");
+pub fn process_withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64, _token_vault_nonce: u8, _user_trove_nonce: u8, _token_coll_nonce: u8, upper_hint: Pubkey, lower_hint: Pubkey) -> ProgramResult {
+    if !ctx.accounts.user_trove.is_active() {
+        return Err(StablePoolError::TroveNotActive.into());
+    }
+    ctx.accounts.global_state.require_not_paused(PAUSE_WITHDRAW_COLL)?;
+    let market_price = ctx.accounts.price_feed.fetch_price(
+        &ctx.accounts.token_vault,
+        &ctx.accounts.pyth_product,
+        &ctx.accounts.pyth_price,
+        &ctx.accounts.clock
+    )?;
+    let coll_decimals = ctx.accounts.mint_coll.decimals;
+    let usd_decimals = ctx.accounts.mint_usd.decimals;
+
+    ctx.accounts.token_vault.apply_pending_rewards(&mut ctx.accounts.user_trove)?;
     
     let mut _amount = amount;
     if amount > ctx.accounts.user_trove.coll {
         _amount = ctx.accounts.user_trove.coll;
     }
+
+    // the trove and the system must stay healthy after the withdrawal
+    let token_vault = &ctx.accounts.token_vault;
+    let user_trove = &ctx.accounts.user_trove;
+    let old_tcr = token_vault.get_tcr(market_price, coll_decimals, usd_decimals)?;
+    let old_icr = compute_icr(user_trove.coll, user_trove.debt, market_price, coll_decimals, usd_decimals)?;
+    let new_icr = compute_icr(user_trove.coll.try_sub(_amount)?, user_trove.debt, market_price, coll_decimals, usd_decimals)?;
+    let new_tcr = compute_icr(token_vault.total_coll.try_sub(_amount)?, token_vault.total_debt, market_price, coll_decimals, usd_decimals)?;
+    require_valid_adjustment(token_vault, old_tcr < token_vault.ccr, _amount, false, old_icr, new_icr, new_tcr)?;
     
     // transfer from pool to user
     let cpi_accounts = Transfer {
@@ -31,12 +58,26 @@ pub fn process_withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64
     let signer = &[&signer_seeds[..]];
 
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-    msg!("transfering ...");
     token::transfer(cpi_ctx, _amount)?;
 
-    msg!("updating ...");
-    ctx.accounts.token_vault.total_coll -= _amount;
-    ctx.accounts.user_trove.coll -= _amount;
+    ctx.accounts.token_vault.total_coll = ctx.accounts.token_vault.total_coll.try_sub(_amount)?;
+    ctx.accounts.user_trove.coll = ctx.accounts.user_trove.coll.try_sub(_amount)?;
+
+    ctx.accounts.token_vault.update_stake_and_total_stakes(&mut ctx.accounts.user_trove)?;
+
+    let token_vault_key = ctx.accounts.token_vault.key();
+    let user_trove_key = ctx.accounts.user_trove.key();
+    update_sorted_troves(
+        ctx.program_id,
+        &token_vault_key,
+        &mut ctx.accounts.token_vault,
+        &user_trove_key,
+        &mut ctx.accounts.user_trove,
+        ctx.remaining_accounts,
+        &upper_hint,
+        &lower_hint,
+    )?;
 
+    emit_trove_updated(&ctx.accounts.user_trove, TroveOperation::WithdrawCollateral);
     Ok(())
 }
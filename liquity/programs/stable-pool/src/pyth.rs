@@ -28,7 +28,7 @@ pub enum AccountType {
     Price,
 }
 
-#[derive(Copy, Clone)]
+#[derive(PartialEq, Copy, Clone)]
 #[repr(C)]
 pub enum PriceStatus {
     Unknown,
@@ -0,0 +1,203 @@
+use anchor_lang::prelude::*;
+use crate::{
+    states::*,
+    error::*,
+    math::*,
+    utils::*,
+};
+
+/*
+* A sorted doubly linked list of the troves of a token vault, ordered by nominal ICR (descending from head to tail).
+*
+* The list head/tail/size live on the `TokenVault` and each `UserTrove` stores its own prev/next links. Troves
+* without debt are not kept in the list.
+*
+* Nodes can not be found on-chain by traversal, so the caller passes the neighbour troves an update touches
+* (the current prev/next of the trove and the upper/lower insert hints) as remaining accounts. The hints must be
+* the exact position of the trove after the update, otherwise the instruction fails with `InvalidSortedTrovesHint`.
+*/
+
+/// load the neighbour troves passed as remaining accounts, skipping the trove being moved and duplicates
+pub fn load_trove_nodes<'info>(remaining_accounts: &[AccountInfo<'info>], token_vault_key: &Pubkey, trove_key: &Pubkey) -> Result<Vec<Account<'info, UserTrove>>> {
+    let mut nodes: Vec<Account<'info, UserTrove>> = Vec::new();
+    for account_info in remaining_accounts.iter() {
+        if account_info.key == trove_key || nodes.iter().any(|node| node.key() == *account_info.key) {
+            continue;
+        }
+        let node = Account::<UserTrove>::try_from(account_info)?;
+        if node.token_vault != *token_vault_key {
+            msg!("Trove {} does not belong to this token vault", account_info.key);
+            return Err(StablePoolError::InvalidAccountInput.into());
+        }
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+/// store the modified neighbour troves again
+pub fn save_trove_nodes(program_id: &Pubkey, nodes: &[Account<UserTrove>]) -> ProgramResult {
+    for node in nodes.iter() {
+        node.exit(program_id)?;
+    }
+    Ok(())
+}
+
+pub fn get_node<'a, 'info>(nodes: &'a [Account<'info, UserTrove>], key: &Pubkey) -> Result<&'a Account<'info, UserTrove>> {
+    let node = nodes.iter().find(|node| node.key() == *key).ok_or_else(|| {
+        msg!("Trove {} is missing in the remaining accounts", key);
+        StablePoolError::InvalidAccountInput
+    })?;
+    Ok(node)
+}
+
+pub fn get_node_mut<'a, 'info>(nodes: &'a mut [Account<'info, UserTrove>], key: &Pubkey) -> Result<&'a mut Account<'info, UserTrove>> {
+    let node = nodes.iter_mut().find(|node| node.key() == *key).ok_or_else(|| {
+        msg!("Trove {} is missing in the remaining accounts", key);
+        StablePoolError::InvalidAccountInput
+    })?;
+    Ok(node)
+}
+
+/// nominal ICR of a trove including its pending redistribution rewards
+pub fn get_nominal_icr(token_vault: &TokenVault, trove: &UserTrove) -> Result<u64> {
+    let (coll, debt) = token_vault.get_entire_coll_and_debt(trove)?;
+    Ok(compute_nominal_cr(coll, debt))
+}
+
+/// check if a pair of nodes is a valid insertion point for a new node with the given NICR
+pub fn valid_insert_position(token_vault: &TokenVault, nodes: &[Account<UserTrove>], nicr: u64, prev_id: &Pubkey, next_id: &Pubkey) -> Result<bool> {
+    let none = Pubkey::default();
+    if *prev_id == none && *next_id == none {
+        // `(null, null)` is a valid insert position if the list is empty
+        return Ok(token_vault.sorted_troves_size == 0);
+    }
+    if *prev_id == none {
+        // `(null, next_id)` is a valid insert position if `next_id` is the head of the list
+        let next = get_node(nodes, next_id)?;
+        return Ok(token_vault.sorted_troves_head == *next_id && nicr >= get_nominal_icr(token_vault, next)?);
+    }
+    if *next_id == none {
+        // `(prev_id, null)` is a valid insert position if `prev_id` is the tail of the list
+        let prev = get_node(nodes, prev_id)?;
+        return Ok(token_vault.sorted_troves_tail == *prev_id && nicr <= get_nominal_icr(token_vault, prev)?);
+    }
+    // `(prev_id, next_id)` is a valid insert position if they are adjacent nodes and `nicr` falls between the two nodes' NICRs
+    let prev = get_node(nodes, prev_id)?;
+    let next = get_node(nodes, next_id)?;
+    Ok(prev.is_listed &&
+        prev.next_trove == *next_id &&
+        get_nominal_icr(token_vault, prev)? >= nicr &&
+        nicr >= get_nominal_icr(token_vault, next)?)
+}
+
+pub fn insert(token_vault: &mut TokenVault, trove_key: &Pubkey, trove: &mut UserTrove, nodes: &mut [Account<UserTrove>], nicr: u64, prev_id: &Pubkey, next_id: &Pubkey) -> ProgramResult {
+    if trove.is_listed {
+        return Err(StablePoolError::InvalidState.into());
+    }
+    if nicr == 0 {
+        return Err(StablePoolError::InvalidState.into());
+    }
+    if !valid_insert_position(token_vault, nodes, nicr, prev_id, next_id)? {
+        return Err(StablePoolError::InvalidSortedTrovesHint.into());
+    }
+
+    let none = Pubkey::default();
+    if *prev_id == none && *next_id == none {
+        // insert as head and tail
+        token_vault.sorted_troves_head = *trove_key;
+        token_vault.sorted_troves_tail = *trove_key;
+    }
+    else if *prev_id == none {
+        // insert before `next_id` as the head
+        trove.next_trove = *next_id;
+        get_node_mut(nodes, next_id)?.prev_trove = *trove_key;
+        token_vault.sorted_troves_head = *trove_key;
+    }
+    else if *next_id == none {
+        // insert after `prev_id` as the tail
+        trove.prev_trove = *prev_id;
+        get_node_mut(nodes, prev_id)?.next_trove = *trove_key;
+        token_vault.sorted_troves_tail = *trove_key;
+    }
+    else {
+        // insert at insert position between `prev_id` and `next_id`
+        trove.prev_trove = *prev_id;
+        trove.next_trove = *next_id;
+        get_node_mut(nodes, prev_id)?.next_trove = *trove_key;
+        get_node_mut(nodes, next_id)?.prev_trove = *trove_key;
+    }
+
+    trove.is_listed = true;
+    token_vault.sorted_troves_size = token_vault.sorted_troves_size.try_add(1)?;
+    Ok(())
+}
+
+pub fn remove(token_vault: &mut TokenVault, trove_key: &Pubkey, trove: &mut UserTrove, nodes: &mut [Account<UserTrove>]) -> ProgramResult {
+    if !trove.is_listed {
+        return Err(StablePoolError::InvalidState.into());
+    }
+
+    let none = Pubkey::default();
+    if token_vault.sorted_troves_size > 1 {
+        if *trove_key == token_vault.sorted_troves_head {
+            // the removed node is the head, set the next node as the new head
+            token_vault.sorted_troves_head = trove.next_trove;
+            get_node_mut(nodes, &trove.next_trove)?.prev_trove = none;
+        }
+        else if *trove_key == token_vault.sorted_troves_tail {
+            // the removed node is the tail, set the previous node as the new tail
+            token_vault.sorted_troves_tail = trove.prev_trove;
+            get_node_mut(nodes, &trove.prev_trove)?.next_trove = none;
+        }
+        else {
+            // the removed node is neither the head nor the tail, link its neighbours together
+            get_node_mut(nodes, &trove.prev_trove)?.next_trove = trove.next_trove;
+            get_node_mut(nodes, &trove.next_trove)?.prev_trove = trove.prev_trove;
+        }
+    }
+    else {
+        // list contains a single node, the list becomes empty
+        token_vault.sorted_troves_head = none;
+        token_vault.sorted_troves_tail = none;
+    }
+
+    trove.is_listed = false;
+    trove.prev_trove = none;
+    trove.next_trove = none;
+    token_vault.sorted_troves_size = token_vault.sorted_troves_size.try_sub(1)?;
+    Ok(())
+}
+
+pub fn re_insert(token_vault: &mut TokenVault, trove_key: &Pubkey, trove: &mut UserTrove, nodes: &mut [Account<UserTrove>], new_nicr: u64, prev_id: &Pubkey, next_id: &Pubkey) -> ProgramResult {
+    remove(token_vault, trove_key, trove, nodes)?;
+    insert(token_vault, trove_key, trove, nodes, new_nicr, prev_id, next_id)
+}
+
+/// move a trove to its position for its current coll and debt, listing it when it takes debt and unlisting it when its debt is cleared
+pub fn update_sorted_troves<'info>(
+    program_id: &Pubkey,
+    token_vault_key: &Pubkey,
+    token_vault: &mut TokenVault,
+    trove_key: &Pubkey,
+    trove: &mut UserTrove,
+    remaining_accounts: &[AccountInfo<'info>],
+    upper_hint: &Pubkey,
+    lower_hint: &Pubkey,
+) -> ProgramResult {
+    let mut nodes = load_trove_nodes(remaining_accounts, token_vault_key, trove_key)?;
+
+    if trove.debt > 0 {
+        let nicr = get_nominal_icr(token_vault, trove)?;
+        if trove.is_listed {
+            re_insert(token_vault, trove_key, trove, &mut nodes, nicr, upper_hint, lower_hint)?;
+        }
+        else {
+            insert(token_vault, trove_key, trove, &mut nodes, nicr, upper_hint, lower_hint)?;
+        }
+    }
+    else if trove.is_listed {
+        remove(token_vault, trove_key, trove, &mut nodes)?;
+    }
+
+    save_trove_nodes(program_id, &nodes)
+}
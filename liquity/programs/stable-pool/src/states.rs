@@ -1,18 +1,116 @@
 use anchor_lang::prelude::*;
 use crate::{
     constant::*,
+    error::*,
+    events::*,
+    math::*,
     utils::*
 };
 #[account]
 #[derive(Default)]
 pub struct GlobalState {
     pub super_owner: Pubkey,
+    // proposed super owner, becomes the super owner once it accepts the transfer
+    pub pending_super_owner: Pubkey,
+
+    // roles delegated by the super owner
+    pub vault_creator: Pubkey,
+    pub params_admin: Pubkey,
+    pub oracle_admin: Pubkey,
+    // key allowed to pause operation classes
+    pub guardian: Pubkey,
+
     pub mint_usd: Pubkey,
 
+    // SOLUSD deposits of all the stability pools, each token vault keeps its own accounting
     pub stability_solusd_pool: Pubkey,
-    pub sp_solusd_amount: u64,
-    pub sp_sol_amount: u64,
-    
+    // SOLUSD gas compensation reserved by the troves with debt
+    pub gas_pool: Pubkey,
+
+    // SOLID staking state receiving the borrowing and redemption fees
+    pub staking_state: Pubkey,
+    // borrowing fees in SOLUSD, sent to the SOLID staking fee pool
+    pub total_borrowing_fee: u64,
+
+    pub base_rate: u64,
+    // The timestamp of the latest fee operation (redemption or new SOLUSD issuance)
+    pub last_fee_operation_time: i64,
+
+    // bitmap of the paused operation classes, see the PAUSE_* constants
+    pub paused_operations: u8,
+
+    // max SOLUSD supply, checked whenever SOLUSD is minted against a trove
+    pub max_total_supply: u64,
+}
+impl GlobalState {
+    pub fn require_not_paused(&self, operation: u8) -> ProgramResult {
+        if self.paused_operations & operation != 0 {
+            return Err(StablePoolError::ProtocolPaused.into());
+        }
+        Ok(())
+    }
+    /// minting `amount` on top of the current SOLUSD supply must stay within the max total supply
+    pub fn require_within_max_total_supply(&self, supply: u64, amount: u64) -> ProgramResult {
+        match supply.checked_add(amount) {
+            Some(new_supply) if new_supply <= self.max_total_supply => Ok(()),
+            _ => Err(StablePoolError::MaxTotalSupplyExceeded.into()),
+        }
+    }
+    pub fn minutes_passed_since_last_fee_op(&self, now: i64) -> Result<u64> {
+        let seconds = if now > self.last_fee_operation_time {now.try_sub(self.last_fee_operation_time)?} else {0};
+        Ok((seconds / SECONDS_IN_ONE_MINUTE) as u64)
+    }
+    pub fn calc_decayed_base_rate(&self, now: i64) -> Result<u64> {
+        let minutes_passed = self.minutes_passed_since_last_fee_op(now)?;
+        let decay_factor = dec_pow(MINUTE_DECAY_FACTOR, minutes_passed)?;
+        dec_mul(self.base_rate, decay_factor)
+    }
+    /*
+    * Update the last fee operation time only if time passed >= decay interval. This prevents base rate griefing.
+    */
+    pub fn update_last_fee_op_time(&mut self, now: i64) -> ProgramResult {
+        if self.minutes_passed_since_last_fee_op(now)? >= 1 {
+            self.last_fee_operation_time = now;
+        }
+        Ok(())
+    }
+    /*
+    * This function has two impacts on the base rate:
+    * 1) decays the base rate based on time passed since last redemption or SOLUSD borrowing operation.
+    * then,
+    * 2) increases the base rate based on the amount redeemed, as a proportion of total supply
+    */
+    pub fn update_base_rate_from_redemption(&mut self, solusd_redeemed: u64, total_solusd_supply: u64, now: i64) -> Result<u64> {
+        let decayed_base_rate = self.calc_decayed_base_rate(now)?;
+        let redeemed_solusd_fraction = mul_div(solusd_redeemed, DECIMAL_PRECISION, total_solusd_supply)?;
+
+        let new_base_rate = min(decayed_base_rate.try_add(redeemed_solusd_fraction / BETA)?, DECIMAL_PRECISION); // cap baseRate at a maximum of 100%
+
+        self.base_rate = new_base_rate;
+        self.update_last_fee_op_time(now)?;
+        self.emit_base_rate_updated();
+        Ok(new_base_rate)
+    }
+    /// decay the base rate on a new SOLUSD issuance, a borrowing never increases it
+    pub fn decay_base_rate_from_borrowing(&mut self, now: i64) -> ProgramResult {
+        let decayed_base_rate = self.calc_decayed_base_rate(now)?;
+        self.base_rate = min(decayed_base_rate, DECIMAL_PRECISION);
+        self.update_last_fee_op_time(now)?;
+        self.emit_base_rate_updated();
+        Ok(())
+    }
+    fn emit_base_rate_updated(&self) {
+        emit!(BaseRateUpdated {
+            base_rate: self.base_rate,
+            last_fee_operation_time: self.last_fee_operation_time,
+        });
+    }
+    pub fn get_borrowing_rate(&self, borrowing_fee_floor: u64) -> Result<u64> {
+        Ok(min(borrowing_fee_floor.try_add(self.base_rate)?, MAX_BORROWING_FEE))
+    }
+    pub fn get_borrowing_fee(&self, borrowing_fee_floor: u64, solusd_debt: u64) -> Result<u64> {
+        mul_div(self.get_borrowing_rate(borrowing_fee_floor)?, solusd_debt, DECIMAL_PRECISION)
+    }
 }
 
 #[account]
@@ -32,11 +130,351 @@ pub struct TokenVault {
     pub oracle_program: Pubkey,
     pub pyth_product: Pubkey,
     pub pyth_price: Pubkey,
+
+    // risk parameters: minimum collateral ratio of a trove, critical collateral ratio of the vault, minimum debt of a
+    // trove with debt, SOLUSD gas compensation reserved by a trove with debt, max total debt of the vault, and the
+    // floors of the borrowing and redemption fees
+    pub mcr: u64,
+    pub ccr: u64,
+    pub min_net_debt: u64,
+    pub solusd_gas_compensation: u64,
+    pub debt_ceiling: u64,
+    pub borrowing_fee_floor: u64,
+    pub redemption_fee_floor: u64,
+
+    // oracle guards: currency the price must be quoted in, max age of the aggregate price in slots,
+    // max confidence interval relative to the price and whether the price status must be trading
+    pub quote_currency: [u8; 32],
+    pub max_price_age_slots: u64,
+    pub max_price_conf_ratio: u64,
+    pub require_trading_status: bool,
+
+    // redemption fees in collateral, sent to the SOLID staking fee vault
+    pub total_redemption_fee: u64,
+
+    // sorted troves list, ordered by descending nominal ICR
+    pub sorted_troves_head: Pubkey,
+    pub sorted_troves_tail: Pubkey,
+    pub sorted_troves_size: u64,
+
+    // redistribution of liquidated debt and collateral, proportional to the trove stakes
+    pub total_stakes: u64,
+    pub total_stakes_snapshot: u64,
+    pub total_collateral_snapshot: u64,
+    // sums of the liquidated collateral and debt per unit staked
+    pub l_coll: u128,
+    pub l_debt: u128,
+    // error trackers for the redistribution calculation
+    pub last_coll_error_redistribution: u128,
+    pub last_debt_error_redistribution: u128,
+
+    // stability pool of this collateral, the SOLUSD deposits are kept in the shared stability pool token account
+    // and the collateral gains in the pool token account
+    pub sp_solusd_amount: u64,
+    pub sp_coll_amount: u64,
+    // stability pool running product P, with the current epoch and scale
+    pub sp_p: u128,
+    pub sp_current_epoch: u64,
+    pub sp_current_scale: u64,
+    // rounding errors of the last offset, carried to the next one
+    pub sp_last_coll_error_offset: u128,
+    pub sp_last_solusd_loss_error_offset: u128,
+
+    // collateral left over by capped recovery mode liquidations, kept in the pool token account until claimed
+    pub total_coll_surplus: u64,
 }
 impl TokenVault {
-    pub fn check_recovery_mode(&self, market_price: u64) -> bool {
-        let tcr = compute_cr(self.total_coll, self.total_debt, market_price);
-        return tcr < CCR ;
+    /// TCR of the token vault, taking the token decimals of the collateral and SOLUSD into account
+    pub fn get_tcr(&self, market_price: u64, coll_decimals: u8, usd_decimals: u8) -> Result<u64> {
+        compute_icr(self.total_coll, self.total_debt, market_price, coll_decimals, usd_decimals)
+    }
+    /// recovery mode check taking the token decimals into account
+    pub fn is_recovery_mode(&self, market_price: u64, coll_decimals: u8, usd_decimals: u8) -> Result<bool> {
+        Ok(self.get_tcr(market_price, coll_decimals, usd_decimals)? < self.ccr)
+    }
+    /// a trove with debt must owe at least the minimum net debt of the vault
+    pub fn require_at_least_min_net_debt(&self, debt: u64) -> ProgramResult {
+        if debt > 0 && debt < self.min_net_debt {
+            return Err(StablePoolError::NetDebtBelowMinimum.into());
+        }
+        Ok(())
+    }
+    /// the total debt of the vault after a debt increase must stay within its debt ceiling
+    pub fn require_within_debt_ceiling(&self, debt_increase: u64) -> ProgramResult {
+        match self.total_debt.checked_add(debt_increase) {
+            Some(new_total_debt) if new_total_debt <= self.debt_ceiling => Ok(()),
+            _ => Err(StablePoolError::DebtCeilingExceeded.into()),
+        }
+    }
+
+    /*
+    * Calculate a new stake based on the snapshots of the total stakes and the total collateral taken at the last liquidation.
+    * A trove without debt has no stake, so it never receives redistributed debt.
+    */
+    pub fn compute_new_stake(&self, coll: u64) -> Result<u64> {
+        if self.total_collateral_snapshot == 0 {
+            Ok(coll)
+        }
+        else {
+            mul_div(coll, self.total_stakes_snapshot, self.total_collateral_snapshot)
+        }
+    }
+    pub fn update_stake_and_total_stakes(&mut self, trove: &mut UserTrove) -> ProgramResult {
+        let new_stake = if trove.debt > 0 {self.compute_new_stake(trove.coll)?} else {0};
+        self.total_stakes = self.total_stakes.try_sub(trove.stake)?.try_add(new_stake)?;
+        trove.stake = new_stake;
+        Ok(())
+    }
+    pub fn remove_stake(&mut self, trove: &mut UserTrove) -> ProgramResult {
+        self.total_stakes = self.total_stakes.try_sub(trove.stake)?;
+        trove.stake = 0;
+        Ok(())
+    }
+
+    pub fn get_pending_coll_reward(&self, trove: &UserTrove) -> Result<u64> {
+        let reward_per_unit_staked = self.l_coll.try_sub(trove.l_coll_snapshot)?;
+        to_u64((trove.stake as u128).try_mul(reward_per_unit_staked)? / DECIMAL_PRECISION as u128)
+    }
+    pub fn get_pending_debt_reward(&self, trove: &UserTrove) -> Result<u64> {
+        let reward_per_unit_staked = self.l_debt.try_sub(trove.l_debt_snapshot)?;
+        to_u64((trove.stake as u128).try_mul(reward_per_unit_staked)? / DECIMAL_PRECISION as u128)
+    }
+    /// collateral and debt of a trove including its pending rewards
+    pub fn get_entire_coll_and_debt(&self, trove: &UserTrove) -> Result<(u64, u64)> {
+        Ok((
+            trove.coll.try_add(self.get_pending_coll_reward(trove)?)?,
+            trove.debt.try_add(self.get_pending_debt_reward(trove)?)?,
+        ))
+    }
+
+    /// add the pending rewards to the trove and take a new reward snapshot, called before every change to a trove
+    pub fn apply_pending_rewards(&mut self, trove: &mut UserTrove) -> ProgramResult {
+        let pending_coll = self.get_pending_coll_reward(trove)?;
+        let pending_debt = self.get_pending_debt_reward(trove)?;
+
+        if pending_coll > 0 || pending_debt > 0 {
+            trove.coll = trove.coll.try_add(pending_coll)?;
+            trove.debt = trove.debt.try_add(pending_debt)?;
+
+            // the pending rewards are no longer waiting in the default totals
+            self.default_total_coll = self.default_total_coll.try_sub(pending_coll)?;
+            self.default_total_debt = self.default_total_debt.try_sub(pending_debt)?;
+        }
+
+        trove.l_coll_snapshot = self.l_coll;
+        trove.l_debt_snapshot = self.l_debt;
+        Ok(())
+    }
+
+    /*
+    * Distribute the debt and collateral the stability pool could not absorb to all troves, proportional to their stakes.
+    * The troves pick up their share lazily in `apply_pending_rewards`.
+    *
+    * The rounding errors of the division are carried to the next redistribution, so no debt or collateral is lost.
+    */
+    pub fn redistribute_debt_and_coll(&mut self, debt: u64, coll: u64) -> ProgramResult {
+        if debt == 0 {
+            return Ok(());
+        }
+        if self.total_stakes == 0 {
+            return Err(StablePoolError::OnlyOneTroveInSystem.into());
+        }
+
+        let coll_numerator = (coll as u128 * DECIMAL_PRECISION as u128).try_add(self.last_coll_error_redistribution)?;
+        let debt_numerator = (debt as u128 * DECIMAL_PRECISION as u128).try_add(self.last_debt_error_redistribution)?;
+
+        // get the per-unit-staked terms, the remainders of the divisions are the new errors
+        let total_stakes = self.total_stakes as u128;
+        let coll_reward_per_unit_staked = coll_numerator / total_stakes;
+        let debt_reward_per_unit_staked = debt_numerator / total_stakes;
+
+        self.last_coll_error_redistribution = coll_numerator % total_stakes;
+        self.last_debt_error_redistribution = debt_numerator % total_stakes;
+
+        // add per-unit-staked terms to the running totals
+        self.l_coll = self.l_coll.try_add(coll_reward_per_unit_staked)?;
+        self.l_debt = self.l_debt.try_add(debt_reward_per_unit_staked)?;
+
+        // the redistributed debt and collateral wait in the default totals until the troves apply their rewards
+        self.default_total_coll = self.default_total_coll.try_add(coll)?;
+        self.default_total_debt = self.default_total_debt.try_add(debt)?;
+        Ok(())
+    }
+
+    /// update the snapshots of the system stakes and collateral, used to compute new stakes after a liquidation
+    pub fn update_system_snapshots(&mut self) {
+        self.total_stakes_snapshot = self.total_stakes;
+        self.total_collateral_snapshot = self.total_coll;
+    }
+
+    /*
+    * Cancel the liquidated debt with the SOLUSD in the stability pool, and give the liquidated collateral to the
+    * depositors. `epoch_to_scale` holds the sum S of the current epoch and scale.
+    */
+    pub fn offset(&mut self, epoch_to_scale: &mut EpochToScale, debt_to_offset: u64, coll_to_add: u64) -> ProgramResult {
+        if self.sp_solusd_amount == 0 || debt_to_offset == 0 {
+            return Ok(());
+        }
+        let (coll_gain_per_unit_staked, solusd_loss_per_unit_staked) = self.compute_rewards_per_unit_staked(coll_to_add, debt_to_offset)?;
+        self.update_reward_sum_and_product(epoch_to_scale, coll_gain_per_unit_staked, solusd_loss_per_unit_staked)?;
+
+        self.sp_solusd_amount = self.sp_solusd_amount.try_sub(debt_to_offset)?;
+        self.sp_coll_amount = self.sp_coll_amount.try_add(coll_to_add)?;
+        Ok(())
+    }
+
+    /*
+    * Compute the SOLUSD and collateral rewards. Uses a "feedback" error correction, to keep the cumulative error in
+    * the P and S state variables low.
+    */
+    pub fn compute_rewards_per_unit_staked(&mut self, coll_to_add: u64, debt_to_offset: u64) -> Result<(u128, u128)> {
+        let total_solusd_deposits = self.sp_solusd_amount as u128;
+        let coll_numerator = (coll_to_add as u128 * DECIMAL_PRECISION as u128).try_add(self.sp_last_coll_error_offset)?;
+
+        let solusd_loss_per_unit_staked = if debt_to_offset as u128 == total_solusd_deposits {
+            // emptying the stability pool
+            self.sp_last_solusd_loss_error_offset = 0;
+            SP_PRECISION
+        }
+        else {
+            let solusd_loss_numerator = (debt_to_offset as u128)
+                .try_mul(SP_PRECISION)?
+                .try_sub(self.sp_last_solusd_loss_error_offset)?;
+            /*
+            * Add 1 to make error in quotient positive. We want "slightly too much" SOLUSD loss,
+            * which ensures the error in any given compounded deposit favors the stability pool.
+            */
+            let loss_per_unit_staked = solusd_loss_numerator.try_div(total_solusd_deposits)?.try_add(1)?;
+            self.sp_last_solusd_loss_error_offset = loss_per_unit_staked
+                .try_mul(total_solusd_deposits)?
+                .try_sub(solusd_loss_numerator)?;
+            loss_per_unit_staked
+        };
+
+        let coll_gain_per_unit_staked = coll_numerator.try_div(total_solusd_deposits)?;
+        self.sp_last_coll_error_offset = coll_numerator % total_solusd_deposits;
+
+        Ok((coll_gain_per_unit_staked, solusd_loss_per_unit_staked))
+    }
+
+    /// update the stability pool reward sum S and product P
+    pub fn update_reward_sum_and_product(&mut self, epoch_to_scale: &mut EpochToScale, coll_gain_per_unit_staked: u128, solusd_loss_per_unit_staked: u128) -> ProgramResult {
+        if epoch_to_scale.epoch != self.sp_current_epoch || epoch_to_scale.scale != self.sp_current_scale {
+            return Err(StablePoolError::InvalidEpochToScale.into());
+        }
+        let current_p = self.sp_p;
+
+        // make product factor 0 if there was a pool-emptying. Otherwise, it is (1 - SOLUSD loss per unit staked)
+        let new_product_factor = SP_PRECISION.try_sub(solusd_loss_per_unit_staked)?;
+
+        /*
+        * Calculate the new S first, before we update P.
+        * The collateral gain for any given depositor from a liquidation depends on the value of their deposit
+        * (and the value of totalDeposits) prior to the stability pool being drained.
+        */
+        let marginal_coll_gain = coll_gain_per_unit_staked.try_mul(current_p)? / DECIMAL_PRECISION as u128;
+        epoch_to_scale.sum = epoch_to_scale.sum.try_add(marginal_coll_gain)?;
+
+        // if the stability pool was emptied, increment the epoch, and reset the scale and product P
+        let scaled_p = current_p.try_mul(new_product_factor)?;
+        let new_p = if new_product_factor == 0 {
+            self.sp_current_epoch = self.sp_current_epoch.try_add(1)?;
+            self.sp_current_scale = 0;
+            SP_PRECISION
+        }
+        // if multiplying P by a non-zero product factor would reduce P below the scale boundary, increment the scale
+        else if scaled_p / SP_PRECISION < SCALE_FACTOR {
+            self.sp_current_scale = self.sp_current_scale.try_add(1)?;
+            scaled_p / (SP_PRECISION / SCALE_FACTOR)
+        }
+        else {
+            scaled_p / SP_PRECISION
+        };
+
+        if new_p == 0 {
+            return Err(StablePoolError::InvalidProductP.into());
+        }
+        self.sp_p = new_p;
+        Ok(())
+    }
+
+    /*
+    * Return the deposit of a depositor after the liquidations since their last snapshot.
+    * Deposits made before the last pool-emptying are fully used, and deposits older than one scale change are
+    * considered used up, since the remaining amount is below the precision.
+    */
+    pub fn get_compounded_solusd_deposit(&self, sp_user_info: &SPUserInfo) -> Result<u64> {
+        let initial_deposit = sp_user_info.deposit_balance as u128;
+        if initial_deposit == 0 || sp_user_info.epoch_snapshot < self.sp_current_epoch {
+            return Ok(0);
+        }
+        let scale_diff = self.sp_current_scale.try_sub(sp_user_info.scale_snapshot)?;
+
+        let compounded_deposit = if scale_diff == 0 {
+            initial_deposit.try_mul(self.sp_p)?.try_div(sp_user_info.p_snapshot)?
+        }
+        else if scale_diff == 1 {
+            initial_deposit.try_mul(self.sp_p)?.try_div(sp_user_info.p_snapshot)? / SCALE_FACTOR
+        }
+        else {
+            0
+        };
+
+        /*
+        * If compounded deposit is less than a billionth of the initial deposit, return 0.
+        * This ensures a deposit never ends up with more than its fair share of the remaining pool.
+        */
+        if compounded_deposit < initial_deposit / 1_000_000_000 {
+            return Ok(0);
+        }
+        to_u64(compounded_deposit)
+    }
+
+    /*
+    * Return the collateral gain of a depositor since their last snapshot.
+    * The gain earned in the snapshot scale is the change of its sum S, and the gain earned in the next scale is that
+    * scale's sum, divided by the scale factor. Later scales add nothing above the precision.
+    */
+    pub fn get_depositor_coll_gain(&self, sp_user_info: &SPUserInfo, epoch_to_scale_snapshot: &EpochToScale, epoch_to_scale_next: &EpochToScale) -> Result<u64> {
+        let initial_deposit = sp_user_info.deposit_balance as u128;
+        if initial_deposit == 0 {
+            return Ok(0);
+        }
+        let first_portion = epoch_to_scale_snapshot.sum.try_sub(sp_user_info.s_snapshot)?;
+        let second_portion = epoch_to_scale_next.sum / SCALE_FACTOR;
+
+        to_u64(initial_deposit.try_mul(first_portion.try_add(second_portion)?)?.try_div(sp_user_info.p_snapshot)?)
+    }
+
+    /*
+    * Move the collateral gain of a depositor to its pending gains, compound its deposit and take new snapshots.
+    * Called before every change to a deposit.
+    */
+    pub fn update_depositor(&self, sp_user_info: &mut SPUserInfo, epoch_to_scale_snapshot: &EpochToScale, epoch_to_scale_next: &EpochToScale, epoch_to_scale_current: &EpochToScale) -> ProgramResult {
+        let coll_gain = self.get_depositor_coll_gain(sp_user_info, epoch_to_scale_snapshot, epoch_to_scale_next)?;
+        sp_user_info.coll_gain = sp_user_info.coll_gain.try_add(coll_gain)?;
+        sp_user_info.deposit_balance = self.get_compounded_solusd_deposit(sp_user_info)?;
+
+        sp_user_info.p_snapshot = self.sp_p;
+        sp_user_info.s_snapshot = epoch_to_scale_current.sum;
+        sp_user_info.epoch_snapshot = self.sp_current_epoch;
+        sp_user_info.scale_snapshot = self.sp_current_scale;
+        Ok(())
+    }
+}
+
+/// trove status, mirroring the Status of the native programs
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TroveStatus {
+    NonExistent,
+    Active,
+    ClosedByOwner,
+    ClosedByLiquidation,
+    ClosedByRedemption,
+}
+impl Default for TroveStatus {
+    fn default() -> Self {
+        TroveStatus::NonExistent
     }
 }
 
@@ -44,25 +482,105 @@ impl TokenVault {
 #[derive(Default)]
 pub struct UserTrove {
     pub owner: Pubkey,
-    pub state: u8,
+    pub token_vault: Pubkey,
+    pub status: TroveStatus,
     pub coll: u64,
-    pub debt: u64
+    pub debt: u64,
+
+    // sorted troves list node
+    pub is_listed: bool,
+    pub prev_trove: Pubkey,
+    pub next_trove: Pubkey,
+
+    // redistribution stake and the L_coll/L_debt snapshots taken at the last reward update
+    pub stake: u64,
+    pub l_coll_snapshot: u128,
+    pub l_debt_snapshot: u128,
+
+    // collateral above the MCR share of a trove liquidated in recovery mode, claimable by the owner
+    pub coll_surplus: u64,
+
+    // SOLUSD reserved in the gas pool, part of the debt, released when the debt is repaid
+    pub solusd_gas_compensation: u64,
 }
 impl UserTrove {
-    pub fn is_closed(&self)->bool{
-        self.state == 0
+    pub fn is_active(&self)->bool{
+        self.status == TroveStatus::Active
     }
-    pub fn close(&mut self) {
-        self.state = 0;
+    pub fn close(&mut self, status: TroveStatus) {
+        self.status = status;
         self.coll = 0;
         self.debt = 0;
+        self.solusd_gas_compensation = 0;
+    }
+    /// debt of the trove without the SOLUSD gas compensation, the part the owner repays
+    pub fn net_debt(&self) -> Result<u64> {
+        self.debt.try_sub(self.solusd_gas_compensation)
     }
 }
 #[account]
 #[derive(Default)]
 pub struct SPUserInfo {
     pub owner: Pubkey,
+    pub token_vault: Pubkey,
+    // deposit at the last snapshot, the compounded deposit is derived from it and the snapshots
     pub deposit_balance: u64,
+    // collateral gains taken at the last snapshot, waiting to be claimed
+    pub coll_gain: u64,
+
+    pub p_snapshot: u128,
+    pub s_snapshot: u128,
+    pub epoch_snapshot: u64,
+    pub scale_snapshot: u64,
+}
+
+/// status of the price feed of a token vault, mirroring the status machine of the liquity PriceFeed with pyth as the
+/// primary source and the pushed fallback price as the secondary one
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PriceFeedStatus {
+    PythWorking,
+    UsingFallbackPythUntrusted,
+    BothUntrusted,
+    UsingFallbackPythFrozen,
+    UsingPythFallbackUntrusted,
+}
+impl Default for PriceFeedStatus {
+    fn default() -> Self {
+        PriceFeedStatus::PythWorking
+    }
+}
+
+/// price source of a token vault, falls back to a pushed price or to the last good price when pyth can not be trusted
+#[account]
+#[derive(Default)]
+pub struct PriceFeed {
+    pub token_vault: Pubkey,
+    pub status: PriceFeedStatus,
+
+    // last price returned by a trusted source, used while no source can be trusted
+    pub last_good_price: u64,
+    pub last_good_price_slot: u64,
+
+    // fallback price, pushed by the fallback authority
+    pub fallback_authority: Pubkey,
+    pub fallback_price: u64,
+    pub fallback_price_slot: u64,
+
+    // max relative change of the pyth price from the last good price, max relative difference between the two sources
+    // to trust both again, and max age of the fallback price in slots
+    pub max_price_deviation: u64,
+    pub max_price_difference_between_sources: u64,
+    pub max_fallback_price_age_slots: u64,
+}
+
+/// sum S of the collateral gains of a token vault's stability pool, for one epoch and scale
+#[account]
+#[derive(Default)]
+pub struct EpochToScale {
+    pub token_vault: Pubkey,
+    pub epoch: u64,
+    pub scale: u64,
+    pub sum: u128,
 }
 
 
@@ -91,4 +609,163 @@ impl LiquidationTotals{
             total_coll_surplus:0,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOL: u64 = 1_000_000_000;
+    const SOLUSD: u64 = 1_000_000;
+
+    #[test]
+    fn base_rate() {
+        let mut global_state = GlobalState::default();
+
+        // redeeming 100 of the 10000 SOLUSD supply raises the base rate by half the redeemed fraction
+        assert_eq!(global_state.update_base_rate_from_redemption(100 * SOLUSD, 10_000 * SOLUSD, 3_600), Ok(DECIMAL_PRECISION / 200));
+        assert_eq!(global_state.last_fee_operation_time, 3_600);
+        assert_eq!(global_state.get_borrowing_fee(DEFAULT_BORROWING_FEE_FLOOR, 1000 * SOLUSD), Ok(10 * SOLUSD));
+
+        // less than a minute passed, the base rate and the last fee operation time are kept
+        global_state.decay_base_rate_from_borrowing(3_659).unwrap();
+        assert_eq!((global_state.base_rate, global_state.last_fee_operation_time), (DECIMAL_PRECISION / 200, 3_600));
+
+        // the base rate is halved after 12 hours
+        global_state.decay_base_rate_from_borrowing(3_600 + 720 * SECONDS_IN_ONE_MINUTE).unwrap();
+        assert_eq!(global_state.base_rate, 2_500_000);
+        assert_eq!(global_state.last_fee_operation_time, 3_600 + 720 * SECONDS_IN_ONE_MINUTE);
+
+        // the base rate is capped at 100%, and the borrowing rate at the max borrowing fee
+        assert_eq!(global_state.update_base_rate_from_redemption(30_000 * SOLUSD, 10_000 * SOLUSD, 3_600), Ok(DECIMAL_PRECISION));
+        assert_eq!(global_state.get_borrowing_fee(DEFAULT_BORROWING_FEE_FLOOR, 1000 * SOLUSD), Ok(50 * SOLUSD));
+    }
+
+    #[test]
+    fn redistribution() {
+        let mut token_vault = TokenVault {
+            total_stakes: 3 * SOL,
+            ..TokenVault::default()
+        };
+        let mut trove = UserTrove {
+            coll: SOL,
+            debt: 2000 * SOLUSD,
+            stake: SOL,
+            ..UserTrove::default()
+        };
+
+        // the trove holds a third of the stakes, the remainders of the divisions are carried to the next redistribution
+        token_vault.redistribute_debt_and_coll(100 * SOLUSD, SOL).unwrap();
+        assert_eq!((token_vault.l_coll, token_vault.l_debt), (333_333_333, 33_333_333));
+        assert_eq!(token_vault.last_coll_error_redistribution, 1_000_000_000);
+        assert_eq!(token_vault.last_debt_error_redistribution, 1_000_000_000);
+        assert_eq!((token_vault.default_total_coll, token_vault.default_total_debt), (SOL, 100 * SOLUSD));
+        assert_eq!(token_vault.get_entire_coll_and_debt(&trove), Ok((1_333_333_333, 2_033_333_333)));
+
+        token_vault.apply_pending_rewards(&mut trove).unwrap();
+        assert_eq!((trove.coll, trove.debt), (1_333_333_333, 2_033_333_333));
+        assert_eq!((trove.l_coll_snapshot, trove.l_debt_snapshot), (333_333_333, 33_333_333));
+        assert_eq!((token_vault.default_total_coll, token_vault.default_total_debt), (666_666_667, 66_666_667));
+
+        // without stakes there is no trove left to take the debt
+        token_vault.total_stakes = 0;
+        assert_eq!(token_vault.redistribute_debt_and_coll(100 * SOLUSD, SOL), Err(StablePoolError::OnlyOneTroveInSystem.into()));
+    }
+
+    #[test]
+    fn stakes_and_recovery_mode() {
+        let mut token_vault = TokenVault {
+            total_stakes: 3 * SOL,
+            ..TokenVault::default()
+        };
+        let mut trove = UserTrove {
+            coll: 2 * SOL,
+            debt: 2000 * SOLUSD,
+            stake: SOL,
+            ..UserTrove::default()
+        };
+
+        // before the first liquidation the stake is the collateral, after it the stakes are scaled by the snapshots
+        token_vault.update_stake_and_total_stakes(&mut trove).unwrap();
+        assert_eq!((trove.stake, token_vault.total_stakes), (2 * SOL, 4 * SOL));
+        token_vault.total_stakes_snapshot = 3 * SOL;
+        token_vault.total_collateral_snapshot = 4 * SOL;
+        token_vault.update_stake_and_total_stakes(&mut trove).unwrap();
+        assert_eq!((trove.stake, token_vault.total_stakes), (1_500_000_000, 3_500_000_000));
+
+        // a trove without debt has no stake
+        trove.debt = 0;
+        token_vault.update_stake_and_total_stakes(&mut trove).unwrap();
+        assert_eq!((trove.stake, token_vault.total_stakes), (0, 2 * SOL));
+        trove.stake = 3 * SOL;
+        assert_eq!(token_vault.remove_stake(&mut trove), Err(StablePoolError::MathOverflow.into()));
+
+        // 10 SOL against 1000 SOLUSD enters recovery mode below a price of 150
+        token_vault.ccr = DEFAULT_CCR;
+        token_vault.total_coll = 10 * SOL;
+        token_vault.total_debt = 1000 * SOLUSD;
+        assert_eq!(token_vault.get_tcr(150, 9, SOLUSD_DECIMALS), Ok(DEFAULT_CCR));
+        assert_eq!(token_vault.is_recovery_mode(150, 9, SOLUSD_DECIMALS), Ok(false));
+        assert_eq!(token_vault.is_recovery_mode(149, 9, SOLUSD_DECIMALS), Ok(true));
+    }
+
+    #[test]
+    fn stability_pool_offset() {
+        let mut token_vault = TokenVault {
+            sp_solusd_amount: 1000 * SOLUSD,
+            sp_p: SP_PRECISION,
+            ..TokenVault::default()
+        };
+        let mut epoch_to_scale = EpochToScale::default();
+        let mut sp_user_info = SPUserInfo {
+            deposit_balance: 400 * SOLUSD,
+            p_snapshot: SP_PRECISION,
+            ..SPUserInfo::default()
+        };
+
+        // 100 SOLUSD of debt is cancelled with the pool for 1 SOL, the SOLUSD loss is rounded up
+        token_vault.offset(&mut epoch_to_scale, 100 * SOLUSD, SOL).unwrap();
+        assert_eq!((token_vault.sp_solusd_amount, token_vault.sp_coll_amount), (900 * SOLUSD, SOL));
+        assert_eq!(token_vault.sp_p, 899_999_999_999_999_999);
+        assert_eq!(token_vault.sp_last_solusd_loss_error_offset, 1_000_000_000);
+        assert_eq!(epoch_to_scale.sum, 1_000_000_000_000_000_000);
+
+        // the depositor of 40% of the pool loses 40 SOLUSD, rounded against it, and gains 0.4 SOL
+        assert_eq!(token_vault.get_compounded_solusd_deposit(&sp_user_info), Ok(359_999_999));
+        assert_eq!(token_vault.get_depositor_coll_gain(&sp_user_info, &epoch_to_scale, &EpochToScale::default()), Ok(400_000_000));
+        token_vault.update_depositor(&mut sp_user_info, &epoch_to_scale, &EpochToScale::default(), &epoch_to_scale).unwrap();
+        assert_eq!((sp_user_info.deposit_balance, sp_user_info.coll_gain), (359_999_999, 400_000_000));
+        assert_eq!((sp_user_info.p_snapshot, sp_user_info.s_snapshot), (token_vault.sp_p, epoch_to_scale.sum));
+
+        // the pool can not offset more than its deposits
+        assert!(token_vault.offset(&mut epoch_to_scale, 1000 * SOLUSD, SOL).is_err());
+    }
+
+    #[test]
+    fn stability_pool_emptied() {
+        let mut token_vault = TokenVault {
+            sp_solusd_amount: 100 * SOLUSD,
+            sp_p: SP_PRECISION,
+            ..TokenVault::default()
+        };
+        let mut epoch_to_scale = EpochToScale::default();
+        let sp_user_info = SPUserInfo {
+            deposit_balance: 100 * SOLUSD,
+            p_snapshot: SP_PRECISION,
+            ..SPUserInfo::default()
+        };
+
+        // emptying the pool starts a new epoch with a reset product, the older deposits are used up
+        token_vault.offset(&mut epoch_to_scale, 100 * SOLUSD, SOL).unwrap();
+        assert_eq!((token_vault.sp_current_epoch, token_vault.sp_current_scale, token_vault.sp_p), (1, 0, SP_PRECISION));
+        assert_eq!(token_vault.sp_solusd_amount, 0);
+        assert_eq!(token_vault.get_compounded_solusd_deposit(&sp_user_info), Ok(0));
+        assert_eq!(token_vault.get_depositor_coll_gain(&sp_user_info, &epoch_to_scale, &EpochToScale::default()), Ok(SOL));
+
+        // an epoch to scale of another epoch is rejected
+        assert_eq!(
+            token_vault.update_reward_sum_and_product(&mut epoch_to_scale, 0, 0),
+            Err(StablePoolError::InvalidEpochToScale.into())
+        );
+    }
+}
@@ -3,10 +3,11 @@ use crate::{
     pyth::*,
     error::*,
     constant::*,
+    math::*,
+    states::TokenVault,
 };
 use std::u64::MAX;
 use std::convert::TryInto;
-use std::convert::TryFrom;
 use spl_math::{precise_number::PreciseNumber};
 
 pub fn get_pyth_product_quote_currency(pyth_product: &Product) -> Result<[u8; 32]> {
@@ -51,9 +52,8 @@ pub fn get_pyth_product_quote_currency(pyth_product: &Product) -> Result<[u8; 32
     Err(StablePoolError::InvalidOracleConfig.into())
 }
 
-pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<PreciseNumber> {
-    const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
-
+/// aggregate pyth price, guarded by the oracle limits of the token vault
+pub fn get_pyth_price(token_vault: &TokenVault, pyth_price_info: &AccountInfo, clock: &Clock) -> Result<PreciseNumber> {
     let pyth_price_data = pyth_price_info.try_borrow_data()?;
     let pyth_price = load::<Price>(&pyth_price_data)
         .map_err(|_| ProgramError::InvalidAccountData)?;
@@ -67,9 +67,14 @@ pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Pr
         .slot
         .checked_sub(pyth_price.valid_slot)
         .ok_or(StablePoolError::MathOverflow)?;
-    if slots_elapsed >= STALE_AFTER_SLOTS_ELAPSED {
+    if slots_elapsed >= token_vault.max_price_age_slots {
         msg!("Oracle price is stale");
-        return Err(StablePoolError::InvalidOracleConfig.into());
+        return Err(StablePoolError::StalePrice.into());
+    }
+
+    if token_vault.require_trading_status && pyth_price.agg.status != PriceStatus::Trading {
+        msg!("Oracle price is not trading");
+        return Err(StablePoolError::PriceNotTrading.into());
     }
 
     let price: u64 = pyth_price.agg.price.try_into().map_err(|_| {
@@ -77,6 +82,12 @@ pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Pr
         StablePoolError::InvalidOracleConfig
     })?;
 
+    // the confidence interval has the same exponent as the price
+    if price == 0 || pyth_price.agg.conf as u128 * DECIMAL_PRECISION as u128 / price as u128 > token_vault.max_price_conf_ratio as u128 {
+        msg!("Oracle price confidence interval is too wide");
+        return Err(StablePoolError::PriceUncertain.into());
+    }
+
     let market_price = if pyth_price.expo >= 0 {
         let exponent = pyth_price
             .expo
@@ -107,12 +118,13 @@ pub fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Pr
 }
 
 pub fn get_market_price(
-    oracle_program_id:Pubkey,
+    token_vault:&TokenVault,
     pyth_product_info:&AccountInfo,
     pyth_price_info:&AccountInfo,
     clock:&Clock
 )->Result<u64>{
     // get market price
+    let oracle_program_id = token_vault.oracle_program;
     if &oracle_program_id != pyth_product_info.owner {
         msg!("Pyth product account provided is not owned by the lending market oracle program");
         return Err(StablePoolError::InvalidOracleConfig.into());
@@ -148,34 +160,18 @@ pub fn get_market_price(
         return Err(StablePoolError::InvalidOracleConfig.into());
     }
 
-    let _quote_currency = get_pyth_product_quote_currency(pyth_product)?;
-    // if quote_currency != _quote_currency {
-    //     msg!("Lending market quote currency does not match the oracle quote currency");
-    //     return Err(StablePoolError::InvalidOracleConfig.into());
-    // }
+    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
+    if quote_currency != token_vault.quote_currency {
+        msg!("Token vault quote currency does not match the oracle quote currency");
+        return Err(StablePoolError::InvalidQuoteCurrency.into());
+    }
 
-    let market_price = get_pyth_price(pyth_price_info, clock)?;
+    let market_price = get_pyth_price(token_vault, pyth_price_info, clock)?;
     
     Ok(market_price.to_u64()?)
 }
 
 
-pub fn assert_debt_allowed(locked_coll_balance: u64, user_debt: u64, amount: u64, market_price: u64, coll_decimals: u8, usd_decimals: u8)-> ProgramResult{
-    msg!("market sol price = {}", market_price);
-    let debt_limit = market_price
-        .checked_mul(locked_coll_balance).unwrap()
-        .checked_mul(DECIMAL_PRECISION).unwrap()
-        .checked_div(MCR).unwrap()
-        .checked_mul(pow(10, usd_decimals)).unwrap()
-        .checked_div(pow(10, coll_decimals)).unwrap();
-    msg!("debt_limit = {}", debt_limit);
-    msg!("user_debt + amount = {}", user_debt + amount);
-    if debt_limit < (user_debt + amount) as u64 {
-        return Err(StablePoolError::NotAllowed.into())
-    }
-    Ok(())
-}
-
 pub fn min(a: u64, b: u64)-> u64{
     if a < b {a} else {b}
 }
@@ -190,10 +186,10 @@ pub fn max(a: u64, b: u64)-> u64{
 *
 * Used only inside the exponentiation, _decPow().
 */
-pub fn dec_mul(x : u64, y :u64)-> u64{
-    let prod_xy = x * y;
-    let dec_prod = (prod_xy + DECIMAL_PRECISION / 2) / DECIMAL_PRECISION;
-    return dec_prod;
+pub fn dec_mul(x : u64, y :u64)-> Result<u64>{
+    let prod_xy = (x as u128).try_mul(y as u128)?;
+    let dec_prod = prod_xy.try_add(DECIMAL_PRECISION as u128 / 2)? / DECIMAL_PRECISION as u128;
+    to_u64(dec_prod)
 }
 
 /* 
@@ -214,7 +210,7 @@ pub fn dec_mul(x : u64, y :u64)-> u64{
 * In function 1), the decayed base rate will be 0 for 1000 years or > 1000 years
 * In function 2), the difference in tokens issued at 1000 years and any time > 1000 years, will be negligible
 */
-pub fn dec_pow(base:u64, minutes:u64)->u64{
+pub fn dec_pow(base:u64, minutes:u64)->Result<u64>{
     let mut _minutes = minutes;
     let mut _base = base;
     if _minutes > 525600000 {
@@ -222,7 +218,7 @@ pub fn dec_pow(base:u64, minutes:u64)->u64{
         _minutes = 525600000;
     }
     if _minutes == 0 {
-        return DECIMAL_PRECISION;
+        return Ok(DECIMAL_PRECISION);
     }
     let mut y = DECIMAL_PRECISION;
     let mut x = _base;
@@ -231,17 +227,17 @@ pub fn dec_pow(base:u64, minutes:u64)->u64{
     // Exponentiation-by-squaring
     while n > 1 {
         if n % 2 == 0 {
-            x = dec_mul(x, x);
+            x = dec_mul(x, x)?;
             n = n / 2;
         }
         else { // if (n % 2 != 0)
-            y = dec_mul(x, y);
-            x = dec_mul(x, x);
+            y = dec_mul(x, y)?;
+            x = dec_mul(x, x)?;
             n = (n - 1) / 2;
         }
     }
 
-    return dec_mul(x, y);
+    dec_mul(x, y)
 }
 
 pub fn get_absolute_difference(a: u64, b: u64)->u64{
@@ -250,21 +246,80 @@ pub fn get_absolute_difference(a: u64, b: u64)->u64{
 
 pub fn compute_nominal_cr(coll: u64, debt: u64)->u64{
     if debt > 0 {
-        coll * NICR_PRECISION / debt
+        let nicr = coll as u128 * NICR_PRECISION as u128 / debt as u128;
+        if nicr > MAX as u128 {MAX} else {nicr as u64}
     }
     else {
         MAX
     }
 }
-pub fn compute_cr(coll: u64, debt: u64, price: u64)->u64{
+/// collateral ratio of a trove in DECIMAL_PRECISION, taking the token decimals of the collateral and SOLUSD into account
+pub fn compute_icr(coll: u64, debt: u64, price: u64, coll_decimals: u8, usd_decimals: u8)->Result<u64>{
     if debt > 0 {
-        let new_coll_ratio = coll * price / debt;
-        return new_coll_ratio;
+        let coll_value = (coll as u128)
+            .try_mul(price as u128)?
+            .try_mul(pow(10, usd_decimals)? as u128)?
+            .try_mul(DECIMAL_PRECISION as u128)?;
+        let icr = coll_value.try_div((debt as u128).try_mul(pow(10, coll_decimals)? as u128)?)?;
+        return Ok(if icr > MAX as u128 {MAX} else {icr as u64});
+    }
+    else {
+        return Ok(MAX);
+    }
+}
+/// amount of collateral worth `usd_amount` SOLUSD at the market price
+pub fn get_coll_amount_for_usd(usd_amount: u64, price: u64, coll_decimals: u8, usd_decimals: u8)->Result<u64>{
+    let usd_value = (usd_amount as u128).try_mul(pow(10, coll_decimals)? as u128)?;
+    to_u64(usd_value.try_div((price as u128).try_mul(pow(10, usd_decimals)? as u128)?)?)
+}
+pub fn get_redemption_fee(redemption_fee_floor: u64, base_rate: u64, coll_drawn: u64)->Result<u64>{
+    let redemption_rate = min(redemption_fee_floor.try_add(base_rate)?, DECIMAL_PRECISION);
+    let redemption_fee = mul_div(redemption_rate, coll_drawn, DECIMAL_PRECISION)?;
+    if redemption_fee >= coll_drawn {
+        msg!("Fee would eat up all returned collateral");
+        return Err(StablePoolError::FeeExceededMaxPercentage.into());
+    }
+    Ok(redemption_fee)
+}
+/// collateral paid to the liquidator of a trove
+pub fn get_coll_gas_compensation(entire_coll: u64)->u64{
+    entire_coll / PERCENT_DIVISOR
+}
+/*
+* Adjust trove rules. In Recovery Mode collateral can not be withdrawn, and a debt increase must leave the trove with
+* ICR >= CCR without lowering its ICR. In normal mode the trove must keep ICR >= MCR, and the operation must not push
+* the TCR below CCR.
+*/
+pub fn require_valid_adjustment(token_vault: &TokenVault, recovery_mode: bool, coll_withdrawal: u64, is_debt_increase: bool, old_icr: u64, new_icr: u64, new_tcr: u64)->ProgramResult{
+    if recovery_mode {
+        if coll_withdrawal > 0 {
+            return Err(StablePoolError::CollWithdrawalInRecoveryMode.into());
+        }
+        if is_debt_increase {
+            if new_icr < token_vault.ccr {
+                return Err(StablePoolError::ICRBelowCCR.into());
+            }
+            if new_icr < old_icr {
+                return Err(StablePoolError::ICRDecreaseInRecoveryMode.into());
+            }
+        }
     }
-    // Return the maximal value for uint256 if the Trove has a debt of 0. Represents "infinite" CR.
-    else {// if (_debt == 0)
-        return MAX;
+    else {
+        if new_icr < token_vault.mcr {
+            return Err(StablePoolError::ICRBelowMCR.into());
+        }
+        if new_tcr < token_vault.ccr {
+            return Err(StablePoolError::TCRBelowCCR.into());
+        }
     }
+    Ok(())
+}
+pub fn require_user_accepts_fee(fee: u64, amount: u64, max_fee_percentage: u64)->ProgramResult{
+    let fee_percentage = (fee as u128).try_mul(DECIMAL_PRECISION as u128)?.try_div(amount as u128)?;
+    if fee_percentage > max_fee_percentage as u128 {
+        return Err(StablePoolError::FeeExceededMaxPercentage.into());
+    }
+    Ok(())
 }
 
 
@@ -291,7 +346,7 @@ impl ToPrecise for u128 {
 }
 impl ToU64U128 for PreciseNumber {
     fn to_u64(&self) -> Result<u64> {
-        Ok(u64::try_from(self.to_imprecise().ok_or(StablePoolError::PreciseError)?).unwrap_or(0))
+        to_u64(self.to_imprecise().ok_or(StablePoolError::PreciseError)?)
     }
     fn to_u128(&self) -> Result<u128> {
         Ok(self.to_imprecise().ok_or(StablePoolError::PreciseError)?)
@@ -299,12 +354,71 @@ impl ToU64U128 for PreciseNumber {
 }
 
 
-pub fn pow(x:u64, y:u8)->u64{
-    let mut result = x;
-    let mut index = y;
-    while index > 1 {
-        result *= x;
-        index -= 1;
+pub fn pow(x:u64, y:u8)->Result<u64>{
+    x.checked_pow(y as u32).ok_or_else(|| StablePoolError::MathOverflow.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOL: u64 = 1_000_000_000;
+    const SOLUSD: u64 = 1_000_000;
+
+    #[test]
+    fn collateral_ratios() {
+        // 10 SOL at 150 against 1000 SOLUSD is 150%, 1 SOL at 1500 against 1200 SOLUSD is 125%
+        assert_eq!(compute_icr(10 * SOL, 1000 * SOLUSD, 150, 9, SOLUSD_DECIMALS), Ok(1_500_000_000));
+        assert_eq!(compute_icr(SOL, 1200 * SOLUSD, 1500, 9, SOLUSD_DECIMALS), Ok(1_250_000_000));
+        // a trove without debt, or with a ratio above u64, is capped
+        assert_eq!(compute_icr(SOL, 0, 150, 9, SOLUSD_DECIMALS), Ok(MAX));
+        assert_eq!(compute_icr(MAX, 1, 150, 9, SOLUSD_DECIMALS), Ok(MAX));
+        assert_eq!(compute_icr(MAX, 1, MAX, 9, SOLUSD_DECIMALS), Err(StablePoolError::MathOverflow.into()));
+        assert_eq!(compute_icr(SOL, SOLUSD, 150, 20, SOLUSD_DECIMALS), Err(StablePoolError::MathOverflow.into()));
+
+        assert_eq!(compute_nominal_cr(10 * SOL, 1000 * SOLUSD), 1_000_000_000);
+        assert_eq!(compute_nominal_cr(SOL, 0), MAX);
+
+        assert_eq!(get_coll_amount_for_usd(300 * SOLUSD, 150, 9, SOLUSD_DECIMALS), Ok(2 * SOL));
+        assert_eq!(get_coll_amount_for_usd(SOLUSD, 3, 9, SOLUSD_DECIMALS), Ok(333_333_333));
+        assert_eq!(get_coll_amount_for_usd(SOLUSD, 0, 9, SOLUSD_DECIMALS), Err(StablePoolError::DivisionByZero.into()));
     }
-    return result;
-}
\ No newline at end of file
+
+    #[test]
+    fn fees() {
+        assert_eq!(get_redemption_fee(DEFAULT_REDEMPTION_FEE_FLOOR, 0, SOL), Ok(5_000_000));
+        assert_eq!(get_redemption_fee(DEFAULT_REDEMPTION_FEE_FLOOR, 25_000_000, 2 * SOL), Ok(60_000_000));
+        // the rate is capped at 100%, which would eat up all the collateral
+        assert_eq!(
+            get_redemption_fee(DECIMAL_PRECISION, DECIMAL_PRECISION, SOL),
+            Err(StablePoolError::FeeExceededMaxPercentage.into())
+        );
+
+        assert_eq!(require_user_accepts_fee(5 * SOLUSD, 1000 * SOLUSD, DEFAULT_BORROWING_FEE_FLOOR), Ok(()));
+        assert_eq!(
+            require_user_accepts_fee(5 * SOLUSD + 1, 1000 * SOLUSD, DEFAULT_BORROWING_FEE_FLOOR),
+            Err(StablePoolError::FeeExceededMaxPercentage.into())
+        );
+        assert_eq!(require_user_accepts_fee(0, 0, DEFAULT_BORROWING_FEE_FLOOR), Err(StablePoolError::DivisionByZero.into()));
+    }
+
+    #[test]
+    fn decimal_pow() {
+        // the product is rounded half up
+        assert_eq!(dec_mul(DECIMAL_PRECISION / 2, DECIMAL_PRECISION / 3), Ok(166_666_667));
+        assert_eq!(dec_mul(1, DECIMAL_PRECISION / 2), Ok(1));
+        assert_eq!(dec_mul(1, 1), Ok(0));
+        assert_eq!(dec_mul(MAX, MAX), Err(StablePoolError::MathOverflow.into()));
+
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, 0), Ok(DECIMAL_PRECISION));
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, 1), Ok(MINUTE_DECAY_FACTOR));
+        assert_eq!(dec_pow(DECIMAL_PRECISION / 2, 3), Ok(125_000_000));
+        // the decay factor halves in 12 hours, and the exponent is capped at 1000 years
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, 720), Ok(500_000_072));
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, MAX), Ok(0));
+        assert_eq!(dec_pow(DECIMAL_PRECISION, MAX), Ok(DECIMAL_PRECISION));
+
+        assert_eq!(pow(10, 9), Ok(1_000_000_000));
+        assert_eq!(pow(10, 20), Err(StablePoolError::MathOverflow.into()));
+    }
+}
@@ -7,14 +7,24 @@ export const USER_TROVE_TAG = "user-trove-seed";
 export const SOLUSD_MINT_TAG = "solusd-mint";
 export const TOKEN_VAULT_POOL_TAG = "token-vault-pool";
 export const STABILITY_POOL_TAG = "stability-pool";
+export const SP_USER_INFO_TAG = "sp-user-info";
+export const EPOCH_TO_SCALE_TAG = "epoch-to-scale";
+export const PRICE_FEED_TAG = "price-feed";
+export const GAS_POOL_TAG = "gas-pool";
 export const SOLUSD_DECIMALS = 6;
 
+export const STAKING_STATE_TAG = "staking-state-seed";
+export const SOLUSD_FEE_POOL_TAG = "solusd-fee-pool";
+export const FEE_VAULT_TAG = "fee-vault-seed";
+export const FEE_VAULT_POOL_TAG = "fee-vault-pool";
+
 export const TOKEN_PROGRAM_ID = new anchor.web3.PublicKey('TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA');
 export const SYSVAR_RENT_PUBKEY = new anchor.web3.PublicKey('SysvarRent111111111111111111111111111111111');
 export const SYSVAR_CLOCK_PUBKEY = new anchor.web3.PublicKey('SysvarC1ock11111111111111111111111111111111');
 export const SYSTEM_PROGRAM_ID = new anchor.web3.PublicKey('11111111111111111111111111111111');
 
 export const LIQUITY_PROGRAM_ID = new anchor.web3.PublicKey('GYiTbo5gmrPfTNaHwBhdW4Dq7taRaNADwhp9iRBfxgQ4');
+export const STAKING_PROGRAM_ID = new anchor.web3.PublicKey('Ve3QUUFsr6n2W1GVWkUY7BaYLptQEkYPj6WXPQVX1Q9');
 export const PYTH_PROGRAM_ID = new anchor.web3.PublicKey(DEVNET_MODE?'gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s':'FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH');
 export const PYTH_PRODUCT_SOL = new anchor.web3.PublicKey(DEVNET_MODE?'3Mnn2fX6rQyUsyELYms1sBJyChWofzSNRoqYzvgMVz5E':'ALP8SdU9oARYVLgLR7LrqMNCYBnhtnQz1cj6bwgwQmgj');
 export const PYTH_PRICE_SOL = new anchor.web3.PublicKey(DEVNET_MODE?'J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix':'H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG');
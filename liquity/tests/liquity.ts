@@ -1,6 +1,6 @@
 import * as anchor from "@project-serum/anchor";
 import { initializeAccount, closeAccount } from '@project-serum/serum/lib/token-instructions'
-import { GLOBAL_STATE_TAG, LIQUITY_PROGRAM_ID, PYTH_PRICE_SOL, PYTH_PRODUCT_SOL, PYTH_PROGRAM_ID, SOLUSD_MINT_TAG, SOL_MINT_ADDRESS, STABILITY_POOL_TAG, SYSTEM_PROGRAM_ID, SYSVAR_CLOCK_PUBKEY, SYSVAR_RENT_PUBKEY, TOKEN_PROGRAM_ID, TOKEN_VAULT_POOL_TAG, TOKEN_VAULT_TAG, USER_TROVE_TAG } from "./ids";
+import { EPOCH_TO_SCALE_TAG, FEE_VAULT_POOL_TAG, FEE_VAULT_TAG, GAS_POOL_TAG, GLOBAL_STATE_TAG, LIQUITY_PROGRAM_ID, PRICE_FEED_TAG, PYTH_PRICE_SOL, PYTH_PRODUCT_SOL, PYTH_PROGRAM_ID, SOLUSD_MINT_TAG, SOL_MINT_ADDRESS, SOLUSD_DECIMALS, SOLUSD_FEE_POOL_TAG, SP_USER_INFO_TAG, STABILITY_POOL_TAG, STAKING_PROGRAM_ID, STAKING_STATE_TAG, SYSTEM_PROGRAM_ID, SYSVAR_CLOCK_PUBKEY, SYSVAR_RENT_PUBKEY, TOKEN_PROGRAM_ID, TOKEN_VAULT_POOL_TAG, TOKEN_VAULT_TAG, USER_TROVE_TAG } from "./ids";
 import idl from "../target/idl/stable_pool.json";
 import { StablePool } from "../target/types/stable_pool";
 import { AccountLayout } from "@solana/spl-token";
@@ -26,6 +26,98 @@ export function getLiquityProgram(
   return program;
 }
 
+const NICR_PRECISION = new anchor.BN(100000000);
+
+function computeNominalCR(coll: anchor.BN, debt: anchor.BN) {
+  if (debt.isZero()) return null;
+  return coll.mul(NICR_PRECISION).div(debt);
+}
+
+const REWARD_PRECISION = new anchor.BN(1000000000);
+
+// coll and debt of a trove including its pending redistribution rewards
+function getEntireCollAndDebt(tokenVault: any, trove: any) {
+  const pendingColl = trove.stake.mul(tokenVault.lColl.sub(trove.lCollSnapshot)).div(REWARD_PRECISION);
+  const pendingDebt = trove.stake.mul(tokenVault.lDebt.sub(trove.lDebtSnapshot)).div(REWARD_PRECISION);
+  return { coll: trove.coll.add(pendingColl), debt: trove.debt.add(pendingDebt) };
+}
+
+// find the position of a trove in the sorted troves list of a token vault for its new coll and debt.
+// returns the insert hints and the neighbour troves the program needs as remaining accounts.
+// the pending rewards of the trove are added to `coll` and `debt` unless `includePendingRewards` is false
+const DECIMAL_PRECISION = 1000000000;
+const MAX_BORROWING_FEE = DECIMAL_PRECISION / 100 * 5;
+const MINUTE_DECAY_FACTOR = 0.999037759;
+
+// borrowing fee of `amount` at the base rate decayed to now and the fee floor of the token vault, as computed by `borrow_usd`
+function getBorrowingFee(globalState: any, tokenVault: any, amount: number) {
+  const minutesPassed = Math.floor(Math.max(Date.now() / 1000 - globalState.lastFeeOperationTime.toNumber(), 0) / 60);
+  const decayedBaseRate = globalState.baseRate.toNumber() * Math.pow(MINUTE_DECAY_FACTOR, minutesPassed);
+  const borrowingRate = Math.min(tokenVault.borrowingFeeFloor.toNumber() + Math.floor(decayedBaseRate), MAX_BORROWING_FEE);
+  return new anchor.BN(amount).mul(new anchor.BN(borrowingRate)).div(new anchor.BN(DECIMAL_PRECISION));
+}
+
+export async function getSortedTrovesHints(
+  program: anchor.Program<StablePool>,
+  tokenVaultKey: anchor.web3.PublicKey,
+  userTroveKey: anchor.web3.PublicKey,
+  coll: anchor.BN,
+  debt: anchor.BN,
+  includePendingRewards: boolean = true,
+) {
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+  const troves = await program.account.userTrove.all();
+  const troveMap = new Map<string, any>();
+  troves.forEach((trove) => {
+    if (trove.account.tokenVault.equals(tokenVaultKey)) {
+      troveMap.set(trove.publicKey.toBase58(), trove.account);
+    }
+  });
+
+  const neighbours: anchor.web3.PublicKey[] = [];
+  const userTrove = troveMap.get(userTroveKey.toBase58());
+  if (userTrove && userTrove.isListed) {
+    neighbours.push(userTrove.prevTrove, userTrove.nextTrove);
+  }
+
+  if (userTrove && includePendingRewards) {
+    const entire = getEntireCollAndDebt(tokenVault, userTrove);
+    coll = coll.add(entire.coll.sub(userTrove.coll));
+    debt = debt.add(entire.debt.sub(userTrove.debt));
+  }
+
+  let upperHint = anchor.web3.PublicKey.default;
+  let lowerHint = anchor.web3.PublicKey.default;
+  const nicr = computeNominalCR(coll, debt);
+  if (nicr) {
+    // walk from the head (highest NICR) until the first trove with a lower NICR
+    let current = tokenVault.sortedTrovesHead;
+    while (!current.equals(anchor.web3.PublicKey.default)) {
+      const node = troveMap.get(current.toBase58());
+      if (!node) break;
+      if (!current.equals(userTroveKey)) {
+        const entire = getEntireCollAndDebt(tokenVault, node);
+        if (computeNominalCR(entire.coll, entire.debt).lte(nicr)) {
+          lowerHint = current;
+          break;
+        }
+        upperHint = current;
+      }
+      current = node.nextTrove;
+    }
+  }
+  neighbours.push(upperHint, lowerHint);
+
+  const remainingAccounts = neighbours
+    .filter((key, index) =>
+      !key.equals(anchor.web3.PublicKey.default) &&
+      !key.equals(userTroveKey) &&
+      neighbours.findIndex((other) => other.equals(key)) === index)
+    .map((key) => ({ pubkey: key, isWritable: true, isSigner: false }));
+
+  return { upperHint, lowerHint, remainingAccounts };
+}
+
 export async function createGlobalState(
   connection: anchor.web3.Connection,
   wallet: any
@@ -47,6 +139,11 @@ export async function createGlobalState(
       [Buffer.from(STABILITY_POOL_TAG)],
       program.programId
     );
+  let [gasPoolKey, gasPoolKeyNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GAS_POOL_TAG)],
+      program.programId
+    );
 
   const globalState = await program.account.globalState.fetchNullable(globalStateKey);
   if(globalState){
@@ -58,12 +155,14 @@ export async function createGlobalState(
     globalStateKeyNonce,
     mintUsdKeyNonce,
     stabilityPoolKeyNonce,
+    gasPoolKeyNonce,
     {
       accounts: {
         superOwner: wallet.publicKey,
         globalState: globalStateKey,
         mintUsd: mintUsdKey,
         stabilitySolusdPool: stabilityPoolKey,
+        gasPool: gasPoolKey,
         systemProgram: SYSTEM_PROGRAM_ID,
         tokenProgram: TOKEN_PROGRAM_ID,
         rent: SYSVAR_RENT_PUBKEY,
@@ -74,6 +173,71 @@ export async function createGlobalState(
   console.log("createGlobalState txid = ", tx);
 }
 
+// the SOLID staking state must use the global state as its fee authority
+export async function setStakingState(
+  connection: anchor.web3.Connection,
+  wallet: any
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  let [globalStateKey, globalStateKeyNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId
+    );
+  let [stakingStateKey, stakingStateKeyNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(STAKING_STATE_TAG)],
+      STAKING_PROGRAM_ID
+    );
+
+  const tx = await program.rpc.setStakingState(
+    globalStateKeyNonce,
+    {
+      accounts: {
+        superOwner: wallet.publicKey,
+        globalState: globalStateKey,
+        stakingState: stakingStateKey,
+      },
+    }
+  );
+  console.log("setStakingState txid = ", tx);
+}
+
+// bits of the pause bitmap, repaying and closing troves can not be paused
+export const PAUSE_BORROW = 1 << 0;
+export const PAUSE_WITHDRAW_COLL = 1 << 1;
+export const PAUSE_LIQUIDATE = 1 << 2;
+export const PAUSE_REDEEM = 1 << 3;
+export const PAUSE_SP_DEPOSIT = 1 << 4;
+
+// the wallet must be the guardian of the global state
+export async function setPauseState(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  pausedOperations: number
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  let [globalStateKey, globalStateKeyNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId
+    );
+
+  const tx = await program.rpc.setPauseState(
+    globalStateKeyNonce,
+    pausedOperations,
+    {
+      accounts: {
+        guardian: wallet.publicKey,
+        globalState: globalStateKey,
+      },
+    }
+  );
+  console.log("setPauseState txid = ", tx);
+}
+
 export async function createTokenVault(
   connection: anchor.web3.Connection,
   wallet: any,
@@ -129,6 +293,57 @@ export async function createTokenVault(
   console.log("createTokenVault txid = ", tx);
 }
 
+export async function createPriceFeed(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  collateralTokenMint: anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  let [globalStateKey, globalStateKeyNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId
+    );
+  let [tokenVaultKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), collateralTokenMint.toBuffer()],
+      program.programId
+    );
+  let [priceFeedKey, priceFeedKeyNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(PRICE_FEED_TAG), tokenVaultKey.toBuffer()],
+      program.programId
+    );
+
+  const priceFeed = await program.account.priceFeed.fetchNullable(priceFeedKey);
+  if(priceFeed){
+    console.log("already created!");
+    return;
+  }
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+
+  const tx = await program.rpc.createPriceFeed(
+    priceFeedKeyNonce,
+    globalStateKeyNonce,
+    {
+      accounts: {
+        oracleAdmin: wallet.publicKey,
+        globalState: globalStateKey,
+        tokenVault: tokenVaultKey,
+        priceFeed: priceFeedKey,
+        oracleProgram: tokenVault.oracleProgram,
+        pythProduct: tokenVault.pythProduct,
+        pythPrice: tokenVault.pythPrice,
+        clock: SYSVAR_CLOCK_PUBKEY,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      },
+    }
+  );
+  console.log("createPriceFeed txid = ", tx);
+}
+
 export async function createUserTrove(
   connection: anchor.web3.Connection,
   wallet: any,
@@ -216,12 +431,22 @@ export async function depositCollateral(
     console.log("user doesn't have any collateral");
     return;
   }
+  const userTroveState = await program.account.userTrove.fetch(userTroveKey);
+  const { upperHint, lowerHint, remainingAccounts } = await getSortedTrovesHints(
+    program,
+    tokenVaultKey,
+    userTroveKey,
+    userTroveState.coll.add(new anchor.BN(amount)),
+    userTroveState.debt,
+  );
   instructions.push(
     program.instruction.depositCollateral(
       new anchor.BN(amount),
       userTroveKeyNonce,
       tokenVaultKeyNonce,
       tokenVaultPoolKeyNonce,
+      upperHint,
+      lowerHint,
       {
         accounts: {
           owner: wallet.publicKey,
@@ -232,6 +457,7 @@ export async function depositCollateral(
           userTokenColl: userTokenAccount,
           tokenProgram: TOKEN_PROGRAM_ID
         },
+        remainingAccounts,
       }
     )
   )
@@ -292,12 +518,29 @@ export async function withdrawCollateral(
     instructions,
     signers
   )
+  let [globalStateKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId
+    );
+  const globalState = await program.account.globalState.fetch(globalStateKey);
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+  const userTroveState = await program.account.userTrove.fetch(userTroveKey);
+  const { upperHint, lowerHint, remainingAccounts } = await getSortedTrovesHints(
+    program,
+    tokenVaultKey,
+    userTroveKey,
+    userTroveState.coll.sub(anchor.BN.min(new anchor.BN(amount), userTroveState.coll)),
+    userTroveState.debt,
+  );
   instructions.push(
     program.instruction.withdrawCollateral(
       new anchor.BN(amount),
       userTroveKeyNonce,
       tokenVaultKeyNonce,
       tokenVaultPoolKeyNonce,
+      upperHint,
+      lowerHint,
       {
         accounts: {
           owner: wallet.publicKey,
@@ -306,8 +549,16 @@ export async function withdrawCollateral(
           poolTokenColl: tokenVaultPoolKey,
           mintColl: collateralTokenMint,
           userTokenColl: userTokenAccount,
-          tokenProgram: TOKEN_PROGRAM_ID
+          tokenProgram: TOKEN_PROGRAM_ID,
+          globalState: globalStateKey,
+          mintUsd: globalState.mintUsd,
+          oracleProgram: tokenVault.oracleProgram,
+          pythProduct: tokenVault.pythProduct,
+          pythPrice: tokenVault.pythPrice,
+          priceFeed: await getPriceFeedKey(program, tokenVaultKey),
+          clock: SYSVAR_CLOCK_PUBKEY,
         },
+        remainingAccounts,
       }
     )
   )
@@ -329,11 +580,13 @@ export async function withdrawCollateral(
 }
 
 
+// `maxFeePercentage` is the highest borrowing fee accepted, in DECIMAL_PRECISION (1e9 = 100%)
 export async function borrowSOLUSD(
   connection: anchor.web3.Connection,
   wallet: any,
   amount:number,
   mintCollKey:anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+  maxFeePercentage:number = 50000000,
 ) {
   const program = getLiquityProgram(connection, wallet);
 
@@ -377,12 +630,33 @@ export async function borrowSOLUSD(
     signers
   )
   
+  const [stakingSolusdFeePoolKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(SOLUSD_FEE_POOL_TAG)],
+      STAKING_PROGRAM_ID,
+    );
+
+  // the borrowing fee and the gas compensation of a first borrow are added to the trove debt, the hints need the
+  // debt including them
+  const userTroveState = await program.account.userTrove.fetch(userTroveKey);
+  const borrowingFee = getBorrowingFee(globalState, tokenVault, amount)
+    .add(userTroveState.debt.isZero() ? tokenVault.solusdGasCompensation : new anchor.BN(0));
+  const { upperHint, lowerHint, remainingAccounts } = await getSortedTrovesHints(
+    program,
+    tokenVaultKey,
+    userTroveKey,
+    userTroveState.coll,
+    userTroveState.debt.add(new anchor.BN(amount)).add(borrowingFee),
+  );
   const tx = await program.rpc.borrowUsd(
     new anchor.BN(amount), 
     tokenVaultNonce,
     userTroveNonce,
     globalStateNonce,
     mintUsdNonce,
+    upperHint,
+    lowerHint,
+    new anchor.BN(maxFeePercentage),
     {
       accounts: {
         owner: wallet.publicKey,
@@ -391,13 +665,19 @@ export async function borrowSOLUSD(
         globalState: globalStateKey,
         mintUsd: mintUsdKey,
         userTokenUsd: userUsdTokenKey,
+        gasPool: globalState.gasPool,
+        stakingProgram: STAKING_PROGRAM_ID,
+        stakingState: globalState.stakingState,
+        stakingSolusdFeePool: stakingSolusdFeePoolKey,
         mintColl: mintCollKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         oracleProgram: tokenVault.oracleProgram,
         pythProduct: tokenVault.pythProduct,
         pythPrice: tokenVault.pythPrice,
+        priceFeed: await getPriceFeedKey(program, tokenVaultKey),
         clock: SYSVAR_CLOCK_PUBKEY,
       },
+      remainingAccounts,
       instructions: instructions,
       signers
     }
@@ -450,12 +730,25 @@ export async function repaySOLUSD(
     return;
   }
   
+  // repaying the whole net debt also releases the gas compensation
+  const userTroveState = await program.account.userTrove.fetch(userTroveKey);
+  const netDebt = userTroveState.debt.sub(userTroveState.solusdGasCompensation);
+  const newNetDebt = netDebt.sub(anchor.BN.min(new anchor.BN(amount), netDebt));
+  const { upperHint, lowerHint, remainingAccounts } = await getSortedTrovesHints(
+    program,
+    tokenVaultKey,
+    userTroveKey,
+    userTroveState.coll,
+    newNetDebt.isZero() ? newNetDebt : newNetDebt.add(userTroveState.solusdGasCompensation),
+  );
   const repayInstruction = await program.instruction.repayUsd(
     new anchor.BN(amount), 
     tokenVaultNonce,
     userTroveNonce,
     globalStateNonce,
     mintUsdNonce,
+    upperHint,
+    lowerHint,
     {
       accounts: {
         owner: wallet.publicKey,
@@ -464,9 +757,11 @@ export async function repaySOLUSD(
         globalState: globalStateKey,
         mintUsd: mintUsdKey,
         userTokenUsd: paramUserUsdTokenKey,
+        gasPool: globalState.gasPool,
         mintColl: mintCollKey,
         tokenProgram: TOKEN_PROGRAM_ID,
       },
+      remainingAccounts,
     }
   );
   instructions.push(repayInstruction);
@@ -479,6 +774,291 @@ export async function repaySOLUSD(
   console.log("tx id->",tx);
 }
 
+// trove PDAs and the collateral token account of the owner. native SOL is wrapped in a temporary account holding
+// `wrapAmount` lamports, closed again at the end of the transaction.
+async function getTroveAccounts(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  program: anchor.Program<StablePool>,
+  collateralTokenMint: anchor.web3.PublicKey,
+  wrapAmount: number,
+  instructions: any[],
+  signers: any[],
+) {
+  const [globalStateKey, globalStateNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId,
+    );
+  const [mintUsdKey, mintUsdNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(SOLUSD_MINT_TAG)],
+      program.programId,
+    );
+  const [tokenVaultKey, tokenVaultNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), collateralTokenMint.toBuffer()],
+      program.programId
+    );
+  const [userTroveKey, userTroveNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(USER_TROVE_TAG), tokenVaultKey.toBuffer(), wallet.publicKey.toBuffer()],
+      program.programId
+    );
+  const [tokenVaultPoolKey, tokenVaultPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_POOL_TAG), tokenVaultKey.toBuffer()],
+      program.programId
+    );
+  const [stakingSolusdFeePoolKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(SOLUSD_FEE_POOL_TAG)],
+      STAKING_PROGRAM_ID,
+    );
+
+  const globalState = await program.account.globalState.fetch(globalStateKey);
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+
+  const isNative = collateralTokenMint.equals(SOL_MINT_ADDRESS);
+  let accountRentExempt = await connection.getMinimumBalanceForRentExemption(
+    AccountLayout.span
+  );
+  const userTokenColl = await createTokenAccountIfNotExist(
+    connection,
+    isNative ? null : await checkWalletATA(connection, wallet.publicKey, collateralTokenMint.toBase58()),
+    wallet.publicKey,
+    collateralTokenMint.toBase58(),
+    isNative ? accountRentExempt + wrapAmount : null,
+    instructions,
+    signers
+  );
+  const userTokenUsd = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, globalState.mintUsd.toBase58()),
+    wallet.publicKey,
+    globalState.mintUsd.toBase58(),
+    null,
+    instructions,
+    signers
+  );
+
+  return {
+    globalStateKey, globalStateNonce, mintUsdKey, mintUsdNonce, tokenVaultKey, tokenVaultNonce,
+    userTroveKey, userTroveNonce, tokenVaultPoolKey, tokenVaultPoolNonce, stakingSolusdFeePoolKey,
+    globalState, tokenVault, isNative, userTokenColl, userTokenUsd,
+  };
+}
+
+// deposit `collAmount` collateral and borrow `debtAmount` SOLUSD in a new trove
+export async function openTrove(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  collAmount: number,
+  debtAmount: number,
+  maxFeePercentage: number = 50000000,
+  collateralTokenMint: anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+  const instructions = [];
+  const signers = [];
+  const accounts = await getTroveAccounts(connection, wallet, program, collateralTokenMint, collAmount, instructions, signers);
+
+  const borrowingFee = getBorrowingFee(accounts.globalState, accounts.tokenVault, debtAmount);
+  const { upperHint, lowerHint, remainingAccounts } = await getSortedTrovesHints(
+    program,
+    accounts.tokenVaultKey,
+    accounts.userTroveKey,
+    new anchor.BN(collAmount),
+    debtAmount > 0 ? new anchor.BN(debtAmount).add(borrowingFee).add(accounts.tokenVault.solusdGasCompensation) : new anchor.BN(0),
+  );
+  instructions.push(
+    program.instruction.openTrove(
+      new anchor.BN(collAmount),
+      new anchor.BN(debtAmount),
+      accounts.userTroveNonce,
+      accounts.tokenVaultNonce,
+      accounts.globalStateNonce,
+      accounts.mintUsdNonce,
+      accounts.tokenVaultPoolNonce,
+      upperHint,
+      lowerHint,
+      new anchor.BN(maxFeePercentage),
+      {
+        accounts: {
+          owner: wallet.publicKey,
+          userTrove: accounts.userTroveKey,
+          tokenVault: accounts.tokenVaultKey,
+          globalState: accounts.globalStateKey,
+          mintUsd: accounts.mintUsdKey,
+          userTokenUsd: accounts.userTokenUsd,
+          gasPool: accounts.globalState.gasPool,
+          poolTokenColl: accounts.tokenVaultPoolKey,
+          userTokenColl: accounts.userTokenColl,
+          mintColl: collateralTokenMint,
+          stakingProgram: STAKING_PROGRAM_ID,
+          stakingState: accounts.globalState.stakingState,
+          stakingSolusdFeePool: accounts.stakingSolusdFeePoolKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+          oracleProgram: accounts.tokenVault.oracleProgram,
+          pythProduct: accounts.tokenVault.pythProduct,
+          pythPrice: accounts.tokenVault.pythPrice,
+          priceFeed: await getPriceFeedKey(program, accounts.tokenVaultKey),
+          clock: SYSVAR_CLOCK_PUBKEY,
+        },
+        remainingAccounts,
+      }
+    )
+  );
+  if (accounts.isNative) {
+    instructions.push(closeAccount({ source: accounts.userTokenColl, destination: wallet.publicKey, owner: wallet.publicKey }));
+  }
+  const transaction = new Transaction();
+  instructions.forEach((instruction) => transaction.add(instruction));
+  const tx = await sendTransaction(connection, wallet, transaction, signers);
+  console.log("openTrove txid = ", tx);
+}
+
+// change the collateral and the debt of a trove in one call, a negative amount withdraws or repays
+export async function adjustTrove(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  collChange: number,
+  debtChange: number,
+  maxFeePercentage: number = 50000000,
+  collateralTokenMint: anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+  const instructions = [];
+  const signers = [];
+  const accounts = await getTroveAccounts(connection, wallet, program, collateralTokenMint, Math.max(collChange, 0), instructions, signers);
+
+  const userTroveState = await program.account.userTrove.fetch(accounts.userTroveKey);
+  const borrowingFee = debtChange > 0 ? getBorrowingFee(accounts.globalState, accounts.tokenVault, debtChange) : new anchor.BN(0);
+  // a first borrow reserves the gas compensation, repaying the whole net debt releases it
+  const netDebt = userTroveState.debt.sub(userTroveState.solusdGasCompensation);
+  let newDebt = userTroveState.debt.add(new anchor.BN(debtChange)).add(borrowingFee);
+  if (debtChange > 0 && userTroveState.debt.isZero()) {
+    newDebt = newDebt.add(accounts.tokenVault.solusdGasCompensation);
+  }
+  if (debtChange < 0 && netDebt.eq(new anchor.BN(-debtChange))) {
+    newDebt = new anchor.BN(0);
+  }
+  const { upperHint, lowerHint, remainingAccounts } = await getSortedTrovesHints(
+    program,
+    accounts.tokenVaultKey,
+    accounts.userTroveKey,
+    userTroveState.coll.add(new anchor.BN(collChange)),
+    newDebt,
+  );
+  instructions.push(
+    program.instruction.adjustTrove(
+      new anchor.BN(Math.abs(collChange)),
+      collChange > 0,
+      new anchor.BN(Math.abs(debtChange)),
+      debtChange > 0,
+      accounts.tokenVaultNonce,
+      accounts.userTroveNonce,
+      accounts.globalStateNonce,
+      accounts.mintUsdNonce,
+      accounts.tokenVaultPoolNonce,
+      upperHint,
+      lowerHint,
+      new anchor.BN(maxFeePercentage),
+      {
+        accounts: {
+          owner: wallet.publicKey,
+          userTrove: accounts.userTroveKey,
+          tokenVault: accounts.tokenVaultKey,
+          globalState: accounts.globalStateKey,
+          mintUsd: accounts.mintUsdKey,
+          userTokenUsd: accounts.userTokenUsd,
+          gasPool: accounts.globalState.gasPool,
+          poolTokenColl: accounts.tokenVaultPoolKey,
+          userTokenColl: accounts.userTokenColl,
+          mintColl: collateralTokenMint,
+          stakingProgram: STAKING_PROGRAM_ID,
+          stakingState: accounts.globalState.stakingState,
+          stakingSolusdFeePool: accounts.stakingSolusdFeePoolKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          oracleProgram: accounts.tokenVault.oracleProgram,
+          pythProduct: accounts.tokenVault.pythProduct,
+          pythPrice: accounts.tokenVault.pythPrice,
+          priceFeed: await getPriceFeedKey(program, accounts.tokenVaultKey),
+          clock: SYSVAR_CLOCK_PUBKEY,
+        },
+        remainingAccounts,
+      }
+    )
+  );
+  if (accounts.isNative) {
+    instructions.push(closeAccount({ source: accounts.userTokenColl, destination: wallet.publicKey, owner: wallet.publicKey }));
+  }
+  const transaction = new Transaction();
+  instructions.forEach((instruction) => transaction.add(instruction));
+  const tx = await sendTransaction(connection, wallet, transaction, signers);
+  console.log("adjustTrove txid = ", tx);
+}
+
+// repay all the debt of a trove, take all its collateral back and close the trove account
+export async function closeTrove(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  collateralTokenMint: anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+  const instructions = [];
+  const signers = [];
+  const accounts = await getTroveAccounts(connection, wallet, program, collateralTokenMint, 0, instructions, signers);
+
+  const { remainingAccounts } = await getSortedTrovesHints(
+    program,
+    accounts.tokenVaultKey,
+    accounts.userTroveKey,
+    new anchor.BN(0),
+    new anchor.BN(0),
+    false,
+  );
+  instructions.push(
+    program.instruction.closeTrove(
+      accounts.tokenVaultNonce,
+      accounts.userTroveNonce,
+      accounts.globalStateNonce,
+      accounts.mintUsdNonce,
+      accounts.tokenVaultPoolNonce,
+      {
+        accounts: {
+          owner: wallet.publicKey,
+          userTrove: accounts.userTroveKey,
+          tokenVault: accounts.tokenVaultKey,
+          globalState: accounts.globalStateKey,
+          mintUsd: accounts.mintUsdKey,
+          userTokenUsd: accounts.userTokenUsd,
+          gasPool: accounts.globalState.gasPool,
+          poolTokenColl: accounts.tokenVaultPoolKey,
+          userTokenColl: accounts.userTokenColl,
+          mintColl: collateralTokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          oracleProgram: accounts.tokenVault.oracleProgram,
+          pythProduct: accounts.tokenVault.pythProduct,
+          pythPrice: accounts.tokenVault.pythPrice,
+          priceFeed: await getPriceFeedKey(program, accounts.tokenVaultKey),
+          clock: SYSVAR_CLOCK_PUBKEY,
+        },
+        remainingAccounts,
+      }
+    )
+  );
+  if (accounts.isNative) {
+    instructions.push(closeAccount({ source: accounts.userTokenColl, destination: wallet.publicKey, owner: wallet.publicKey }));
+  }
+  const transaction = new Transaction();
+  instructions.forEach((instruction) => transaction.add(instruction));
+  const tx = await sendTransaction(connection, wallet, transaction, signers);
+  console.log("closeTrove txid = ", tx);
+}
+
 export async function liquidateTrove(
   connection: anchor.web3.Connection,
   wallet: any,
@@ -505,26 +1085,341 @@ export async function liquidateTrove(
     [Buffer.from(USER_TROVE_TAG), tokenVaultKey.toBuffer(),userTrove.owner.toBuffer()],
     program.programId,
   );
+  const [tokenVaultPoolKey, tokenVaultPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_POOL_TAG), tokenVaultKey.toBuffer()],
+      program.programId
+    );
   const globalState = await program.account.globalState.fetchNullable(globalStateKey)
-  const tx = await program.rpc.liquidateTrove(
-    globalStateNonce,
-    tokenVaultNonce,
-    userTroveNonce,
-    {
-      accounts: {
-        liquidator: wallet.publicKey,
-        tokenVault: tokenVaultKey,
-        userTrove: userTroveKey,
-        userTroveOwner: userTrove.owner,
-        globalState: globalStateKey,
+  const remainingAccounts = [userTrove.prevTrove, userTrove.nextTrove]
+    .filter((key) => !key.equals(anchor.web3.PublicKey.default))
+    .map((key) => ({ pubkey: key, isWritable: true, isSigner: false }));
+
+  // the liquidator receives the collateral and the SOLUSD gas compensations
+  const instructions = [];
+  const signers = [];
+  const liquidatorTokenColl = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, mintCollKey.toBase58()),
+    wallet.publicKey,
+    mintCollKey.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+  const liquidatorTokenUsd = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, globalState.mintUsd.toBase58()),
+    wallet.publicKey,
+    globalState.mintUsd.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+  const epochToScaleKey = await getEpochToScaleKey(
+    program,
+    wallet,
+    tokenVaultKey,
+    tokenVault.spCurrentEpoch,
+    tokenVault.spCurrentScale,
+    instructions
+  );
+
+  const tx = await program.rpc.liquidateTrove(
+    globalStateNonce,
+    tokenVaultNonce,
+    userTroveNonce,
+    tokenVaultPoolNonce,
+    {
+      accounts: {
+        liquidator: wallet.publicKey,
+        tokenVault: tokenVaultKey,
+        userTrove: userTroveKey,
+        userTroveOwner: userTrove.owner,
+        globalState: globalStateKey,
         mintColl: tokenVault.mintColl,
+        poolTokenColl: tokenVaultPoolKey,
+        liquidatorTokenColl: liquidatorTokenColl,
+        mintUsd: globalState.mintUsd,
+        stabilitySolusdPool: globalState.stabilitySolusdPool,
+        gasPool: globalState.gasPool,
+        liquidatorTokenUsd: liquidatorTokenUsd,
+        epochToScale: epochToScaleKey,
+        oracleProgram: tokenVault.oracleProgram,
+        pythProduct: tokenVault.pythProduct,
+        pythPrice: tokenVault.pythPrice,
+        priceFeed: await getPriceFeedKey(program, tokenVaultKey),
+        clock: SYSVAR_CLOCK_PUBKEY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts,
+      instructions: instructions,
+      signers
+    }
+  );
+  console.log("tx id->",tx);
+}
+
+// liquidate up to `count` troves starting from the tail of the sorted list.
+// troves that can not be liquidated are skipped by the program
+export async function batchLiquidateTroves(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  count: number,
+  mintCollKey:anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  const [globalStateKey, globalStateNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId,
+    );
+  const [tokenVaultKey, tokenVaultNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), mintCollKey.toBuffer()],
+      program.programId,
+    );
+  const [tokenVaultPoolKey, tokenVaultPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_POOL_TAG), tokenVaultKey.toBuffer()],
+      program.programId
+    );
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+  const globalState = await program.account.globalState.fetch(globalStateKey);
+
+  // walk up from the tail, the trove above the last one is passed too since its link changes
+  const keys: anchor.web3.PublicKey[] = [];
+  let current = tokenVault.sortedTrovesTail;
+  while (!current.equals(anchor.web3.PublicKey.default) && keys.length <= count) {
+    keys.push(current);
+    const trove = await program.account.userTrove.fetch(current);
+    current = trove.prevTrove;
+  }
+  const remainingAccounts = keys.map((key) => ({ pubkey: key, isWritable: true, isSigner: false }));
+
+  // the liquidator receives the collateral and the SOLUSD gas compensations
+  const instructions = [];
+  const signers = [];
+  const liquidatorTokenColl = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, mintCollKey.toBase58()),
+    wallet.publicKey,
+    mintCollKey.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+  const liquidatorTokenUsd = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, globalState.mintUsd.toBase58()),
+    wallet.publicKey,
+    globalState.mintUsd.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+  const epochToScaleKey = await getEpochToScaleKey(
+    program,
+    wallet,
+    tokenVaultKey,
+    tokenVault.spCurrentEpoch,
+    tokenVault.spCurrentScale,
+    instructions
+  );
+
+  const tx = await program.rpc.batchLiquidateTroves(
+    globalStateNonce,
+    tokenVaultNonce,
+    tokenVaultPoolNonce,
+    {
+      accounts: {
+        liquidator: wallet.publicKey,
+        tokenVault: tokenVaultKey,
+        globalState: globalStateKey,
+        mintColl: mintCollKey,
+        poolTokenColl: tokenVaultPoolKey,
+        liquidatorTokenColl: liquidatorTokenColl,
+        mintUsd: globalState.mintUsd,
         stabilitySolusdPool: globalState.stabilitySolusdPool,
+        gasPool: globalState.gasPool,
+        liquidatorTokenUsd: liquidatorTokenUsd,
+        epochToScale: epochToScaleKey,
         oracleProgram: tokenVault.oracleProgram,
         pythProduct: tokenVault.pythProduct,
         pythPrice: tokenVault.pythPrice,
+        priceFeed: await getPriceFeedKey(program, tokenVaultKey),
         clock: SYSVAR_CLOCK_PUBKEY,
         tokenProgram: TOKEN_PROGRAM_ID,
       },
+      remainingAccounts,
+      instructions: instructions,
+      signers
+    }
+  );
+  console.log("tx id->",tx);
+}
+
+// redeem solUSD for collateral from the lowest ICR troves of a token vault.
+// `price` is the collateral price in USD, used to precompute the partially redeemed trove and its new position
+export async function redeemCollateral(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  amount: number,
+  price: number,
+  maxFeePercentage: number = 0.05 * 1000000000,
+  maxIterations: number = 0,
+  mintCollKey:anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  const [globalStateKey, globalStateNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId,
+    );
+  const [tokenVaultKey, tokenVaultNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), mintCollKey.toBuffer()],
+      program.programId,
+    );
+  const [mintUsdKey, mintUsdNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(SOLUSD_MINT_TAG)],
+      program.programId,
+    );
+  const [tokenVaultPoolKey, tokenVaultPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_POOL_TAG), tokenVaultKey.toBuffer()],
+      program.programId
+    );
+
+  const [stakingFeeVaultKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(FEE_VAULT_TAG), mintCollKey.toBuffer()],
+      STAKING_PROGRAM_ID
+    );
+  const [stakingFeeVaultPoolKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(FEE_VAULT_POOL_TAG), stakingFeeVaultKey.toBuffer()],
+      STAKING_PROGRAM_ID
+    );
+
+  const globalState = await program.account.globalState.fetch(globalStateKey);
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+  const collDecimals = (await connection.getParsedAccountInfo(mintCollKey)).value.data["parsed"].info.decimals;
+
+  const userUsdTokenKey = await checkWalletATA(connection, wallet.publicKey, globalState.mintUsd.toBase58());
+  if(!userUsdTokenKey){
+    console.log("user doesn't have any solusd")
+    return;
+  }
+
+  const troves = await program.account.userTrove.all();
+  const troveMap = new Map<string, any>();
+  troves.forEach((trove) => {
+    if (trove.account.tokenVault.equals(tokenVaultKey)) {
+      troveMap.set(trove.publicKey.toBase58(), trove.account);
+    }
+  });
+
+  // walk the troves from the tail the same way the program does
+  const walked: anchor.web3.PublicKey[] = [];
+  let remaining = new anchor.BN(amount);
+  let partialHintNICR = new anchor.BN(0);
+  let partialTroveKey = null;
+  let partialColl = new anchor.BN(0);
+  let partialDebt = new anchor.BN(0);
+  const usdPerColl = new anchor.BN(Math.floor(price)).mul(new anchor.BN(10).pow(new anchor.BN(SOLUSD_DECIMALS)));
+  const collUnit = new anchor.BN(10).pow(new anchor.BN(collDecimals));
+  let current = tokenVault.sortedTrovesTail;
+  while (!current.equals(anchor.web3.PublicKey.default) && remaining.gtn(0)) {
+    const node = troveMap.get(current.toBase58());
+    if (!node) break;
+    walked.push(current);
+    const entire = getEntireCollAndDebt(tokenVault, node);
+    const icrOk = entire.coll.mul(usdPerColl).mul(new anchor.BN(100)).gte(entire.debt.mul(collUnit).mul(new anchor.BN(110)));
+    if (icrOk) {
+      // a trove is redeemed up to its debt minus the gas compensation
+      const netDebt = entire.debt.sub(node.solusdGasCompensation);
+      const lot = anchor.BN.min(remaining, netDebt);
+      const collLot = anchor.BN.min(lot.mul(collUnit).div(usdPerColl), entire.coll);
+      if (lot.lt(netDebt)) {
+        partialTroveKey = current;
+        partialColl = entire.coll.sub(collLot);
+        partialDebt = entire.debt.sub(lot);
+        partialHintNICR = computeNominalCR(partialColl, partialDebt);
+      }
+      remaining = remaining.sub(lot);
+    }
+    current = node.prevTrove;
+  }
+  if (!current.equals(anchor.web3.PublicKey.default)) {
+    walked.push(current);
+  }
+
+  let upperHint = anchor.web3.PublicKey.default;
+  let lowerHint = anchor.web3.PublicKey.default;
+  let hintAccounts = [];
+  if (partialTroveKey) {
+    const hints = await getSortedTrovesHints(program, tokenVaultKey, partialTroveKey, partialColl, partialDebt, false);
+    upperHint = hints.upperHint;
+    lowerHint = hints.lowerHint;
+    hintAccounts = hints.remainingAccounts.map((account) => account.pubkey);
+  }
+  const keys = walked.concat(hintAccounts);
+  const remainingAccounts = keys
+    .filter((key, index) => keys.findIndex((other) => other.equals(key)) === index)
+    .map((key) => ({ pubkey: key, isWritable: true, isSigner: false }));
+
+  const instructions = [];
+  const signers = [];
+  const userTokenColl = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, mintCollKey.toBase58()),
+    wallet.publicKey,
+    mintCollKey.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+
+  const tx = await program.rpc.redeemCollateral(
+    new anchor.BN(amount),
+    tokenVaultNonce,
+    globalStateNonce,
+    mintUsdNonce,
+    tokenVaultPoolNonce,
+    partialHintNICR,
+    upperHint,
+    lowerHint,
+    new anchor.BN(maxIterations),
+    new anchor.BN(maxFeePercentage),
+    {
+      accounts: {
+        redeemer: wallet.publicKey,
+        tokenVault: tokenVaultKey,
+        globalState: globalStateKey,
+        mintUsd: mintUsdKey,
+        userTokenUsd: userUsdTokenKey,
+        gasPool: globalState.gasPool,
+        poolTokenColl: tokenVaultPoolKey,
+        userTokenColl: userTokenColl,
+        mintColl: mintCollKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        stakingProgram: STAKING_PROGRAM_ID,
+        stakingState: globalState.stakingState,
+        stakingFeeVault: stakingFeeVaultKey,
+        stakingFeeVaultPool: stakingFeeVaultPoolKey,
+        oracleProgram: tokenVault.oracleProgram,
+        pythProduct: tokenVault.pythProduct,
+        pythPrice: tokenVault.pythPrice,
+        priceFeed: await getPriceFeedKey(program, tokenVaultKey),
+        clock: SYSVAR_CLOCK_PUBKEY,
+      },
+      remainingAccounts,
+      instructions: instructions,
+      signers
     }
   );
   console.log("tx id->",tx);
@@ -549,4 +1444,294 @@ export async function getTroveKeyFromOwner(
     program.programId,
   );
   return userTroveKey;
-}
\ No newline at end of file
+}
+
+export async function getPriceFeedKey(
+  program: anchor.Program<StablePool>,
+  tokenVaultKey: anchor.web3.PublicKey,
+) {
+  const [priceFeedKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(PRICE_FEED_TAG), tokenVaultKey.toBuffer()],
+      program.programId,
+    );
+  return priceFeedKey;
+}
+
+// key of the account holding the sum S of a token vault's stability pool for an epoch and scale.
+// the account is created in `instructions` if it does not exist yet
+export async function getEpochToScaleKey(
+  program: anchor.Program<StablePool>,
+  wallet: any,
+  tokenVaultKey: anchor.web3.PublicKey,
+  epoch: anchor.BN,
+  scale: anchor.BN,
+  instructions: any[],
+) {
+  const [epochToScaleKey, epochToScaleNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(EPOCH_TO_SCALE_TAG), tokenVaultKey.toBuffer(), epoch.toArrayLike(Buffer, 'le', 8), scale.toArrayLike(Buffer, 'le', 8)],
+      program.programId,
+    );
+  const epochToScale = await program.account.epochToScale.fetchNullable(epochToScaleKey);
+  if (!epochToScale) {
+    instructions.push(program.instruction.createEpochToScale(
+      epoch,
+      scale,
+      epochToScaleNonce,
+      {
+        accounts: {
+          payer: wallet.publicKey,
+          epochToScale: epochToScaleKey,
+          tokenVault: tokenVaultKey,
+          systemProgram: SYSTEM_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+      }
+    ));
+  }
+  return epochToScaleKey;
+}
+
+// the sum S accounts a depositor of a token vault's stability pool needs: its snapshot scale, the next scale and
+// the current scale. the sp user info is created in `instructions` if it does not exist yet
+async function getDepositorAccounts(
+  program: anchor.Program<StablePool>,
+  wallet: any,
+  tokenVaultKey: anchor.web3.PublicKey,
+  instructions: any[],
+) {
+  const [spUserInfoKey, spUserInfoNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(SP_USER_INFO_TAG), tokenVaultKey.toBuffer(), wallet.publicKey.toBuffer()],
+      program.programId,
+    );
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+  let epochSnapshot = tokenVault.spCurrentEpoch;
+  let scaleSnapshot = tokenVault.spCurrentScale;
+
+  const spUserInfo = await program.account.spUserInfo.fetchNullable(spUserInfoKey);
+  if (spUserInfo) {
+    epochSnapshot = spUserInfo.epochSnapshot;
+    scaleSnapshot = spUserInfo.scaleSnapshot;
+  }
+  else {
+    instructions.push(program.instruction.createSpUserInfo(
+      spUserInfoNonce,
+      {
+        accounts: {
+          owner: wallet.publicKey,
+          spUserInfo: spUserInfoKey,
+          tokenVault: tokenVaultKey,
+          systemProgram: SYSTEM_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+      }
+    ));
+  }
+
+  const epochToScaleSnapshot = await getEpochToScaleKey(program, wallet, tokenVaultKey, epochSnapshot, scaleSnapshot, instructions);
+  const epochToScaleNext = await getEpochToScaleKey(program, wallet, tokenVaultKey, epochSnapshot, scaleSnapshot.addn(1), instructions);
+  const epochToScaleCurrent = await getEpochToScaleKey(program, wallet, tokenVaultKey, tokenVault.spCurrentEpoch, tokenVault.spCurrentScale, instructions);
+
+  return { spUserInfoKey, spUserInfoNonce, epochToScaleSnapshot, epochToScaleNext, epochToScaleCurrent };
+}
+
+export async function spDeposit(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  amount: number,
+  mintCollKey:anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  const [globalStateKey, globalStateNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId,
+    );
+  const [tokenVaultKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), mintCollKey.toBuffer()],
+      program.programId,
+    );
+  const [stabilityPoolKey, stabilityPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(STABILITY_POOL_TAG)],
+      program.programId,
+    );
+  const globalState = await program.account.globalState.fetch(globalStateKey);
+
+  const instructions = [];
+  const signers = [];
+  const { spUserInfoKey, spUserInfoNonce, epochToScaleSnapshot, epochToScaleNext, epochToScaleCurrent } =
+    await getDepositorAccounts(program, wallet, tokenVaultKey, instructions);
+
+  const userUsdTokenKey = await checkWalletATA(connection, wallet.publicKey, globalState.mintUsd.toBase58());
+
+  const tx = await program.rpc.spDeposit(
+    new anchor.BN(amount),
+    globalStateNonce,
+    spUserInfoNonce,
+    stabilityPoolNonce,
+    {
+      accounts: {
+        owner: wallet.publicKey,
+        globalState: globalStateKey,
+        tokenVault: tokenVaultKey,
+        spUserInfo: spUserInfoKey,
+        epochToScaleSnapshot,
+        epochToScaleNext,
+        epochToScaleCurrent,
+        stabilitySolusdPool: stabilityPoolKey,
+        userSolusdToken: userUsdTokenKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+      instructions: instructions,
+      signers
+    }
+  );
+  console.log("tx id->",tx);
+}
+
+export async function spWithdraw(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  amount: number,
+  mintCollKey:anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  const [globalStateKey, globalStateNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId,
+    );
+  const [tokenVaultKey] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), mintCollKey.toBuffer()],
+      program.programId,
+    );
+  const [stabilityPoolKey, stabilityPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(STABILITY_POOL_TAG)],
+      program.programId,
+    );
+  const globalState = await program.account.globalState.fetch(globalStateKey);
+  const tokenVault = await program.account.tokenVault.fetch(tokenVaultKey);
+
+  const instructions = [];
+  const signers = [];
+  const { spUserInfoKey, spUserInfoNonce, epochToScaleSnapshot, epochToScaleNext, epochToScaleCurrent } =
+    await getDepositorAccounts(program, wallet, tokenVaultKey, instructions);
+
+  const userUsdTokenKey = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, globalState.mintUsd.toBase58()),
+    wallet.publicKey,
+    globalState.mintUsd.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+
+  // the program checks the ICR of the lowest trove, the tail of the sorted list
+  const remainingAccounts = tokenVault.sortedTrovesTail.equals(anchor.web3.PublicKey.default) ? [] :
+    [{ pubkey: tokenVault.sortedTrovesTail, isWritable: false, isSigner: false }];
+
+  const tx = await program.rpc.spWithdraw(
+    new anchor.BN(amount),
+    globalStateNonce,
+    spUserInfoNonce,
+    stabilityPoolNonce,
+    {
+      accounts: {
+        owner: wallet.publicKey,
+        globalState: globalStateKey,
+        tokenVault: tokenVaultKey,
+        spUserInfo: spUserInfoKey,
+        epochToScaleSnapshot,
+        epochToScaleNext,
+        epochToScaleCurrent,
+        stabilitySolusdPool: stabilityPoolKey,
+        userSolusdToken: userUsdTokenKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        mintUsd: globalState.mintUsd,
+        mintColl: mintCollKey,
+        oracleProgram: tokenVault.oracleProgram,
+        pythProduct: tokenVault.pythProduct,
+        pythPrice: tokenVault.pythPrice,
+        priceFeed: await getPriceFeedKey(program, tokenVaultKey),
+        clock: SYSVAR_CLOCK_PUBKEY,
+      },
+      remainingAccounts,
+      instructions: instructions,
+      signers
+    }
+  );
+  console.log("tx id->",tx);
+}
+
+// claim the collateral the stability pool deposit earned from liquidations
+export async function spClaimGains(
+  connection: anchor.web3.Connection,
+  wallet: any,
+  mintCollKey:anchor.web3.PublicKey = SOL_MINT_ADDRESS,
+) {
+  const program = getLiquityProgram(connection, wallet);
+
+  const [globalStateKey, globalStateNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(GLOBAL_STATE_TAG)],
+      program.programId,
+    );
+  const [tokenVaultKey, tokenVaultNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_TAG), mintCollKey.toBuffer()],
+      program.programId,
+    );
+  const [tokenVaultPoolKey, tokenVaultPoolNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from(TOKEN_VAULT_POOL_TAG), tokenVaultKey.toBuffer()],
+      program.programId
+    );
+  const instructions = [];
+  const signers = [];
+  const { spUserInfoKey, spUserInfoNonce, epochToScaleSnapshot, epochToScaleNext, epochToScaleCurrent } =
+    await getDepositorAccounts(program, wallet, tokenVaultKey, instructions);
+
+  const userTokenColl = await createTokenAccountIfNotExist(
+    connection,
+    await checkWalletATA(connection, wallet.publicKey, mintCollKey.toBase58()),
+    wallet.publicKey,
+    mintCollKey.toBase58(),
+    null,
+    instructions,
+    signers
+  )
+
+  const tx = await program.rpc.spClaimGains(
+    globalStateNonce,
+    spUserInfoNonce,
+    tokenVaultNonce,
+    tokenVaultPoolNonce,
+    {
+      accounts: {
+        owner: wallet.publicKey,
+        globalState: globalStateKey,
+        spUserInfo: spUserInfoKey,
+        epochToScaleSnapshot,
+        epochToScaleNext,
+        epochToScaleCurrent,
+        tokenVault: tokenVaultKey,
+        mintColl: mintCollKey,
+        poolTokenColl: tokenVaultPoolKey,
+        userTokenColl: userTokenColl,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+      instructions: instructions,
+      signers
+    }
+  );
+  console.log("tx id->",tx);
+}
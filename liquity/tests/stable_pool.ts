@@ -4,12 +4,15 @@ import { StablePool } from '../target/types/stable_pool';
 import {
   createGlobalState,
   createTokenVault,
+  createPriceFeed,
   createUserTrove,
   depositCollateral,
   withdrawCollateral,
   borrowSOLUSD,
   repaySOLUSD,
   liquidateTrove,
+  spDeposit,
+  spWithdraw,
   getTroveKeyFromOwner,
 } from "./liquity";
 
@@ -20,15 +23,20 @@ const connection = program.provider.connection;
 const wallet = program.provider.wallet;
 const user1 = Keypair.generate();
 const user1Wallet = new anchor.Wallet(user1)
-const MIN_SOL_AMOUNT = 5 * 1000000000;
+const MIN_SOL_AMOUNT = 30 * 1000000000;
+// the troves borrow above the default min net debt of 1800 SOLUSD and repay part of it, keeping at least the min net debt
+const BORROW_AMOUNT = 2000 * 1000000;
+const REPAY_AMOUNT = 100 * 1000000;
+const COLL_AMOUNT = 25 * 1000000000;
+const WITHDRAW_AMOUNT = 1 * 1000000000;
 
 describe('liquity', () => {
   it('Setup', async () => {
     if(await connection.getBalance(user1.publicKey) < MIN_SOL_AMOUNT){
-      await connection.requestAirdrop(user1.publicKey, 5 * 1000000000);
+      await connection.requestAirdrop(user1.publicKey, MIN_SOL_AMOUNT);
     }
     if(await connection.getBalance(wallet.publicKey) < MIN_SOL_AMOUNT){
-      await connection.requestAirdrop(wallet.publicKey, 5 * 1000000000);
+      await connection.requestAirdrop(wallet.publicKey, MIN_SOL_AMOUNT);
     }
   });
   it('Create global state', async () => {
@@ -37,39 +45,49 @@ describe('liquity', () => {
   it('Create token vault', async () => {
     await createTokenVault(connection, wallet);
   });
+  it('Create price feed', async () => {
+    await createPriceFeed(connection, wallet);
+  });
   it('Create user trove', async () => {
     await createUserTrove(connection, wallet);
   });
   it('Deposit collateral', async () => {
-    await depositCollateral(connection, wallet, 0.1 * 1000000000);
+    await depositCollateral(connection, wallet, COLL_AMOUNT);
   });
   it('Borrow SOLUSD', async () => {
-    await borrowSOLUSD(connection, wallet, 10 * 1000000);
+    await borrowSOLUSD(connection, wallet, BORROW_AMOUNT);
   });
   it('Repay SOLUSD', async () => {
-    await repaySOLUSD(connection, wallet, 10 * 1000000);
+    await repaySOLUSD(connection, wallet, REPAY_AMOUNT);
   });
   it('Withdraw collateral', async () => {
-    await withdrawCollateral(connection, wallet, 0.1 * 1000000000);
+    await withdrawCollateral(connection, wallet, WITHDRAW_AMOUNT);
+  });
+  // the only trove is healthy, so the deposit can leave the stability pool before user1 is liquidated
+  it('Deposit to stability pool', async () => {
+    await spDeposit(connection, wallet, REPAY_AMOUNT);
+  });
+  it('Withdraw from stability pool', async () => {
+    await spWithdraw(connection, wallet, REPAY_AMOUNT);
   });
 
   it('user1: Create user trove', async () => {
     await createUserTrove(connection, user1Wallet);
   });
   it('user1: Deposit collateral', async () => {
-    await depositCollateral(connection, user1Wallet, 0.1 * 1000000000);
+    await depositCollateral(connection, user1Wallet, COLL_AMOUNT);
   });
   it('user1: Borrow SOLUSD', async () => {
-    await borrowSOLUSD(connection, user1Wallet, 10 * 1000000);
+    await borrowSOLUSD(connection, user1Wallet, BORROW_AMOUNT);
   });
   it('Liquidate user1 trove', async () => {
     const troveKey = await getTroveKeyFromOwner(connection, wallet, user1Wallet.publicKey);
     await liquidateTrove(connection, wallet, troveKey);
   });
   it('user1: Repay SOLUSD', async () => {
-    await repaySOLUSD(connection, user1Wallet, 10 * 1000000);
+    await repaySOLUSD(connection, user1Wallet, REPAY_AMOUNT);
   });
   it('user1: Withdraw collateral', async () => {
-    await withdrawCollateral(connection, user1Wallet, 0.1 * 1000000000);
+    await withdrawCollateral(connection, user1Wallet, WITHDRAW_AMOUNT);
   });
 });